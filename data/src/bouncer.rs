@@ -0,0 +1,148 @@
+//! Networks of a bouncer like soju, each connected to as its own server
+//!
+//! Reference: https://codeberg.org/emersion/soju/src/branch/master/doc/ext/bouncer-networks.md
+use std::collections::BTreeMap;
+
+use crate::Server;
+
+pub const CAP: &str = "soju.im/bouncer-networks";
+/// Notifies us of networks added, changed or removed
+pub const NOTIFY_CAP: &str = "soju.im/bouncer-networks-notify";
+
+/// The bouncer network a connection is bound to with `BOUNCER BIND`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// The connection to the bouncer itself, which listed the network
+    pub parent: Server,
+    pub netid: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    pub netid: String,
+    attributes: BTreeMap<String, String>,
+}
+
+impl Network {
+    pub fn new(netid: String) -> Self {
+        Self {
+            netid,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.attribute("name")
+            .or_else(|| self.attribute("host"))
+            .unwrap_or(&self.netid)
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Updates the attributes from `BOUNCER NETWORK`, where attributes without
+    /// a value are removed
+    pub fn update(&mut self, attributes: &str) {
+        for (key, value) in parse_attributes(attributes) {
+            match value {
+                Some(value) if !value.is_empty() => {
+                    self.attributes.insert(key, value);
+                }
+                _ => {
+                    self.attributes.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Server for the network under the bouncer's own, keyed by id as
+    /// names can change & be shared
+    pub fn server(&self, parent: &Server) -> Server {
+        Server::from(format!("{parent}/{}", self.netid).as_str())
+    }
+}
+
+/// Attributes of a network to add with `BOUNCER ADDNETWORK`
+pub fn new_network_attributes(host: &str, name: Option<&str>) -> String {
+    [("host", Some(host)), ("name", name)]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", escape(value?))))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn parse_attributes(attributes: &str) -> impl Iterator<Item = (String, Option<String>)> + '_ {
+    attributes
+        .split(';')
+        .filter(|attribute| !attribute.is_empty())
+        .map(|attribute| match attribute.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(unescape(value))),
+            None => (attribute.to_string(), None),
+        })
+}
+
+/// Attribute values are escaped like message tag values
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ';' => "\\:".to_string(),
+            ' ' => "\\s".to_string(),
+            '\\' => "\\\\".to_string(),
+            '\r' => "\\r".to_string(),
+            '\n' => "\\n".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(':') => unescaped.push(';'),
+            Some('s') => unescaped.push(' '),
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn network() {
+        let mut network = Network::new("44".to_string());
+
+        network.update("name=Libera\\sChat;host=irc.libera.chat;state=connecting");
+        assert_eq!(network.name(), "Libera Chat");
+        assert_eq!(network.attribute("state"), Some("connecting"));
+        assert_eq!(
+            network.server(&Server::from("soju")),
+            Server::from("soju/44")
+        );
+
+        network.update("state=connected;name=");
+        assert_eq!(network.name(), "irc.libera.chat");
+        assert_eq!(network.attribute("state"), Some("connected"));
+
+        assert_eq!(
+            new_network_attributes("irc.oftc.net", Some("OFTC; the best")),
+            "host=irc.oftc.net;name=OFTC\\:\\sthe\\sbest"
+        );
+    }
+}
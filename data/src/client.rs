@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use futures::channel::mpsc;
 use irc::proto::{self, command, Command};
 use itertools::{Either, Itertools};
@@ -9,10 +9,17 @@ use std::time::{Duration, Instant};
 use crate::message::server_time;
 use crate::time::Posix;
 use crate::user::{Nick, NickRef};
-use crate::{config, ctcp, dcc, isupport, message, mode, Buffer, Server, User};
-use crate::{file_transfer, server};
+use crate::{
+    bouncer, config, ctcp, dcc, history, ignore, isupport, message, mode, sasl, typing, whois,
+    Buffer, Server, User,
+};
+use crate::{dcc_chat, file_transfer, server};
 
 const HIGHLIGHT_BLACKOUT_INTERVAL: Duration = Duration::from_secs(5);
+/// Max # messages to request in a single CHATHISTORY query
+const CHATHISTORY_REQUEST_LIMIT: u16 = 500;
+/// Least time between WHOIS requests for hover cards
+const WHOIS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, Copy)]
 pub enum Status {
@@ -27,12 +34,63 @@ impl Status {
     }
 }
 
+/// Entries added & patterns removed from a server's configured ignore list
+#[derive(Debug, Default)]
+struct IgnoreChanges {
+    ignored: Vec<ignore::Entry>,
+    unignored: Vec<ignore::Pattern>,
+}
+
+impl IgnoreChanges {
+    fn ignore(&mut self, entry: ignore::Entry) {
+        self.unignored.retain(|pattern| *pattern != entry.pattern);
+        self.ignored
+            .retain(|existing| existing.pattern != entry.pattern);
+        self.ignored.push(entry);
+    }
+
+    fn unignore(&mut self, pattern: &ignore::Pattern) {
+        self.ignored.retain(|entry| entry.pattern != *pattern);
+
+        if !self.unignored.contains(pattern) {
+            self.unignored.push(pattern.clone());
+        }
+    }
+
+    fn apply(&self, ignores: &mut Vec<ignore::Entry>) {
+        ignores.retain(|entry| {
+            !self.unignored.contains(&entry.pattern)
+                && !self
+                    .ignored
+                    .iter()
+                    .any(|ignored| ignored.pattern == entry.pattern)
+        });
+        ignores.extend(self.ignored.iter().cloned());
+    }
+}
+
 #[derive(Debug)]
 pub enum State {
     Disconnected,
     Ready(Client),
 }
 
+/// Online status of a user tracked with MONITOR or ISON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    Online,
+    Offline,
+}
+
+impl fmt::Display for Presence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Presence::Online => write!(f, "online"),
+            Presence::Offline => write!(f, "offline"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Notification {
     Highlight(User, String),
@@ -67,6 +125,18 @@ pub enum Broadcast {
         channels: Vec<String>,
         sent_time: DateTime<Utc>,
     },
+    ChangeRealname {
+        old_user: User,
+        new_realname: String,
+        ourself: bool,
+        channels: Vec<String>,
+        sent_time: DateTime<Utc>,
+    },
+    Presence {
+        nicks: Vec<Nick>,
+        presence: Presence,
+        sent_time: DateTime<Utc>,
+    },
 }
 
 #[derive(Debug)]
@@ -76,6 +146,30 @@ pub enum Event {
     Broadcast(Broadcast),
     Notification(message::Encoded, Nick, Notification),
     FileTransferRequest(file_transfer::ReceiveRequest),
+    FileTransferResume(file_transfer::ResumeRequest),
+    DccChatRequest(dcc_chat::ReceiveRequest),
+    /// A message replayed from the server's history
+    ChatHistorySingle(message::Encoded, Nick),
+    /// We joined a channel, or a query had activity while we were away, &
+    /// can backfill it from the server's history
+    ChatHistoryRequest(history::Kind),
+    /// Authentication failed, with the reason
+    SaslFailed(String),
+    /// Messages in `buffer` sent up to the time were read, possibly by
+    /// another client
+    ReadMarker(Buffer, DateTime<Utc>),
+    /// The echo of a message we sent from this client, with its label
+    Echo(message::Encoded, Nick, String),
+    /// The message `msgid` sent to `target` was deleted
+    Redact {
+        target: message::Target,
+        msgid: String,
+        reason: Option<String>,
+    },
+    /// The bouncer listed a network or changed its attributes
+    BouncerNetwork(bouncer::Network),
+    /// The bouncer removed the network with the id
+    BouncerNetworkRemoved(String),
 }
 
 pub struct Client {
@@ -92,13 +186,39 @@ pub struct Client {
     reroute_responses_to: Option<Buffer>,
     registration_step: RegistrationStep,
     listed_caps: Vec<String>,
+    sasl: Option<sasl::Session>,
+    /// Mechanisms tried so far, so we don't retry them
+    sasl_tried: Vec<sasl::Mechanism>,
+    /// Mechanisms listed in RPL_SASLMECHS after requesting one the server doesn't support
+    sasl_mechanisms: Option<String>,
+    /// Why we aborted authenticating
+    sasl_error: Option<String>,
     supports_labels: bool,
     supports_away_notify: bool,
     supports_account_notify: bool,
     supports_extended_join: bool,
+    supports_chathistory: bool,
+    supports_message_tags: bool,
+    supports_redaction: bool,
+    supports_read_marker: bool,
+    supports_bouncer_networks: bool,
+    /// Networks listed by the bouncer, keyed by id
+    bouncer_networks: BTreeMap<String, bouncer::Network>,
+    chathistory_requests: HashMap<String, ChatHistoryRequest>,
+    chathistory_targets_requested: bool,
     highlight_blackout: HighlightBlackout,
     registration_required_channels: Vec<String>,
     isupport: HashMap<isupport::Kind, isupport::Parameter>,
+    presence_tracking: Option<PresenceTracking>,
+    /// Keyed by nick folded with the server's casemapping
+    presence: HashMap<String, Presence>,
+    ignores: Vec<ignore::Entry>,
+    highlights: config::Highlights,
+    typing: typing::Typing,
+    /// Our typing state in each channel & query
+    typing_sent: HashMap<String, typing::Outgoing>,
+    whois: whois::Cache,
+    whois_requested_at: Option<Instant>,
 }
 
 impl fmt::Debug for Client {
@@ -132,6 +252,12 @@ impl Client {
 
         Self {
             server,
+            ignores: config.ignore.clone(),
+            highlights: config::Highlights::default(),
+            typing: typing::Typing::default(),
+            typing_sent: HashMap::new(),
+            whois: whois::Cache::default(),
+            whois_requested_at: None,
             config,
             handle: sender,
             resolved_nick: None,
@@ -144,13 +270,27 @@ impl Client {
             reroute_responses_to: None,
             registration_step,
             listed_caps: vec![],
+            sasl: None,
+            sasl_tried: vec![],
+            sasl_mechanisms: None,
+            sasl_error: None,
             supports_labels: false,
             supports_away_notify: false,
             supports_account_notify: false,
             supports_extended_join: false,
+            supports_chathistory: false,
+            supports_message_tags: false,
+            supports_redaction: false,
+            supports_read_marker: false,
+            supports_bouncer_networks: false,
+            bouncer_networks: BTreeMap::new(),
+            chathistory_requests: HashMap::new(),
+            chathistory_targets_requested: false,
             highlight_blackout: HighlightBlackout::Blackout(Instant::now()),
             registration_required_channels: vec![],
             isupport: HashMap::new(),
+            presence_tracking: None,
+            presence: HashMap::new(),
         }
     }
 
@@ -164,6 +304,246 @@ impl Client {
         }
     }
 
+    /// Sends our typing state in `buffer` after its input changed
+    fn update_typing(&mut self, buffer: &Buffer, input: &str) {
+        let Some(target) = buffer.target() else {
+            return;
+        };
+
+        // Commands aren't sent as messages, so typing one isn't shown
+        let is_message = !input.is_empty() && !input.starts_with('/');
+
+        let state = self
+            .typing_sent
+            .entry(target.clone())
+            .or_default()
+            .input(!is_message, Instant::now());
+
+        if let Some(state) = state {
+            self.send_typing(target, state);
+        }
+    }
+
+    fn send_typing(&mut self, target: String, state: typing::State) {
+        if !self.supports_message_tags || !self.allows_client_tag("typing") {
+            return;
+        }
+
+        let mut message = command!("TAGMSG", target);
+        message.tags = vec![proto::Tag {
+            key: typing::TAG.to_string(),
+            value: Some(state.to_string()),
+        }];
+
+        let _ = self.handle.try_send(message);
+    }
+
+    /// Lets our other clients know messages in `buffer` were read up to
+    /// `read_at`
+    fn mark_read(&mut self, buffer: &Buffer, read_at: DateTime<Utc>) {
+        let Some(target) = buffer.target() else {
+            return;
+        };
+
+        if !self.supports_read_marker {
+            return;
+        }
+
+        let timestamp = read_at.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        let _ = self.handle.try_send(command!(
+            "MARKREAD",
+            target,
+            format!("timestamp={timestamp}")
+        ));
+    }
+
+    /// Ends capability negotiation, first binding to our bouncer network
+    fn end_registration(&mut self) {
+        self.registration_step = RegistrationStep::End;
+
+        if let Some(binding) = &self.config.bouncer {
+            let _ = self
+                .handle
+                .try_send(command!("BOUNCER", "BIND", &binding.netid));
+        }
+
+        let _ = self.handle.try_send(command!("CAP", "END"));
+    }
+
+    /// Asks the bouncer to add a network connecting to `host`
+    fn add_bouncer_network(&mut self, host: &str, name: Option<&str>) {
+        if !self.supports_bouncer_networks {
+            return;
+        }
+
+        let _ = self.handle.try_send(command!(
+            "BOUNCER",
+            "ADDNETWORK",
+            bouncer::new_network_attributes(host, name)
+        ));
+    }
+
+    fn remove_bouncer_network(&mut self, netid: &str) {
+        if !self.supports_bouncer_networks {
+            return;
+        }
+
+        let _ = self
+            .handle
+            .try_send(command!("BOUNCER", "DELNETWORK", netid));
+    }
+
+    /// Sends WHOIS for a hover card, unless we already have a reply for
+    /// `nick`
+    fn request_whois(&mut self, nick: NickRef<'_>) {
+        // Hovering many users mustn't flood the server
+        if self
+            .whois_requested_at
+            .is_some_and(|requested_at| requested_at.elapsed() < WHOIS_INTERVAL)
+        {
+            return;
+        }
+
+        if self.whois.request(nick, self.casemapping()) {
+            self.whois_requested_at = Some(Instant::now());
+
+            let _ = self.handle.try_send(command!("WHOIS", nick.as_ref()));
+        }
+    }
+
+    /// Deletes the message `msgid` in `buffer`
+    fn redact(&mut self, buffer: &Buffer, msgid: &str) {
+        let Some(target) = buffer.target() else {
+            return;
+        };
+
+        if !self.supports_redaction {
+            return;
+        }
+
+        let _ = self.handle.try_send(command!("REDACT", target, msgid));
+    }
+
+    /// Reacts to the message `msgid` in `buffer`, returning whether the
+    /// reaction was sent
+    fn react(&mut self, buffer: &Buffer, msgid: &str, reaction: &str) -> bool {
+        let Some(target) = buffer.target() else {
+            return false;
+        };
+
+        if !self.supports_message_tags
+            || !self.allows_client_tag("draft/reply")
+            || !self.allows_client_tag("draft/react")
+        {
+            return false;
+        }
+
+        let mut message = message::Encoded::from(command!("TAGMSG", target));
+        message.tags = vec![
+            proto::Tag {
+                key: message::REPLY_TAG.to_string(),
+                value: Some(msgid.to_string()),
+            },
+            proto::Tag {
+                key: message::REACT_TAG.to_string(),
+                value: Some(reaction.to_string()),
+            },
+        ];
+
+        self.send(buffer, message);
+
+        true
+    }
+
+    /// Whether the server relays the client-only tag `tag` (without `+`)
+    fn allows_client_tag(&self, tag: &str) -> bool {
+        let Some(isupport::Parameter::CLIENTTAGDENY(denials)) =
+            self.isupport.get(&isupport::Kind::CLIENTTAGDENY)
+        else {
+            return true;
+        };
+
+        let denied = denials.iter().any(
+            |denial| matches!(denial, isupport::ClientOnlyTags::Denied(denied) if denied == tag),
+        );
+        let deny_all = denials
+            .iter()
+            .any(|denial| matches!(denial, isupport::ClientOnlyTags::DenyAll));
+        let allowed = denials.iter().any(
+            |denial| matches!(denial, isupport::ClientOnlyTags::Allowed(allowed) if allowed == tag),
+        );
+
+        !denied && (!deny_all || allowed)
+    }
+
+    fn casemapping(&self) -> isupport::CaseMap {
+        match self.isupport.get(&isupport::Kind::CASEMAPPING) {
+            Some(isupport::Parameter::CASEMAPPING(casemapping)) => *casemapping,
+            _ => isupport::CaseMap::default(),
+        }
+    }
+
+    /// Starts authenticating with the first configured mechanism in
+    /// `supported` which hasn't been tried yet
+    fn start_sasl(&mut self, supported: Option<&str>) -> bool {
+        let Some(sasl) = self.config.sasl.as_ref() else {
+            return false;
+        };
+
+        let untried = sasl
+            .mechanisms()
+            .iter()
+            .copied()
+            .filter(|mechanism| !self.sasl_tried.contains(mechanism))
+            .collect::<Vec<_>>();
+
+        let Some(mechanism) = sasl::Mechanism::choose(&untried, supported) else {
+            return false;
+        };
+
+        log::info!("[{}] sasl: authenticating with {mechanism}", self.server);
+
+        self.sasl_tried.push(mechanism);
+        self.sasl = Some(sasl::Session::new(mechanism, sasl));
+        self.registration_step = RegistrationStep::Sasl;
+        let _ = self
+            .handle
+            .try_send(command!("AUTHENTICATE", mechanism.name()));
+
+        true
+    }
+
+    /// Ends authentication as configured by `sasl.on_failure`
+    fn sasl_failed(&mut self, reason: String) -> Vec<Event> {
+        let reason = format!("SASL authentication failed: {reason}");
+
+        log::warn!("[{}] {reason}", self.server);
+
+        self.sasl = None;
+        self.sasl_error = None;
+        self.sasl_mechanisms = None;
+
+        let on_failure = self
+            .config
+            .sasl
+            .as_ref()
+            .map(|sasl| sasl.on_failure)
+            .unwrap_or_default();
+
+        match on_failure {
+            config::server::SaslOnFailure::Continue => {
+                self.end_registration();
+            }
+            config::server::SaslOnFailure::Disconnect => {
+                // Quitting stops the connection from being retried until restart
+                self.quit(Some("SASL authentication failed".into()));
+            }
+        }
+
+        vec![Event::SaslFailed(reason)]
+    }
+
     fn join(&mut self, channels: &[String]) {
         let keys = HashMap::new();
 
@@ -176,8 +556,14 @@ impl Client {
         }
     }
 
-    fn send(&mut self, buffer: &Buffer, mut message: message::Encoded) {
-        if self.supports_labels {
+    /// Sends `message`, returning the label its echo will have
+    fn send(&mut self, buffer: &Buffer, mut message: message::Encoded) -> Option<String> {
+        // Sending a message ends typing, without sending `done`
+        if let Command::PRIVMSG(target, _) = &message.command {
+            self.typing_sent.remove(target);
+        }
+
+        let label = if self.supports_labels {
             use proto::Tag;
 
             let label = generate_label();
@@ -186,17 +572,23 @@ impl Client {
             self.labels.insert(label.clone(), context);
 
             // IRC: Encode tags
-            message.tags = vec![Tag {
+            message.tags.push(Tag {
                 key: "label".to_string(),
-                value: Some(label),
-            }];
-        }
+                value: Some(label.clone()),
+            });
+
+            Some(label)
+        } else {
+            None
+        };
 
         self.reroute_responses_to = start_reroute(&message.command).then(|| buffer.clone());
 
         if let Err(e) = self.handle.try_send(message.into()) {
             log::warn!("Error sending message: {e}");
         }
+
+        label
     }
 
     fn receive(&mut self, message: message::Encoded) -> Vec<Event> {
@@ -225,8 +617,9 @@ impl Client {
 
         let context = parent_context.or_else(|| {
             label_tag
+                .as_deref()
                 // Remove context associated to label if we get resp for it
-                .and_then(|label| self.labels.remove(&label))
+                .and_then(|label| self.labels.remove(label))
                 // Otherwise if we're in a batch, get it's context
                 .or_else(|| {
                     batch_tag.as_ref().and_then(|batch| {
@@ -237,19 +630,65 @@ impl Client {
                 })
         });
 
+        // Replies to WHOIS requested for a hover card aren't shown, unless
+        // WHOIS was also sent from a buffer
+        if let Command::Numeric(numeric, args) = &message.command {
+            let is_hover_reply = self.whois.update(*numeric, args, self.casemapping());
+
+            if is_hover_reply && !context.as_ref().is_some_and(Context::is_whois) {
+                return None;
+            }
+        }
+
+        // Keep accounts current with the `account` tag
+        if let Some(user) = message
+            .user()
+            .filter(|_| message.tag(message::ACCOUNT_TAG).is_some())
+        {
+            self.chanmap.values_mut().for_each(|channel| {
+                if channel
+                    .users
+                    .get(&user)
+                    .is_some_and(|known| known.accountname() != user.accountname())
+                {
+                    if let Some(known) = channel.users.take(&user) {
+                        channel
+                            .users
+                            .insert(known.with_accountname(user.accountname().unwrap_or("*")));
+                    }
+                }
+            });
+        }
+
         match &message.command {
-            Command::BATCH(batch, ..) => {
+            Command::BATCH(batch, params) => {
                 let mut chars = batch.chars();
                 let symbol = chars.next()?;
                 let reference = chars.collect::<String>();
 
                 match symbol {
                     '+' => {
-                        let batch = Batch::new(context);
+                        let mut batch = Batch::new(context);
+
+                        match params.first().map(String::as_str) {
+                            Some("chathistory") => {
+                                batch.chathistory =
+                                    params.get(1).cloned().map(ChatHistoryBatch::new);
+                            }
+                            Some("draft/chathistory-targets") => {
+                                batch.chathistory_targets = true;
+                            }
+                            _ => {}
+                        }
+
                         self.batches.insert(reference, batch);
                     }
                     '-' => {
                         if let Some(finished) = self.batches.remove(&reference) {
+                            if let Some(chathistory) = &finished.chathistory {
+                                self.continue_chathistory(chathistory);
+                            }
+
                             // If nested, extend events into parent batch
                             if let Some(parent) = batch_tag
                                 .as_ref()
@@ -267,9 +706,23 @@ impl Client {
                 return None;
             }
             _ if batch_tag.is_some() => {
-                let events = self.handle(message, context)?;
+                let batch_tag = batch_tag.unwrap();
+
+                let batch = self.batches.get(&batch_tag);
+
+                let events = if batch.is_some_and(|batch| batch.chathistory.is_some()) {
+                    self.handle_chathistory(&batch_tag, message)
+                        .into_iter()
+                        .collect()
+                } else if batch.is_some_and(|batch| batch.chathistory_targets) {
+                    self.handle_chathistory_target(&message)
+                        .into_iter()
+                        .collect()
+                } else {
+                    self.handle(message, context)?
+                };
 
-                if let Some(batch) = self.batches.get_mut(&batch_tag.unwrap()) {
+                if let Some(batch) = self.batches.get_mut(&batch_tag) {
                     batch.events.extend(events);
                     return None;
                 } else {
@@ -337,6 +790,20 @@ impl Client {
                     if contains("chghost") {
                         requested.push("chghost");
                     }
+                    if contains("account-tag") {
+                        requested.push("account-tag");
+                    }
+                    if contains("setname") {
+                        requested.push("setname");
+                    }
+                    if contains(bouncer::CAP) {
+                        requested.push(bouncer::CAP);
+
+                        // Only the connection to the bouncer itself tracks its networks
+                        if self.config.bouncer.is_none() && contains(bouncer::NOTIFY_CAP) {
+                            requested.push(bouncer::NOTIFY_CAP);
+                        }
+                    }
                     if contains("account-notify") {
                         requested.push("account-notify");
 
@@ -346,6 +813,11 @@ impl Client {
                     }
                     if contains("batch") {
                         requested.push("batch");
+
+                        // We require batch so we can properly handle replayed history
+                        if contains("draft/chathistory") {
+                            requested.push("draft/chathistory");
+                        }
                     }
                     if contains("labeled-response") {
                         requested.push("labeled-response");
@@ -361,6 +833,12 @@ impl Client {
                     if contains("multi-prefix") {
                         requested.push("multi-prefix");
                     }
+                    if contains("draft/message-redaction") {
+                        requested.push("draft/message-redaction");
+                    }
+                    if contains("draft/read-marker") {
+                        requested.push("draft/read-marker");
+                    }
 
                     if !requested.is_empty() {
                         // Request
@@ -368,10 +846,11 @@ impl Client {
                         let _ = self
                             .handle
                             .try_send(command!("CAP", "REQ", requested.join(" ")));
+                    } else if self.config.sasl.is_some() {
+                        return Some(self.sasl_failed("server doesn't support SASL".into()));
                     } else {
                         // If none requested, end negotiation
-                        self.registration_step = RegistrationStep::End;
-                        let _ = self.handle.try_send(command!("CAP", "END"));
+                        self.end_registration();
                     }
                 }
             }
@@ -393,17 +872,45 @@ impl Client {
                 if caps.contains(&"extended-join") {
                     self.supports_extended_join = true;
                 }
+                if caps.contains(&"draft/chathistory") {
+                    self.supports_chathistory = true;
+                }
+                if caps.contains(&"message-tags") {
+                    self.supports_message_tags = true;
+                }
+                if caps.contains(&"draft/message-redaction") {
+                    self.supports_redaction = true;
+                }
+                if caps.contains(&"draft/read-marker") {
+                    self.supports_read_marker = true;
+                }
+                if caps.contains(&bouncer::CAP) {
+                    self.supports_bouncer_networks = true;
+                }
 
                 let supports_sasl = caps.iter().any(|cap| cap.contains("sasl"));
 
-                if let Some(sasl) = self.config.sasl.as_ref().filter(|_| supports_sasl) {
-                    self.registration_step = RegistrationStep::Sasl;
-                    let _ = self
-                        .handle
-                        .try_send(command!("AUTHENTICATE", sasl.command()));
-                } else {
-                    self.registration_step = RegistrationStep::End;
-                    let _ = self.handle.try_send(command!("CAP", "END"));
+                // Later acknowledgements are for capabilities requested after CAP NEW
+                if self.registration_step == RegistrationStep::Req {
+                    if self.config.sasl.is_none() {
+                        self.end_registration();
+                    } else if !supports_sasl {
+                        return Some(self.sasl_failed("server doesn't support SASL".into()));
+                    } else {
+                        // Mechanisms the server supports, if it listed them
+                        let mechanisms = self
+                            .listed_caps
+                            .iter()
+                            .find_map(|cap| cap.strip_prefix("sasl="))
+                            .map(String::from);
+
+                        if !self.start_sasl(mechanisms.as_deref()) {
+                            return Some(self.sasl_failed(format!(
+                                "server doesn't support any configured mechanism (supports {})",
+                                mechanisms.unwrap_or_default()
+                            )));
+                        }
+                    }
                 }
             }
             Command::CAP(_, sub, a, b) if sub == "NAK" => {
@@ -412,8 +919,14 @@ impl Client {
 
                 // End we didn't move to sasl or already ended
                 if self.registration_step < RegistrationStep::Sasl {
-                    self.registration_step = RegistrationStep::End;
-                    let _ = self.handle.try_send(command!("CAP", "END"));
+                    // The whole request is rejected, including sasl
+                    if self.config.sasl.is_some() {
+                        return Some(
+                            self.sasl_failed(format!("capabilities not acknowledged: {caps}")),
+                        );
+                    }
+
+                    self.end_registration();
                 }
             }
             Command::CAP(_, sub, a, b) if sub == "NEW" => {
@@ -445,6 +958,18 @@ impl Client {
                 if newly_contains("chghost") {
                     requested.push("chghost");
                 }
+                if newly_contains("account-tag") {
+                    requested.push("account-tag");
+                }
+                if newly_contains("setname") {
+                    requested.push("setname");
+                }
+                if newly_contains(bouncer::CAP) {
+                    requested.push(bouncer::CAP);
+                }
+                if self.config.bouncer.is_none() && newly_contains(bouncer::NOTIFY_CAP) {
+                    requested.push(bouncer::NOTIFY_CAP);
+                }
                 if contains("account-notify") || newly_contains("account-notify") {
                     if newly_contains("account-notify") {
                         requested.push("account-notify");
@@ -454,8 +979,15 @@ impl Client {
                         requested.push("extended-join");
                     }
                 }
-                if newly_contains("batch") {
-                    requested.push("batch");
+                if contains("batch") || newly_contains("batch") {
+                    if newly_contains("batch") {
+                        requested.push("batch");
+                    }
+
+                    // We require batch so we can properly handle replayed history
+                    if newly_contains("draft/chathistory") {
+                        requested.push("draft/chathistory");
+                    }
                 }
                 if contains("labeled-response") || newly_contains("labeled-response") {
                     if newly_contains("labeled-response") {
@@ -470,6 +1002,12 @@ impl Client {
                 if newly_contains("multi-prefix") {
                     requested.push("multi-prefix");
                 }
+                if newly_contains("draft/message-redaction") {
+                    requested.push("draft/message-redaction");
+                }
+                if newly_contains("draft/read-marker") {
+                    requested.push("draft/read-marker");
+                }
 
                 if !requested.is_empty() {
                     // Request
@@ -497,18 +1035,86 @@ impl Client {
                 if del_caps.contains(&"extended-join") {
                     self.supports_extended_join = false;
                 }
+                if del_caps.contains(&"draft/chathistory") {
+                    self.supports_chathistory = false;
+                }
+                if del_caps.contains(&"message-tags") {
+                    self.supports_message_tags = false;
+                }
+                if del_caps.contains(&"draft/message-redaction") {
+                    self.supports_redaction = false;
+                }
+                if del_caps.contains(&"draft/read-marker") {
+                    self.supports_read_marker = false;
+                }
+                if del_caps.contains(&bouncer::CAP) {
+                    self.supports_bouncer_networks = false;
+                }
 
                 self.listed_caps
                     .retain(|cap| !del_caps.iter().any(|del_cap| del_cap == cap));
             }
-            Command::AUTHENTICATE(param) if param == "+" => {
-                if let Some(sasl) = self.config.sasl.as_ref() {
-                    log::info!("[{}] sasl auth: {}", self.server, sasl.command());
+            Command::AUTHENTICATE(param) => {
+                if let Some(session) = self.sasl.as_mut() {
+                    log::info!("[{}] sasl auth: {}", self.server, session.mechanism());
+
+                    match session.receive(param) {
+                        Ok(params) => {
+                            for param in params {
+                                let _ = self.handle.try_send(command!("AUTHENTICATE", param));
+                            }
+                        }
+                        Err(error) => {
+                            log::warn!("[{}] sasl auth failed: {error}", self.server);
+
+                            self.sasl_error = Some(error.to_string());
+                            // Server replies with ERR_SASLABORTED
+                            let _ = self.handle.try_send(command!("AUTHENTICATE", "*"));
+                        }
+                    }
+                }
+            }
+            Command::Numeric(RPL_SASLMECHS, args) if self.sasl.is_some() => {
+                // Followed by ERR_SASLFAIL, where we retry with one of these
+                self.sasl_mechanisms = args.get(1).cloned();
 
-                    let _ = self.handle.try_send(command!("AUTHENTICATE", sasl.param()));
-                    self.registration_step = RegistrationStep::End;
-                    let _ = self.handle.try_send(command!("CAP", "END"));
+                return None;
+            }
+            Command::Numeric(RPL_SASLSUCCESS, _) if self.sasl.is_some() => {
+                let session = self.sasl.take()?;
+
+                if !session.is_complete() {
+                    return Some(self.sasl_failed(format!(
+                        "{}: server reported success without proving it knows the password",
+                        session.mechanism()
+                    )));
+                }
+
+                self.end_registration();
+            }
+            Command::Numeric(ERR_SASLALREADY, _) if self.sasl.is_some() => {
+                self.sasl = None;
+                self.end_registration();
+            }
+            Command::Numeric(
+                ERR_NICKLOCKED | ERR_SASLFAIL | ERR_SASLTOOLONG | ERR_SASLABORTED,
+                args,
+            ) if self.sasl.is_some() => {
+                let mechanism = self.sasl.take().map(|session| session.mechanism())?;
+
+                if let Some(mechanisms) = self.sasl_mechanisms.take() {
+                    if self.start_sasl(Some(&mechanisms)) {
+                        return None;
+                    }
                 }
+
+                let reason = self
+                    .sasl_error
+                    .take()
+                    .or_else(|| args.last().cloned())
+                    .unwrap_or_default();
+
+                return Some(self.sasl_failed(format!("{mechanism}: {reason}")));
             }
             Command::Numeric(RPL_LOGGEDIN, args) => {
                 log::info!("[{}] logged in", self.server);
@@ -551,6 +1157,35 @@ impl Client {
             }
             Command::PRIVMSG(channel, text) | Command::NOTICE(channel, text) => {
                 if let Some(user) = message.user() {
+                    // Sending a message ends typing
+                    let typing_target = if proto::is_channel(channel) {
+                        channel.clone()
+                    } else {
+                        user.nickname().to_string()
+                    };
+                    self.typing
+                        .remove(&typing_target, user.nickname(), self.casemapping());
+
+                    let is_ctcp = ctcp::is_query(text) && !message::is_action(text);
+
+                    if is_ctcp && self.is_ignored(&user, ignore::Scope::Ctcp) {
+                        return None;
+                    }
+
+                    let scope = if matches!(&message.command, Command::PRIVMSG(_, _)) {
+                        ignore::Scope::Messages
+                    } else {
+                        ignore::Scope::Notices
+                    };
+                    let ignored = !is_ctcp && self.is_ignored(&user, scope);
+
+                    // Only messages are kept collapsed, other scopes are hidden
+                    if ignored
+                        && !(self.config.collapse_ignored && scope == ignore::Scope::Messages)
+                    {
+                        return None;
+                    }
+
                     if let Some(command) = dcc::decode(text) {
                         match command {
                             dcc::Command::Send(request) => {
@@ -558,12 +1193,54 @@ impl Client {
                                 return Some(vec![Event::FileTransferRequest(
                                     file_transfer::ReceiveRequest {
                                         from: user.nickname().to_owned(),
+                                        account: user
+                                            .accountname()
+                                            .or_else(|| {
+                                                self.chanmap.values().find_map(|channel| {
+                                                    channel.users.get(&user)?.accountname()
+                                                })
+                                            })
+                                            .map(ToString::to_string),
+                                        casemapping: self.casemapping(),
                                         dcc_send: request,
                                         server: self.server.clone(),
                                         server_handle: self.handle.clone(),
                                     },
                                 )]);
                             }
+                            dcc::Command::Chat(request) => {
+                                log::trace!("DCC Chat => {request:?}");
+                                return Some(vec![Event::DccChatRequest(
+                                    dcc_chat::ReceiveRequest {
+                                        from: user.nickname().to_owned(),
+                                        nick: self.nickname().to_owned(),
+                                        dcc_chat: request,
+                                        server: self.server.clone(),
+                                    },
+                                )]);
+                            }
+                            dcc::Command::Resume(resume) => {
+                                log::trace!("DCC Resume => {resume:?}");
+                                return Some(vec![Event::FileTransferResume(
+                                    file_transfer::ResumeRequest {
+                                        from: user.nickname().to_owned(),
+                                        resume,
+                                        accepted: false,
+                                        server: self.server.clone(),
+                                    },
+                                )]);
+                            }
+                            dcc::Command::Accept(resume) => {
+                                log::trace!("DCC Accept => {resume:?}");
+                                return Some(vec![Event::FileTransferResume(
+                                    file_transfer::ResumeRequest {
+                                        from: user.nickname().to_owned(),
+                                        resume,
+                                        accepted: true,
+                                        server: self.server.clone(),
+                                    },
+                                )]);
+                            }
                             dcc::Command::Unsupported(command) => {
                                 log::debug!("Unsupported DCC command: {command}",);
                                 return None;
@@ -623,9 +1300,18 @@ impl Client {
                             }
                         }
 
+                        let highlight_channel =
+                            proto::is_channel(channel).then_some(channel.as_str());
+
                         // Highlight notification
-                        if message::reference_user_text(user.nickname(), self.nickname(), text)
-                            && self.highlight_blackout.allow_highlights()
+                        if self.highlights.matches(
+                            user.nickname(),
+                            self.nickname(),
+                            highlight_channel,
+                            text,
+                        ) && self.highlight_blackout.allow_highlights()
+                            && !ignored
+                            && !self.is_ignored(&user, ignore::Scope::Highlights)
                         {
                             return Some(vec![Event::Notification(
                                 message.clone(),
@@ -633,8 +1319,17 @@ impl Client {
                                 Notification::Highlight(user, channel.clone()),
                             )]);
                         } else if user.nickname() == self.nickname() && context.is_some() {
-                            // If we sent (echo) & context exists (we sent from this client), ignore
-                            return None;
+                            // If we sent (echo) & context exists (we sent from this client),
+                            // only keep the msgid the server gave it
+                            return label_tag.filter(|_| message.tag("msgid").is_some()).map(
+                                |label| {
+                                    vec![Event::Echo(
+                                        message.clone(),
+                                        self.nickname().to_owned(),
+                                        label,
+                                    )]
+                                },
+                            );
                         }
                     }
                 }
@@ -661,6 +1356,8 @@ impl Client {
 
                 let new_nick = Nick::from(nick.as_str());
 
+                self.whois.remove(old_user.nickname(), self.casemapping());
+
                 self.chanmap.values_mut().for_each(|channel| {
                     if let Some(user) = channel.users.take(&old_user) {
                         channel.users.insert(user.with_nickname(new_nick.clone()));
@@ -768,15 +1465,45 @@ impl Client {
                 for message in group_joins(&self.config.channels, &self.config.channel_keys) {
                     let _ = self.handle.try_send(message);
                 }
+
+                // List the bouncer's networks, each connected to separately
+                if self.supports_bouncer_networks && self.config.bouncer.is_none() {
+                    let _ = self.handle.try_send(command!("BOUNCER", "LISTNETWORKS"));
+                }
+            }
+            Command::BOUNCER(subcommand, args)
+                if subcommand == "NETWORK" && self.config.bouncer.is_none() =>
+            {
+                let netid = args.first()?;
+                let attributes = args.get(1)?;
+
+                if attributes == "*" {
+                    self.bouncer_networks.remove(netid)?;
+
+                    return Some(vec![Event::BouncerNetworkRemoved(netid.clone())]);
+                }
+
+                let network = self
+                    .bouncer_networks
+                    .entry(netid.clone())
+                    .or_insert_with(|| bouncer::Network::new(netid.clone()));
+                network.update(attributes);
+
+                return Some(vec![Event::BouncerNetwork(network.clone())]);
             }
             // QUIT
             Command::QUIT(comment) => {
                 let user = message.user()?;
+                let ignored = self.is_ignored(&user, ignore::Scope::JoinsParts);
 
                 self.chanmap.values_mut().for_each(|channel| {
                     channel.users.remove(&user);
                 });
 
+                if ignored {
+                    return None;
+                }
+
                 let channels = self.user_channels(user.nickname());
 
                 return Some(vec![Event::Broadcast(Broadcast::Quit {
@@ -791,8 +1518,16 @@ impl Client {
 
                 if user.nickname() == self.nickname() {
                     self.chanmap.remove(channel);
-                } else if let Some(channel) = self.chanmap.get_mut(channel) {
-                    channel.users.remove(&user);
+                } else {
+                    let ignored = self.is_ignored(&user, ignore::Scope::JoinsParts);
+
+                    if let Some(channel) = self.chanmap.get_mut(channel) {
+                        channel.users.remove(&user);
+                    }
+
+                    if ignored {
+                        return None;
+                    }
                 }
             }
             Command::JOIN(channel, accountname) => {
@@ -827,6 +1562,15 @@ impl Client {
                         }
                         log::debug!("[{}] {channel} - WHO requested", self.server);
                     }
+
+                    if self.supports_chathistory {
+                        let channel = channel.clone();
+
+                        return Some(vec![
+                            Event::Single(message, self.nickname().to_owned()),
+                            Event::ChatHistoryRequest(history::Kind::Channel(channel)),
+                        ]);
+                    }
                 } else if let Some(channel) = self.chanmap.get_mut(channel) {
                     let user = if self.supports_extended_join {
                         accountname.as_ref().map_or(user.clone(), |accountname| {
@@ -836,7 +1580,16 @@ impl Client {
                         user
                     };
 
+                    let ignored = self
+                        .ignores
+                        .iter()
+                        .any(|entry| entry.matches(&user, ignore::Scope::JoinsParts));
+
                     channel.users.insert(user);
+
+                    if ignored {
+                        return None;
+                    }
                 }
             }
             Command::KICK(channel, victim, _) => {
@@ -916,6 +1669,8 @@ impl Client {
                 let away = args.is_some();
                 let user = message.user()?;
 
+                self.whois.remove(user.nickname(), self.casemapping());
+
                 for channel in self.chanmap.values_mut() {
                     if let Some(mut user) = channel.users.take(&user) {
                         user.update_away(away);
@@ -1095,14 +1850,171 @@ impl Client {
 
                 return None;
             }
-            Command::TAGMSG(_) => {
-                return None;
+            // ISUPPORT is known by the end of the MOTD, so we can pick
+            // between MONITOR & ISON
+            Command::Numeric(RPL_ENDOFMOTD | ERR_NOMOTD, _) => {
+                if self.presence_tracking.is_none() {
+                    self.start_presence_tracking();
+                }
+
+                // Channels are backfilled as they're joined, queries need
+                // to be discovered
+                if !self.chathistory_targets_requested {
+                    self.send_chathistory_targets_request();
+                }
             }
-            Command::ACCOUNT(accountname) => {
-                let old_user = message.user()?;
+            Command::Numeric(RPL_MONONLINE, args) => {
+                let nicks = args
+                    .get(1)?
+                    .split(',')
+                    .filter_map(|target| User::try_from(target).ok())
+                    .map(|user| user.nickname().to_owned())
+                    .collect::<Vec<_>>();
+
+                return Some(
+                    self.update_presence(nicks, Presence::Online, server_time(&message))
+                        .into_iter()
+                        .collect(),
+                );
+            }
+            Command::Numeric(RPL_MONOFFLINE, args) => {
+                let nicks = args.get(1)?.split(',').map(Nick::from).collect::<Vec<_>>();
 
-                self.chanmap.values_mut().for_each(|channel| {
-                    if let Some(user) = channel.users.take(&old_user) {
+                return Some(
+                    self.update_presence(nicks, Presence::Offline, server_time(&message))
+                        .into_iter()
+                        .collect(),
+                );
+            }
+            Command::Numeric(RPL_ISON, args)
+                if matches!(
+                    self.presence_tracking,
+                    Some(PresenceTracking::Ison(IsonStatus::Requested(..)))
+                ) =>
+            {
+                let Some(PresenceTracking::Ison(IsonStatus::Requested(requested, replies))) =
+                    self.presence_tracking
+                else {
+                    return None;
+                };
+
+                // Replies arrive in the order the chunks were sent
+                let chunks = ison_chunks(&self.config.monitor);
+                let nicks = chunks.get(replies).copied().unwrap_or_default();
+
+                self.presence_tracking =
+                    Some(PresenceTracking::Ison(if replies + 1 < chunks.len() {
+                        IsonStatus::Requested(requested, replies + 1)
+                    } else {
+                        IsonStatus::Done(Instant::now())
+                    }));
+
+                let online = args
+                    .get(1)
+                    .map(|nicks| nicks.split_whitespace().map(Nick::from).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                let offline = nicks
+                    .iter()
+                    .filter(|nick| {
+                        !online
+                            .iter()
+                            .any(|online| online.as_ref().eq_ignore_ascii_case(nick))
+                    })
+                    .map(|nick| Nick::from(nick.as_str()))
+                    .collect::<Vec<_>>();
+
+                let sent_time = server_time(&message);
+
+                return Some(
+                    self.update_presence(online, Presence::Online, sent_time)
+                        .into_iter()
+                        .chain(self.update_presence(offline, Presence::Offline, sent_time))
+                        .collect(),
+                );
+            }
+            Command::TAGMSG(target) => {
+                let user = message.user()?;
+                let ourself = user.nickname() == self.nickname();
+
+                if self.is_ignored(&user, ignore::Scope::Messages) {
+                    return None;
+                }
+
+                let state = message
+                    .tag(typing::TAG)
+                    .and_then(|state| state.parse::<typing::State>().ok());
+
+                if let Some(state) = state.filter(|_| !ourself) {
+                    let target = if proto::is_channel(target) {
+                        target.clone()
+                    } else {
+                        user.nickname().to_string()
+                    };
+
+                    self.typing.update(
+                        &target,
+                        user.nickname().to_owned(),
+                        state,
+                        Instant::now(),
+                        self.casemapping(),
+                    );
+                }
+
+                // Reactions are kept in history, unless we sent it from this client
+                if !message.is_reaction() || (ourself && context.is_some()) {
+                    return None;
+                }
+            }
+            Command::MARKREAD(target, Some(timestamp)) => {
+                // `*` when nothing has been read yet
+                let read_at = timestamp
+                    .strip_prefix("timestamp=")
+                    .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())?
+                    .with_timezone(&Utc);
+
+                let buffer = if proto::is_channel(target) {
+                    Buffer::Channel(self.server.clone(), target.clone())
+                } else {
+                    Buffer::Query(self.server.clone(), Nick::from(target.as_str()))
+                };
+
+                return Some(vec![Event::ReadMarker(buffer, read_at)]);
+            }
+            Command::REDACT(target, msgid, reason) => {
+                let user = message.user()?;
+
+                let target = if proto::is_channel(target) {
+                    message::Target::Channel {
+                        channel: target.clone(),
+                        source: message::Source::User(user),
+                    }
+                } else {
+                    // A message in our query with `user`, or one we sent to `target`
+                    let nick = if user.nickname() == self.nickname() {
+                        Nick::from(target.as_str())
+                    } else {
+                        user.nickname().to_owned()
+                    };
+
+                    message::Target::Query {
+                        nick,
+                        source: message::Source::User(user),
+                    }
+                };
+
+                return Some(vec![Event::Redact {
+                    target,
+                    msgid: msgid.clone(),
+                    reason: reason.clone(),
+                }]);
+            }
+            Command::ACCOUNT(accountname) => {
+                let old_user = message.user()?;
+
+                self.whois.remove(old_user.nickname(), self.casemapping());
+
+                self.chanmap.values_mut().for_each(|channel| {
+                    if let Some(user) = channel.users.take(&old_user) {
                         channel.users.insert(user.with_accountname(accountname));
                     }
                 });
@@ -1146,12 +2058,252 @@ impl Client {
                     sent_time: server_time(&message),
                 })]);
             }
+            Command::SETNAME(new_realname) => {
+                let old_user = message.user()?;
+
+                self.whois.remove(old_user.nickname(), self.casemapping());
+
+                let ourself = old_user.nickname() == self.nickname();
+
+                self.chanmap.values_mut().for_each(|channel| {
+                    if let Some(user) = channel.users.take(&old_user) {
+                        channel
+                            .users
+                            .insert(user.with_realname(new_realname.clone()));
+                    }
+                });
+
+                let channels = self.user_channels(old_user.nickname());
+
+                return Some(vec![Event::Broadcast(Broadcast::ChangeRealname {
+                    old_user,
+                    new_realname: new_realname.clone(),
+                    ourself,
+                    channels,
+                    sent_time: server_time(&message),
+                })]);
+            }
             _ => {}
         }
 
         Some(vec![Event::Single(message, self.nickname().to_owned())])
     }
 
+    fn start_presence_tracking(&mut self) {
+        if self.config.monitor.is_empty() {
+            return;
+        }
+
+        if let Some(isupport::Parameter::MONITOR(limit)) =
+            self.isupport.get(&isupport::Kind::MONITOR)
+        {
+            let targets = if let Some(limit) = limit.map(usize::from) {
+                if self.config.monitor.len() > limit {
+                    log::warn!(
+                        "[{}] monitor list exceeds server limit of {limit} nicknames",
+                        self.server
+                    );
+                }
+
+                &self.config.monitor[..limit.min(self.config.monitor.len())]
+            } else {
+                &self.config.monitor[..]
+            };
+
+            for message in group_monitors(targets) {
+                let _ = self.handle.try_send(message);
+            }
+
+            self.presence_tracking = Some(PresenceTracking::Monitor);
+        } else {
+            self.send_ison();
+        }
+    }
+
+    fn send_ison(&mut self) {
+        for nicks in ison_chunks(&self.config.monitor) {
+            let _ = self.handle.try_send(command!("ISON", nicks.join(" ")));
+        }
+
+        self.presence_tracking = Some(PresenceTracking::Ison(IsonStatus::Requested(
+            Instant::now(),
+            0,
+        )));
+    }
+
+    /// Record the presence of `nicks`, returning a broadcast for those that changed.
+    /// Users going from unknown to offline aren't considered a change.
+    fn update_presence(
+        &mut self,
+        nicks: Vec<Nick>,
+        presence: Presence,
+        sent_time: DateTime<Utc>,
+    ) -> Option<Event> {
+        let casemapping = self.casemapping();
+
+        let nicks = nicks
+            .into_iter()
+            .filter(|nick| {
+                let previous = self
+                    .presence
+                    .insert(casemapping.normalize(nick.as_ref()), presence);
+
+                match previous {
+                    Some(previous) => previous != presence,
+                    None => presence == Presence::Online,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        (!nicks.is_empty()).then_some(Event::Broadcast(Broadcast::Presence {
+            nicks,
+            presence,
+            sent_time,
+        }))
+    }
+
+    fn handle_chathistory(&mut self, batch: &str, message: message::Encoded) -> Option<Event> {
+        if let Some(chathistory) = self
+            .batches
+            .get_mut(batch)
+            .and_then(|batch| batch.chathistory.as_mut())
+        {
+            chathistory.received(server_time(&message));
+        }
+
+        match &message.command {
+            Command::PRIVMSG(_, text) | Command::NOTICE(_, text) => {
+                // Replayed DCC requests & CTCP queries shouldn't be acted upon again
+                if dcc::decode(text).is_some()
+                    || (ctcp::is_query(text) && !message::is_action(text))
+                {
+                    return None;
+                }
+
+                let scope = if matches!(&message.command, Command::PRIVMSG(_, _)) {
+                    ignore::Scope::Messages
+                } else {
+                    ignore::Scope::Notices
+                };
+
+                if !(self.config.collapse_ignored && scope == ignore::Scope::Messages)
+                    && message
+                        .user()
+                        .is_some_and(|user| self.is_ignored(&user, scope))
+                {
+                    return None;
+                }
+
+                Some(Event::ChatHistorySingle(
+                    message,
+                    self.nickname().to_owned(),
+                ))
+            }
+            Command::TAGMSG(_) if message.is_reaction() => Some(Event::ChatHistorySingle(
+                message,
+                self.nickname().to_owned(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn send_chathistory_request(&mut self, target: &str, after: Option<DateTime<Utc>>) {
+        if !self.supports_chathistory {
+            return;
+        }
+
+        let limit = match self.isupport.get(&isupport::Kind::CHATHISTORY) {
+            // 0 indicates there is no limit
+            Some(isupport::Parameter::CHATHISTORY(max)) if *max > 0 => {
+                (*max).min(CHATHISTORY_REQUEST_LIMIT)
+            }
+            _ => CHATHISTORY_REQUEST_LIMIT,
+        };
+
+        let command = if let Some(after) = after {
+            let timestamp = after.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+            command!(
+                "CHATHISTORY",
+                "AFTER",
+                target,
+                format!("timestamp={timestamp}"),
+                limit.to_string()
+            )
+        } else {
+            command!("CHATHISTORY", "LATEST", target, "*", limit.to_string())
+        };
+
+        log::debug!(
+            "[{}] {target} - CHATHISTORY requested {}",
+            self.server,
+            after.map_or("latest".to_string(), |after| format!("after {after}"))
+        );
+
+        self.chathistory_requests.insert(
+            target.to_string(),
+            ChatHistoryRequest {
+                after: after.is_some(),
+                limit,
+            },
+        );
+
+        let _ = self.handle.try_send(command);
+    }
+
+    /// Asks for the most recently active targets, from now back to the
+    /// start of the server's history
+    fn send_chathistory_targets_request(&mut self) {
+        if !self.supports_chathistory {
+            return;
+        }
+
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let start = DateTime::<Utc>::UNIX_EPOCH.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        let _ = self.handle.try_send(command!(
+            "CHATHISTORY",
+            "TARGETS",
+            format!("timestamp={now}"),
+            format!("timestamp={start}"),
+            CHATHISTORY_REQUEST_LIMIT.to_string()
+        ));
+
+        log::debug!("[{}] CHATHISTORY targets requested", self.server);
+
+        self.chathistory_targets_requested = true;
+    }
+
+    /// Backfills a query listed in a `draft/chathistory-targets` batch
+    fn handle_chathistory_target(&self, message: &message::Encoded) -> Option<Event> {
+        let Command::Unknown(command, params) = &message.command else {
+            return None;
+        };
+
+        if command != "CHATHISTORY" || params.first().map(String::as_str) != Some("TARGETS") {
+            return None;
+        }
+
+        let target = params.get(1)?;
+
+        (!proto::is_channel(target) && NickRef::from(target.as_str()) != self.nickname())
+            .then(|| Event::ChatHistoryRequest(history::Kind::Query(Nick::from(target.as_str()))))
+    }
+
+    fn continue_chathistory(&mut self, batch: &ChatHistoryBatch) {
+        let Some(request) = self.chathistory_requests.remove(&batch.target) else {
+            return;
+        };
+
+        // A full page means there may be more messages we missed, keep
+        // requesting until we've caught up
+        if request.after && batch.count >= usize::from(request.limit) {
+            if let Some(latest) = batch.latest {
+                self.send_chathistory_request(&batch.target, Some(latest));
+            }
+        }
+    }
+
     fn sync(&mut self) {
         self.channels = self.chanmap.keys().cloned().collect();
         self.users = self
@@ -1199,6 +2351,40 @@ impl Client {
             .collect()
     }
 
+    /// Whether `scope` is ignored from `user`. The account & host of users
+    /// we share a channel with are used if missing from `user`.
+    pub fn is_ignored(&self, user: &User, scope: ignore::Scope) -> bool {
+        if self.ignores.is_empty() {
+            return false;
+        }
+
+        let known = self
+            .chanmap
+            .values()
+            .find_map(|channel| channel.users.get(user));
+
+        let user = match known {
+            Some(known) => {
+                let mut resolved = user.clone();
+
+                if let (None, Some(username), Some(hostname)) =
+                    (resolved.hostname(), known.username(), known.hostname())
+                {
+                    resolved = resolved
+                        .with_username_and_hostname(username.to_string(), hostname.to_string());
+                }
+
+                match (resolved.accountname(), known.accountname()) {
+                    (None, Some(accountname)) => resolved.with_accountname(accountname),
+                    _ => resolved,
+                }
+            }
+            None => user.clone(),
+        };
+
+        self.ignores.iter().any(|entry| entry.matches(&user, scope))
+    }
+
     pub fn nickname(&self) -> NickRef {
         // TODO: Fallback nicks
         NickRef::from(
@@ -1208,7 +2394,25 @@ impl Client {
         )
     }
 
+    pub fn presence(&self, nick: NickRef) -> Option<Presence> {
+        self.presence
+            .get(&self.casemapping().normalize(nick.as_ref()))
+            .copied()
+    }
+
     pub fn tick(&mut self, now: Instant) {
+        self.typing.tick(now);
+
+        let paused = self
+            .typing_sent
+            .iter_mut()
+            .filter_map(|(target, outgoing)| Some((target.clone(), outgoing.tick(now)?)))
+            .collect::<Vec<_>>();
+
+        for (target, state) in paused {
+            self.send_typing(target, state);
+        }
+
         match self.highlight_blackout {
             HighlightBlackout::Blackout(instant) => {
                 if now.duration_since(instant) >= HIGHLIGHT_BLACKOUT_INTERVAL {
@@ -1268,6 +2472,18 @@ impl Client {
                 );
             }
         }
+
+        if let Some(PresenceTracking::Ison(status)) = self.presence_tracking {
+            let last = match status {
+                IsonStatus::Requested(requested, _) => requested,
+                IsonStatus::Done(done) => done,
+            };
+
+            if now.duration_since(last) >= self.config.ison_poll_interval {
+                self.send_ison();
+                log::debug!("[{}] ISON poll", self.server);
+            }
+        }
     }
 }
 
@@ -1287,30 +2503,52 @@ impl HighlightBlackout {
 }
 
 #[derive(Debug, Default)]
-pub struct Map(BTreeMap<Server, State>);
+pub struct Map {
+    clients: BTreeMap<Server, State>,
+    /// Changes made with `/ignore` & `/unignore`, kept across reconnects
+    ignores: HashMap<Server, IgnoreChanges>,
+    highlights: config::Highlights,
+}
 
 impl Map {
     pub fn disconnected(&mut self, server: Server) {
-        self.0.insert(server, State::Disconnected);
+        self.clients.insert(server, State::Disconnected);
     }
 
-    pub fn ready(&mut self, server: Server, client: Client) {
-        self.0.insert(server, State::Ready(client));
+    pub fn ready(&mut self, server: Server, mut client: Client) {
+        if let Some(changes) = self.ignores.get(&server) {
+            changes.apply(&mut client.ignores);
+        }
+        client.highlights.clone_from(&self.highlights);
+
+        self.clients.insert(server, State::Ready(client));
+    }
+
+    /// Sets the highlight rules used by every client, such as after the
+    /// config is reloaded
+    pub fn set_highlights(&mut self, highlights: &config::Highlights) {
+        self.highlights.clone_from(highlights);
+
+        for state in self.clients.values_mut() {
+            if let State::Ready(client) = state {
+                client.highlights.clone_from(highlights);
+            }
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.clients.is_empty()
     }
 
     pub fn remove(&mut self, server: &Server) -> Option<Client> {
-        self.0.remove(server).and_then(|state| match state {
+        self.clients.remove(server).and_then(|state| match state {
             State::Disconnected => None,
             State::Ready(client) => Some(client),
         })
     }
 
     pub fn client(&self, server: &Server) -> Option<&Client> {
-        if let Some(State::Ready(client)) = self.0.get(server) {
+        if let Some(State::Ready(client)) = self.clients.get(server) {
             Some(client)
         } else {
             None
@@ -1318,7 +2556,7 @@ impl Map {
     }
 
     pub fn client_mut(&mut self, server: &Server) -> Option<&mut Client> {
-        if let Some(State::Ready(client)) = self.0.get_mut(server) {
+        if let Some(State::Ready(client)) = self.clients.get_mut(server) {
             Some(client)
         } else {
             None
@@ -1329,6 +2567,55 @@ impl Map {
         self.client(server).map(Client::nickname)
     }
 
+    pub fn ignores(&self, server: &Server) -> &[ignore::Entry] {
+        self.client(server)
+            .map(|client| client.ignores.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Ignores `entry`, replacing an entry with the same pattern. Applied
+    /// once the server connects, if it isn't connected.
+    pub fn ignore(&mut self, server: &Server, entry: ignore::Entry) {
+        let changes = self.ignores.entry(server.clone()).or_default();
+        changes.ignore(entry);
+
+        if let Some(State::Ready(client)) = self.clients.get_mut(server) {
+            changes.apply(&mut client.ignores);
+        }
+    }
+
+    /// Returns whether an entry with `pattern` was removed. Without a
+    /// connected client the ignore list isn't known, so it's removed once
+    /// the server connects.
+    pub fn unignore(&mut self, server: &Server, pattern: &ignore::Pattern) -> bool {
+        let changes = self.ignores.entry(server.clone()).or_default();
+        changes.unignore(pattern);
+
+        let Some(State::Ready(client)) = self.clients.get_mut(server) else {
+            return true;
+        };
+
+        let count = client.ignores.len();
+        changes.apply(&mut client.ignores);
+
+        client.ignores.len() != count
+    }
+
+    /// Whether a message from `user` may highlight us, as the client's own
+    /// highlight notifications require
+    pub fn allows_highlight(&self, server: &Server, user: &User) -> bool {
+        self.client(server).is_some_and(|client| {
+            client.highlight_blackout.allow_highlights()
+                && !client.is_ignored(user, ignore::Scope::Messages)
+                && !client.is_ignored(user, ignore::Scope::Highlights)
+        })
+    }
+
+    pub fn is_ignored(&self, server: &Server, user: &User, scope: ignore::Scope) -> bool {
+        self.client(server)
+            .is_some_and(|client| client.is_ignored(user, scope))
+    }
+
     pub fn receive(&mut self, server: &Server, message: message::Encoded) -> Vec<Event> {
         self.client_mut(server)
             .map(|client| client.receive(message))
@@ -1336,23 +2623,133 @@ impl Map {
     }
 
     pub fn sync(&mut self, server: &Server) {
-        if let Some(State::Ready(client)) = self.0.get_mut(server) {
+        if let Some(State::Ready(client)) = self.clients.get_mut(server) {
             client.sync();
         }
     }
 
-    pub fn send(&mut self, buffer: &Buffer, message: message::Encoded) {
+    /// Sends `message`, returning the label its echo will have
+    pub fn send(&mut self, buffer: &Buffer, message: message::Encoded) -> Option<String> {
+        self.client_mut(buffer.server())?.send(buffer, message)
+    }
+
+    /// Lets others in `buffer` know whether we're typing, after its input
+    /// changed
+    pub fn update_typing(&mut self, buffer: &Buffer, input: &str) {
+        if let Some(client) = self.client_mut(buffer.server()) {
+            client.update_typing(buffer, input);
+        }
+    }
+
+    /// Reacts to the message `msgid` in `buffer`, returning our reaction to
+    /// record in history
+    pub fn react(
+        &mut self,
+        buffer: &Buffer,
+        msgid: String,
+        reaction: String,
+    ) -> Option<crate::Message> {
+        let client = self.client_mut(buffer.server())?;
+
+        if !client.react(buffer, &msgid, &reaction) {
+            return None;
+        }
+
+        let source = message::Source::User(User::from(client.nickname().to_owned()));
+        let target = crate::history::Kind::from(buffer.clone()).target(source);
+
+        Some(crate::Message::reaction_sent(target, msgid, reaction))
+    }
+
+    pub fn mark_read(&mut self, buffer: &Buffer, read_at: DateTime<Utc>) {
+        if let Some(client) = self.client_mut(buffer.server()) {
+            client.mark_read(buffer, read_at);
+        }
+    }
+
+    /// Asks the server to delete the message `msgid` in `buffer`
+    pub fn redact(&mut self, buffer: &Buffer, msgid: &str) {
         if let Some(client) = self.client_mut(buffer.server()) {
-            client.send(buffer, message);
+            client.redact(buffer, msgid);
+        }
+    }
+
+    /// Asks the bouncer on `server` to add a network connecting to `host`
+    pub fn add_bouncer_network(&mut self, server: &Server, host: &str, name: Option<&str>) {
+        if let Some(client) = self.client_mut(server) {
+            client.add_bouncer_network(host, name);
+        }
+    }
+
+    /// Asks the bouncer to remove the network `server` is bound to
+    pub fn remove_bouncer_network(&mut self, server: &Server) {
+        let Some(binding) = self.bouncer_binding(server).cloned() else {
+            return;
+        };
+
+        if let Some(client) = self.client_mut(&binding.parent) {
+            client.remove_bouncer_network(&binding.netid);
         }
     }
 
+    /// Whether `server` is a bouncer we can add networks to
+    pub fn supports_bouncer_networks(&self, server: &Server) -> bool {
+        self.client(server).is_some_and(|client| {
+            client.supports_bouncer_networks && client.config.bouncer.is_none()
+        })
+    }
+
+    /// The bouncer network `server` is bound to
+    pub fn bouncer_binding(&self, server: &Server) -> Option<&bouncer::Binding> {
+        self.client(server)
+            .and_then(|client| client.config.bouncer.as_ref())
+    }
+
+    /// Name shown for `server`. Bouncer networks are shown by their current
+    /// name, rather than the id they're keyed by.
+    pub fn server_name(&self, server: &Server) -> String {
+        self.bouncer_binding(server)
+            .and_then(|binding| {
+                let network = self
+                    .client(&binding.parent)?
+                    .bouncer_networks
+                    .get(&binding.netid)?;
+
+                Some(format!("{}/{}", binding.parent, network.name()))
+            })
+            .unwrap_or_else(|| server.to_string())
+    }
+
+    pub fn supports_redaction(&self, server: &Server) -> bool {
+        self.client(server)
+            .is_some_and(|client| client.supports_redaction)
+    }
+
+    /// Nicks typing in `buffer`
+    pub fn typing(&self, buffer: &Buffer) -> Vec<&Nick> {
+        self.client(buffer.server())
+            .zip(buffer.target())
+            .map(|(client, target)| client.typing.active(&target, client.casemapping()))
+            .unwrap_or_default()
+    }
+
     pub fn join(&mut self, server: &Server, channels: &[String]) {
         if let Some(client) = self.client_mut(server) {
             client.join(channels);
         }
     }
 
+    pub fn send_chathistory_request(
+        &mut self,
+        server: &Server,
+        target: &str,
+        after: Option<DateTime<Utc>>,
+    ) {
+        if let Some(client) = self.client_mut(server) {
+            client.send_chathistory_request(target, after);
+        }
+    }
+
     pub fn quit(&mut self, server: &Server, reason: Option<String>) {
         if let Some(client) = self.client_mut(server) {
             client.quit(reason);
@@ -1369,6 +2766,18 @@ impl Map {
             .and_then(|client| client.resolve_user_attributes(channel, user))
     }
 
+    pub fn request_whois(&mut self, server: &Server, nick: NickRef<'_>) {
+        if let Some(client) = self.client_mut(server) {
+            client.request_whois(nick);
+        }
+    }
+
+    /// WHOIS reply for `nick`, once requested for a hover card
+    pub fn get_whois<'a>(&'a self, server: &Server, nick: NickRef<'_>) -> Option<&'a whois::Whois> {
+        self.client(server)
+            .and_then(|client| client.whois.get(nick, client.casemapping()))
+    }
+
     pub fn get_channel_users<'a>(&'a self, server: &Server, channel: &str) -> &'a [User] {
         self.client(server)
             .map(|client| client.users(channel))
@@ -1381,6 +2790,10 @@ impl Map {
             .unwrap_or_default()
     }
 
+    pub fn get_user_presence(&self, server: &Server, nick: NickRef) -> Option<Presence> {
+        self.client(server).and_then(|client| client.presence(nick))
+    }
+
     pub fn get_channel_topic<'a>(&'a self, server: &Server, channel: &str) -> Option<&'a Topic> {
         self.client(server)
             .map(|client| client.topic(channel))
@@ -1404,7 +2817,7 @@ impl Map {
     }
 
     pub fn connected_servers(&self) -> impl Iterator<Item = &Server> {
-        self.0.iter().filter_map(|(server, state)| {
+        self.clients.iter().filter_map(|(server, state)| {
             if let State::Ready(_) = state {
                 Some(server)
             } else {
@@ -1414,11 +2827,11 @@ impl Map {
     }
 
     pub fn iter(&self) -> std::collections::btree_map::Iter<Server, State> {
-        self.0.iter()
+        self.clients.iter()
     }
 
     pub fn status(&self, server: &Server) -> Status {
-        self.0
+        self.clients
             .get(server)
             .map(|s| match s {
                 State::Disconnected => Status::Disconnected,
@@ -1428,7 +2841,7 @@ impl Map {
     }
 
     pub fn tick(&mut self, now: Instant) {
-        self.0.values_mut().for_each(|client| {
+        self.clients.values_mut().for_each(|client| {
             if let State::Ready(client) = client {
                 client.tick(now);
             }
@@ -1467,6 +2880,8 @@ impl Context {
 pub struct Batch {
     context: Option<Context>,
     events: Vec<Event>,
+    chathistory: Option<ChatHistoryBatch>,
+    chathistory_targets: bool,
 }
 
 impl Batch {
@@ -1474,8 +2889,38 @@ impl Batch {
         Self {
             context,
             events: vec![],
+            chathistory: None,
+            chathistory_targets: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ChatHistoryBatch {
+    target: String,
+    count: usize,
+    latest: Option<DateTime<Utc>>,
+}
+
+impl ChatHistoryBatch {
+    fn new(target: String) -> Self {
+        Self {
+            target,
+            count: 0,
+            latest: None,
         }
     }
+
+    fn received(&mut self, server_time: DateTime<Utc>) {
+        self.count += 1;
+        self.latest = self.latest.max(Some(server_time));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChatHistoryRequest {
+    after: bool,
+    limit: u16,
 }
 
 fn generate_label() -> String {
@@ -1570,6 +3015,64 @@ pub enum WhoStatus {
     Done(Instant),
 }
 
+#[derive(Debug, Clone, Copy)]
+enum PresenceTracking {
+    Monitor,
+    Ison(IsonStatus),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum IsonStatus {
+    /// With the # of replies received so far, one per chunk
+    Requested(Instant, usize),
+    Done(Instant),
+}
+
+/// Group nicknames together into as few MONITOR messages as possible
+fn group_monitors(targets: &[String]) -> impl Iterator<Item = proto::Message> + '_ {
+    const MAX_LEN: usize = proto::format::BYTE_LIMIT - b"MONITOR + \r\n".len();
+
+    targets
+        .iter()
+        .scan(0, |count, target| {
+            // Target + a comma
+            *count += target.len() + 1;
+
+            let chunk = *count / MAX_LEN;
+
+            Some((chunk, target))
+        })
+        .into_group_map()
+        .into_values()
+        .map(|targets| command!("MONITOR", "+", targets.into_iter().join(",")))
+}
+
+/// Split nicknames into as few ISON messages as possible, in order
+fn ison_chunks(nicks: &[String]) -> Vec<&[String]> {
+    const MAX_LEN: usize = proto::format::BYTE_LIMIT - b"ISON :\r\n".len();
+
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut len = 0;
+
+    for (index, nick) in nicks.iter().enumerate() {
+        // Nick + a space
+        if len + nick.len() + 1 > MAX_LEN && index > start {
+            chunks.push(&nicks[start..index]);
+            start = index;
+            len = 0;
+        }
+
+        len += nick.len() + 1;
+    }
+
+    if start < nicks.len() {
+        chunks.push(&nicks[start..]);
+    }
+
+    chunks
+}
+
 /// Group channels together into as few JOIN messages as possible
 fn group_joins<'a>(
     channels: &'a [String],
@@ -1619,3 +3122,37 @@ fn group_joins<'a>(
 
     joins_without_keys.chain(joins_with_keys)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ignore_before_connecting() {
+        let server = Server::from("test");
+        let mut config = config::Server::default();
+        config.ignore = vec![
+            ignore::Entry::new(ignore::Pattern::from("troll"), vec![]),
+            ignore::Entry::new(ignore::Pattern::from("spammer"), vec![]),
+        ];
+
+        let mut clients = Map::default();
+        clients.disconnected(server.clone());
+
+        let flooder = ignore::Entry::new(ignore::Pattern::from("flooder"), vec![]);
+        clients.ignore(&server, flooder.clone());
+        assert!(clients.unignore(&server, &ignore::Pattern::from("spammer")));
+
+        let (sender, _receiver) = mpsc::channel(100);
+        clients.ready(server.clone(), Client::new(server.clone(), config, sender));
+
+        assert_eq!(
+            clients.ignores(&server),
+            [
+                ignore::Entry::new(ignore::Pattern::from("troll"), vec![]),
+                flooder
+            ]
+        );
+        assert!(!clients.unignore(&server, &ignore::Pattern::from("spammer")));
+    }
+}
@@ -1,9 +1,11 @@
 use std::str::FromStr;
 
+use chrono::NaiveDate;
 use irc::proto;
 use itertools::Itertools;
 
-use crate::{ctcp, message::formatting, Buffer};
+use crate::history::{export, import};
+use crate::{ctcp, ignore, message::formatting, Buffer};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Kind {
@@ -21,6 +23,11 @@ pub enum Kind {
     Format,
     Away,
     Raw,
+    Export,
+    Import,
+    Ignore,
+    Unignore,
+    Setname,
 }
 
 impl FromStr for Kind {
@@ -42,6 +49,11 @@ impl FromStr for Kind {
             "format" | "f" => Ok(Kind::Format),
             "away" => Ok(Kind::Away),
             "raw" => Ok(Kind::Raw),
+            "export" => Ok(Kind::Export),
+            "import" => Ok(Kind::Import),
+            "ignore" => Ok(Kind::Ignore),
+            "unignore" => Ok(Kind::Unignore),
+            "setname" => Ok(Kind::Setname),
             _ => Err(()),
         }
     }
@@ -62,9 +74,29 @@ pub enum Command {
     Mode(String, Option<String>, Vec<String>),
     Away(Option<String>),
     Raw(String),
+    /// Export the buffer's history, optionally between two dates
+    Export(export::Format, Option<NaiveDate>, Option<NaiveDate>),
+    /// Import logs written by another client into the buffer's history
+    Import(import::Format),
+    /// Ignore a user, or list ignored users
+    Ignore(Option<ignore::Entry>),
+    Unignore(ignore::Pattern),
+    /// Change our realname
+    Setname(String),
     Unknown(String, Vec<String>),
 }
 
+impl Command {
+    /// Whether the command is handled by the client, rather than sent to
+    /// the server
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Command::Export(..) | Command::Import(_) | Command::Ignore(_) | Command::Unignore(_)
+        )
+    }
+}
+
 pub fn parse(s: &str, buffer: Option<&Buffer>) -> Result<Command, Error> {
     let (head, rest) = s.split_once('/').ok_or(Error::MissingSlash)?;
     // Don't allow leading whitespace before slash
@@ -76,12 +108,15 @@ pub fn parse(s: &str, buffer: Option<&Buffer>) -> Result<Command, Error> {
 
     let cmd = split.next().ok_or(Error::MissingCommand)?;
 
-    if rest.len() == cmd.len() {
+    let kind = cmd.parse::<Kind>();
+
+    // Export & ignore can be used without args
+    if rest.len() == cmd.len() && !matches!(kind, Ok(Kind::Export | Kind::Ignore)) {
         return Err(Error::MissingArgs);
     }
 
     let args = split.collect::<Vec<_>>();
-    let raw = &rest[cmd.len() + 1..];
+    let raw = rest.get(cmd.len() + 1..).unwrap_or_default();
 
     let unknown = || {
         Command::Unknown(
@@ -90,7 +125,7 @@ pub fn parse(s: &str, buffer: Option<&Buffer>) -> Result<Command, Error> {
         )
     };
 
-    match cmd.parse::<Kind>() {
+    match kind {
         Ok(kind) => match kind {
             Kind::Join => validated::<1, 1, false>(args, |[chanlist], [chankeys]| {
                 Command::Join(chanlist, chankeys)
@@ -130,7 +165,7 @@ pub fn parse(s: &str, buffer: Option<&Buffer>) -> Result<Command, Error> {
                     Some(mode.to_string()),
                     users.iter().map(|s| s.to_string()).collect(),
                 ))
-            },
+            }
             Kind::Away => validated::<0, 1, true>(args, |_, [comment]| Command::Away(comment)),
             Kind::Raw => Ok(Command::Raw(raw.to_string())),
             Kind::Format => {
@@ -140,6 +175,68 @@ pub fn parse(s: &str, buffer: Option<&Buffer>) -> Result<Command, Error> {
                     Ok(unknown())
                 }
             }
+            Kind::Export => {
+                let (format, dates) = match args.split_first() {
+                    Some((format, rest)) if format.parse::<export::Format>().is_ok() => {
+                        (format.parse().unwrap_or_default(), rest)
+                    }
+                    _ => (export::Format::default(), args.as_slice()),
+                };
+
+                let dates = dates
+                    .iter()
+                    .map(|date| {
+                        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                            .map_err(|_| Error::InvalidArgument(date.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match dates.as_slice() {
+                    [] => Ok(Command::Export(format, None, None)),
+                    [since] => Ok(Command::Export(format, Some(*since), None)),
+                    [since, until] => Ok(Command::Export(format, Some(*since), Some(*until))),
+                    _ => Err(Error::IncorrectArgCount {
+                        min: 0,
+                        max: 3,
+                        actual: args.len(),
+                    }),
+                }
+            }
+            Kind::Import => match args.as_slice() {
+                [format] => format
+                    .parse()
+                    .map(Command::Import)
+                    .map_err(|_| Error::InvalidArgument(format.to_string())),
+                _ => Err(Error::IncorrectArgCount {
+                    min: 1,
+                    max: 1,
+                    actual: args.len(),
+                }),
+            },
+            Kind::Ignore => match args.split_first() {
+                None => Ok(Command::Ignore(None)),
+                Some((pattern, scopes)) => {
+                    let scopes = scopes
+                        .iter()
+                        .map(|scope| {
+                            scope
+                                .parse()
+                                .map_err(|_| Error::InvalidArgument(scope.to_string()))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    Ok(Command::Ignore(Some(ignore::Entry::new(
+                        ignore::Pattern::from(*pattern),
+                        scopes,
+                    ))))
+                }
+            },
+            Kind::Unignore => validated::<1, 0, false>(args, |[pattern], _| {
+                Command::Unignore(ignore::Pattern::from(pattern.as_str()))
+            }),
+            Kind::Setname => {
+                validated::<1, 0, true>(args, |[realname], _| Command::Setname(realname))
+            }
         },
         Err(_) => Ok(unknown()),
     }
@@ -204,7 +301,12 @@ impl TryFrom<Command> for proto::Command {
             Command::Mode(channel, mode, users) => proto::Command::MODE(channel, mode, users),
             Command::Away(comment) => proto::Command::AWAY(comment),
             Command::Raw(raw) => proto::Command::Raw(raw),
+            Command::Setname(realname) => proto::Command::SETNAME(realname),
             Command::Unknown(command, args) => proto::Command::new(&command, args),
+            Command::Export(..)
+            | Command::Import(_)
+            | Command::Ignore(_)
+            | Command::Unignore(_) => return Err(()),
         })
     }
 }
@@ -223,6 +325,8 @@ pub enum Error {
     MissingCommand,
     #[error("missing args")]
     MissingArgs,
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
 }
 
 fn fmt_incorrect_arg_count(min: usize, max: usize, actual: usize) -> String {
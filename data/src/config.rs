@@ -7,28 +7,38 @@ use rand_chacha::ChaCha8Rng;
 use serde::Deserialize;
 use thiserror::Error;
 
+pub use self::aliases::Aliases;
 pub use self::buffer::Buffer;
 pub use self::channel::Channel;
 pub use self::file_transfer::FileTransfer;
+pub use self::highlights::Highlights;
+pub use self::history::History;
 pub use self::keys::Keyboard;
+pub use self::logging::Logging;
 pub use self::notification::Notifications;
 pub use self::proxy::Proxy;
 pub use self::server::Server;
 pub use self::sidebar::Sidebar;
+pub use self::vault::Vault;
 use crate::audio::{self, Sound};
 use crate::environment::config_dir;
 use crate::server::Map as ServerMap;
 use crate::theme::Palette;
 use crate::{environment, Theme};
 
+pub mod aliases;
 pub mod buffer;
 pub mod channel;
 pub mod file_transfer;
+pub mod highlights;
+pub mod history;
 mod keys;
+pub mod logging;
 pub mod notification;
 pub mod proxy;
 pub mod server;
 pub mod sidebar;
+pub mod vault;
 
 const CONFIG_TEMPLATE: &str = include_str!("../../config.toml");
 const DEFAULT_THEME_FILE_NAME: &str = "ferra.toml";
@@ -45,6 +55,10 @@ pub struct Config {
     pub keyboard: Keyboard,
     pub notifications: Notifications<Sound>,
     pub file_transfer: FileTransfer,
+    pub history: History,
+    pub highlights: Highlights,
+    pub logging: Logging,
+    pub aliases: Aliases,
     pub tooltips: bool,
 }
 
@@ -128,6 +142,14 @@ impl Config {
         Self::config_dir().join(environment::CONFIG_FILE_NAME)
     }
 
+    /// Loads the config on a blocking thread, as credential commands & the
+    /// vault's key derivation can take a while
+    pub async fn reload() -> Result<Self, Error> {
+        tokio::task::spawn_blocking(Self::load)
+            .await
+            .map_err(|error| Error::Read(error.to_string()))?
+    }
+
     pub fn load() -> Result<Self, Error> {
         #[derive(Deserialize)]
         pub struct Configuration {
@@ -149,8 +171,18 @@ impl Config {
             pub notifications: Notifications,
             #[serde(default)]
             pub file_transfer: FileTransfer,
+            #[serde(default)]
+            pub history: History,
+            #[serde(default)]
+            pub highlights: Highlights,
+            #[serde(default)]
+            pub logging: Logging,
+            #[serde(default)]
+            pub aliases: Aliases,
             #[serde(default = "default_tooltip")]
             pub tooltips: bool,
+            #[serde(default)]
+            pub vault: Vault,
         }
 
         let path = Self::path();
@@ -167,10 +199,22 @@ impl Config {
             keyboard,
             notifications,
             file_transfer,
+            history,
+            highlights,
+            logging,
+            aliases,
             tooltips,
+            vault,
         } = toml::from_str(content.as_ref()).map_err(|e| Error::Parse(e.to_string()))?;
 
-        servers.read_password_files()?;
+        // Only servers needing a credential from the vault fail when it
+        // can't be unlocked
+        let vault = vault.unlock();
+        if let Err(error) = &vault {
+            log::warn!("{error}, credentials won't be read from it");
+        }
+
+        servers.resolve_credentials(vault.as_ref().map(Option::as_ref))?;
 
         let loaded_notifications = notifications.load_sounds()?;
 
@@ -187,6 +231,10 @@ impl Config {
             keyboard,
             notifications: loaded_notifications,
             file_transfer,
+            history,
+            highlights,
+            logging,
+            aliases,
             tooltips,
         })
     }
@@ -293,6 +341,38 @@ pub fn has_yaml_config() -> bool {
     config_dir().join("config.yaml").exists()
 }
 
+/// Runs `command` with the shell & returns what it printed, without the
+/// trailing newline
+pub(crate) fn read_command(command: &str) -> Result<String, Error> {
+    #[cfg(windows)]
+    let output = std::process::Command::new("cmd")
+        .args(["/C", command])
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .output();
+
+    let output = output.map_err(|e| Error::Command(command.to_string(), e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        return Err(Error::Command(
+            command.to_string(),
+            if stderr.trim().is_empty() {
+                output.status.to_string()
+            } else {
+                stderr.trim().to_string()
+            },
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string())
+}
+
 fn default_tooltip() -> bool {
     true
 }
@@ -307,6 +387,10 @@ pub enum Error {
     Parse(String),
     #[error("error loading sound: {0}")]
     LoadSounds(#[from] audio::LoadError),
+    #[error("command `{0}` failed: {1}")]
+    Command(String, String),
+    #[error("vault: {0}")]
+    Vault(String),
 }
 
 impl From<std::io::Error> for Error {
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// User defined commands, mapping a command name to the commands it runs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Aliases(HashMap<String, Expansion>);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Expansion {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Expansion {
+    fn lines(&self) -> &[String] {
+        match self {
+            Expansion::Single(line) => std::slice::from_ref(line),
+            Expansion::Multiple(lines) => lines,
+        }
+    }
+}
+
+/// Values substituted into an alias expansion
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    pub nick: Option<&'a str>,
+    pub channel: Option<&'a str>,
+    pub server: &'a str,
+}
+
+impl Aliases {
+    /// Name & expansion of each alias, sorted by name
+    pub fn list(&self) -> Vec<(&str, String)> {
+        let mut list = self
+            .0
+            .iter()
+            .map(|(name, expansion)| (name.as_str(), expansion.lines().join("; ")))
+            .collect::<Vec<_>>();
+
+        list.sort_by_key(|(name, _)| *name);
+
+        list
+    }
+
+    /// Expands `input` if it's an alias, returning each line to run.
+    ///
+    /// `$1`, `$2`... are replaced with that argument and `$1-`, `$2-`... with
+    /// that argument & every one after it. `$nick`, `$channel` & `$server`
+    /// are replaced from `context`.
+    pub fn expand(&self, input: &str, context: Context) -> Option<Vec<String>> {
+        let rest = input.strip_prefix('/')?;
+        let (name, args) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        let expansion = self
+            .0
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+            .map(|(_, expansion)| expansion)?;
+
+        let args = args.split_ascii_whitespace().collect::<Vec<_>>();
+
+        Some(
+            expansion
+                .lines()
+                .iter()
+                .map(|line| substitute(line, &args, context))
+                .collect(),
+        )
+    }
+}
+
+fn substitute(line: &str, args: &[&str], context: Context) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(index) = rest.find('$') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+
+        if let Some(number) = rest[..digits]
+            .parse::<usize>()
+            .ok()
+            .filter(|number| *number > 0)
+        {
+            rest = &rest[digits..];
+
+            if let Some(remaining) = rest.strip_prefix('-') {
+                rest = remaining;
+                output.push_str(&args.get(number - 1..).unwrap_or_default().join(" "));
+            } else {
+                output.push_str(args.get(number - 1).copied().unwrap_or_default());
+            }
+
+            continue;
+        }
+
+        let variable = [
+            ("nick", context.nick),
+            ("channel", context.channel),
+            ("server", Some(context.server)),
+        ]
+        .into_iter()
+        .find(|(name, _)| rest.starts_with(name));
+
+        match variable {
+            Some((name, value)) => {
+                rest = &rest[name.len()..];
+                output.push_str(value.unwrap_or_default());
+            }
+            None => output.push('$'),
+        }
+    }
+
+    output.push_str(rest);
+
+    output.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expand() {
+        let aliases: Aliases = toml::from_str(
+            r#"
+            ns = "/msg NickServ $1-"
+            op = "/mode $channel +o $1"
+            hi = ["/me waves", "hello from $nick on $server, $2-"]
+            "#,
+        )
+        .unwrap();
+
+        let context = Context {
+            nick: Some("casper"),
+            channel: Some("#halloy"),
+            server: "libera",
+        };
+
+        let tests = [
+            (
+                "/ns identify hunter2",
+                Some(vec!["/msg NickServ identify hunter2"]),
+            ),
+            ("/OP cory", Some(vec!["/mode #halloy +o cory"])),
+            ("/op", Some(vec!["/mode #halloy +o"])),
+            (
+                "/hi all of you",
+                Some(vec!["/me waves", "hello from casper on libera, of you"]),
+            ),
+            ("/msg NickServ help", None),
+            ("ns", None),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                aliases.expand(input, context),
+                expected.map(|lines| lines.into_iter().map(String::from).collect())
+            );
+        }
+    }
+}
@@ -21,6 +21,8 @@ pub struct Buffer {
     pub server_messages: ServerMessages,
     #[serde(default)]
     pub internal_messages: InternalMessages,
+    #[serde(default)]
+    pub typing: Typing,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -106,6 +108,25 @@ impl Default for InternalMessage {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct Typing {
+    /// Show who is typing under the text input
+    #[serde(default = "default_bool_true")]
+    pub show: bool,
+    /// Let others know when we are typing
+    #[serde(default = "default_bool_true")]
+    pub share: bool,
+}
+
+impl Default for Typing {
+    fn default() -> Self {
+        Self {
+            show: true,
+            share: true,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum UsernameFormat {
@@ -126,6 +147,7 @@ impl Default for Buffer {
             channel: Channel::default(),
             server_messages: Default::default(),
             internal_messages: Default::default(),
+            typing: Default::default(),
         }
     }
 }
@@ -1,7 +1,16 @@
-use std::{net::IpAddr, num::NonZeroU16, ops::RangeInclusive, path::PathBuf};
+use std::{
+    net::IpAddr,
+    num::NonZeroU16,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use serde::Deserialize;
 
+use crate::isupport::CaseMap;
+use crate::user::NickRef;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct FileTransfer {
     /// Directory opened when prompted to save a file
@@ -13,7 +22,25 @@ pub struct FileTransfer {
     /// Time in seconds to wait before timing out a transfer waiting to be accepted.
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// Transfers active at once. Others wait for one to finish.
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Combined rate of all transfers in KiB/s
+    pub bandwidth_limit: Option<u64>,
+    /// Rate of each transfer in KiB/s
+    pub transfer_bandwidth_limit: Option<u64>,
+    /// Compute the SHA-256 of each transferred file
+    #[serde(default = "default_checksum")]
+    pub checksum: bool,
+    /// Command & arguments run after a file is received
+    #[serde(default)]
+    pub on_complete: Vec<String>,
+    /// Refuse files with extensions that can run code, like `.exe`
+    #[serde(default)]
+    pub refuse_risky_extensions: bool,
     pub server: Option<Server>,
+    #[serde(default)]
+    pub auto_accept: AutoAccept,
 }
 
 impl Default for FileTransfer {
@@ -22,11 +49,79 @@ impl Default for FileTransfer {
             save_directory: default_save_directory(),
             passive: default_passive(),
             timeout: default_timeout(),
+            max_concurrent: default_max_concurrent(),
+            bandwidth_limit: None,
+            transfer_bandwidth_limit: None,
+            checksum: default_checksum(),
+            on_complete: vec![],
+            refuse_risky_extensions: false,
             server: None,
+            auto_accept: AutoAccept::default(),
         }
     }
 }
 
+/// Extensions of files that run code when opened
+const RISKY_EXTENSIONS: &[&str] = &[
+    "app", "bat", "cmd", "com", "cpl", "dll", "dmg", "exe", "hta", "jar", "js", "jse", "lnk",
+    "msi", "pif", "ps1", "reg", "scr", "sh", "vbe", "vbs", "wsf",
+];
+
+impl FileTransfer {
+    /// Whether a file offered to us is refused for its extension
+    pub fn refuses(&self, filename: &str) -> bool {
+        if !self.refuse_risky_extensions {
+            return false;
+        }
+
+        // Trailing dots & spaces are dropped by Windows
+        Path::new(filename.trim_end_matches(['.', ' ']))
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                RISKY_EXTENSIONS
+                    .iter()
+                    .any(|risky| risky.eq_ignore_ascii_case(extension))
+            })
+    }
+
+    /// The `on_complete` command for a received file, with `{path}`,
+    /// `{filename}`, `{nick}`, `{server}` & `{sha256}` replaced. They're
+    /// replaced as is, and the remote user picks the nick & filename, so
+    /// they must never be part of a shell script.
+    pub fn on_complete_command(
+        &self,
+        path: &Path,
+        nick: &str,
+        server: &str,
+        sha256: Option<&str>,
+    ) -> Option<Command> {
+        let filename = path
+            .file_name()
+            .map(|filename| filename.to_string_lossy())
+            .unwrap_or_default();
+
+        let replace = |arg: &String| {
+            arg.replace("{path}", &path.to_string_lossy())
+                .replace("{filename}", &filename)
+                .replace("{nick}", nick)
+                .replace("{server}", server)
+                .replace("{sha256}", sha256.unwrap_or_default())
+        };
+
+        let (program, args) = self.on_complete.split_first()?;
+
+        let mut command = Command::new(replace(program));
+        command.args(args.iter().map(replace));
+
+        Some(command)
+    }
+}
+
+fn default_checksum() -> bool {
+    true
+}
+
 fn default_passive() -> bool {
     true
 }
@@ -35,6 +130,10 @@ fn default_timeout() -> u64 {
     60 * 5
 }
 
+fn default_max_concurrent() -> usize {
+    3
+}
+
 fn default_save_directory() -> PathBuf {
     dirs_next::download_dir().unwrap_or(PathBuf::from("/tmp/"))
 }
@@ -47,6 +146,10 @@ pub struct Server {
     pub bind_address: IpAddr,
     /// Port range used to bind with
     pub bind_ports: RangeInclusive<u16>,
+    /// Certificate presented when accepting secure DCC chats
+    pub tls_cert_path: Option<PathBuf>,
+    /// Private key of the certificate, if not in the same file
+    pub tls_key_path: Option<PathBuf>,
 }
 
 impl<'de> Deserialize<'de> for Server {
@@ -60,6 +163,8 @@ impl<'de> Deserialize<'de> for Server {
             bind_address: IpAddr,
             bind_port_first: NonZeroU16,
             bind_port_last: NonZeroU16,
+            tls_cert_path: Option<PathBuf>,
+            tls_key_path: Option<PathBuf>,
         }
 
         let Data {
@@ -67,6 +172,8 @@ impl<'de> Deserialize<'de> for Server {
             bind_address,
             bind_port_first,
             bind_port_last,
+            tls_cert_path,
+            tls_key_path,
         } = Data::deserialize(deserializer)?;
 
         if bind_port_last < bind_port_first {
@@ -79,6 +186,107 @@ impl<'de> Deserialize<'de> for Server {
             public_address,
             bind_address,
             bind_ports: bind_port_first.get()..=bind_port_last.get(),
+            tls_cert_path,
+            tls_key_path,
         })
     }
 }
+
+/// Transfers from trusted users accepted without asking
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AutoAccept {
+    /// Not authenticated, anyone using one of these nicks is trusted
+    #[serde(default)]
+    pub nicks: Vec<String>,
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    /// Largest file accepted in bytes
+    pub max_size: Option<u64>,
+    /// Directory saved to instead of `save_directory`
+    pub save_directory: Option<PathBuf>,
+}
+
+impl AutoAccept {
+    pub fn accepts(
+        &self,
+        nick: NickRef<'_>,
+        account: Option<&str>,
+        size: u64,
+        casemapping: CaseMap,
+    ) -> bool {
+        let nick = casemapping.normalize(nick.as_ref());
+
+        let trusted = self
+            .nicks
+            .iter()
+            .any(|trusted| casemapping.normalize(trusted) == nick)
+            || account.is_some_and(|account| {
+                self.accounts
+                    .iter()
+                    .any(|trusted| trusted.eq_ignore_ascii_case(account))
+            });
+
+        trusted && size <= self.max_size.unwrap_or(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auto_accept() {
+        let auto_accept = AutoAccept {
+            nicks: vec!["Casper".to_string(), "[cory]".to_string()],
+            accounts: vec!["tarkah".to_string()],
+            max_size: Some(1024),
+            save_directory: None,
+        };
+        let casemapping = CaseMap::default();
+
+        assert!(auto_accept.accepts(NickRef::from("casper"), None, 1024, casemapping));
+        assert!(auto_accept.accepts(NickRef::from("cory"), Some("Tarkah"), 10, casemapping));
+        assert!(!auto_accept.accepts(NickRef::from("casper"), None, 1025, casemapping));
+        assert!(!auto_accept.accepts(NickRef::from("cory"), None, 10, casemapping));
+        assert!(auto_accept.accepts(NickRef::from("{Cory}"), None, 10, casemapping));
+        assert!(!auto_accept.accepts(NickRef::from("{Cory}"), None, 10, CaseMap::ASCII));
+        assert!(!AutoAccept::default().accepts(NickRef::from("casper"), None, 0, casemapping));
+    }
+
+    #[test]
+    fn refuses() {
+        let mut config = FileTransfer::default();
+        assert!(!config.refuses("setup.exe"));
+
+        config.refuse_risky_extensions = true;
+        assert!(config.refuses("setup.exe"));
+        assert!(config.refuses("invoice.pdf.EXE"));
+        assert!(config.refuses("setup.exe. "));
+        assert!(!config.refuses("invoice.pdf"));
+        assert!(!config.refuses("exe"));
+    }
+
+    #[test]
+    fn on_complete_command() {
+        let mut config = FileTransfer::default();
+        let path = Path::new("/tmp/file.txt");
+        assert!(config
+            .on_complete_command(path, "casper", "libera", None)
+            .is_none());
+
+        config.on_complete = vec![
+            "scan".to_string(),
+            "{path}".to_string(),
+            "--from={nick}@{server}".to_string(),
+            "{filename}:{sha256}".to_string(),
+        ];
+        let command = config
+            .on_complete_command(path, "casper", "libera", Some("abc"))
+            .unwrap();
+        assert_eq!(command.get_program(), "scan");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["/tmp/file.txt", "--from=casper@libera", "file.txt:abc"]
+        );
+    }
+}
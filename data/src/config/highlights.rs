@@ -0,0 +1,143 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::user::NickRef;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Highlights {
+    /// Highlight messages which mention our nickname
+    #[serde(default = "default_nickname")]
+    pub nickname: bool,
+    /// Words which highlight a message, matched case-insensitively as whole
+    /// words
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Regular expressions which highlight a message
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub regex: Vec<Regex>,
+    /// Never highlight messages in these channels or from these nicks
+    #[serde(default)]
+    pub exclude: Exclude,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Self {
+            nickname: default_nickname(),
+            words: vec![],
+            regex: vec![],
+            exclude: Exclude::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Exclude {
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Nicks never highlighting us, such as bots
+    #[serde(default)]
+    pub nicks: Vec<String>,
+}
+
+impl Highlights {
+    /// Whether `text` sent by `sender` in `channel` is a highlight. Messages
+    /// in a query have no channel.
+    pub fn matches(
+        &self,
+        sender: NickRef<'_>,
+        own_nick: NickRef<'_>,
+        channel: Option<&str>,
+        text: &str,
+    ) -> bool {
+        if sender == own_nick
+            || self
+                .exclude
+                .nicks
+                .iter()
+                .any(|nick| nick.eq_ignore_ascii_case(sender.as_ref()))
+            || channel.is_some_and(|channel| {
+                self.exclude
+                    .channels
+                    .iter()
+                    .any(|excluded| excluded.eq_ignore_ascii_case(channel))
+            })
+        {
+            return false;
+        }
+
+        (self.nickname && text.contains(own_nick.as_ref()))
+            || self.words.iter().any(|word| contains_word(text, word))
+            || self.regex.iter().any(|regex| regex.is_match(text))
+    }
+}
+
+/// Whether `word` appears in `text` without being part of a larger word
+fn contains_word(text: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+
+    let text = text.to_lowercase();
+    let word = word.to_lowercase();
+
+    text.match_indices(&word).any(|(index, _)| {
+        let before = text[..index].chars().next_back();
+        let after = text[index + word.len()..].chars().next();
+
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn default_nickname() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches() {
+        let highlights: Highlights = toml::from_str(
+            r##"
+            words = ["halloy", "release"]
+            regex = ['\bv\d+\.\d+\b']
+            exclude = { channels = ["#spam"], nicks = ["bot"] }
+            "##,
+        )
+        .unwrap();
+
+        let own_nick = NickRef::from("casper");
+
+        let tests = [
+            ("cory", Some("#halloy"), "hi casper", true),
+            ("cory", Some("#halloy"), "Halloy is great", true),
+            ("cory", Some("#halloy"), "halloyed", false),
+            ("cory", None, "the release is out", true),
+            ("cory", Some("#halloy"), "v2024.11 is out", true),
+            ("cory", Some("#halloy"), "nothing here", false),
+            ("cory", Some("#Spam"), "halloy", false),
+            ("Bot", Some("#halloy"), "halloy casper", false),
+            ("casper", Some("#halloy"), "halloy", false),
+        ];
+
+        for (sender, channel, text, expected) in tests {
+            assert_eq!(
+                highlights.matches(NickRef::from(sender), own_nick, channel, text),
+                expected,
+                "{text}"
+            );
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct History {
+    /// Approximate # messages to keep per buffer. Oldest messages are
+    /// removed in whole segments once exceeded.
+    #[serde(default = "default_max_messages")]
+    pub max_messages: Option<usize>,
+    /// Age in days after which messages are removed
+    #[serde(default)]
+    pub max_age: Option<u32>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            max_messages: default_max_messages(),
+            max_age: None,
+        }
+    }
+}
+
+/// Keeps disk usage bounded, as history was before it was stored in segments
+fn default_max_messages() -> Option<usize> {
+    Some(10_000)
+}
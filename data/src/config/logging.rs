@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Logging {
+    /// Write every message to plain-text logs
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory logs are written to. Defaults to `logs` in the data directory.
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    /// Format of each line, with `{timestamp}`, `{server}`, `{buffer}`,
+    /// `{prefix}` and `{text}` replaced
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Format of `{timestamp}`, using strftime
+    #[serde(default = "default_timestamp")]
+    pub timestamp: String,
+    /// Only log these servers & buffers. Everything is logged when empty.
+    #[serde(default)]
+    pub include: Filter,
+    /// Never log these servers & buffers
+    #[serde(default)]
+    pub exclude: Filter,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            format: default_format(),
+            timestamp: default_timestamp(),
+            include: Filter::default(),
+            exclude: Filter::default(),
+        }
+    }
+}
+
+impl Logging {
+    /// Whether messages on `server` in `buffer` (a channel or nick) are
+    /// logged. Server messages have no buffer.
+    pub fn is_logged(&self, server: &str, buffer: Option<&str>) -> bool {
+        let included = self.include.is_empty() || self.include.matches(server, buffer);
+
+        self.enabled && included && !self.exclude.matches(server, buffer)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Filter {
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// Channels & nicks
+    #[serde(default)]
+    pub buffers: Vec<String>,
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        self.servers.is_empty() && self.buffers.is_empty()
+    }
+
+    fn matches(&self, server: &str, buffer: Option<&str>) -> bool {
+        self.servers.iter().any(|s| s == server)
+            || buffer
+                .is_some_and(|buffer| self.buffers.iter().any(|b| b.eq_ignore_ascii_case(buffer)))
+    }
+}
+
+fn default_format() -> String {
+    "[{timestamp}] {prefix} {text}".to_string()
+}
+
+fn default_timestamp() -> String {
+    "%H:%M:%S".to_string()
+}
@@ -32,6 +32,8 @@ pub struct Notifications<T = String> {
     pub highlight: Notification<T>,
     #[serde(default)]
     pub file_transfer_request: Notification<T>,
+    #[serde(default)]
+    pub monitor: Notification<T>,
 }
 
 impl<T> Default for Notifications<T> {
@@ -42,6 +44,7 @@ impl<T> Default for Notifications<T> {
             reconnected: Notification::default(),
             highlight: Notification::default(),
             file_transfer_request: Notification::default(),
+            monitor: Notification::default(),
         }
     }
 }
@@ -61,6 +64,7 @@ impl Notifications {
             reconnected: load(&self.reconnected)?,
             highlight: load(&self.highlight)?,
             file_transfer_request: load(&self.file_transfer_request)?,
+            monitor: load(&self.monitor)?,
         })
     }
 }
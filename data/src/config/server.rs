@@ -5,7 +5,7 @@ use std::time::Duration;
 use irc::connection;
 use serde::{Deserialize, Deserializer};
 
-use crate::config;
+use crate::{bouncer, config, ignore, sasl};
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct Server {
@@ -15,6 +15,8 @@ pub struct Server {
     pub nick_password: Option<String>,
     /// The client's NICKSERV password file.
     pub nick_password_file: Option<String>,
+    /// Command printing the client's NICKSERV password.
+    pub nick_password_command: Option<String>,
     /// The server's NICKSERV IDENTIFY syntax.
     pub nick_identify_syntax: Option<IdentifySyntax>,
     /// Alternative nicknames for the client, if the default is taken.
@@ -33,6 +35,8 @@ pub struct Server {
     pub password: Option<String>,
     /// The file with the password to connect to the server.
     pub password_file: Option<String>,
+    /// Command printing the password to connect to the server.
+    pub password_command: Option<String>,
     /// A list of channels to join on connection.
     #[serde(default)]
     pub channels: Vec<String>,
@@ -86,6 +90,26 @@ pub struct Server {
         deserialize_with = "deserialize_duration_from_u64"
     )]
     pub who_retry_interval: Duration,
+    /// A list of nicknames to track the online status of.
+    #[serde(default)]
+    pub monitor: Vec<String>,
+    /// ISON poll interval for servers without MONITOR.
+    #[serde(
+        default = "default_ison_poll_interval",
+        deserialize_with = "deserialize_duration_from_u64"
+    )]
+    pub ison_poll_interval: Duration,
+    /// Users to ignore, by `nick!user@host` glob or `$a:account`.
+    #[serde(default)]
+    pub ignore: Vec<ignore::Entry>,
+    /// Keep messages from ignored users in history, shown collapsed. Other
+    /// ignored content is always hidden.
+    #[serde(default)]
+    pub collapse_ignored: bool,
+    /// The bouncer network this connection is bound to, listed by the
+    /// bouncer rather than configured.
+    #[serde(skip)]
+    pub bouncer: Option<bouncer::Binding>,
 }
 
 impl Server {
@@ -111,6 +135,33 @@ impl Server {
         }
     }
 
+    /// Connect with TLS on `port`, as required by the server's STS policy.
+    /// The server's certificate is always verified, since STS is meant to
+    /// protect the connection.
+    pub fn with_sts_port(self, port: u16) -> Self {
+        Self {
+            use_tls: true,
+            port,
+            dangerously_accept_invalid_certs: false,
+            ..self
+        }
+    }
+
+    /// Connect to the bouncer's network `netid`, which joins its own
+    /// channels & identifies with the network itself
+    pub fn with_bouncer_network(&self, parent: crate::Server, netid: String) -> Self {
+        Self {
+            channels: vec![],
+            channel_keys: HashMap::new(),
+            nick_password: None,
+            nick_password_file: None,
+            nick_password_command: None,
+            on_connect: vec![],
+            bouncer: Some(bouncer::Binding { parent, netid }),
+            ..self.clone()
+        }
+    }
+
     pub fn connection(&self, proxy: Option<config::Proxy>) -> connection::Config {
         let security = if self.use_tls {
             connection::Security::Secured {
@@ -138,6 +189,7 @@ impl Default for Server {
             nickname: Default::default(),
             nick_password: Default::default(),
             nick_password_file: Default::default(),
+            nick_password_command: Default::default(),
             nick_identify_syntax: Default::default(),
             alt_nicks: Default::default(),
             username: Default::default(),
@@ -146,6 +198,7 @@ impl Default for Server {
             port: default_tls_port(),
             password: Default::default(),
             password_file: Default::default(),
+            password_command: Default::default(),
             channels: Default::default(),
             channel_keys: Default::default(),
             ping_time: default_ping_time(),
@@ -161,6 +214,11 @@ impl Default for Server {
             on_connect: Default::default(),
             who_poll_interval: default_who_poll_interval(),
             who_retry_interval: default_who_retry_interval(),
+            monitor: Default::default(),
+            ison_poll_interval: default_ison_poll_interval(),
+            ignore: Default::default(),
+            collapse_ignored: Default::default(),
+            bouncer: Default::default(),
         }
     }
 }
@@ -173,8 +231,28 @@ pub enum IdentifySyntax {
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Deserialize)]
+pub struct Sasl {
+    #[serde(flatten)]
+    pub auth: SaslAuth,
+    /// Whether to stay connected when authentication fails
+    #[serde(default)]
+    pub on_failure: SaslOnFailure,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "kebab-case")]
-pub enum Sasl {
+pub enum SaslOnFailure {
+    /// Continue connecting without an account
+    #[default]
+    Continue,
+    /// Disconnect, so nothing is sent before being logged in. The server
+    /// isn't reconnected to until Halloy restarts
+    Disconnect,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SaslAuth {
     Plain {
         /// Account name
         username: String,
@@ -182,6 +260,8 @@ pub enum Sasl {
         password: Option<String>,
         /// Account password file
         password_file: Option<String>,
+        /// Command printing the account password
+        password_command: Option<String>,
     },
     External {
         /// The path to PEM encoded X509 user certificate for external auth
@@ -189,36 +269,65 @@ pub enum Sasl {
         /// The path to PEM encoded PKCS#8 private key corresponding to the user certificate for external auth
         key: Option<PathBuf>,
     },
+    /// SCRAM-SHA-256, falling back to SCRAM-SHA-1
+    Scram {
+        /// Account name
+        username: String,
+        /// Account password,
+        password: Option<String>,
+        /// Account password file
+        password_file: Option<String>,
+        /// Command printing the account password
+        password_command: Option<String>,
+    },
+    /// ECDSA-NIST256P-CHALLENGE
+    Ecdsa {
+        /// Account name
+        username: String,
+        /// The path to PEM encoded NIST P-256 private key registered with the account
+        key: PathBuf,
+    },
 }
 
 impl Sasl {
-    pub fn command(&self) -> &'static str {
-        match self {
-            Sasl::Plain { .. } => "PLAIN",
-            Sasl::External { .. } => "EXTERNAL",
+    /// Mechanisms which can be used, in order of preference
+    pub fn mechanisms(&self) -> &'static [sasl::Mechanism] {
+        match &self.auth {
+            SaslAuth::Plain { .. } => &[sasl::Mechanism::Plain],
+            SaslAuth::External { .. } => &[sasl::Mechanism::External],
+            SaslAuth::Scram { .. } => &[sasl::Mechanism::ScramSha256, sasl::Mechanism::ScramSha1],
+            SaslAuth::Ecdsa { .. } => &[sasl::Mechanism::EcdsaNist256pChallenge],
         }
     }
 
-    pub fn param(&self) -> String {
-        match self {
-            Sasl::Plain {
-                username, password, ..
-            } => {
-                use base64::engine::Engine;
-
-                let password = password
-                    .as_ref()
-                    .expect("SASL password must exist at this point!");
+    pub fn username(&self) -> Option<&str> {
+        match &self.auth {
+            SaslAuth::Plain { username, .. }
+            | SaslAuth::Scram { username, .. }
+            | SaslAuth::Ecdsa { username, .. } => Some(username),
+            SaslAuth::External { .. } => None,
+        }
+    }
 
-                base64::engine::general_purpose::STANDARD
-                    .encode(format!("{username}\x00{username}\x00{password}"))
+    pub fn password(&self) -> Option<&str> {
+        match &self.auth {
+            SaslAuth::Plain { password, .. } | SaslAuth::Scram { password, .. } => {
+                password.as_deref()
             }
-            Sasl::External { .. } => "+".into(),
+            SaslAuth::External { .. } | SaslAuth::Ecdsa { .. } => None,
+        }
+    }
+
+    pub fn key(&self) -> Option<&PathBuf> {
+        match &self.auth {
+            SaslAuth::External { key, .. } => key.as_ref(),
+            SaslAuth::Ecdsa { key, .. } => Some(key),
+            SaslAuth::Plain { .. } | SaslAuth::Scram { .. } => None,
         }
     }
 
     fn external_cert(&self) -> Option<&PathBuf> {
-        if let Self::External { cert, .. } = self {
+        if let SaslAuth::External { cert, .. } = &self.auth {
             Some(cert)
         } else {
             None
@@ -226,7 +335,7 @@ impl Sasl {
     }
 
     fn external_key(&self) -> Option<&PathBuf> {
-        if let Self::External { key, .. } = self {
+        if let SaslAuth::External { key, .. } = &self.auth {
             key.as_ref()
         } else {
             None
@@ -277,3 +386,59 @@ fn default_who_poll_interval() -> Duration {
 fn default_who_retry_interval() -> Duration {
     Duration::from_secs(10)
 }
+
+fn default_ison_poll_interval() -> Duration {
+    Duration::from_secs(60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sasl() {
+        let sasl: Sasl = toml::from_str(
+            r#"
+            on_failure = "disconnect"
+
+            [scram]
+            username = "casper"
+            password = "hunter2"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(sasl.on_failure, SaslOnFailure::Disconnect);
+        assert_eq!(sasl.username(), Some("casper"));
+        assert_eq!(
+            sasl.mechanisms(),
+            [sasl::Mechanism::ScramSha256, sasl::Mechanism::ScramSha1]
+        );
+
+        let sasl: Sasl = toml::from_str(
+            r#"
+            [plain]
+            username = "casper"
+            password = "hunter2"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(sasl.on_failure, SaslOnFailure::Continue);
+        assert_eq!(sasl.password(), Some("hunter2"));
+    }
+
+    #[test]
+    fn sts_port() {
+        let config = Server {
+            use_tls: false,
+            dangerously_accept_invalid_certs: true,
+            ..Server::default()
+        }
+        .with_sts_port(6697);
+
+        assert!(config.use_tls);
+        assert_eq!(config.port, 6697);
+        assert!(!config.dangerously_accept_invalid_certs);
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use super::{read_command, Config, Error};
+use crate::vault;
+
+/// Environment variable holding the master passphrase
+pub const PASSPHRASE_ENV: &str = "HALLOY_VAULT_PASSPHRASE";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Vault {
+    /// Vault file, defaults to `vault` in the config directory
+    pub path: Option<PathBuf>,
+    /// Command printing the master passphrase, such as a password prompt
+    pub passphrase_command: Option<String>,
+}
+
+impl Vault {
+    /// Reads only the `[vault]` section of the config file, for managing the
+    /// vault before the rest of the config can be loaded
+    pub fn load() -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Configuration {
+            #[serde(default)]
+            vault: Vault,
+        }
+
+        let content = fs::read_to_string(Config::path()).map_err(|e| Error::Read(e.to_string()))?;
+
+        let Configuration { vault } =
+            toml::from_str(content.as_ref()).map_err(|e| Error::Parse(e.to_string()))?;
+
+        Ok(vault)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(vault::default_path)
+    }
+
+    /// The master passphrase, from the environment or `passphrase_command`
+    pub fn passphrase(&self) -> Result<Option<String>, Error> {
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+            return Ok(Some(passphrase));
+        }
+
+        self.passphrase_command
+            .as_deref()
+            .map(read_command)
+            .transpose()
+    }
+
+    /// Unlocks the vault, if one has been created
+    pub fn unlock(&self) -> Result<Option<vault::Vault>, Error> {
+        let path = self.path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let passphrase = self.passphrase()?.ok_or_else(|| {
+            Error::Vault(format!(
+                "{} is locked, set {PASSPHRASE_ENV} or vault.passphrase_command",
+                path.display()
+            ))
+        })?;
+
+        vault::Vault::unlock(&path, &passphrase)
+            .map(Some)
+            .map_err(|error| Error::Vault(format!("{}: {error}", path.display())))
+    }
+}
@@ -18,6 +18,10 @@ pub fn decode(content: &str) -> Option<Command> {
 
     match args.next()?.to_lowercase().as_str() {
         "send" => Send::decode(args).map(Command::Send),
+        "chat" => Chat::decode(args, false).map(Command::Chat),
+        "schat" => Chat::decode(args, true).map(Command::Chat),
+        "resume" => Resume::decode(args).map(Command::Resume),
+        "accept" => Resume::decode(args).map(Command::Accept),
         cmd => Some(Command::Unsupported(cmd.to_string())),
     }
 }
@@ -25,9 +29,119 @@ pub fn decode(content: &str) -> Option<Command> {
 #[derive(Debug, Clone)]
 pub enum Command {
     Send(Send),
+    Chat(Chat),
+    /// Receiver asks to resume a send from a position
+    Resume(Resume),
+    /// Sender agrees to resume a send from a position
+    Accept(Resume),
     Unsupported(String),
 }
 
+/// An offer to chat over a direct connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chat {
+    pub host: IpAddr,
+    pub port: NonZeroU16,
+    /// Over TLS, offered with `SCHAT`
+    pub secure: bool,
+}
+
+impl Chat {
+    fn decode<'a>(mut args: impl Iterator<Item = &'a str>, secure: bool) -> Option<Self> {
+        // The protocol is always "chat"
+        let _protocol = args.next()?;
+        let host = args.next().and_then(decode_host)?;
+        let port = NonZeroU16::new(args.next()?.parse().ok()?)?;
+
+        Some(Self { host, port, secure })
+    }
+
+    pub fn encode(self, target: &dyn ToString) -> proto::Message {
+        let Self { host, port, secure } = self;
+
+        let host = encode_host(host);
+        let command = if secure { "SCHAT" } else { "CHAT" };
+
+        ctcp::query_message(
+            &ctcp::Command::DCC,
+            target.to_string(),
+            Some(format!("{command} chat {host} {port}")),
+        )
+    }
+}
+
+/// Arguments of both `DCC RESUME` & `DCC ACCEPT`, which identify the send by
+/// its port, or by its token when reverse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resume {
+    pub filename: String,
+    pub port: Option<NonZeroU16>,
+    pub position: u64,
+    pub token: Option<String>,
+}
+
+impl Resume {
+    fn decode<'a>(args: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let args = args.collect::<Vec<_>>();
+
+        if args.len() < 3 {
+            return None;
+        }
+
+        // Reverse sends are resumed with port 0 followed by the token
+        let reverse = args.len() >= 4 && args[args.len() - 3] == "0";
+        let filename_len = args.len() - if reverse { 3 } else { 2 };
+
+        let filename = args[..filename_len]
+            .join(" ")
+            .trim_matches('\"')
+            .to_string();
+
+        let mut remaining_args = args.into_iter().skip(filename_len);
+
+        let port = NonZeroU16::new(remaining_args.next()?.parse().ok()?);
+        let position = remaining_args.next()?.parse().ok()?;
+        let token = remaining_args.next().map(ToString::to_string);
+
+        if port.is_none() && token.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            filename,
+            port,
+            position,
+            token,
+        })
+    }
+
+    pub fn encode_resume(self, target: &dyn ToString) -> proto::Message {
+        self.encode("RESUME", target)
+    }
+
+    pub fn encode_accept(self, target: &dyn ToString) -> proto::Message {
+        self.encode("ACCEPT", target)
+    }
+
+    fn encode(self, command: &str, target: &dyn ToString) -> proto::Message {
+        let Self {
+            filename,
+            port,
+            position,
+            token,
+        } = self;
+
+        let port = port.map(NonZeroU16::get).unwrap_or(0);
+        let token = token.map(|token| format!(" {token}")).unwrap_or_default();
+
+        ctcp::query_message(
+            &ctcp::Command::DCC,
+            target.to_string(),
+            Some(format!("{command} {filename} {port} {position}{token}")),
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Send {
     Reverse {
@@ -67,6 +181,26 @@ impl Send {
         }
     }
 
+    /// Asks to resume this send from `position`
+    pub fn resume(&self, position: u64) -> Resume {
+        match self {
+            Send::Reverse {
+                filename, token, ..
+            } => Resume {
+                filename: filename.clone(),
+                port: None,
+                position,
+                token: Some(token.clone()),
+            },
+            Send::Direct { filename, port, .. } => Resume {
+                filename: filename.clone(),
+                port: Some(*port),
+                position,
+                token: None,
+            },
+        }
+    }
+
     fn decode<'a>(args: impl Iterator<Item = &'a str>) -> Option<Self> {
         let args = args.collect::<Vec<_>>();
 
@@ -255,6 +389,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn chat_decode() {
+        assert!(matches!(
+            decode("\u{1}DCC CHAT chat 1402301083 12350\u{1}"),
+            Some(Command::Chat(Chat {
+                host: IpAddr::V4(_),
+                secure: false,
+                ..
+            }))
+        ));
+
+        let chat = Chat::decode("chat ::1 12350".split_whitespace(), true);
+        assert_eq!(
+            chat,
+            Some(Chat {
+                host: "::1".parse().unwrap(),
+                port: NonZeroU16::new(12350).unwrap(),
+                secure: true,
+            })
+        );
+
+        // Reverse chats aren't supported
+        let chat = Chat::decode("chat 1402301083 0".split_whitespace(), false);
+        assert_eq!(chat, None);
+    }
+
+    #[test]
+    fn resume_decode() {
+        let resume = Resume::decode("\"my file name\" 12350 1024".split_whitespace());
+        assert_eq!(
+            resume,
+            Some(Resume {
+                filename: "my file name".to_string(),
+                port: NonZeroU16::new(12350),
+                position: 1024,
+                token: None,
+            })
+        );
+
+        let resume = Resume::decode("file.txt 0 1024 42".split_whitespace());
+        assert_eq!(
+            resume,
+            Some(Resume {
+                filename: "file.txt".to_string(),
+                port: None,
+                position: 1024,
+                token: Some("42".to_string()),
+            })
+        );
+
+        // Port 0 requires a token
+        let resume = Resume::decode("file.txt 0 1024".split_whitespace());
+        assert_eq!(resume, None);
+
+        let send = Send::decode("file.txt 1402301083 12350 4096".split_whitespace()).unwrap();
+        assert_eq!(
+            send.resume(1024),
+            Resume {
+                filename: "file.txt".to_string(),
+                port: NonZeroU16::new(12350),
+                position: 1024,
+                token: None,
+            }
+        );
+    }
+
     #[test]
     fn send_decode_numeric_filename() {
         // Succeeds because only 4 args so we know to only
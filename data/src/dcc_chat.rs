@@ -0,0 +1,212 @@
+//! Chats over a direct connection, offered with `DCC CHAT` or `DCC SCHAT`
+//! over TLS
+use chrono::{DateTime, Utc};
+
+use crate::file_transfer::Direction;
+use crate::message::{self, Source, Target};
+use crate::time::Posix;
+use crate::user::{Nick, NickRef};
+use crate::{ctcp, dcc, server, Message, Server};
+
+pub use self::manager::Manager;
+pub use self::task::Task;
+
+pub mod manager;
+pub mod task;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Id(u16);
+
+impl From<u16> for Id {
+    fn from(value: u16) -> Self {
+        Id(value)
+    }
+}
+
+impl From<Id> for u16 {
+    fn from(id: Id) -> Self {
+        id.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chat {
+    pub id: Id,
+    pub server: Server,
+    pub created_at: DateTime<Utc>,
+    /// Whether we offered the chat or received the offer
+    pub direction: Direction,
+    pub remote_user: Nick,
+    /// Our nickname when the chat started, which sent lines are from
+    pub nick: Nick,
+    pub secure: bool,
+    pub status: Status,
+}
+
+impl Chat {
+    pub fn is_connected(&self) -> bool {
+        matches!(self.status, Status::Connected)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// Pending approval
+    PendingApproval,
+    /// Ready (waiting for remote user to connect)
+    Ready,
+    /// Connecting to the remote user
+    Connecting,
+    Connected,
+    /// Closed by either side
+    Closed,
+    /// An error occured
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub at: DateTime<Utc>,
+    pub direction: Direction,
+    pub text: String,
+    /// Sent with CTCP ACTION, like `/me`
+    pub is_action: bool,
+}
+
+impl Line {
+    /// Decodes a line received, which may be a CTCP ACTION
+    pub fn received(text: String) -> Self {
+        let action = ctcp::parse_query(&text)
+            .filter(|query| matches!(query.command, ctcp::Command::Action))
+            .map(|query| query.params.unwrap_or_default().to_string());
+
+        Self {
+            at: Utc::now(),
+            direction: Direction::Received,
+            is_action: action.is_some(),
+            text: action.unwrap_or(text),
+        }
+    }
+
+    /// A line we sent, where `/me` sends an action
+    pub fn sent(text: &str) -> Self {
+        let action = text.strip_prefix("/me ");
+
+        Self {
+            at: Utc::now(),
+            direction: Direction::Sent,
+            is_action: action.is_some(),
+            text: action.unwrap_or(text).to_string(),
+        }
+    }
+
+    /// The line as a message of the chat's history
+    pub fn message(&self, chat: &Chat) -> Message {
+        let (direction, from) = match self.direction {
+            Direction::Sent => (message::Direction::Sent, &chat.nick),
+            Direction::Received => (message::Direction::Received, &chat.remote_user),
+        };
+
+        let (source, content) = if self.is_action {
+            (
+                Source::Action,
+                message::action_text(NickRef::from(from.as_ref()), Some(&self.text)),
+            )
+        } else {
+            (
+                Source::User(from.clone().into()),
+                message::parse_fragments(self.text.clone()),
+            )
+        };
+
+        Message {
+            received_at: Posix::now(),
+            server_time: self.at,
+            direction,
+            target: Target::Query {
+                nick: chat.remote_user.clone(),
+                source,
+            },
+            content,
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        }
+    }
+
+    /// The line as written to the connection
+    pub fn encode(&self) -> String {
+        if self.is_action {
+            ctcp::format(&ctcp::Command::Action, Some(&self.text))
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceiveRequest {
+    pub from: Nick,
+    /// Our nickname
+    pub nick: Nick,
+    pub dcc_chat: dcc::Chat,
+    pub server: Server,
+}
+
+#[derive(Debug)]
+pub struct OfferRequest {
+    pub to: Nick,
+    /// Our nickname
+    pub nick: Nick,
+    pub server: Server,
+    pub server_handle: server::Handle,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line() {
+        let line = Line::received("\u{1}ACTION waves\u{1}".to_string());
+        assert!(line.is_action);
+        assert_eq!(line.text, "waves");
+
+        let line = Line::sent("/me waves back");
+        assert!(line.is_action);
+        assert_eq!(line.encode(), "\u{1}ACTION waves back\u{1}");
+
+        assert_eq!(Line::sent("hello").encode(), "hello");
+    }
+
+    #[test]
+    fn line_message() {
+        let chat = Chat {
+            id: Id(1),
+            server: Server::from("libera"),
+            created_at: Utc::now(),
+            direction: Direction::Received,
+            remote_user: Nick::from("alice"),
+            nick: Nick::from("bob"),
+            secure: false,
+            status: Status::Connected,
+        };
+
+        let message = Line::sent("hello").message(&chat);
+        assert!(matches!(message.direction, message::Direction::Sent));
+        assert_eq!(message.content.text(), "hello");
+        assert!(matches!(
+            message.target,
+            Target::Query { ref nick, source: Source::User(ref user) }
+                if *nick == chat.remote_user && user.nickname() == chat.nick
+        ));
+
+        let message = Line::received("\u{1}ACTION waves\u{1}".to_string()).message(&chat);
+        assert!(matches!(message.direction, message::Direction::Received));
+        assert_eq!(message.content.text(), " ∙ alice waves");
+        assert!(matches!(message.target.source(), Source::Action));
+    }
+}
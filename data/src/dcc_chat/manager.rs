@@ -0,0 +1,252 @@
+use std::{collections::HashMap, num::NonZeroU16, time::Duration};
+
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt};
+use itertools::Itertools;
+use rand::Rng;
+
+use super::{task, Chat, Id, Line, OfferRequest, ReceiveRequest, Status, Task};
+use crate::user::Nick;
+use crate::{config, file_transfer::Direction, Message, Server};
+
+struct Item {
+    chat: Chat,
+    task: Option<task::Handle>,
+    /// Port listened on until the remote user connects
+    port: Option<NonZeroU16>,
+}
+
+pub enum Event {
+    NewChat(Chat, BoxStream<'static, task::Update>),
+    /// A line received, to record in the chat's history
+    Received {
+        server: Server,
+        remote_user: Nick,
+        message: Message,
+    },
+    /// The chat no longer listens on the port
+    PortReleased(NonZeroU16),
+}
+
+pub struct Manager {
+    config: config::FileTransfer,
+    items: HashMap<Id, Item>,
+}
+
+impl Manager {
+    pub fn new(config: config::FileTransfer) -> Self {
+        Self {
+            config,
+            items: HashMap::new(),
+        }
+    }
+
+    fn get_random_id(&self) -> Id {
+        let mut rng = rand::thread_rng();
+
+        loop {
+            let id = Id(rng.gen());
+
+            if !self.items.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+
+    fn server(&self) -> Option<task::Server> {
+        self.config.server.as_ref().map(|server| task::Server {
+            public_address: server.public_address,
+            bind_address: server.bind_address,
+            tls_cert_path: server.tls_cert_path.clone(),
+            tls_key_path: server.tls_key_path.clone(),
+        })
+    }
+
+    /// Offers a chat, listening on `port` for the remote user to connect.
+    /// It's secure when we have a certificate to present.
+    pub fn offer(&mut self, request: OfferRequest, port: NonZeroU16) -> Event {
+        let OfferRequest {
+            to,
+            nick,
+            server,
+            server_handle,
+        } = request;
+
+        let secure = self
+            .config
+            .server
+            .as_ref()
+            .is_some_and(|server| server.tls_cert_path.is_some());
+
+        log::debug!("DCC chat offered to {to} on port {port}");
+
+        let id = self.get_random_id();
+
+        let chat = Chat {
+            id,
+            server,
+            created_at: Utc::now(),
+            direction: Direction::Sent,
+            remote_user: to.clone(),
+            nick,
+            secure,
+            // Task will trigger ready update
+            status: Status::Connecting,
+        };
+
+        let task = Task::offer(id, port, secure, to, server_handle);
+        let (handle, stream) = task.spawn(
+            self.server(),
+            Duration::from_secs(self.config.timeout),
+            None,
+        );
+
+        self.items.insert(
+            id,
+            Item {
+                chat: chat.clone(),
+                task: Some(handle),
+                port: Some(port),
+            },
+        );
+
+        Event::NewChat(chat, stream.boxed())
+    }
+
+    pub fn receive(&mut self, request: ReceiveRequest, proxy: Option<&config::Proxy>) -> Event {
+        let ReceiveRequest {
+            from,
+            nick,
+            dcc_chat,
+            server,
+        } = request;
+
+        log::debug!("DCC chat offer received from {from}");
+
+        let id = self.get_random_id();
+
+        let chat = Chat {
+            id,
+            server,
+            created_at: Utc::now(),
+            direction: Direction::Received,
+            remote_user: from,
+            nick,
+            secure: dcc_chat.secure,
+            status: Status::PendingApproval,
+        };
+
+        let task = Task::receive(id, dcc_chat);
+        let (handle, stream) = task.spawn(
+            self.server(),
+            Duration::from_secs(self.config.timeout),
+            proxy.cloned(),
+        );
+
+        self.items.insert(
+            id,
+            Item {
+                chat: chat.clone(),
+                task: Some(handle),
+                port: None,
+            },
+        );
+
+        Event::NewChat(chat, stream.boxed())
+    }
+
+    /// Applies an update from a chat's task
+    pub fn update(&mut self, update: task::Update) -> Option<Event> {
+        match update {
+            task::Update::Ready(id) => {
+                let item = self.items.get_mut(&id)?;
+                item.chat.status = Status::Ready;
+            }
+            task::Update::Connecting(id) => {
+                let item = self.items.get_mut(&id)?;
+                item.chat.status = Status::Connecting;
+            }
+            task::Update::Connected(id) => {
+                let item = self.items.get_mut(&id)?;
+                log::debug!("DCC chat connected with {}", item.chat.remote_user);
+                item.chat.status = Status::Connected;
+
+                return item.port.take().map(Event::PortReleased);
+            }
+            task::Update::Received(id, text) => {
+                let chat = &self.items.get(&id)?.chat;
+
+                return Some(Event::Received {
+                    server: chat.server.clone(),
+                    remote_user: chat.remote_user.clone(),
+                    message: Line::received(text).message(chat),
+                });
+            }
+            task::Update::Closed(id) => {
+                let item = self.items.get_mut(&id)?;
+                log::debug!("DCC chat closed with {}", item.chat.remote_user);
+                item.chat.status = Status::Closed;
+                item.task = None;
+
+                return item.port.take().map(Event::PortReleased);
+            }
+            task::Update::Failed(id, error) => {
+                let item = self.items.get_mut(&id)?;
+                log::error!("DCC chat failed with {}: {error}", item.chat.remote_user);
+                item.chat.status = Status::Failed { error };
+                item.task = None;
+
+                return item.port.take().map(Event::PortReleased);
+            }
+        }
+
+        None
+    }
+
+    pub fn approve(&mut self, id: &Id) {
+        if let Some(Item {
+            task: Some(task), ..
+        }) = self.items.get_mut(id)
+        {
+            task.approve();
+        }
+    }
+
+    /// Sends a line, returning it to record in the chat's history
+    pub fn send(&mut self, id: &Id, text: &str) -> Option<Message> {
+        let Some(Item {
+            chat,
+            task: Some(task),
+            ..
+        }) = self.items.get_mut(id)
+        else {
+            return None;
+        };
+
+        if !chat.is_connected() {
+            return None;
+        }
+
+        let line = Line::sent(text);
+
+        task.send(line.encode());
+
+        Some(line.message(chat))
+    }
+
+    /// Closes the chat, returning the port it listened on
+    pub fn remove(&mut self, id: &Id) -> Option<NonZeroU16> {
+        self.items.remove(id)?.port
+    }
+
+    pub fn get<'a>(&'a self, id: &Id) -> Option<&'a Chat> {
+        self.items.get(id).map(|item| &item.chat)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &'_ Chat> {
+        self.items
+            .values()
+            .map(|item| &item.chat)
+            .sorted_by_key(|chat| chat.created_at)
+    }
+}
@@ -0,0 +1,308 @@
+use std::{io, net::IpAddr, num::NonZeroU16, path::PathBuf, time::Duration};
+
+use futures::{
+    channel::mpsc::{self, Receiver, Sender},
+    future, stream, SinkExt, Stream, StreamExt,
+};
+use irc::{connection, Connection, LossyLinesCodec};
+use thiserror::Error;
+use tokio::{task::JoinHandle, time};
+
+use super::Id;
+use crate::{config, dcc, server, user::Nick};
+
+/// Longer lines are truncated so a peer can't exhaust memory by never
+/// sending a newline
+const MAX_LINE_LENGTH: usize = 4096;
+
+pub struct Handle {
+    sender: Sender<Action>,
+    task: JoinHandle<()>,
+}
+
+impl Handle {
+    pub fn approve(&mut self) {
+        let _ = self.sender.try_send(Action::Approve);
+    }
+
+    pub fn send(&mut self, line: String) {
+        let _ = self.sender.try_send(Action::Send(line));
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+pub enum Task {
+    /// Connect to the remote user once approved
+    Receive { id: Id, dcc_chat: dcc::Chat },
+    /// Offer a chat & wait for the remote user to connect on `port`
+    Offer {
+        id: Id,
+        port: NonZeroU16,
+        secure: bool,
+        remote_user: Nick,
+        server_handle: server::Handle,
+    },
+}
+
+impl Task {
+    pub fn receive(id: Id, dcc_chat: dcc::Chat) -> Self {
+        Self::Receive { id, dcc_chat }
+    }
+
+    pub fn offer(
+        id: Id,
+        port: NonZeroU16,
+        secure: bool,
+        remote_user: Nick,
+        server_handle: server::Handle,
+    ) -> Self {
+        Self::Offer {
+            id,
+            port,
+            secure,
+            remote_user,
+            server_handle,
+        }
+    }
+
+    pub fn spawn(
+        self,
+        server: Option<Server>,
+        timeout: Duration,
+        proxy: Option<config::Proxy>,
+    ) -> (Handle, impl Stream<Item = Update>) {
+        let (action_sender, action_receiver) = mpsc::channel(100);
+        let (update_sender, update_receiver) = mpsc::channel(100);
+
+        let task = tokio::spawn(async move {
+            let mut update = update_sender.clone();
+
+            let id = match &self {
+                Task::Receive { id, .. } | Task::Offer { id, .. } => *id,
+            };
+
+            let result = match self {
+                Task::Receive { id, dcc_chat } => {
+                    receive(id, dcc_chat, action_receiver, update_sender, proxy).await
+                }
+                Task::Offer {
+                    id,
+                    port,
+                    secure,
+                    remote_user,
+                    server_handle,
+                } => {
+                    offer(
+                        id,
+                        port,
+                        secure,
+                        remote_user,
+                        server_handle,
+                        action_receiver,
+                        update_sender,
+                        server,
+                        timeout,
+                    )
+                    .await
+                }
+            };
+
+            match result {
+                Ok(()) => {
+                    let _ = update.send(Update::Closed(id)).await;
+                }
+                Err(error) => {
+                    let _ = update.send(Update::Failed(id, error.to_string())).await;
+                }
+            }
+        });
+
+        (
+            Handle {
+                sender: action_sender,
+                task,
+            },
+            update_receiver,
+        )
+    }
+}
+
+pub enum Action {
+    Approve,
+    Send(String),
+}
+
+#[derive(Debug)]
+pub enum Update {
+    Ready(Id),
+    Connecting(Id),
+    Connected(Id),
+    Received(Id, String),
+    Closed(Id),
+    Failed(Id, String),
+}
+
+pub struct Server {
+    pub public_address: IpAddr,
+    pub bind_address: IpAddr,
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+}
+
+async fn receive(
+    id: Id,
+    dcc_chat: dcc::Chat,
+    mut action: Receiver<Action>,
+    mut update: Sender<Update>,
+    proxy: Option<config::Proxy>,
+) -> Result<(), Error> {
+    // Wait for approval
+    let Some(Action::Approve) = action.next().await else {
+        return Ok(());
+    };
+
+    let _ = update.send(Update::Connecting(id)).await;
+
+    let security = if dcc_chat.secure {
+        // Certificates of remote users are self-signed
+        connection::Security::Secured {
+            accept_invalid_certs: true,
+            root_cert_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+        }
+    } else {
+        connection::Security::Unsecured
+    };
+
+    let connection = Connection::new(
+        connection::Config {
+            server: &dcc_chat.host.to_string(),
+            port: dcc_chat.port.get(),
+            security,
+            proxy: proxy.map(From::from),
+        },
+        LossyLinesCodec::new(MAX_LINE_LENGTH),
+    )
+    .await?;
+
+    run(id, connection, action, update).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn offer(
+    id: Id,
+    port: NonZeroU16,
+    secure: bool,
+    remote_user: Nick,
+    mut server_handle: server::Handle,
+    action: Receiver<Action>,
+    mut update: Sender<Update>,
+    server: Option<Server>,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let server = server.ok_or(Error::OfferNoServerConfig)?;
+
+    let _ = server_handle
+        .send(
+            dcc::Chat {
+                host: server.public_address,
+                port,
+                secure,
+            }
+            .encode(&remote_user),
+        )
+        .await;
+
+    let _ = update.send(Update::Ready(id)).await;
+
+    let security = if secure {
+        connection::Security::Secured {
+            accept_invalid_certs: false,
+            root_cert_path: None,
+            client_cert_path: server.tls_cert_path.as_ref(),
+            client_key_path: server.tls_key_path.as_ref(),
+        }
+    } else {
+        connection::Security::Unsecured
+    };
+
+    let connection = time::timeout(
+        timeout,
+        Connection::listen_and_accept(
+            server.bind_address,
+            port.get(),
+            security,
+            LossyLinesCodec::new(MAX_LINE_LENGTH),
+        ),
+    )
+    .await
+    .map_err(|_| Error::TimeoutConnection)??;
+
+    // Whoever connects to the port first gets the chat, so record who did
+    match connection.peer_addr() {
+        Ok(address) => log::info!("DCC chat offered to {remote_user} accepted from {address}"),
+        Err(error) => {
+            log::warn!("DCC chat offered to {remote_user} accepted from unknown address: {error}")
+        }
+    }
+
+    run(id, connection, action, update).await
+}
+
+/// Relays lines until either side closes the chat
+async fn run(
+    id: Id,
+    connection: Connection<LossyLinesCodec>,
+    action: Receiver<Action>,
+    mut update: Sender<Update>,
+) -> Result<(), Error> {
+    enum Input {
+        Line(Result<String, io::Error>),
+        Disconnected,
+        Action(Action),
+    }
+
+    let _ = update.send(Update::Connected(id)).await;
+
+    let (mut sink, lines) = connection.split();
+
+    let mut inputs = stream::select(
+        lines
+            .map(Input::Line)
+            .chain(stream::once(future::ready(Input::Disconnected))),
+        action.map(Input::Action),
+    );
+
+    while let Some(input) = inputs.next().await {
+        match input {
+            Input::Line(line) => {
+                let _ = update.send(Update::Received(id, line?)).await;
+            }
+            Input::Action(Action::Send(line)) => {
+                sink.send(line).await?;
+            }
+            Input::Action(Action::Approve) => {}
+            Input::Disconnected => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("[file_transfer.server] must be configured to offer a chat")]
+    OfferNoServerConfig,
+    #[error("connection error: {0}")]
+    Connection(#[from] connection::Error),
+    #[error("connection error: {0}")]
+    Io(#[from] io::Error),
+    #[error("timed out waiting for remote to connect")]
+    TimeoutConnection,
+}
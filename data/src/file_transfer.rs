@@ -4,13 +4,15 @@ use std::time::Duration;
 use chrono::{DateTime, Utc};
 
 use crate::user::Nick;
-use crate::{dcc, server, Server};
+use crate::{dcc, isupport, server, Server};
 
 pub use self::manager::Manager;
 pub use self::task::Task;
+pub use self::throttle::Throttle;
 
 pub mod manager;
 pub mod task;
+pub mod throttle;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Id(u16);
@@ -36,10 +38,26 @@ pub struct FileTransfer {
     pub remote_user: Nick,
     pub filename: String,
     pub size: u64,
+    /// Bytes already transferred before the transfer was resumed
+    pub resumed_from: u64,
     pub status: Status,
+    /// Paused while active
+    pub paused: bool,
 }
 
 impl FileTransfer {
+    /// Time left at the current speed
+    pub fn eta(&self) -> Option<Duration> {
+        match self.status {
+            Status::Active {
+                transferred, speed, ..
+            } if speed > 0 && !self.paused => Some(Duration::from_secs(
+                self.size.saturating_sub(transferred) / speed,
+            )),
+            _ => None,
+        }
+    }
+
     pub fn progress(&self) -> f64 {
         match self.status {
             Status::Active { transferred, .. } => transferred as f64 / self.size as f64,
@@ -78,26 +96,54 @@ pub enum Status {
     PendingApproval,
     /// Pending reverse confirmation
     PendingReverseConfirmation,
+    /// Pending the sender accepting to resume from a position
+    PendingResume { position: u64 },
+    /// Waiting for one of `max_concurrent` transfers to finish
+    Waiting,
     /// Queued (needs an open port to begin)
     Queued,
     /// Ready (waiting for remote user to connect)
     Ready,
     /// Transfer is actively sending / receiving
-    Active { transferred: u64, elapsed: Duration },
+    Active {
+        transferred: u64,
+        elapsed: Duration,
+        /// Bytes per second
+        speed: u64,
+    },
     /// Transfer is complete
-    Completed { elapsed: Duration, sha256: String },
+    Completed {
+        elapsed: Duration,
+        sha256: Option<String>,
+    },
     /// An error occured
     Failed { error: String },
+    /// Refused without asking, so there's nothing to retry
+    Refused { reason: String },
 }
 
 #[derive(Debug, Clone)]
 pub struct ReceiveRequest {
     pub from: Nick,
+    /// Account of the sender, if known
+    pub account: Option<String>,
+    /// Of the server, to compare `from` with trusted nicks
+    pub casemapping: isupport::CaseMap,
     pub dcc_send: dcc::Send,
     pub server: Server,
     pub server_handle: server::Handle,
 }
 
+/// A `DCC RESUME` from who we send to, or the `DCC ACCEPT` replying to the
+/// one we sent
+#[derive(Debug, Clone)]
+pub struct ResumeRequest {
+    pub from: Nick,
+    pub resume: dcc::Resume,
+    pub accepted: bool,
+    pub server: Server,
+}
+
 #[derive(Debug)]
 pub struct SendRequest {
     pub to: Nick,
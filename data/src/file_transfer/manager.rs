@@ -1,7 +1,8 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     num::NonZeroU16,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
@@ -10,17 +11,38 @@ use futures::{stream::BoxStream, StreamExt};
 use itertools::Itertools;
 use rand::Rng;
 
-use super::{task, Direction, FileTransfer, Id, ReceiveRequest, SendRequest, Status, Task};
-use crate::{config, dcc};
+use super::{
+    task, Direction, FileTransfer, Id, ReceiveRequest, ResumeRequest, SendRequest, Status, Task,
+    Throttle,
+};
+use crate::user::{Nick, NickRef};
+use crate::{config, dcc, isupport, server};
 
 enum Item {
     Working {
         file_transfer: FileTransfer,
         task: task::Handle,
+        request: Request,
     },
     Finished(FileTransfer),
 }
 
+/// What a transfer was requested with, to start it once approved or again
+/// once failed
+enum Request {
+    Receive {
+        dcc_send: dcc::Send,
+        server_handle: server::Handle,
+        save_to: Option<PathBuf>,
+        /// Resume a partial file at `save_to`, only once retried
+        resume: bool,
+    },
+    Send {
+        path: PathBuf,
+        server_handle: server::Handle,
+    },
+}
+
 impl Item {
     fn file_transfer(&self) -> &FileTransfer {
         match self {
@@ -35,10 +57,51 @@ impl Item {
             Item::Finished(file_transfer) => file_transfer,
         }
     }
+
+    /// Resumes are matched by port or token, since some clients don't echo
+    /// the filename. They're only forwarded while the transfer expects one: an
+    /// `ACCEPT` once we asked to resume, a `RESUME` once we offered the file.
+    fn is_resumed_by(
+        &self,
+        id: Id,
+        resume: &dcc::Resume,
+        accepted: bool,
+        used_port: Option<&NonZeroU16>,
+    ) -> bool {
+        match self {
+            Item::Working {
+                file_transfer,
+                request: Request::Receive { dcc_send, .. },
+                ..
+            } if accepted && matches!(file_transfer.status, Status::PendingResume { .. }) => {
+                let expected = dcc_send.resume(resume.position);
+
+                expected.port == resume.port && expected.token == resume.token
+            }
+            Item::Working {
+                file_transfer,
+                request: Request::Send { .. },
+                ..
+            } if !accepted
+                && matches!(
+                    file_transfer.status,
+                    Status::Ready | Status::PendingReverseConfirmation
+                ) =>
+            {
+                match &resume.token {
+                    Some(token) => *token == u16::from(id).to_string(),
+                    None => resume.port.is_some() && resume.port.as_ref() == used_port,
+                }
+            }
+            _ => false,
+        }
+    }
 }
 
 pub enum Event {
     NewTransfer(FileTransfer, BoxStream<'static, task::Update>),
+    /// A failed transfer started again
+    Retried(BoxStream<'static, task::Update>),
 }
 
 pub struct Manager {
@@ -47,15 +110,29 @@ pub struct Manager {
     /// Queued = waiting for port assignment
     queued: VecDeque<Id>,
     used_ports: HashMap<Id, NonZeroU16>,
+    /// Ports listened on for DCC chats, sharing the range
+    reserved_ports: Vec<NonZeroU16>,
+    /// Started transfers, up to `max_concurrent`
+    started: HashSet<Id>,
+    /// Waiting = approved, but waiting for a started transfer to finish
+    waiting: VecDeque<Id>,
+    /// Shared by every transfer for the combined bandwidth limit
+    throttle: Arc<Throttle>,
 }
 
 impl Manager {
     pub fn new(config: config::FileTransfer) -> Self {
+        let throttle = Arc::new(Throttle::new(config.bandwidth_limit.map(kibibytes)));
+
         Self {
             config,
             items: HashMap::new(),
             queued: VecDeque::new(),
             used_ports: HashMap::new(),
+            reserved_ports: vec![],
+            started: HashSet::new(),
+            waiting: VecDeque::new(),
+            throttle,
         }
     }
 
@@ -78,6 +155,13 @@ impl Manager {
         })
     }
 
+    fn pace(&self) -> task::Pace {
+        task::Pace::new(
+            self.throttle.clone(),
+            self.config.transfer_bandwidth_limit.map(kibibytes),
+        )
+    }
+
     pub fn send(&mut self, request: SendRequest, proxy: Option<config::Proxy>) -> Option<Event> {
         let SendRequest {
             to,
@@ -108,19 +192,30 @@ impl Manager {
             filename: filename.clone(),
             // Will be updated by task
             size: 0,
+            resumed_from: 0,
             status: if reverse {
                 Status::PendingReverseConfirmation
             } else {
                 // Task will trigger queued update
                 Status::Queued
             },
+            paused: false,
         };
 
-        let task = Task::send(id, path, filename, to, reverse, server_handle);
+        let task = Task::send(
+            id,
+            path.clone(),
+            filename,
+            to,
+            reverse,
+            server_handle.clone(),
+        );
         let (handle, stream) = task.spawn(
             self.server(),
             Duration::from_secs(self.config.timeout),
             proxy,
+            self.pace(),
+            self.config.checksum,
         );
 
         self.items.insert(
@@ -128,9 +223,15 @@ impl Manager {
             Item::Working {
                 file_transfer: file_transfer.clone(),
                 task: handle,
+                request: Request::Send {
+                    path,
+                    server_handle,
+                },
             },
         );
 
+        self.start(id);
+
         Some(Event::NewTransfer(file_transfer, stream.boxed()))
     }
 
@@ -141,6 +242,8 @@ impl Manager {
     ) -> Option<Event> {
         let ReceiveRequest {
             from,
+            account,
+            casemapping,
             dcc_send,
             server,
             server_handle,
@@ -158,6 +261,7 @@ impl Manager {
                 if let Some(Item::Working {
                     file_transfer,
                     task,
+                    ..
                 }) = self.items.get_mut(&id)
                 {
                     if file_transfer.filename == *filename {
@@ -188,14 +292,43 @@ impl Manager {
             remote_user: from.clone(),
             filename: dcc_send.filename().to_string(),
             size: dcc_send.size(),
+            resumed_from: 0,
             status: Status::PendingApproval,
+            paused: false,
         };
 
-        let task = Task::receive(id, dcc_send, from, server_handle);
+        if self.config.refuses(&file_transfer.filename) {
+            log::warn!(
+                "File transfer refused from {from} for risky file {:?}",
+                file_transfer.filename
+            );
+
+            let file_transfer = FileTransfer {
+                status: Status::Refused {
+                    reason: "file has a risky extension".to_string(),
+                },
+                ..file_transfer
+            };
+
+            self.items.insert(id, Item::Finished(file_transfer.clone()));
+
+            // Nothing to run, but the request is still shown
+            return Some(Event::NewTransfer(
+                file_transfer,
+                futures::stream::empty().boxed(),
+            ));
+        }
+
+        let auto_accept =
+            self.auto_accept_path(&from, account.as_deref(), casemapping, &file_transfer);
+
+        let task = Task::receive(id, dcc_send.clone(), from, server_handle.clone());
         let (handle, stream) = task.spawn(
             self.server(),
             Duration::from_secs(self.config.timeout),
             proxy.cloned(),
+            self.pace(),
+            self.config.checksum,
         );
 
         self.items.insert(
@@ -203,12 +336,103 @@ impl Manager {
             Item::Working {
                 file_transfer: file_transfer.clone(),
                 task: handle,
+                request: Request::Receive {
+                    dcc_send,
+                    server_handle,
+                    save_to: None,
+                    resume: false,
+                },
             },
         );
 
+        if let Some(save_to) = auto_accept {
+            log::debug!(
+                "File transfer auto accepted from {} to {save_to:?}",
+                file_transfer.remote_user
+            );
+            self.approve(&id, save_to);
+        }
+
         Some(Event::NewTransfer(file_transfer, stream.boxed()))
     }
 
+    /// Runs the `on_complete` command for a received file in the background
+    fn on_complete(&self, file_transfer: &FileTransfer, save_to: &Path, sha256: Option<&str>) {
+        let Some(mut command) = self.config.on_complete_command(
+            save_to,
+            file_transfer.remote_user.as_ref(),
+            file_transfer.server.as_ref(),
+            sha256,
+        ) else {
+            return;
+        };
+
+        std::thread::spawn(move || match command.output() {
+            Ok(output) if output.status.success() => {
+                log::debug!("File transfer on_complete command finished: {command:?}");
+            }
+            Ok(output) => log::warn!(
+                "File transfer on_complete command {command:?} failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(error) => {
+                log::warn!("File transfer on_complete command {command:?} failed: {error}");
+            }
+        });
+    }
+
+    /// Where a transfer from a trusted user is saved, a new file named after
+    /// the remote filename
+    fn auto_accept_path(
+        &self,
+        from: &Nick,
+        account: Option<&str>,
+        casemapping: isupport::CaseMap,
+        file_transfer: &FileTransfer,
+    ) -> Option<PathBuf> {
+        let auto_accept = &self.config.auto_accept;
+
+        if !auto_accept.accepts(
+            NickRef::from(from.as_ref()),
+            account,
+            file_transfer.size,
+            casemapping,
+        ) {
+            return None;
+        }
+
+        // The remote filename can't point outside the directory
+        let filename = Path::new(&file_transfer.filename).file_name()?.to_str()?;
+
+        let directory = auto_accept
+            .save_directory
+            .as_ref()
+            .unwrap_or(&self.config.save_directory);
+
+        let saving = self
+            .items
+            .values()
+            .filter_map(|item| match item {
+                Item::Working {
+                    request:
+                        Request::Receive {
+                            save_to: Some(save_to),
+                            ..
+                        },
+                    ..
+                } => Some(save_to.as_path()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Never write into an existing file, which anyone using a trusted
+        // nick could otherwise append to, nor one another transfer saves to
+        Some(unique_path(directory, filename, |path| {
+            std::fs::symlink_metadata(path).is_ok() || saving.contains(&path)
+        }))
+    }
+
     pub fn update(&mut self, update: task::Update) {
         match update {
             task::Update::Metadata(id, size) => {
@@ -222,6 +446,7 @@ impl Manager {
                 if let Some(Item::Working {
                     file_transfer,
                     task,
+                    ..
                 }) = self.items.get_mut(&id)
                 {
                     if let Some(port) = available_port {
@@ -235,6 +460,26 @@ impl Manager {
                     }
                 }
             }
+            task::Update::PendingResume(id, position) => {
+                if let Some(item) = self.items.get_mut(&id) {
+                    item.file_transfer_mut().status = Status::PendingResume { position };
+                }
+            }
+            task::Update::Resumed(id, position) => {
+                if let Some(item) = self.items.get_mut(&id) {
+                    let file_transfer = item.file_transfer_mut();
+                    log::debug!(
+                        "File transfer resumed {} {} for {:?} from {position}",
+                        match file_transfer.direction {
+                            Direction::Sent => "to",
+                            Direction::Received => "from",
+                        },
+                        file_transfer.remote_user,
+                        file_transfer.filename,
+                    );
+                    file_transfer.resumed_from = position;
+                }
+            }
             task::Update::Ready(id) => {
                 if let Some(item) = self.items.get_mut(&id) {
                     item.file_transfer_mut().status = Status::Ready;
@@ -244,6 +489,7 @@ impl Manager {
                 id,
                 transferred,
                 elapsed,
+                speed,
             } => {
                 if let Some(item) = self.items.get_mut(&id) {
                     let file_transfer = item.file_transfer_mut();
@@ -260,6 +506,7 @@ impl Manager {
                     file_transfer.status = Status::Active {
                         transferred,
                         elapsed,
+                        speed,
                     };
                 }
             }
@@ -268,7 +515,12 @@ impl Manager {
                 elapsed,
                 sha256,
             } => {
-                if let Some(Item::Working { file_transfer, .. }) = self.items.remove(&id) {
+                if let Some(Item::Working {
+                    file_transfer,
+                    request,
+                    ..
+                }) = self.items.remove(&id)
+                {
                     log::debug!(
                         "File transfer completed {} {} for {:?} in {:.2}s",
                         match file_transfer.direction {
@@ -280,17 +532,55 @@ impl Manager {
                         elapsed.as_secs_f32()
                     );
 
+                    if let Request::Receive {
+                        save_to: Some(save_to),
+                        ..
+                    } = &request
+                    {
+                        self.on_complete(&file_transfer, save_to, sha256.as_deref());
+                    }
+
                     self.items.insert(
                         id,
                         Item::Finished(FileTransfer {
                             status: Status::Completed { elapsed, sha256 },
+                            paused: false,
                             ..file_transfer
                         }),
                     );
 
                     self.recycle_port(id);
+                    self.finish(id);
                 }
             }
+            task::Update::Exited(id) => {
+                // Ended without finishing, e.g. once nothing can approve it
+                if let Some(item) = self.items.get_mut(&id) {
+                    let file_transfer = item.file_transfer_mut();
+
+                    if !matches!(
+                        file_transfer.status,
+                        Status::Completed { .. } | Status::Failed { .. }
+                    ) {
+                        log::warn!(
+                            "File transfer {} {} for {:?} ended unexpectedly",
+                            match file_transfer.direction {
+                                Direction::Sent => "to",
+                                Direction::Received => "from",
+                            },
+                            &file_transfer.remote_user,
+                            &file_transfer.filename,
+                        );
+                        file_transfer.status = Status::Failed {
+                            error: "transfer ended unexpectedly".to_string(),
+                        };
+                        file_transfer.paused = false;
+                    }
+                }
+
+                self.recycle_port(id);
+                self.finish(id);
+            }
             task::Update::Failed(id, error) => {
                 if let Some(item) = self.items.get_mut(&id) {
                     let file_transfer = item.file_transfer_mut();
@@ -304,8 +594,10 @@ impl Manager {
                         &file_transfer.filename,
                     );
                     file_transfer.status = Status::Failed { error };
+                    file_transfer.paused = false;
 
                     self.recycle_port(id);
+                    self.finish(id);
                 }
             }
         }
@@ -317,22 +609,105 @@ impl Manager {
         server
             .bind_ports
             .clone()
-            .find(|port| !self.used_ports.values().any(|used| used.get() == *port))
-            .and_then(NonZeroU16::new)
+            .filter_map(NonZeroU16::new)
+            .find(|port| {
+                !self.used_ports.values().any(|used| used == port)
+                    && !self.reserved_ports.contains(port)
+            })
     }
 
     fn recycle_port(&mut self, id: Id) {
         if let Some(port) = self.used_ports.remove(&id) {
-            if let Some(Item::Working {
-                task,
-                file_transfer,
-            }) = self
-                .queued
-                .pop_front()
-                .and_then(|id| self.items.get_mut(&id))
-            {
-                task.port_available(port);
-                self.used_ports.insert(file_transfer.id, port);
+            self.port_freed(port);
+        }
+    }
+
+    fn port_freed(&mut self, port: NonZeroU16) {
+        if let Some(Item::Working {
+            task,
+            file_transfer,
+            ..
+        }) = self
+            .queued
+            .pop_front()
+            .and_then(|id| self.items.get_mut(&id))
+        {
+            task.port_available(port);
+            self.used_ports.insert(file_transfer.id, port);
+        }
+    }
+
+    /// Reserves a port of the range to listen on outside a transfer
+    pub fn reserve_port(&mut self) -> Option<NonZeroU16> {
+        let port = self.get_available_port()?;
+
+        self.reserved_ports.push(port);
+
+        Some(port)
+    }
+
+    pub fn release_port(&mut self, port: NonZeroU16) {
+        if let Some(index) = self.reserved_ports.iter().position(|used| *used == port) {
+            self.reserved_ports.remove(index);
+            self.port_freed(port);
+        }
+    }
+
+    /// Starts the transfer, unless `max_concurrent` are started and it has to
+    /// wait for one to finish
+    fn start(&mut self, id: Id) {
+        let max_concurrent = self.config.max_concurrent.max(1);
+
+        if self.started.contains(&id) || self.waiting.contains(&id) {
+            return;
+        }
+
+        if self.started.len() >= max_concurrent {
+            if let Some(item) = self.items.get_mut(&id) {
+                item.file_transfer_mut().status = Status::Waiting;
+                self.waiting.push_back(id);
+            }
+            return;
+        }
+
+        let reverse = self.config.passive;
+
+        if let Some(Item::Working {
+            file_transfer,
+            task,
+            request,
+        }) = self.items.get_mut(&id)
+        {
+            match request {
+                Request::Receive {
+                    save_to: Some(save_to),
+                    resume,
+                    ..
+                } => {
+                    // Task will trigger its next update
+                    file_transfer.status = Status::Ready;
+                    task.approve(save_to.clone(), *resume);
+                }
+                Request::Receive { save_to: None, .. } => return,
+                Request::Send { .. } => {
+                    file_transfer.status = if reverse {
+                        Status::PendingReverseConfirmation
+                    } else {
+                        Status::Queued
+                    };
+                    task.start();
+                }
+            }
+
+            self.started.insert(id);
+        }
+    }
+
+    /// Frees the transfer's slot for the next waiting one
+    fn finish(&mut self, id: Id) {
+        if self.started.remove(&id) {
+            if let Some(next) = self.waiting.pop_front() {
+                self.start(next);
             }
         }
     }
@@ -340,15 +715,137 @@ impl Manager {
     pub fn remove(&mut self, id: &Id) {
         let _ = self.items.remove(id);
         self.queued.retain(|i| i != id);
+        self.waiting.retain(|i| i != id);
         self.recycle_port(*id);
+        self.finish(*id);
     }
 
     pub fn approve(&mut self, id: &Id, save_to: PathBuf) {
-        if let Some(Item::Working { task, .. }) = self.items.get_mut(id) {
-            task.approve(save_to);
+        if let Some(Item::Working {
+            request: Request::Receive {
+                save_to: approved, ..
+            },
+            ..
+        }) = self.items.get_mut(id)
+        {
+            *approved = Some(save_to);
+            self.start(*id);
+        }
+    }
+
+    pub fn set_paused(&mut self, id: &Id, paused: bool) {
+        if let Some(Item::Working {
+            file_transfer,
+            task,
+            ..
+        }) = self.items.get_mut(id)
+        {
+            file_transfer.paused = paused;
+            task.set_paused(paused);
         }
     }
 
+    /// Forwards a `DCC RESUME` or `DCC ACCEPT` to the transfer it resumes
+    pub fn resume(&mut self, request: ResumeRequest) {
+        let ResumeRequest {
+            from,
+            resume,
+            accepted,
+            server,
+        } = request;
+
+        let used_ports = &self.used_ports;
+
+        let Some((_, Item::Working { task, .. })) = self.items.iter_mut().find(|(id, item)| {
+            let file_transfer = item.file_transfer();
+
+            file_transfer.server == server
+                && file_transfer.remote_user == from
+                && item.is_resumed_by(**id, &resume, accepted, used_ports.get(id))
+        }) else {
+            log::debug!("File transfer resume from {from} matches no transfer: {resume:?}");
+            return;
+        };
+
+        if accepted {
+            task.resume_accepted(resume.position);
+        } else {
+            task.resume(resume.position);
+        }
+    }
+
+    /// Starts a failed transfer again. Receiving to the same file resumes it,
+    /// while sending offers the file again for the remote user to resume.
+    pub fn retry(&mut self, id: &Id, proxy: Option<config::Proxy>) -> Option<Event> {
+        let server = self.server();
+        let timeout = Duration::from_secs(self.config.timeout);
+        let pace = self.pace();
+        let checksum = self.config.checksum;
+
+        let Some(Item::Working {
+            file_transfer,
+            task,
+            request,
+        }) = self.items.get_mut(id)
+        else {
+            return None;
+        };
+
+        if !matches!(file_transfer.status, Status::Failed { .. }) {
+            return None;
+        }
+
+        log::debug!(
+            "File transfer retried {} {} for {:?}",
+            match file_transfer.direction {
+                Direction::Sent => "to",
+                Direction::Received => "from",
+            },
+            file_transfer.remote_user,
+            file_transfer.filename,
+        );
+
+        let (handle, stream) = match request {
+            Request::Receive {
+                dcc_send,
+                server_handle,
+                resume,
+                ..
+            } => {
+                file_transfer.status = Status::PendingApproval;
+                *resume = true;
+
+                Task::receive(
+                    *id,
+                    dcc_send.clone(),
+                    file_transfer.remote_user.clone(),
+                    server_handle.clone(),
+                )
+                .spawn(server, timeout, proxy, pace, checksum)
+            }
+            Request::Send {
+                path,
+                server_handle,
+            } => Task::send(
+                *id,
+                path.clone(),
+                file_transfer.filename.clone(),
+                file_transfer.remote_user.clone(),
+                self.config.passive,
+                server_handle.clone(),
+            )
+            .spawn(server, timeout, proxy, pace, checksum),
+        };
+
+        file_transfer.resumed_from = 0;
+        *task = handle;
+
+        // Received to the same file once approved, which resumes it
+        self.start(*id);
+
+        Some(Event::Retried(stream.boxed()))
+    }
+
     pub fn get<'a>(&'a self, id: &Id) -> Option<&'a FileTransfer> {
         self.items.get(id).map(Item::file_transfer)
     }
@@ -361,3 +858,62 @@ impl Manager {
         self.items.values().len() == 0
     }
 }
+
+fn kibibytes(kib: u64) -> u64 {
+    kib * 1024
+}
+
+/// `filename` in `directory`, numbered like `file (1).ext` while `is_taken`
+fn unique_path(directory: &Path, filename: &str, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let path = directory.join(filename);
+
+    if !is_taken(&path) {
+        return path;
+    }
+
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+        _ => (filename, String::new()),
+    };
+
+    let mut n = 1;
+
+    loop {
+        let path = directory.join(format!("{stem} ({n}){extension}"));
+
+        if !is_taken(&path) {
+            return path;
+        }
+
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unique_path_numbers_taken_files() {
+        let directory = Path::new("downloads");
+        let taken = [
+            directory.join("notes.txt"),
+            directory.join("notes (1).txt"),
+            directory.join(".profile"),
+        ];
+        let is_taken = |path: &Path| taken.iter().any(|taken| taken == path);
+
+        assert_eq!(
+            unique_path(directory, "notes.txt", is_taken),
+            directory.join("notes (2).txt")
+        );
+        assert_eq!(
+            unique_path(directory, ".profile", is_taken),
+            directory.join(".profile (1)")
+        );
+        assert_eq!(
+            unique_path(directory, "archive.tar.gz", is_taken),
+            directory.join("archive.tar.gz")
+        );
+    }
+}
@@ -1,41 +1,60 @@
 use std::{
-    io,
+    io::{self, SeekFrom},
     net::IpAddr,
     num::NonZeroU16,
     path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use bytes::{Bytes, BytesMut};
 use futures::{
     channel::mpsc::{self, Receiver, Sender},
+    future::{self, Either},
     SinkExt, Stream,
 };
 use irc::{connection, proto::command, BytesCodec, Connection};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncWriteExt},
+    fs::{self, File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
     task::JoinHandle,
     time,
 };
 use tokio_stream::StreamExt;
 
-use super::Id;
+use super::{Id, Throttle};
 use crate::{config, dcc, server, user::Nick};
 
 /// 16 KiB
 pub const BUFFER_SIZE: usize = 16 * 1024;
 
+/// How often a paused transfer checks if it was unpaused
+const PAUSED_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct Handle {
     sender: Sender<Action>,
+    paused: Arc<AtomicBool>,
     task: JoinHandle<()>,
 }
 
 impl Handle {
-    pub fn approve(&mut self, save_to: PathBuf) {
-        let _ = self.sender.try_send(Action::Approve { save_to });
+    /// Receives to `save_to`, resuming it if it's a partial file and `resume`
+    pub fn approve(&mut self, save_to: PathBuf, resume: bool) {
+        let _ = self.sender.try_send(Action::Approve { save_to, resume });
+    }
+
+    /// Starts offering a file to send
+    pub fn start(&mut self) {
+        let _ = self.sender.try_send(Action::Start);
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
     }
 
     pub fn confirm_reverse(&mut self, host: IpAddr, port: NonZeroU16) {
@@ -47,6 +66,16 @@ impl Handle {
     pub fn port_available(&mut self, port: NonZeroU16) {
         let _ = self.sender.try_send(Action::PortAvailable { port });
     }
+
+    /// Remote user asked to resume our send from `position`
+    pub fn resume(&mut self, position: u64) {
+        let _ = self.sender.try_send(Action::Resume { position });
+    }
+
+    /// Remote user accepted to resume their send from `position`
+    pub fn resume_accepted(&mut self, position: u64) {
+        let _ = self.sender.try_send(Action::ResumeAccepted { position });
+    }
 }
 
 impl Drop for Handle {
@@ -110,21 +139,29 @@ impl Task {
         server: Option<Server>,
         timeout: Duration,
         proxy: Option<config::Proxy>,
+        pace: Pace,
+        checksum: bool,
     ) -> (Handle, impl Stream<Item = Update>) {
         let (action_sender, action_receiver) = mpsc::channel(1);
         let (update_sender, update_receiver) = mpsc::channel(100);
 
+        let paused = pace.paused.clone();
+
         let task = tokio::spawn(async move {
             let mut update = update_sender.clone();
 
-            match self {
+            let id = match &self {
+                Task::Receive { id, .. } | Task::Send { id, .. } => *id,
+            };
+
+            let result = match self {
                 Task::Receive {
                     id,
                     dcc_send,
                     remote_user,
                     server_handle,
                 } => {
-                    if let Err(error) = receive(
+                    receive(
                         id,
                         dcc_send,
                         remote_user,
@@ -134,11 +171,10 @@ impl Task {
                         server,
                         timeout,
                         proxy,
+                        pace,
+                        checksum,
                     )
                     .await
-                    {
-                        let _ = update.send(Update::Failed(id, error.to_string())).await;
-                    }
                 }
                 Task::Send {
                     id,
@@ -148,7 +184,7 @@ impl Task {
                     reverse,
                     server_handle,
                 } => {
-                    if let Err(error) = send(
+                    send(
                         id,
                         path,
                         sanitized_filename,
@@ -160,18 +196,25 @@ impl Task {
                         server,
                         timeout,
                         proxy,
+                        pace,
+                        checksum,
                     )
                     .await
-                    {
-                        let _ = update.send(Update::Failed(id, error.to_string())).await;
-                    }
                 }
+            };
+
+            if let Err(error) = result {
+                let _ = update.send(Update::Failed(id, error.to_string())).await;
             }
+
+            // Whatever happened, the manager can free the transfer's slot
+            let _ = update.send(Update::Exited(id)).await;
         });
 
         (
             Handle {
                 sender: action_sender,
+                paused,
                 task,
             },
             update_receiver,
@@ -180,27 +223,36 @@ impl Task {
 }
 
 pub enum Action {
-    Approve { save_to: PathBuf },
+    Approve { save_to: PathBuf, resume: bool },
+    Start,
     ReverseConfirmed { host: IpAddr, port: NonZeroU16 },
     PortAvailable { port: NonZeroU16 },
+    Resume { position: u64 },
+    ResumeAccepted { position: u64 },
 }
 
 #[derive(Debug)]
 pub enum Update {
     Metadata(Id, u64),
     Queued(Id),
+    PendingResume(Id, u64),
+    Resumed(Id, u64),
     Ready(Id),
     Progress {
         id: Id,
         transferred: u64,
         elapsed: Duration,
+        /// Bytes per second
+        speed: u64,
     },
     Finished {
         id: Id,
         elapsed: Duration,
-        sha256: String,
+        sha256: Option<String>,
     },
     Failed(Id, String),
+    /// The task ended, after its last update
+    Exited(Id),
 }
 
 pub struct Server {
@@ -208,6 +260,63 @@ pub struct Server {
     pub bind_address: IpAddr,
 }
 
+/// Pauses a transfer, and limits its rate on its own & with every transfer
+#[derive(Debug, Clone)]
+pub struct Pace {
+    paused: Arc<AtomicBool>,
+    transfer: Arc<Throttle>,
+    shared: Arc<Throttle>,
+}
+
+impl Pace {
+    pub fn new(shared: Arc<Throttle>, rate: Option<u64>) -> Self {
+        Self {
+            paused: Arc::new(AtomicBool::new(false)),
+            transfer: Arc::new(Throttle::new(rate)),
+            shared,
+        }
+    }
+
+    /// Waits while paused, then until `bytes` are within the rates
+    async fn wait(&self, bytes: u64) {
+        while self.paused.load(Ordering::Relaxed) {
+            time::sleep(PAUSED_INTERVAL).await;
+        }
+
+        self.transfer.consume(bytes).await;
+        self.shared.consume(bytes).await;
+    }
+}
+
+/// Bytes per second over the last second
+struct Throughput {
+    since: Instant,
+    transferred: u64,
+    speed: u64,
+}
+
+impl Throughput {
+    fn new(transferred: u64) -> Self {
+        Self {
+            since: Instant::now(),
+            transferred,
+            speed: 0,
+        }
+    }
+
+    fn update(&mut self, transferred: u64) -> u64 {
+        let elapsed = self.since.elapsed();
+
+        if elapsed >= Duration::from_secs(1) {
+            self.speed = ((transferred - self.transferred) as f64 / elapsed.as_secs_f64()) as u64;
+            self.since = Instant::now();
+            self.transferred = transferred;
+        }
+
+        self.speed
+    }
+}
+
 async fn receive(
     id: Id,
     dcc_send: dcc::Send,
@@ -218,12 +327,62 @@ async fn receive(
     server: Option<Server>,
     timeout: Duration,
     proxy: Option<config::Proxy>,
+    pace: Pace,
+    checksum: bool,
 ) -> Result<(), Error> {
     // Wait for approval
-    let Some(Action::Approve { save_to }) = action.next().await else {
+    let Some((save_to, resume)) = next_action(&mut action, |action| match action {
+        Action::Approve { save_to, resume } => Some((save_to, resume)),
+        _ => None,
+    })
+    .await
+    else {
         return Ok(());
     };
 
+    // Resume when retrying to a partial file
+    let position = match fs::metadata(&save_to).await {
+        Ok(metadata) if resume && metadata.len() > 0 && metadata.len() < dcc_send.size() => {
+            metadata.len()
+        }
+        _ => 0,
+    };
+
+    let position = if position > 0 {
+        let _ = update.send(Update::PendingResume(id, position)).await;
+
+        let _ = server_handle
+            .send(dcc_send.resume(position).encode_resume(&remote_user))
+            .await;
+
+        let accepted = time::timeout(
+            timeout,
+            next_action(&mut action, |action| match action {
+                Action::ResumeAccepted { position } => Some(position),
+                _ => None,
+            }),
+        )
+        .await;
+
+        match accepted {
+            Ok(Some(accepted)) => {
+                let position = accepted.min(position);
+
+                let _ = update.send(Update::Resumed(id, position)).await;
+
+                position
+            }
+            Ok(None) => return Ok(()),
+            // Remote user doesn't support resuming, download it all again
+            Err(_) => {
+                log::debug!("File transfer resume from {remote_user} not accepted, restarting");
+                0
+            }
+        }
+    } else {
+        0
+    };
+
     let (host, port, filename, size, reverse) = match dcc_send {
         dcc::Send::Direct {
             host,
@@ -242,8 +401,13 @@ async fn receive(
 
             let _ = update.send(Update::Queued(id)).await;
 
-            let Some(Action::PortAvailable { port }) = action.next().await else {
-                unreachable!();
+            let Some(port) = next_action(&mut action, |action| match action {
+                Action::PortAvailable { port } => Some(port),
+                _ => None,
+            })
+            .await
+            else {
+                return Ok(());
             };
 
             let _ = server_handle
@@ -292,20 +456,40 @@ async fn receive(
         .await?
     };
 
-    let mut file = File::create(&save_to).await?;
-    let mut hasher = Sha256::new();
+    let mut hasher = checksum.then(Sha256::new);
+
+    let mut file = if position > 0 {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&save_to)
+            .await?;
+        file.set_len(position).await?;
+
+        skip_prefix(&mut file, position, hasher.as_mut()).await?;
+
+        file
+    } else {
+        File::create(&save_to).await?
+    };
 
-    let mut transferred = 0;
+    let mut transferred = position;
     let mut last_progress = started_at;
+    let mut throughput = Throughput::new(transferred);
 
     while transferred < size {
         if let Some(bytes) = connection.next().await {
             let bytes = bytes?;
 
+            // Reading slower holds back the sender
+            pace.wait(bytes.len() as u64).await;
+
             transferred += bytes.len() as u64;
 
             // Update hasher
-            hasher.update(&bytes);
+            if let Some(hasher) = &mut hasher {
+                hasher.update(&bytes);
+            }
 
             // Write bytes to file
             file.write_all(&bytes).await?;
@@ -320,6 +504,7 @@ async fn receive(
                         id,
                         elapsed: started_at.elapsed(),
                         transferred,
+                        speed: throughput.update(transferred),
                     })
                     .await;
                 last_progress = Instant::now();
@@ -329,13 +514,13 @@ async fn receive(
 
     let _ = connection.shutdown().await;
 
-    let sha256 = hex::encode(hasher.finalize());
+    let sha256 = hasher.map(|hasher| hex::encode(hasher.finalize()));
 
     let _ = server_handle
         .send(command!(
             "PRIVMSG",
             remote_user.to_string(),
-            format!("Finished receiving \"{filename}\", sha256: {sha256}")
+            finished_message("receiving", &filename, sha256.as_deref())
         ))
         .await;
 
@@ -362,12 +547,26 @@ async fn send(
     server: Option<Server>,
     timeout: Duration,
     proxy: Option<config::Proxy>,
+    pace: Pace,
+    checksum: bool,
 ) -> Result<(), Error> {
     let mut file = File::open(path).await?;
     let size = file.metadata().await?.len();
 
     let _ = update.send(Update::Metadata(id, size)).await;
 
+    // Wait for a free slot
+    let Some(()) = next_action(&mut action, |action| match action {
+        Action::Start => Some(()),
+        _ => None,
+    })
+    .await
+    else {
+        return Ok(());
+    };
+
+    let mut position = 0;
+
     let mut connection = if reverse {
         // Host doesn't matter for reverse connection
         let host = IpAddr::V4([127, 0, 0, 1].into());
@@ -380,17 +579,39 @@ async fn send(
                     host,
                     port: None,
                     size,
-                    token,
+                    token: token.clone(),
                 }
                 .encode(&remote_user),
             )
             .await;
 
-        let Some(Action::ReverseConfirmed { host, port }) = time::timeout(timeout, action.next())
-            .await
-            .map_err(|_| Error::TimeoutPassive)?
-        else {
-            unreachable!();
+        // Remote user may ask to resume before confirming
+        let (host, port) = loop {
+            match time::timeout(timeout, action.next())
+                .await
+                .map_err(|_| Error::TimeoutPassive)?
+            {
+                Some(Action::ReverseConfirmed { host, port }) => break (host, port),
+                Some(Action::Resume {
+                    position: requested,
+                }) if requested < size => {
+                    position = requested;
+
+                    let _ = server_handle
+                        .send(
+                            dcc::Resume {
+                                filename: sanitized_filename.clone(),
+                                port: None,
+                                position,
+                                token: Some(token.clone()),
+                            }
+                            .encode_accept(&remote_user),
+                        )
+                        .await;
+                }
+                Some(_) => {}
+                None => return Ok(()),
+            }
         };
 
         let _ = update.send(Update::Ready(id)).await;
@@ -410,8 +631,13 @@ async fn send(
 
         let _ = update.send(Update::Queued(id)).await;
 
-        let Some(Action::PortAvailable { port }) = action.next().await else {
-            unreachable!();
+        let Some(port) = next_action(&mut action, |action| match action {
+            Action::PortAvailable { port } => Some(port),
+            _ => None,
+        })
+        .await
+        else {
+            return Ok(());
         };
 
         let _ = server_handle
@@ -428,7 +654,7 @@ async fn send(
 
         let _ = update.send(Update::Ready(id)).await;
 
-        time::timeout(
+        let mut listen = std::pin::pin!(time::timeout(
             timeout,
             Connection::listen_and_accept(
                 server.bind_address,
@@ -436,25 +662,67 @@ async fn send(
                 connection::Security::Unsecured,
                 BytesCodec::new(),
             ),
-        )
-        .await
-        .map_err(|_| Error::TimeoutConnection)??
+        ));
+
+        // Remote user may ask to resume before connecting
+        loop {
+            let next_action = std::pin::pin!(action.next());
+
+            match future::select(listen.as_mut(), next_action).await {
+                Either::Left((connection, _)) => {
+                    break connection.map_err(|_| Error::TimeoutConnection)??;
+                }
+                Either::Right((
+                    Some(Action::Resume {
+                        position: requested,
+                    }),
+                    _,
+                )) if requested < size => {
+                    position = requested;
+
+                    let _ = server_handle
+                        .send(
+                            dcc::Resume {
+                                filename: sanitized_filename.clone(),
+                                port: Some(port),
+                                position,
+                                token: None,
+                            }
+                            .encode_accept(&remote_user),
+                        )
+                        .await;
+                }
+                Either::Right((Some(_), _)) => {}
+                Either::Right((None, _)) => return Ok(()),
+            }
+        }
     };
 
     let started_at = Instant::now();
 
     let mut buffer = BytesMut::with_capacity(BUFFER_SIZE);
-    let mut hasher = Sha256::new();
+    let mut hasher = checksum.then(Sha256::new);
+
+    if position > 0 {
+        skip_prefix(&mut file, position, hasher.as_mut()).await?;
 
-    let mut transferred = 0;
+        let _ = update.send(Update::Resumed(id, position)).await;
+    }
+
+    let mut transferred = position;
     let mut last_progress = started_at;
+    let mut throughput = Throughput::new(transferred);
 
     while transferred < size {
         // Read bytes from file
         let n = file.read_buf(&mut buffer).await?;
 
+        pace.wait(n as u64).await;
+
         // Update hasher
-        hasher.update(&buffer);
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&buffer);
+        }
 
         // Send bytes
         connection.send(buffer.split().freeze()).await?;
@@ -470,6 +738,7 @@ async fn send(
                     id,
                     elapsed: started_at.elapsed(),
                     transferred,
+                    speed: throughput.update(transferred),
                 })
                 .await;
             last_progress = Instant::now();
@@ -491,13 +760,13 @@ async fn send(
 
     let _ = connection.shutdown().await;
 
-    let sha256 = hex::encode(hasher.finalize());
+    let sha256 = hasher.map(|hasher| hex::encode(hasher.finalize()));
 
     let _ = server_handle
         .send(command!(
             "PRIVMSG",
             remote_user.to_string(),
-            format!("Finished sending \"{sanitized_filename}\", sha256: {sha256}")
+            finished_message("sending", &sanitized_filename, sha256.as_deref())
         ))
         .await;
 
@@ -512,6 +781,45 @@ async fn send(
     Ok(())
 }
 
+/// Waits for the action `select` picks, ignoring any other the remote user may
+/// have caused, e.g. an unsolicited `DCC ACCEPT`
+async fn next_action<T>(
+    action: &mut Receiver<Action>,
+    select: impl Fn(Action) -> Option<T>,
+) -> Option<T> {
+    while let Some(action) = action.next().await {
+        if let Some(value) = select(action) {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+fn finished_message(verb: &str, filename: &str, sha256: Option<&str>) -> String {
+    match sha256 {
+        Some(sha256) => format!("Finished {verb} \"{filename}\", sha256: {sha256}"),
+        None => format!("Finished {verb} \"{filename}\""),
+    }
+}
+
+/// Positions `file` after its first `len` bytes, hashing them with `hasher`
+async fn skip_prefix(file: &mut File, len: u64, hasher: Option<&mut Sha256>) -> io::Result<()> {
+    let Some(hasher) = hasher else {
+        return file.seek(SeekFrom::Start(len)).await.map(|_| ());
+    };
+
+    let mut prefix = file.take(len);
+    let mut buffer = BytesMut::with_capacity(BUFFER_SIZE);
+
+    while prefix.read_buf(&mut buffer).await? > 0 {
+        hasher.update(&buffer);
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Error)]
 enum Error {
     #[error("sender requested passive send but [file_transfer.server] is not configured")]
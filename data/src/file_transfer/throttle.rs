@@ -0,0 +1,78 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::time;
+
+/// Limits the rate of bytes transferred, either by one transfer or shared by
+/// every transfer
+#[derive(Debug)]
+pub struct Throttle {
+    /// Bytes per second
+    rate: Option<u64>,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes that may be transferred right away, negative once overdrawn
+    allowance: f64,
+    refilled_at: Instant,
+}
+
+impl Throttle {
+    pub fn new(rate: Option<u64>) -> Self {
+        Self {
+            rate: rate.filter(|rate| *rate > 0),
+            bucket: Mutex::new(Bucket {
+                allowance: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until `bytes` are within the rate
+    pub async fn consume(&self, bytes: u64) {
+        if let Some(wait) = self.overdrawn_by(bytes) {
+            time::sleep(wait).await;
+        }
+    }
+
+    fn overdrawn_by(&self, bytes: u64) -> Option<Duration> {
+        let rate = self.rate? as f64;
+
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * rate;
+
+        // Allow bursts of up to a second
+        bucket.allowance = (bucket.allowance + refill).min(rate) - bytes as f64;
+        bucket.refilled_at = now;
+
+        (bucket.allowance < 0.0).then(|| Duration::from_secs_f64(-bucket.allowance / rate))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn overdrawn() {
+        let throttle = Throttle::new(Some(1000));
+
+        // Starts without allowance
+        let wait = throttle.overdrawn_by(500).unwrap();
+        assert!(wait <= Duration::from_millis(500) && wait > Duration::from_millis(400));
+
+        // Debt accumulates
+        let wait = throttle.overdrawn_by(500).unwrap();
+        assert!(wait <= Duration::from_secs(1) && wait > Duration::from_millis(900));
+
+        let unlimited = Throttle::new(None);
+        assert_eq!(unlimited.overdrawn_by(u64::MAX), None);
+    }
+}
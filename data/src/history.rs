@@ -1,32 +1,67 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
 
+use chrono::{DateTime, NaiveDate, Utc};
 use futures::future::BoxFuture;
+use futures::lock::{Mutex, OwnedMutexGuard};
 use futures::{Future, FutureExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::time::Instant;
 
 pub use self::manager::{Manager, Resource};
 use crate::time::Posix;
 use crate::user::Nick;
-use crate::{compression, environment, message, server, Message};
+use crate::{compression, config, environment, message, server, Buffer, Message};
 
+pub mod export;
+pub mod highlights;
+pub mod import;
+pub mod logging;
 pub mod manager;
+pub mod search;
+mod storage;
 
-// TODO: Make this configurable?
-/// Max # messages to persist
+/// Max # messages to keep in memory, older messages are read back from disk
+/// as needed
 const MAX_MESSAGES: usize = 10_000;
 /// # messages to tuncate after hitting [`MAX_MESSAGES`]
 const TRUNC_COUNT: usize = 500;
+/// Min # messages read from disk at a time
+const PAGE_SIZE: usize = 500;
 /// Duration to wait after receiving last message before flushing
 const FLUSH_AFTER_LAST_RECEIVED: Duration = Duration::from_secs(5);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Held while reading or writing a log, so writes to it can't interleave.
+/// Each log has its own, leaving other buffers free to read & write theirs.
+static LOCKS: Lazy<std::sync::Mutex<HashMap<(server::Server, Kind), Lock>>> =
+    Lazy::new(Default::default);
+
+type Lock = Arc<Mutex<()>>;
+
+/// Waits for exclusive access to the log of `kind` on `server`
+async fn lock(server: &server::Server, kind: &Kind) -> OwnedMutexGuard<()> {
+    let lock = LOCKS
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .entry((server.clone(), kind.clone()))
+        .or_default()
+        .clone();
+
+    lock.lock_owned().await
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Kind {
     Server,
     Channel(String),
     Query(Nick),
+    /// A direct chat with the user, offered with `DCC CHAT`
+    DccChat(Nick),
 }
 
 impl fmt::Display for Kind {
@@ -35,6 +70,33 @@ impl fmt::Display for Kind {
             Kind::Server => write!(f, "server"),
             Kind::Channel(channel) => write!(f, "channel {channel}"),
             Kind::Query(nick) => write!(f, "user {}", nick),
+            Kind::DccChat(nick) => write!(f, "DCC chat with {nick}"),
+        }
+    }
+}
+
+impl Kind {
+    pub fn target(&self, source: message::Source) -> message::Target {
+        match self {
+            Kind::Server => message::Target::Server { source },
+            Kind::Channel(channel) => message::Target::Channel {
+                channel: channel.clone(),
+                source,
+            },
+            Kind::Query(nick) | Kind::DccChat(nick) => message::Target::Query {
+                nick: nick.clone(),
+                source,
+            },
+        }
+    }
+}
+
+impl From<Buffer> for Kind {
+    fn from(buffer: Buffer) -> Self {
+        match buffer {
+            Buffer::Server(_) => Kind::Server,
+            Buffer::Channel(_, channel) => Kind::Channel(channel),
+            Buffer::Query(_, nick) => Kind::Query(nick),
         }
     }
 }
@@ -49,10 +111,37 @@ impl From<message::Target> for Kind {
     }
 }
 
-pub async fn load(server: &server::Server, kind: &Kind) -> Result<Vec<Message>, Error> {
-    let path = path(server, kind).await?;
+/// Loads the most recent page of messages
+pub async fn load(server: &server::Server, kind: &Kind) -> Result<Page, Error> {
+    let _guard = lock(server, kind).await;
+    let log = open(server, kind).await?;
+
+    log.latest(PAGE_SIZE).await
+}
+
+/// Loads the page of messages sent at or before `before`
+pub async fn load_before(
+    server: &server::Server,
+    kind: &Kind,
+    before: DateTime<Utc>,
+) -> Result<Page, Error> {
+    let _guard = lock(server, kind).await;
+    let log = open(server, kind).await?;
+
+    log.before(before, PAGE_SIZE).await
+}
+
+/// Loads all messages sent between `since` & `until`, inclusive
+pub async fn load_between(
+    server: &server::Server,
+    kind: &Kind,
+    since: NaiveDate,
+    until: NaiveDate,
+) -> Result<Vec<Message>, Error> {
+    let _guard = lock(server, kind).await;
+    let log = open(server, kind).await?;
 
-    Ok(read_all(&path).await.unwrap_or_default())
+    log.between(since, until).await
 }
 
 pub async fn overwrite(
@@ -60,16 +149,14 @@ pub async fn overwrite(
     kind: &Kind,
     messages: &[Message],
 ) -> Result<(), Error> {
-    if messages.is_empty() {
-        return Ok(());
-    }
-
-    let latest = &messages[messages.len().saturating_sub(MAX_MESSAGES)..];
+    let _guard = lock(server, kind).await;
+    let mut log = open(server, kind).await?;
 
-    let path = path(server, kind).await?;
-    let compressed = compression::compress(&latest)?;
+    log.overwrite(messages).await?;
 
-    fs::write(path, &compressed).await?;
+    if let Err(error) = search::index(server, kind, messages).await {
+        log::warn!("failed to update search index for {kind} on {server}: {error}");
+    }
 
     Ok(())
 }
@@ -83,18 +170,235 @@ pub async fn append(
         return Ok(());
     }
 
-    let mut all_messages = load(server, kind).await?;
-    all_messages.extend(messages);
+    let _guard = lock(server, kind).await;
+    let mut log = open(server, kind).await?;
+
+    let appended = log.append(messages).await?;
+
+    if let Err(error) = search::index(server, kind, &appended).await {
+        log::warn!("failed to update search index for {kind} on {server}: {error}");
+    }
+
+    Ok(())
+}
+
+/// Deletes the stored message `msgid`
+pub async fn redact(
+    server: &server::Server,
+    kind: &Kind,
+    msgid: String,
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let _guard = lock(server, kind).await;
+    let mut log = open(server, kind).await?;
+
+    log.redact(&msgid, reason.as_deref()).await
+}
+
+/// Removes stored messages which fall outside of `retention`
+pub async fn prune(
+    server: &server::Server,
+    kind: &Kind,
+    retention: config::History,
+) -> Result<(), Error> {
+    let _guard = lock(server, kind).await;
+    let mut log = open(server, kind).await?;
+
+    log.prune(retention).await
+}
+
+/// Messages read from disk
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub messages: Vec<Message>,
+    /// Whether older messages are stored before this page
+    pub has_more: bool,
+}
+
+/// Inserts `message` ordered by its server time, skipping it if we already
+/// have it. Returns whether the message was inserted.
+fn insert_ordered(messages: &mut Vec<Message>, message: Message) -> bool {
+    let index = messages
+        .iter()
+        .rposition(|stored| stored.server_time <= message.server_time)
+        .map_or(0, |index| index + 1);
+
+    let is_duplicate = messages[..index]
+        .iter()
+        .rev()
+        .take_while(|stored| stored.server_time == message.server_time)
+        .any(|stored| stored.is_duplicate(&message));
+
+    if is_duplicate {
+        return false;
+    }
+
+    messages.insert(index, message);
 
-    overwrite(server, kind, &all_messages).await
+    true
 }
 
-async fn read_all(path: &PathBuf) -> Result<Vec<Message>, Error> {
-    let bytes = fs::read(path).await?;
-    Ok(compression::decompress(&bytes)?)
+/// Deletes the message `msgid` from `messages`. Reactions are removed, other
+/// messages are kept with their content replaced. Returns whether the message
+/// was found.
+fn redact_message(messages: &mut Vec<Message>, msgid: &str, reason: Option<&str>) -> bool {
+    let Some(index) = messages
+        .iter()
+        .rposition(|message| message.id.as_deref() == Some(msgid))
+    else {
+        return false;
+    };
+
+    if messages[index].reaction.is_some() {
+        messages.remove(index);
+    } else {
+        messages[index].redact(reason);
+    }
+
+    true
 }
 
-async fn path(server: &server::Server, kind: &Kind) -> Result<PathBuf, Error> {
+fn is_unread(message: &Message, read_marker: Option<DateTime<Utc>>) -> bool {
+    message.triggers_unread()
+        && read_marker
+            .filter(|read_at| message.server_time <= *read_at)
+            .is_none()
+}
+
+/// Server time of the latest message the server could replay through
+/// CHATHISTORY
+fn latest_server_time(messages: &[Message]) -> Option<DateTime<Utc>> {
+    messages
+        .iter()
+        .rev()
+        .find(|stored| {
+            matches!(
+                stored.target.source(),
+                message::Source::User(_) | message::Source::Action
+            )
+        })
+        .map(|stored| stored.server_time)
+}
+
+/// Writes stored messages between `since` & `until` and `unsaved` to `path`
+/// in `format`, returning the # messages written
+#[allow(clippy::too_many_arguments)]
+pub async fn export(
+    server: &server::Server,
+    kind: &Kind,
+    unsaved: Vec<Message>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    format: export::Format,
+    config: &config::Buffer,
+    path: &Path,
+) -> Result<usize, Error> {
+    // Stored days are in UTC, widen them to cover the local days
+    let mut messages = load_between(
+        server,
+        kind,
+        since
+            .and_then(|since| since.pred_opt())
+            .unwrap_or(NaiveDate::MIN),
+        until
+            .and_then(|until| until.succ_opt())
+            .unwrap_or(NaiveDate::MAX),
+    )
+    .await?;
+
+    messages.retain(|message| export::in_range(message, since, until));
+
+    for message in unsaved {
+        if export::in_range(&message, since, until) {
+            insert_ordered(&mut messages, message);
+        }
+    }
+
+    let encoded = export::encode(&messages, format, config)?;
+
+    fs::write(path, encoded).await?;
+
+    Ok(messages.len())
+}
+
+/// Merges messages read from the logs at `paths` into the stored history,
+/// returning the # messages imported
+pub async fn import(
+    server: &server::Server,
+    kind: &Kind,
+    format: import::Format,
+    paths: &[PathBuf],
+) -> Result<usize, Error> {
+    let mut imported = vec![];
+
+    for path in paths {
+        let contents = fs::read_to_string(path).await?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        imported.extend(import::parse(format, kind, &file_name, &contents));
+    }
+
+    imported.sort_by_key(|message| message.server_time);
+
+    let _guard = lock(server, kind).await;
+    let mut log = open(server, kind).await?;
+
+    let mut messages = log.between(NaiveDate::MIN, NaiveDate::MAX).await?;
+    let mut count = 0;
+
+    for message in imported {
+        if insert_ordered(&mut messages, message) {
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        log.overwrite(&messages).await?;
+
+        if let Err(error) = search::index(server, kind, &messages).await {
+            log::warn!("failed to update search index for {kind} on {server}: {error}");
+        }
+    }
+
+    Ok(count)
+}
+
+/// Opens the log for `kind`, migrating history stored in the legacy format
+/// of a single compressed file
+async fn open(server: &server::Server, kind: &Kind) -> Result<storage::Log, Error> {
+    let (legacy, dir) = paths(server, kind).await?;
+
+    let mut log = storage::Log::open(dir).await?;
+
+    if legacy.exists() {
+        let messages: Vec<Message> = compression::decompress(&fs::read(&legacy).await?)?;
+
+        if log.is_empty() {
+            log.overwrite(&messages).await?;
+        } else {
+            log.append(messages.clone()).await?;
+        }
+
+        if let Err(error) = search::index(server, kind, &messages).await {
+            log::warn!("failed to update search index for {kind} on {server}: {error}");
+        }
+
+        fs::remove_file(&legacy).await?;
+
+        log::info!(
+            "migrated history for {kind} on {server}: {} messages",
+            messages.len()
+        );
+    }
+
+    Ok(log)
+}
+
+/// Path of the legacy history file & the log directory for `kind`
+async fn paths(server: &server::Server, kind: &Kind) -> Result<(PathBuf, PathBuf), Error> {
     let data_dir = environment::data_dir();
 
     // TODO: Is this stable enough? What if user's nickname changes
@@ -102,6 +406,7 @@ async fn path(server: &server::Server, kind: &Kind) -> Result<PathBuf, Error> {
         Kind::Server => format!("{server}"),
         Kind::Channel(channel) => format!("{server}channel{channel}"),
         Kind::Query(nick) => format!("{server}nickname{}", nick),
+        Kind::DccChat(nick) => format!("{server}dccchat{nick}"),
     };
     let hashed_name = seahash::hash(name.as_bytes());
 
@@ -111,7 +416,10 @@ async fn path(server: &server::Server, kind: &Kind) -> Result<PathBuf, Error> {
         fs::create_dir_all(&parent).await?;
     }
 
-    Ok(parent.join(format!("{hashed_name}.json.gz")))
+    Ok((
+        parent.join(format!("{hashed_name}.json.gz")),
+        parent.join(hashed_name.to_string()),
+    ))
 }
 
 #[derive(Debug)]
@@ -121,13 +429,19 @@ pub enum History {
         kind: Kind,
         messages: Vec<Message>,
         last_received_at: Option<Instant>,
-        unread_message_count: usize,
+        /// Server times of unread messages, so messages read elsewhere
+        /// can be marked read
+        unread: Vec<DateTime<Utc>>,
         opened_at: Posix,
     },
     Full {
         server: server::Server,
         kind: Kind,
         messages: Vec<Message>,
+        /// Messages not yet written to disk
+        unsaved: Vec<Message>,
+        /// Whether older messages can be loaded from disk
+        has_more: bool,
         last_received_at: Option<Instant>,
         opened_at: Posix,
     },
@@ -140,21 +454,22 @@ impl History {
             kind,
             messages: vec![],
             last_received_at: None,
-            unread_message_count: 0,
+            unread: vec![],
             opened_at,
         }
     }
 
-    fn add_message(&mut self, message: Message) {
+    /// Adds `message`, which is unread unless it was sent before `read_marker`
+    fn add_message(&mut self, message: Message, read_marker: Option<DateTime<Utc>>) {
         match self {
             History::Partial {
                 messages,
                 last_received_at,
-                unread_message_count,
+                unread,
                 ..
             } => {
-                if message.triggers_unread() {
-                    *unread_message_count += 1;
+                if is_unread(&message, read_marker) {
+                    unread.push(message.server_time);
                 }
 
                 messages.push(message);
@@ -162,16 +477,164 @@ impl History {
             }
             History::Full {
                 messages,
+                unsaved,
                 last_received_at,
                 ..
             } => {
+                unsaved.push(message.clone());
                 messages.push(message);
                 *last_received_at = Some(Instant::now());
             }
         }
     }
 
-    fn flush(&mut self, now: Instant) -> Option<BoxFuture<'static, Result<(), Error>>> {
+    fn insert_message(&mut self, message: Message, read_marker: Option<DateTime<Utc>>) {
+        match self {
+            History::Partial {
+                messages,
+                last_received_at,
+                unread,
+                ..
+            } => {
+                let is_unread = is_unread(&message, read_marker);
+                let server_time = message.server_time;
+
+                if insert_ordered(messages, message) {
+                    if is_unread {
+                        unread.push(server_time);
+                    }
+
+                    *last_received_at = Some(Instant::now());
+                }
+            }
+            History::Full {
+                messages,
+                unsaved,
+                last_received_at,
+                ..
+            } => {
+                if insert_ordered(messages, message.clone()) {
+                    unsaved.push(message);
+                    *last_received_at = Some(Instant::now());
+                }
+            }
+        }
+    }
+
+    /// Clears unread messages sent up to `read_at`, which were read
+    fn mark_read(&mut self, read_at: DateTime<Utc>) {
+        if let History::Partial { unread, .. } = self {
+            unread.retain(|server_time| *server_time > read_at);
+        }
+    }
+
+    /// Latest message we've read, if it's newer than `read_marker`
+    fn read_up_to(&self, read_marker: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        let History::Full { messages, .. } = self else {
+            return None;
+        };
+
+        latest_server_time(messages).filter(|latest| read_marker < Some(*latest))
+    }
+
+    /// Sets the `msgid` of the message we sent, recorded at `sent_at`
+    fn record_echo(&mut self, sent_at: Posix, msgid: Option<String>) {
+        let is_echoed = |message: &Message| {
+            matches!(message.direction, message::Direction::Sent)
+                && message.id.is_none()
+                && message.received_at == sent_at
+        };
+
+        let messages = match self {
+            History::Partial { messages, .. } => messages,
+            History::Full {
+                messages, unsaved, ..
+            } => {
+                if let Some(message) = unsaved.iter_mut().rev().find(|message| is_echoed(message)) {
+                    message.id.clone_from(&msgid);
+                }
+
+                messages
+            }
+        };
+
+        if let Some(message) = messages.iter_mut().rev().find(|message| is_echoed(message)) {
+            message.id = msgid;
+        }
+    }
+
+    /// Deletes the message `msgid` if it's loaded
+    fn redact(&mut self, msgid: &str, reason: Option<&str>) {
+        match self {
+            History::Partial { messages, .. } => {
+                redact_message(messages, msgid, reason);
+            }
+            History::Full {
+                messages, unsaved, ..
+            } => {
+                redact_message(messages, msgid, reason);
+                redact_message(unsaved, msgid, reason);
+            }
+        }
+    }
+
+    /// Messages not yet written to disk
+    fn unsaved(&self) -> Vec<Message> {
+        match self {
+            History::Partial { messages, .. } => messages.clone(),
+            History::Full { unsaved, .. } => unsaved.clone(),
+        }
+    }
+
+    /// Replaces the loaded messages with `page`, keeping unsaved messages
+    fn reload(&mut self, page: Page) {
+        if let History::Full {
+            messages,
+            unsaved,
+            has_more,
+            ..
+        } = self
+        {
+            *messages = page.messages;
+
+            for message in unsaved.iter() {
+                insert_ordered(messages, message.clone());
+            }
+
+            *has_more = page.has_more;
+        }
+    }
+
+    /// Merges a page of older messages read from disk
+    fn insert_older(&mut self, page: Page) {
+        if let History::Full {
+            messages, has_more, ..
+        } = self
+        {
+            let mut older = page.messages;
+
+            // Pages overlap with messages sent at the same time as our oldest
+            if let Some(oldest) = messages.first() {
+                older.retain(|message| {
+                    message.server_time < oldest.server_time
+                        || (message.server_time == oldest.server_time
+                            && !messages
+                                .iter()
+                                .take_while(|stored| stored.server_time == oldest.server_time)
+                                .any(|stored| stored.is_duplicate(message)))
+                });
+            }
+
+            messages.splice(0..0, older);
+            *has_more = page.has_more;
+        }
+    }
+
+    fn flush(
+        &mut self,
+        now: Instant,
+        retention: config::History,
+    ) -> Option<BoxFuture<'static, Result<(), Error>>> {
         match self {
             History::Partial {
                 server,
@@ -189,7 +652,9 @@ impl History {
                         let messages = std::mem::take(messages);
                         *last_received_at = None;
 
-                        return Some(async move { append(&server, &kind, messages).await }.boxed());
+                        return Some(
+                            async move { save(&server, &kind, messages, retention).await }.boxed(),
+                        );
                     }
                 }
 
@@ -199,25 +664,29 @@ impl History {
                 server,
                 kind,
                 messages,
+                unsaved,
+                has_more,
                 last_received_at,
                 ..
             } => {
                 if let Some(last_received) = *last_received_at {
                     let since = now.duration_since(last_received);
 
-                    if since >= FLUSH_AFTER_LAST_RECEIVED && !messages.is_empty() {
+                    if since >= FLUSH_AFTER_LAST_RECEIVED && !unsaved.is_empty() {
                         let server = server.clone();
                         let kind = kind.clone();
                         *last_received_at = None;
 
+                        // Truncated messages are still stored on disk
                         if messages.len() > MAX_MESSAGES {
                             messages.drain(0..messages.len() - (MAX_MESSAGES - TRUNC_COUNT));
+                            *has_more = true;
                         }
 
-                        let messages = messages.clone();
+                        let unsaved = std::mem::take(unsaved);
 
                         return Some(
-                            async move { overwrite(&server, &kind, &messages).await }.boxed(),
+                            async move { save(&server, &kind, unsaved, retention).await }.boxed(),
                         );
                     }
                 }
@@ -227,47 +696,64 @@ impl History {
         }
     }
 
-    fn make_partial(&mut self) -> Option<impl Future<Output = Result<(), Error>>> {
+    fn make_partial(
+        &mut self,
+        retention: config::History,
+    ) -> Option<impl Future<Output = Result<(), Error>>> {
         match self {
             History::Partial { .. } => None,
             History::Full {
                 server,
                 kind,
-                messages,
+                unsaved,
                 ..
             } => {
                 let server = server.clone();
                 let kind = kind.clone();
-                let messages = std::mem::take(messages);
+                let unsaved = std::mem::take(unsaved);
 
                 *self = Self::partial(server.clone(), kind.clone(), Posix::now());
 
-                Some(async move { overwrite(&server, &kind, &messages).await })
+                Some(async move { save(&server, &kind, unsaved, retention).await })
             }
         }
     }
 
-    async fn close(self) -> Result<(), Error> {
+    async fn close(self, retention: config::History) -> Result<(), Error> {
         match self {
             History::Partial {
                 server,
                 kind,
                 messages,
                 ..
-            } => append(&server, &kind, messages).await,
+            } => save(&server, &kind, messages, retention).await,
             History::Full {
                 server,
                 kind,
-                messages,
+                unsaved,
                 ..
-            } => overwrite(&server, &kind, &messages).await,
+            } => save(&server, &kind, unsaved, retention).await,
         }
     }
 }
 
+/// Appends `messages`, then removes stored messages which fall outside of
+/// `retention`
+async fn save(
+    server: &server::Server,
+    kind: &Kind,
+    messages: Vec<Message>,
+    retention: config::History,
+) -> Result<(), Error> {
+    append(server, kind, messages).await?;
+    prune(server, kind, retention).await
+}
+
 #[derive(Debug)]
 pub struct View<'a> {
     pub total: usize,
+    /// Whether older messages can be loaded from disk
+    pub has_more: bool,
     pub old_messages: Vec<&'a Message>,
     pub new_messages: Vec<&'a Message>,
 }
@@ -278,4 +764,125 @@ pub enum Error {
     Compression(#[from] compression::Error),
     #[error(transparent)]
     Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{self, Direction, Source, Target};
+    use crate::time::Posix;
+    use crate::User;
+
+    fn message(seconds: i64, text: &str, id: Option<&str>) -> Message {
+        Message {
+            received_at: Posix::now(),
+            server_time: DateTime::from_timestamp(seconds, 0).unwrap(),
+            direction: Direction::Received,
+            target: Target::Channel {
+                channel: "#halloy".into(),
+                source: Source::User(User::try_from("casper").unwrap()),
+            },
+            content: message::plain(text.into()),
+            is_highlight: Some(false),
+            id: id.map(String::from),
+            reply_to: None,
+            reaction: None,
+        }
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|message| message.content.text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn insert_ordered_by_server_time() {
+        let mut messages = vec![message(1, "a", None), message(3, "c", None)];
+
+        assert!(insert_ordered(&mut messages, message(2, "b", None)));
+        assert!(insert_ordered(&mut messages, message(0, "first", None)));
+        assert!(insert_ordered(&mut messages, message(4, "last", None)));
+        // Same time is kept after what we already have
+        assert!(insert_ordered(&mut messages, message(3, "c2", None)));
+
+        assert_eq!(texts(&messages), ["first", "a", "b", "c", "c2", "last"]);
+    }
+
+    #[test]
+    fn insert_ordered_skips_duplicates() {
+        let mut messages = vec![message(1, "a", None), message(2, "b", Some("msgid"))];
+
+        assert!(!insert_ordered(&mut messages, message(1, "a", None)));
+        // Matched by msgid when both have one
+        assert!(!insert_ordered(
+            &mut messages,
+            message(2, "edited", Some("msgid"))
+        ));
+        assert!(insert_ordered(
+            &mut messages,
+            message(2, "b", Some("other"))
+        ));
+        assert!(insert_ordered(&mut messages, message(1, "a2", None)));
+
+        assert_eq!(texts(&messages), ["a", "a2", "b", "b"]);
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn unread_after_read_marker() {
+        assert!(is_unread(&message(1, "a", None), None));
+        assert!(!is_unread(&message(1, "a", None), Some(at(2))));
+        assert!(!is_unread(&message(2, "b", None), Some(at(2))));
+        assert!(is_unread(&message(3, "c", None), Some(at(2))));
+    }
+
+    #[test]
+    fn mark_read_keeps_newer_unread() {
+        let mut history = History::partial(
+            server::Server::from("libera"),
+            Kind::Channel("#halloy".into()),
+            Posix::now(),
+        );
+
+        history.add_message(message(3, "c", None), None);
+        history.insert_message(message(1, "a", None), None);
+        history.add_message(message(2, "b", None), Some(at(2)));
+
+        let unread = |history: &History| match history {
+            History::Partial { unread, .. } => unread.len(),
+            History::Full { .. } => 0,
+        };
+
+        assert_eq!(unread(&history), 2);
+
+        history.mark_read(at(2));
+        assert_eq!(unread(&history), 1);
+
+        history.mark_read(at(3));
+        assert_eq!(unread(&history), 0);
+    }
+
+    #[test]
+    fn read_up_to_latest_message() {
+        let history = History::Full {
+            server: server::Server::from("libera"),
+            kind: Kind::Channel("#halloy".into()),
+            messages: vec![message(1, "a", None), message(3, "c", None)],
+            unsaved: vec![],
+            has_more: false,
+            last_received_at: None,
+            opened_at: Posix::now(),
+        };
+
+        assert_eq!(history.read_up_to(None), Some(at(3)));
+        assert_eq!(history.read_up_to(Some(at(1))), Some(at(3)));
+        assert_eq!(history.read_up_to(Some(at(3))), None);
+    }
 }
@@ -0,0 +1,258 @@
+//! Export of history to log formats readable by people & other clients
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use super::Error;
+use crate::message::Source;
+use crate::user::AccessLevel;
+use crate::{config, Message};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Human readable text using the configured timestamp format
+    #[default]
+    Text,
+    /// Layout of irssi's default log theme
+    Irssi,
+    /// Tab separated layout of weechat's logger plugin
+    Weechat,
+    /// One JSON encoded message per line
+    Json,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Irssi | Format::Weechat => "log",
+            Format::Json => "jsonl",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Format::Text),
+            "irssi" => Ok(Format::Irssi),
+            "weechat" => Ok(Format::Weechat),
+            "json" | "jsonl" => Ok(Format::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Irssi => write!(f, "irssi"),
+            Format::Weechat => write!(f, "weechat"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Encodes `messages` as a log in `format`. Reactions are left out, as
+/// they're shown under the message they react to rather than on their own
+pub fn encode(
+    messages: &[Message],
+    format: Format,
+    config: &config::Buffer,
+) -> Result<Vec<u8>, Error> {
+    let mut output = vec![];
+
+    let messages = messages
+        .iter()
+        .filter(|message| message.reaction.is_none())
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Text => {
+            for message in &messages {
+                let timestamp = config
+                    .format_timestamp(&message.server_time)
+                    .unwrap_or_default();
+
+                let line = match message.target.source() {
+                    Source::User(user) => format!(
+                        "{timestamp}{} {}",
+                        config.nickname.brackets.format(user),
+                        message.content.text()
+                    ),
+                    _ => format!("{timestamp}{}", message.content.text().trim_start()),
+                };
+
+                output.extend(line.as_bytes());
+                output.push(b'\n');
+            }
+        }
+        Format::Irssi => {
+            let mut day = None;
+
+            for message in &messages {
+                let time = local(message.server_time);
+
+                match day {
+                    None => output.extend(
+                        format!("--- Log opened {}\n", time.format("%a %b %d %H:%M:%S %Y"))
+                            .as_bytes(),
+                    ),
+                    Some(day) if day != time.date_naive() => output.extend(
+                        format!("--- Day changed {}\n", time.format("%a %b %d %Y")).as_bytes(),
+                    ),
+                    _ => {}
+                }
+                day = Some(time.date_naive());
+
+                let text = message.content.text();
+
+                let line = match message.target.source() {
+                    Source::User(user) => {
+                        let mode = match user.highest_access_level() {
+                            AccessLevel::Member => " ".to_string(),
+                            level => level.to_string(),
+                        };
+
+                        format!("<{mode}{}> {text}", user.nickname())
+                    }
+                    Source::Action => format!(" * {}", action(&text)),
+                    _ => format!("-!- {}", text.trim_start()),
+                };
+
+                output.extend(format!("{} {line}\n", time.format("%H:%M")).as_bytes());
+            }
+
+            if let Some(last) = messages.last() {
+                output.extend(
+                    format!(
+                        "--- Log closed {}\n",
+                        local(last.server_time).format("%a %b %d %H:%M:%S %Y")
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+        Format::Weechat => {
+            for message in &messages {
+                let text = message.content.text();
+
+                let (prefix, text) = match message.target.source() {
+                    Source::User(user) => (user.to_string(), text.into_owned()),
+                    Source::Action => (" *".to_string(), action(&text).to_string()),
+                    _ => ("--".to_string(), text.trim_start().to_string()),
+                };
+
+                output.extend(
+                    format!(
+                        "{}\t{prefix}\t{text}\n",
+                        local(message.server_time).format("%Y-%m-%d %H:%M:%S")
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+        Format::Json => {
+            for message in &messages {
+                serde_json::to_writer(&mut output, message)?;
+                output.push(b'\n');
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Whether `message` was sent within the inclusive range of local dates
+pub fn in_range(message: &Message, since: Option<NaiveDate>, until: Option<NaiveDate>) -> bool {
+    let day = local(message.server_time).date_naive();
+
+    since.iter().all(|since| day >= *since) && until.iter().all(|until| day <= *until)
+}
+
+fn local(time: DateTime<Utc>) -> DateTime<Local> {
+    time.with_timezone(&Local)
+}
+
+/// Action text without the leading marker
+pub(super) fn action(text: &str) -> &str {
+    text.trim_start().trim_start_matches('∙').trim_start()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::message::{self, Direction, Target};
+    use crate::time::Posix;
+    use crate::User;
+
+    #[test]
+    fn weechat() {
+        let server_time = Local
+            .with_ymd_and_hms(2024, 5, 1, 12, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let messages = [
+            Message {
+                received_at: Posix::now(),
+                server_time,
+                direction: Direction::Received,
+                target: Target::Channel {
+                    channel: "#halloy".into(),
+                    source: Source::User(User::try_from("@casper").unwrap()),
+                },
+                content: message::plain("hello".into()),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: None,
+                reaction: None,
+            },
+            Message {
+                received_at: Posix::now(),
+                server_time,
+                direction: Direction::Received,
+                target: Target::Channel {
+                    channel: "#halloy".into(),
+                    source: Source::Action,
+                },
+                content: message::action_text(
+                    User::try_from("cory").unwrap().nickname(),
+                    Some("waves"),
+                ),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: None,
+                reaction: None,
+            },
+            // Left out, as reactions aren't lines of their own
+            Message {
+                received_at: Posix::now(),
+                server_time,
+                direction: Direction::Received,
+                target: Target::Channel {
+                    channel: "#halloy".into(),
+                    source: Source::User(User::try_from("cory").unwrap()),
+                },
+                content: message::plain("👍".into()),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: Some("msgid".into()),
+                reaction: Some("👍".into()),
+            },
+        ];
+
+        let encoded = encode(&messages, Format::Weechat, &config::Buffer::default()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "2024-05-01 12:30:00\t@casper\thello\n2024-05-01 12:30:00\t *\tcory waves\n"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Messages which highlighted us, collected from every server
+//!
+//! Highlights are stored together in a single file, separately from the
+//! history of the buffer they were received in, so they can be listed
+//! without loading every buffer.
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::time::Instant;
+
+use super::{storage, Error, Kind, FLUSH_AFTER_LAST_RECEIVED};
+use crate::time::Posix;
+use crate::{compression, environment, server, Buffer, Message};
+
+/// Max # highlights kept, the oldest are dropped first
+const MAX_HIGHLIGHTS: usize = 500;
+
+/// A message which highlighted us, with the buffer it was received in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    pub server: server::Server,
+    pub kind: Kind,
+    pub message: Message,
+    #[serde(default)]
+    pub read: bool,
+}
+
+impl Highlight {
+    pub fn buffer(&self) -> Buffer {
+        match &self.kind {
+            Kind::Server => Buffer::Server(self.server.clone()),
+            Kind::Channel(channel) => Buffer::Channel(self.server.clone(), channel.clone()),
+            Kind::Query(nick) | Kind::DccChat(nick) => {
+                Buffer::Query(self.server.clone(), nick.clone())
+            }
+        }
+    }
+
+    fn is(&self, server: &server::Server, received_at: Posix) -> bool {
+        self.server == *server && self.message.received_at == received_at
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Highlights {
+    entries: VecDeque<Highlight>,
+    /// When entries were last changed, if they haven't been saved since
+    changed_at: Option<Instant>,
+}
+
+impl Highlights {
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Highlight> {
+        self.entries.iter()
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|highlight| !highlight.read)
+            .count()
+    }
+
+    pub fn push(&mut self, server: server::Server, kind: Kind, message: Message) {
+        // Skip messages replayed by a bouncer which we already have
+        if self.contains(&server, &message) {
+            return;
+        }
+
+        if self.entries.len() == MAX_HIGHLIGHTS {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(Highlight {
+            server,
+            kind,
+            message,
+            read: false,
+        });
+        self.changed();
+    }
+
+    /// Merges highlights read from disk, which are older than any received
+    /// since starting
+    pub fn loaded(&mut self, stored: Vec<Highlight>) {
+        let received = std::mem::replace(&mut self.entries, stored.into());
+
+        for highlight in received {
+            if !self.contains(&highlight.server, &highlight.message) {
+                self.entries.push_back(highlight);
+            }
+        }
+
+        while self.entries.len() > MAX_HIGHLIGHTS {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn mark_read(&mut self, server: &server::Server, received_at: Posix) {
+        if let Some(highlight) = self
+            .entries
+            .iter_mut()
+            .find(|highlight| highlight.is(server, received_at) && !highlight.read)
+        {
+            highlight.read = true;
+            self.changed();
+        }
+    }
+
+    pub fn mark_all_read(&mut self) {
+        if self.unread_count() > 0 {
+            self.entries
+                .iter_mut()
+                .for_each(|highlight| highlight.read = true);
+            self.changed();
+        }
+    }
+
+    /// Removes the highlight of a deleted message
+    pub fn remove(&mut self, server: &server::Server, msgid: &str) {
+        let count = self.entries.len();
+
+        self.entries.retain(|highlight| {
+            highlight.server != *server || highlight.message.id.as_deref() != Some(msgid)
+        });
+
+        if self.entries.len() != count {
+            self.changed();
+        }
+    }
+
+    /// Entries to save, once they've been unchanged for a while
+    pub fn flush(&mut self, now: Instant) -> Option<Vec<Highlight>> {
+        let changed_at = self.changed_at?;
+
+        if now.duration_since(changed_at) < FLUSH_AFTER_LAST_RECEIVED {
+            return None;
+        }
+
+        self.changed_at = None;
+
+        Some(self.entries.iter().cloned().collect())
+    }
+
+    /// Entries to save before closing, if they've changed
+    pub fn close(&mut self) -> Option<Vec<Highlight>> {
+        self.changed_at
+            .take()
+            .map(|_| self.entries.iter().cloned().collect())
+    }
+
+    fn contains(&self, server: &server::Server, message: &Message) -> bool {
+        self.entries
+            .iter()
+            .any(|highlight| highlight.server == *server && highlight.message.is_duplicate(message))
+    }
+
+    fn changed(&mut self) {
+        self.changed_at = Some(Instant::now());
+    }
+}
+
+pub async fn load() -> Result<Vec<Highlight>, Error> {
+    let path = path().await?;
+
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(compression::decompress(&fs::read(&path).await?)?)
+}
+
+pub async fn save(highlights: Vec<Highlight>) -> Result<(), Error> {
+    let path = path().await?;
+
+    storage::write_atomic(&path, &compression::compress(&highlights)?).await
+}
+
+async fn path() -> Result<PathBuf, Error> {
+    let parent = environment::data_dir().join("history");
+
+    if !parent.exists() {
+        fs::create_dir_all(&parent).await?;
+    }
+
+    Ok(parent.join("highlights.json.gz"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{self, Direction, Source, Target};
+    use crate::User;
+
+    #[test]
+    fn push_and_mark_read() {
+        let server = server::Server::from("libera");
+        let message = |text: &str| Message {
+            received_at: Posix::now(),
+            server_time: chrono::Utc::now(),
+            direction: Direction::Received,
+            target: Target::Channel {
+                channel: "#halloy".into(),
+                source: Source::User(User::try_from("cory").unwrap()),
+            },
+            content: message::plain(text.into()),
+            is_highlight: Some(true),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        };
+        let kind = Kind::Channel("#halloy".into());
+
+        let mut highlights = Highlights::default();
+
+        let first = message("hi casper");
+        highlights.push(server.clone(), kind.clone(), first.clone());
+        // Replayed by a bouncer
+        highlights.push(server.clone(), kind.clone(), first.clone());
+        highlights.push(server.clone(), kind.clone(), message("casper?"));
+
+        assert_eq!(highlights.iter().count(), 2);
+        assert_eq!(highlights.unread_count(), 2);
+
+        highlights.mark_read(&server, first.received_at);
+        assert_eq!(highlights.unread_count(), 1);
+
+        highlights.mark_all_read();
+        assert_eq!(highlights.unread_count(), 0);
+    }
+}
@@ -0,0 +1,281 @@
+//! Import of logs written by other clients & bouncers
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use super::Kind;
+use crate::message::{self, Direction, Source};
+use crate::time::Posix;
+use crate::user::NickRef;
+use crate::{Message, User};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Irssi,
+    Weechat,
+    /// ZNC's log module, one file per day named by date
+    Znc,
+    /// One JSON encoded message per line, as written by export
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "irssi" => Ok(Format::Irssi),
+            "weechat" => Ok(Format::Weechat),
+            "znc" => Ok(Format::Znc),
+            "json" | "jsonl" => Ok(Format::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Irssi => write!(f, "irssi"),
+            Format::Weechat => write!(f, "weechat"),
+            Format::Znc => write!(f, "znc"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Parses the log `contents` of file `file_name` into messages for `kind`.
+/// Lines which can't be parsed are skipped.
+pub fn parse(format: Format, kind: &Kind, file_name: &str, contents: &str) -> Vec<Message> {
+    match format {
+        Format::Irssi => irssi(kind, contents),
+        Format::Weechat => weechat(kind, contents),
+        Format::Znc => znc(kind, file_name, contents),
+        Format::Json => contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Message>(line).ok())
+            .map(|message| {
+                let source = message.target.source().clone();
+                message.with_target(kind.target(source))
+            })
+            .collect(),
+    }
+}
+
+fn irssi(kind: &Kind, contents: &str) -> Vec<Message> {
+    let mut day = None;
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            if let Some(opened) = line.strip_prefix("--- Log opened ") {
+                day = NaiveDateTime::parse_from_str(opened, "%a %b %d %H:%M:%S %Y")
+                    .ok()
+                    .map(|time| time.date());
+                return None;
+            }
+
+            if let Some(changed) = line.strip_prefix("--- Day changed ") {
+                day = NaiveDate::parse_from_str(changed, "%a %b %d %Y").ok();
+                return None;
+            }
+
+            let (time, rest) = line.split_once(' ')?;
+            let server_time = server_time(day?, time)?;
+
+            if let Some(rest) = rest.strip_prefix('<') {
+                let (nick, text) = rest.split_once("> ")?;
+
+                Some(user(kind, server_time, nick.trim_start(), text))
+            } else if let Some(rest) = rest.strip_prefix(" * ") {
+                let (nick, text) = rest.split_once(' ')?;
+
+                Some(action(kind, server_time, nick, text))
+            } else {
+                let text = rest.strip_prefix("-!- ")?;
+
+                Some(server(kind, server_time, text))
+            }
+        })
+        .collect()
+}
+
+fn weechat(kind: &Kind, contents: &str) -> Vec<Message> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+
+            let time = NaiveDateTime::parse_from_str(parts.next()?, "%Y-%m-%d %H:%M:%S").ok()?;
+            let server_time = utc(time)?;
+            let prefix = parts.next()?;
+            let text = parts.next().unwrap_or_default();
+
+            match prefix.trim() {
+                "*" => {
+                    let (nick, text) = text.split_once(' ')?;
+
+                    Some(action(kind, server_time, nick, text))
+                }
+                "" | "--" | "-->" | "<--" | "=!=" => Some(server(kind, server_time, text)),
+                nick => Some(user(kind, server_time, nick, text)),
+            }
+        })
+        .collect()
+}
+
+fn znc(kind: &Kind, file_name: &str, contents: &str) -> Vec<Message> {
+    // Either `2024-01-31.log` or `user_network_#channel_20240131.log`
+    let stem = file_name.strip_suffix(".log").unwrap_or(file_name);
+    let day = stem
+        .get(stem.len().saturating_sub(10)..)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .or_else(|| {
+            stem.get(stem.len().saturating_sub(8)..)
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        });
+
+    let Some(day) = day else {
+        log::warn!("skipping znc log without a date in its name: {file_name}");
+        return vec![];
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (time, rest) = line.strip_prefix('[')?.split_once("] ")?;
+            let server_time = server_time(day, time)?;
+
+            if let Some(text) = rest.strip_prefix("*** ") {
+                Some(server(kind, server_time, text))
+            } else if let Some(rest) = rest.strip_prefix("* ") {
+                let (nick, text) = rest.split_once(' ')?;
+
+                Some(action(kind, server_time, nick, text))
+            } else {
+                let (nick, text) = rest
+                    .strip_prefix('<')
+                    .and_then(|rest| rest.split_once("> "))
+                    .or_else(|| {
+                        rest.strip_prefix('-')
+                            .and_then(|rest| rest.split_once("- "))
+                    })?;
+
+                Some(user(kind, server_time, nick, text))
+            }
+        })
+        .collect()
+}
+
+fn server_time(day: NaiveDate, time: &str) -> Option<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+
+    utc(day.and_time(time))
+}
+
+/// Logs are written in local time
+fn utc(time: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn user(kind: &Kind, server_time: DateTime<Utc>, nick: &str, text: &str) -> Message {
+    let source = User::try_from(nick)
+        .map(Source::User)
+        .unwrap_or(Source::Server(None));
+
+    message(
+        kind,
+        server_time,
+        source,
+        message::parse_fragments(text.to_string()),
+    )
+}
+
+fn action(kind: &Kind, server_time: DateTime<Utc>, nick: &str, text: &str) -> Message {
+    message(
+        kind,
+        server_time,
+        Source::Action,
+        message::action_text(NickRef::from(nick), Some(text)),
+    )
+}
+
+fn server(kind: &Kind, server_time: DateTime<Utc>, text: &str) -> Message {
+    message(
+        kind,
+        server_time,
+        Source::Server(None),
+        message::plain(text.to_string()),
+    )
+}
+
+fn message(
+    kind: &Kind,
+    server_time: DateTime<Utc>,
+    source: Source,
+    content: message::Content,
+) -> Message {
+    Message {
+        received_at: Posix::from_seconds(server_time.timestamp().max(0) as u64),
+        server_time,
+        direction: Direction::Received,
+        target: kind.target(source),
+        content,
+        is_highlight: Some(false),
+        id: None,
+        reply_to: None,
+        reaction: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn irssi_and_znc() {
+        let kind = Kind::Channel("#halloy".into());
+
+        let irssi = parse(
+            Format::Irssi,
+            &kind,
+            "halloy.log",
+            "--- Log opened Wed May 01 12:00:00 2024\n\
+             12:30 <@casper> hello\n\
+             12:31  * cory waves\n\
+             12:32 -!- cory [~cory@host] has quit [Quit]\n\
+             --- Day changed Thu May 02 2024\n\
+             09:00 < cory> morning\n",
+        );
+
+        let znc = parse(
+            Format::Znc,
+            &kind,
+            "2024-05-01.log",
+            "[12:30:00] <casper> hello\n\
+             [12:31:00] * cory waves\n\
+             [12:32:00] *** Quits: cory (~cory@host) (Quit)\n\
+             not a log line\n",
+        );
+
+        assert_eq!(irssi.len(), 4);
+        assert_eq!(znc.len(), 3);
+
+        assert!(
+            matches!(irssi[0].target.source(), Source::User(user) if user.nickname().as_ref() == "casper")
+        );
+        assert!(matches!(irssi[1].target.source(), Source::Action));
+        assert_eq!(irssi[1].content.text(), " ∙ cory waves");
+        assert_eq!(irssi[0].server_time, znc[0].server_time);
+        assert_eq!(
+            irssi[3].server_time.with_timezone(&Local).date_naive(),
+            NaiveDate::from_ymd_opt(2024, 5, 2).unwrap()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Plain-text logs of every message, kept separately from history
+//!
+//! Lines are appended to `<server>/<buffer>/YYYY-MM-DD.log` in the logs
+//! directory, with server messages in `<server>/YYYY-MM-DD.log`. Files are
+//! written on a background thread & flushed as soon as the pending lines are
+//! written, so they can be read with other tools while running.
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use chrono::{Local, NaiveDate};
+
+use super::{export, Kind};
+use crate::message::Source;
+use crate::{config, environment, server, Message};
+
+#[derive(Debug)]
+pub struct Logger {
+    config: config::Logging,
+    directory: PathBuf,
+    sender: mpsc::Sender<Line>,
+}
+
+impl Logger {
+    /// Starts the writer thread, if logging is enabled
+    pub fn new(config: config::Logging) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let directory = config
+            .directory
+            .clone()
+            .unwrap_or_else(|| environment::data_dir().join("logs"));
+
+        let (sender, receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("logger".into())
+            .spawn(move || write(receiver))
+            .map_err(|error| log::warn!("failed to start logger: {error}"))
+            .ok()?;
+
+        Some(Self {
+            config,
+            directory,
+            sender,
+        })
+    }
+
+    pub fn log(&self, server: &server::Server, kind: &Kind, message: &Message) {
+        let buffer = match kind {
+            Kind::Server => None,
+            Kind::Channel(channel) => Some(channel.clone()),
+            Kind::Query(nick) | Kind::DccChat(nick) => Some(nick.to_string()),
+        };
+
+        if !self.config.is_logged(server.as_ref(), buffer.as_deref()) {
+            return;
+        }
+
+        let mut directory = self.directory.join(sanitize(server.as_ref()));
+        if let Some(buffer) = &buffer {
+            directory.push(sanitize(&buffer.to_lowercase()));
+        }
+
+        let time = message.server_time.with_timezone(&Local);
+        let text = message.content.text();
+
+        let (prefix, text) = match message.target.source() {
+            Source::User(user) => (format!("<{}>", user.nickname()), text.trim_start()),
+            Source::Action => ("*".to_string(), export::action(&text)),
+            _ => ("--".to_string(), text.trim_start()),
+        };
+
+        // Writing rather than `to_string` avoids panicking on an invalid format
+        let mut timestamp = String::new();
+        let _ = write!(timestamp, "{}", time.format(&self.config.timestamp));
+
+        let line = self
+            .config
+            .format
+            .replace("{timestamp}", &timestamp)
+            .replace("{server}", server.as_ref())
+            .replace("{buffer}", buffer.as_deref().unwrap_or(server.as_ref()))
+            .replace("{prefix}", &prefix)
+            .replace("{text}", text);
+
+        let _ = self.sender.send(Line {
+            directory,
+            date: time.date_naive(),
+            text: line,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    directory: PathBuf,
+    date: NaiveDate,
+    text: String,
+}
+
+/// Writes lines until the logger is dropped, keeping the file for the
+/// current day of each directory open
+fn write(receiver: mpsc::Receiver<Line>) {
+    let mut files = HashMap::<PathBuf, (NaiveDate, BufWriter<File>)>::new();
+
+    while let Ok(line) = receiver.recv() {
+        for line in std::iter::once(line).chain(receiver.try_iter()) {
+            if let Err(error) = append(&mut files, &line) {
+                log::warn!(
+                    "failed to write log in {}: {error}",
+                    line.directory.display()
+                );
+            }
+        }
+
+        for (_, file) in files.values_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn append(
+    files: &mut HashMap<PathBuf, (NaiveDate, BufWriter<File>)>,
+    line: &Line,
+) -> io::Result<()> {
+    // Rotate daily. Lines from a previous day, e.g. replayed by the server,
+    // are written to the current day.
+    let is_current = files
+        .get(&line.directory)
+        .is_some_and(|(date, _)| *date >= line.date);
+
+    if !is_current {
+        let file = open(&line.directory, line.date)?;
+
+        if let Some((_, mut previous)) = files.insert(line.directory.clone(), (line.date, file)) {
+            previous.flush()?;
+        }
+    }
+
+    let Some((_, file)) = files.get_mut(&line.directory) else {
+        return Ok(());
+    };
+
+    writeln!(file, "{}", line.text)
+}
+
+fn open(directory: &Path, date: NaiveDate) -> io::Result<BufWriter<File>> {
+    fs::create_dir_all(directory)?;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(directory.join(format!("{}.log", date.format("%Y-%m-%d"))))?;
+
+    Ok(BufWriter::new(file))
+}
+
+/// Replaces characters which aren't valid in file names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
@@ -1,16 +1,19 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use futures::future::BoxFuture;
 use futures::{future, Future, FutureExt};
 use itertools::Itertools;
 use tokio::time::Instant;
 
+use crate::client::Presence;
+use crate::history::highlights::{self, Highlight, Highlights};
 use crate::history::{self, History};
 use crate::message::{self, Limit};
 use crate::time::Posix;
 use crate::user::Nick;
-use crate::{config, input};
+use crate::{config, input, sts};
 use crate::{server, Buffer, Config, Input, Server, User};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -24,35 +27,68 @@ pub enum Message {
     Loaded(
         server::Server,
         history::Kind,
-        Result<Vec<crate::Message>, history::Error>,
+        Result<history::Page, history::Error>,
+    ),
+    LoadedOlder(
+        server::Server,
+        history::Kind,
+        Result<history::Page, history::Error>,
     ),
     Closed(server::Server, history::Kind, Result<(), history::Error>),
     Flushed(server::Server, history::Kind, Result<(), history::Error>),
+    Exported(
+        server::Server,
+        history::Kind,
+        PathBuf,
+        Result<usize, history::Error>,
+    ),
+    Imported(
+        server::Server,
+        history::Kind,
+        Result<(usize, history::Page), history::Error>,
+    ),
+    Redacted(server::Server, history::Kind, Result<(), history::Error>),
+    HighlightsLoaded(Result<Vec<Highlight>, history::Error>),
+    HighlightsSaved(Result<(), history::Error>),
 }
 
 #[derive(Debug, Default)]
 pub struct Manager {
     resources: HashSet<Resource>,
     data: Data,
+    retention: config::History,
+    logger: Option<history::logging::Logger>,
+    highlights: Highlights,
+    /// When messages we sent were recorded, by label, until the server
+    /// echoes them
+    echoes: HashMap<String, Posix>,
 }
 
 impl Manager {
+    pub fn new(retention: config::History, logging: config::Logging) -> Self {
+        Self {
+            retention,
+            logger: history::logging::Logger::new(logging),
+            ..Self::default()
+        }
+    }
+
     pub fn track(&mut self, new_resources: HashSet<Resource>) -> Vec<BoxFuture<'static, Message>> {
         let added = new_resources.difference(&self.resources).cloned();
         let removed = self.resources.difference(&new_resources).cloned();
 
         let added = added.into_iter().map(|resource| {
             async move {
-                history::load(&resource.server.clone(), &resource.kind.clone())
-                    .map(move |result| Message::Loaded(resource.server, resource.kind, result))
-                    .await
+                let result = history::load(&resource.server, &resource.kind).await;
+
+                Message::Loaded(resource.server, resource.kind, result)
             }
             .boxed()
         });
 
         let removed = removed.into_iter().filter_map(|resource| {
             self.data
-                .untrack(&resource.server, &resource.kind)
+                .untrack(&resource.server, &resource.kind, self.retention)
                 .map(|task| {
                     task.map(|result| Message::Closed(resource.server, resource.kind, result))
                         .boxed()
@@ -68,16 +104,34 @@ impl Manager {
 
     pub fn update(&mut self, message: Message) {
         match message {
-            Message::Loaded(server, kind, Ok(messages)) => {
+            Message::Loaded(server, kind, Ok(page)) => {
                 log::debug!(
                     "loaded history for {kind} on {server}: {} messages",
-                    messages.len()
+                    page.messages.len()
                 );
-                self.data.loaded(server, kind, messages);
+                self.data.loaded(server, kind, page);
             }
             Message::Loaded(server, kind, Err(error)) => {
                 log::warn!("failed to load history for {kind} on {server}: {error}");
             }
+            Message::LoadedOlder(server, kind, Ok(page)) => {
+                log::debug!(
+                    "loaded older history for {kind} on {server}: {} messages",
+                    page.messages.len()
+                );
+
+                if let Some(history) = self
+                    .data
+                    .map
+                    .get_mut(&server)
+                    .and_then(|map| map.get_mut(&kind))
+                {
+                    history.insert_older(page);
+                }
+            }
+            Message::LoadedOlder(server, kind, Err(error)) => {
+                log::warn!("failed to load older history for {kind} on {server}: {error}");
+            }
             Message::Closed(server, kind, Ok(_)) => {
                 log::debug!("closed history for {kind} on {server}",);
             }
@@ -90,11 +144,181 @@ impl Manager {
             Message::Flushed(server, kind, Err(error)) => {
                 log::warn!("failed to flush history for {kind} on {server}: {error}")
             }
+            Message::Exported(server, kind, path, Ok(count)) => {
+                self.record_status(
+                    &server,
+                    kind,
+                    message::source::Status::Success,
+                    format!("exported {count} messages to {}", path.display()),
+                );
+            }
+            Message::Exported(server, kind, path, Err(error)) => {
+                log::warn!("failed to export history for {kind} on {server}: {error}");
+
+                self.record_status(
+                    &server,
+                    kind,
+                    message::source::Status::Error,
+                    format!("failed to export history to {}: {error}", path.display()),
+                );
+            }
+            Message::Imported(server, kind, Ok((count, page))) => {
+                if let Some(history) = self
+                    .data
+                    .map
+                    .get_mut(&server)
+                    .and_then(|map| map.get_mut(&kind))
+                {
+                    history.reload(page);
+                }
+
+                self.record_status(
+                    &server,
+                    kind,
+                    message::source::Status::Success,
+                    format!("imported {count} messages"),
+                );
+            }
+            Message::Imported(server, kind, Err(error)) => {
+                log::warn!("failed to import history for {kind} on {server}: {error}");
+
+                self.record_status(
+                    &server,
+                    kind,
+                    message::source::Status::Error,
+                    format!("failed to import history: {error}"),
+                );
+            }
+            Message::Redacted(server, kind, Ok(_)) => {
+                log::debug!("deleted message from history for {kind} on {server}");
+            }
+            Message::Redacted(server, kind, Err(error)) => {
+                log::warn!("failed to delete message from history for {kind} on {server}: {error}")
+            }
+            Message::HighlightsLoaded(Ok(stored)) => {
+                log::debug!("loaded {} highlights", stored.len());
+                self.highlights.loaded(stored);
+            }
+            Message::HighlightsLoaded(Err(error)) => {
+                log::warn!("failed to load highlights: {error}");
+            }
+            Message::HighlightsSaved(Ok(_)) => {
+                log::debug!("saved highlights");
+            }
+            Message::HighlightsSaved(Err(error)) => {
+                log::warn!("failed to save highlights: {error}");
+            }
         }
     }
 
     pub fn tick(&mut self, now: Instant) -> Vec<BoxFuture<'static, Message>> {
-        self.data.flush_all(now)
+        let mut tasks = self.data.flush_all(now, self.retention);
+
+        if let Some(highlights) = self.highlights.flush(now) {
+            tasks.push(
+                highlights::save(highlights)
+                    .map(Message::HighlightsSaved)
+                    .boxed(),
+            );
+        }
+
+        tasks
+    }
+
+    pub fn load_highlights(&self) -> BoxFuture<'static, Message> {
+        highlights::load().map(Message::HighlightsLoaded).boxed()
+    }
+
+    /// Loads the page of messages stored before those loaded for `kind`, if
+    /// there are any
+    pub fn load_older(
+        &mut self,
+        server: &Server,
+        kind: history::Kind,
+    ) -> Option<BoxFuture<'static, Message>> {
+        let History::Full {
+            messages, has_more, ..
+        } = self.data.map.get_mut(server)?.get_mut(&kind)?
+        else {
+            return None;
+        };
+
+        if !*has_more {
+            return None;
+        }
+
+        // Cleared until the page is loaded, to avoid requesting it twice
+        *has_more = false;
+
+        let before = messages.first()?.server_time;
+        let server = server.clone();
+
+        Some(
+            async move {
+                let result = history::load_before(&server, &kind, before).await;
+
+                Message::LoadedOlder(server, kind, result)
+            }
+            .boxed(),
+        )
+    }
+
+    /// Writes the history of `kind` to `path`, including messages which
+    /// haven't been saved yet
+    #[allow(clippy::too_many_arguments)]
+    pub fn export(
+        &self,
+        server: &Server,
+        kind: history::Kind,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+        format: history::export::Format,
+        config: &config::Buffer,
+        path: PathBuf,
+    ) -> BoxFuture<'static, Message> {
+        let unsaved = self
+            .data
+            .map
+            .get(server)
+            .and_then(|map| map.get(&kind))
+            .map(History::unsaved)
+            .unwrap_or_default();
+        let server = server.clone();
+        let config = config.clone();
+
+        async move {
+            let result = history::export(
+                &server, &kind, unsaved, since, until, format, &config, &path,
+            )
+            .await;
+
+            Message::Exported(server, kind, path, result)
+        }
+        .boxed()
+    }
+
+    /// Merges the logs at `paths` into the stored history of `kind`
+    pub fn import(
+        &self,
+        server: &Server,
+        kind: history::Kind,
+        format: history::import::Format,
+        paths: Vec<PathBuf>,
+    ) -> BoxFuture<'static, Message> {
+        let server = server.clone();
+
+        async move {
+            let result = async {
+                let count = history::import(&server, &kind, format, &paths).await?;
+                let page = history::load(&server, &kind).await?;
+
+                Ok((count, page))
+            }
+            .await;
+
+            Message::Imported(server, kind, result)
+        }
+        .boxed()
     }
 
     pub fn close(
@@ -103,9 +327,10 @@ impl Manager {
         kind: history::Kind,
     ) -> Option<impl Future<Output = ()>> {
         let history = self.data.map.get_mut(&server)?.remove(&kind)?;
+        let retention = self.retention;
 
         Some(async move {
-            match history.close().await {
+            match history.close(retention).await {
                 Ok(_) => {
                     log::debug!("closed history for {kind} on {server}",);
                 }
@@ -118,11 +343,14 @@ impl Manager {
 
     pub fn close_server(&mut self, server: Server) -> Option<impl Future<Output = ()>> {
         let map = self.data.map.remove(&server)?;
+        let retention = self.retention;
 
         Some(async move {
             let tasks = map.into_iter().map(move |(kind, state)| {
                 let server = server.clone();
-                state.close().map(move |result| (server, kind, result))
+                state
+                    .close(retention)
+                    .map(move |result| (server, kind, result))
             });
 
             let results = future::join_all(tasks).await;
@@ -142,12 +370,22 @@ impl Manager {
 
     pub fn close_all(&mut self) -> impl Future<Output = ()> {
         let map = std::mem::take(&mut self.data).map;
+        let retention = self.retention;
+        let highlights = self.highlights.close();
 
         async move {
+            if let Some(highlights) = highlights {
+                if let Err(error) = highlights::save(highlights).await {
+                    log::warn!("failed to save highlights: {error}");
+                }
+            }
+
             let tasks = map.into_iter().flat_map(|(server, map)| {
                 map.into_iter().map(move |(kind, state)| {
                     let server = server.clone();
-                    state.close().map(move |result| (server, kind, result))
+                    state
+                        .close(retention)
+                        .map(move |result| (server, kind, result))
                 })
             });
 
@@ -166,11 +404,22 @@ impl Manager {
         }
     }
 
-    pub fn record_input(&mut self, input: Input, user: User) {
+    /// Records input we sent, which the server will echo with `label`
+    pub fn record_input(&mut self, input: Input, user: User, label: Option<String>) {
         if let Some(message) = input.message(user) {
+            if let Some(label) = label {
+                self.echoes.insert(label, message.received_at);
+            }
+
             self.record_message(input.server(), message);
         }
 
+        self.record_local_input(&input);
+    }
+
+    /// Records input handled by the client so it can be recalled, without
+    /// adding a message to history
+    pub fn record_local_input(&mut self, input: &Input) {
         if let Some(text) = input.raw() {
             self.data.input.record(input.buffer(), text.to_string());
         }
@@ -180,14 +429,235 @@ impl Manager {
         self.data.input.store_draft(draft);
     }
 
+    /// Records a status message from the client in the buffer of `kind`
+    pub fn record_status(
+        &mut self,
+        server: &Server,
+        kind: history::Kind,
+        status: message::source::Status,
+        text: String,
+    ) {
+        let target = kind.target(message::Source::Internal(
+            message::source::Internal::Status(status),
+        ));
+
+        self.record_message(
+            server,
+            crate::Message {
+                received_at: Posix::now(),
+                server_time: Utc::now(),
+                direction: message::Direction::Received,
+                target,
+                content: message::plain(format!(" ∙ {text}")),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: None,
+                reaction: None,
+            },
+        );
+    }
+
     pub fn record_message(&mut self, server: &Server, message: crate::Message) {
-        self.data.add_message(
+        let kind = history::Kind::from(message.target.clone());
+
+        // Reactions aren't messages of their own in logs
+        if let Some(logger) = self.logger.as_ref().filter(|_| message.reaction.is_none()) {
+            logger.log(server, &kind, &message);
+        }
+
+        if message.is_highlight == Some(true) {
+            self.highlights
+                .push(server.clone(), kind.clone(), message.clone());
+        }
+
+        self.data.add_message(server.clone(), kind, message);
+    }
+
+    /// Records a line of the DCC chat with `nick`. Its target is the query
+    /// with `nick`, so it can't be recorded by target like other messages.
+    pub fn record_dcc_chat_message(
+        &mut self,
+        server: &Server,
+        nick: &Nick,
+        message: crate::Message,
+    ) {
+        let kind = history::Kind::DccChat(nick.clone());
+
+        if let Some(logger) = self.logger.as_ref() {
+            logger.log(server, &kind, &message);
+        }
+
+        self.data.add_message(server.clone(), kind, message);
+    }
+
+    /// Deletes the message `msgid` sent to `target`, both loaded and on disk
+    pub fn redact(
+        &mut self,
+        server: &Server,
+        target: message::Target,
+        msgid: String,
+        reason: Option<String>,
+    ) -> BoxFuture<'static, Message> {
+        let server = server.clone();
+        let kind = history::Kind::from(target);
+
+        if let Some(history) = self
+            .data
+            .map
+            .get_mut(&server)
+            .and_then(|map| map.get_mut(&kind))
+        {
+            history.redact(&msgid, reason.as_deref());
+        }
+
+        self.highlights.remove(&server, &msgid);
+
+        async move {
+            let result = history::redact(&server, &kind, msgid, reason).await;
+
+            Message::Redacted(server, kind, result)
+        }
+        .boxed()
+    }
+
+    /// Gives the message we sent with `label` its `msgid` from the server's
+    /// echo
+    pub fn record_echo(&mut self, server: &Server, label: &str, echo: crate::Message) {
+        let Some(sent_at) = self.echoes.remove(label) else {
+            return;
+        };
+
+        let kind = history::Kind::from(echo.target.clone());
+
+        if let Some(history) = self
+            .data
+            .map
+            .get_mut(server)
+            .and_then(|map| map.get_mut(&kind))
+        {
+            history.record_echo(sent_at, echo.id);
+        }
+    }
+
+    /// Messages in `buffer` were read up to `read_at`, possibly by another
+    /// client
+    pub fn mark_read(&mut self, buffer: &Buffer, read_at: DateTime<Utc>) {
+        let server = buffer.server().clone();
+        let kind = history::Kind::from(buffer.clone());
+
+        let read_marker = self
+            .data
+            .read_markers
+            .entry(server.clone())
+            .or_default()
+            .entry(kind.clone())
+            .or_insert(read_at);
+        *read_marker = (*read_marker).max(read_at);
+
+        if let Some(history) = self
+            .data
+            .map
+            .get_mut(&server)
+            .and_then(|map| map.get_mut(&kind))
+        {
+            history.mark_read(*read_marker);
+        }
+    }
+
+    /// Buffers read further since their read marker was last updated, with
+    /// the time they're now read up to
+    pub fn update_read_markers(&mut self) -> Vec<(Buffer, DateTime<Utc>)> {
+        let Data {
+            map, read_markers, ..
+        } = &mut self.data;
+
+        map.iter()
+            .flat_map(|(server, map)| {
+                map.iter().filter_map(move |(kind, history)| {
+                    let buffer = match kind {
+                        history::Kind::Server | history::Kind::DccChat(_) => return None,
+                        history::Kind::Channel(channel) => {
+                            Buffer::Channel(server.clone(), channel.clone())
+                        }
+                        history::Kind::Query(nick) => Buffer::Query(server.clone(), nick.clone()),
+                    };
+
+                    Some((buffer, kind, history))
+                })
+            })
+            .filter_map(|(buffer, kind, history)| {
+                let read_markers = read_markers.entry(buffer.server().clone()).or_default();
+                let read_at = history.read_up_to(read_markers.get(kind).copied())?;
+
+                read_markers.insert(kind.clone(), read_at);
+
+                Some((buffer, read_at))
+            })
+            .collect()
+    }
+
+    /// Highlights from every server, oldest first
+    pub fn highlights(&self) -> impl DoubleEndedIterator<Item = &Highlight> {
+        self.highlights.iter()
+    }
+
+    pub fn unread_highlights(&self) -> usize {
+        self.highlights.unread_count()
+    }
+
+    pub fn mark_highlight_read(&mut self, server: &Server, received_at: Posix) {
+        self.highlights.mark_read(server, received_at);
+    }
+
+    pub fn mark_all_highlights_read(&mut self) {
+        self.highlights.mark_all_read();
+    }
+
+    /// Records a message replayed from the server's history, merging it
+    /// in order & skipping it if we already have it
+    pub fn record_chathistory_message(&mut self, server: &Server, message: crate::Message) {
+        self.data.insert_message(
             server.clone(),
             history::Kind::from(message.target.clone()),
             message,
         );
     }
 
+    /// Server time of the latest message stored for `kind`, used as the
+    /// reference when requesting missed messages from the server
+    pub fn latest_server_time(
+        &self,
+        server: &Server,
+        kind: history::Kind,
+    ) -> BoxFuture<'static, Option<DateTime<Utc>>> {
+        let server = server.clone();
+
+        match self.data.map.get(&server).and_then(|map| map.get(&kind)) {
+            Some(History::Full { messages, .. }) => {
+                future::ready(history::latest_server_time(messages)).boxed()
+            }
+            history => {
+                // Unflushed messages are newer than anything stored on disk
+                let partial = match history {
+                    Some(History::Partial { messages, .. }) => {
+                        history::latest_server_time(messages)
+                    }
+                    _ => None,
+                };
+
+                async move {
+                    let stored = history::load(&server, &kind)
+                        .await
+                        .ok()
+                        .and_then(|page| history::latest_server_time(&page.messages));
+
+                    partial.or(stored)
+                }
+                .boxed()
+            }
+        }
+    }
+
     pub fn get_channel_messages(
         &self,
         server: &Server,
@@ -228,6 +698,21 @@ impl Manager {
         )
     }
 
+    pub fn get_dcc_chat_messages(
+        &self,
+        server: &Server,
+        nick: &Nick,
+        limit: Option<Limit>,
+        buffer_config: &config::Buffer,
+    ) -> Option<history::View<'_>> {
+        self.data.history_view(
+            server,
+            &history::Kind::DccChat(nick.clone()),
+            limit,
+            buffer_config,
+        )
+    }
+
     pub fn get_unique_queries(&self, server: &Server) -> Vec<&Nick> {
         let Some(map) = self.data.map.get(server) else {
             return vec![];
@@ -253,10 +738,7 @@ impl Manager {
             .map(|history| {
                 matches!(
                     history,
-                    History::Partial {
-                        unread_message_count,
-                        ..
-                    } if *unread_message_count > 0
+                    History::Partial { unread, .. } if !unread.is_empty()
                 )
             })
             .unwrap_or_default()
@@ -298,6 +780,7 @@ impl Manager {
             Broadcast::ConnectionFailed { error } => {
                 message::broadcast::connection_failed(error, sent_time)
             }
+            Broadcast::Sts { outcome } => message::broadcast::sts(outcome, sent_time),
             Broadcast::Disconnected { error } => {
                 message::broadcast::disconnected(channels, queries, error, sent_time)
             }
@@ -384,6 +867,43 @@ impl Manager {
                     )
                 }
             }
+            Broadcast::ChangeRealname {
+                old_user,
+                new_realname,
+                ourself,
+                user_channels,
+            } => {
+                if ourself {
+                    // If ourself, broadcast to all query channels (since we are in all of them)
+                    message::broadcast::change_realname(
+                        user_channels,
+                        queries,
+                        &old_user,
+                        &new_realname,
+                        ourself,
+                        sent_time,
+                    )
+                } else {
+                    // Otherwise just the query channel of the user w/ realname change
+                    let user_query = queries.find(|nick| old_user.nickname() == *nick);
+                    message::broadcast::change_realname(
+                        user_channels,
+                        user_query,
+                        &old_user,
+                        &new_realname,
+                        ourself,
+                        sent_time,
+                    )
+                }
+            }
+            Broadcast::Presence { nicks, presence } => nicks
+                .into_iter()
+                .flat_map(|nick| {
+                    let user_query = queries.clone().find(|query| nick == *query);
+
+                    message::broadcast::presence(user_query, &nick, presence, sent_time)
+                })
+                .collect(),
         };
 
         messages.into_iter().for_each(|message| {
@@ -396,16 +916,42 @@ impl Manager {
     }
 }
 
+/// Reactions are shown under the message they react to, so only other
+/// messages count towards `limit`
 fn with_limit<'a>(
     limit: Option<Limit>,
     messages: impl Iterator<Item = &'a crate::Message>,
 ) -> Vec<&'a crate::Message> {
     match limit {
-        Some(Limit::Top(n)) => messages.take(n).collect(),
+        Some(Limit::Top(n)) => {
+            let mut count = 0;
+
+            messages
+                .take_while(|message| {
+                    if message.reaction.is_none() {
+                        count += 1;
+                    }
+
+                    count <= n
+                })
+                .collect()
+        }
         Some(Limit::Bottom(n)) => {
             let collected = messages.collect::<Vec<_>>();
-            let length = collected.len();
-            collected[length.saturating_sub(n)..length].to_vec()
+
+            let start = if n == 0 {
+                collected.len()
+            } else {
+                collected
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, message)| message.reaction.is_none())
+                    .nth(n - 1)
+                    .map_or(0, |(index, _)| index)
+            };
+
+            collected[start..].to_vec()
         }
         Some(Limit::Since(timestamp)) => messages
             .skip_while(|message| message.received_at < timestamp)
@@ -418,17 +964,20 @@ fn with_limit<'a>(
 struct Data {
     map: HashMap<server::Server, HashMap<history::Kind, History>>,
     input: input::Storage,
+    /// Time messages were read up to, shared with our other clients when
+    /// the server supports `draft/read-marker`
+    read_markers: HashMap<server::Server, HashMap<history::Kind, DateTime<Utc>>>,
 }
 
 impl Data {
-    fn loaded(
-        &mut self,
-        server: server::Server,
-        kind: history::Kind,
-        mut messages: Vec<crate::Message>,
-    ) {
+    fn loaded(&mut self, server: server::Server, kind: history::Kind, page: history::Page) {
         use std::collections::hash_map;
 
+        let history::Page {
+            mut messages,
+            has_more,
+        } = page;
+
         match self
             .map
             .entry(server.clone())
@@ -444,11 +993,14 @@ impl Data {
                 } => {
                     let last_received_at = *last_received_at;
                     let opened_at = *opened_at;
-                    messages.extend(std::mem::take(new_messages));
+                    let unsaved = std::mem::take(new_messages);
+                    messages.extend(unsaved.iter().cloned());
                     entry.insert(History::Full {
                         server,
                         kind,
                         messages,
+                        unsaved,
+                        has_more,
                         last_received_at,
                         opened_at,
                     });
@@ -458,6 +1010,8 @@ impl Data {
                         server,
                         kind,
                         messages,
+                        unsaved: vec![],
+                        has_more,
                         last_received_at: None,
                         opened_at: Posix::now(),
                     });
@@ -468,6 +1022,8 @@ impl Data {
                     server,
                     kind,
                     messages,
+                    unsaved: vec![],
+                    has_more,
                     last_received_at: None,
                     opened_at: Posix::now(),
                 });
@@ -484,6 +1040,7 @@ impl Data {
     ) -> Option<history::View> {
         let History::Full {
             messages,
+            has_more,
             opened_at,
             ..
         } = self.map.get(server)?.get(kind)?
@@ -549,7 +1106,10 @@ impl Data {
             })
             .collect::<Vec<_>>();
 
-        let total = filtered.len();
+        let total = filtered
+            .iter()
+            .filter(|message| message.reaction.is_none())
+            .count();
         let limited = with_limit(limit, filtered.into_iter());
 
         let split_at = limited
@@ -561,36 +1121,65 @@ impl Data {
 
         Some(history::View {
             total,
+            has_more: *has_more,
             old_messages: old.to_vec(),
             new_messages: new.to_vec(),
         })
     }
 
+    fn read_marker(&self, server: &server::Server, kind: &history::Kind) -> Option<DateTime<Utc>> {
+        self.read_markers.get(server)?.get(kind).copied()
+    }
+
     fn add_message(
         &mut self,
         server: server::Server,
         kind: history::Kind,
         message: crate::Message,
     ) {
+        let read_marker = self.read_marker(&server, &kind);
+
+        self.map
+            .entry(server.clone())
+            .or_default()
+            .entry(kind.clone())
+            .or_insert_with(|| History::partial(server, kind, message.received_at))
+            .add_message(message, read_marker)
+    }
+
+    fn insert_message(
+        &mut self,
+        server: server::Server,
+        kind: history::Kind,
+        message: crate::Message,
+    ) {
+        let read_marker = self.read_marker(&server, &kind);
+
         self.map
             .entry(server.clone())
             .or_default()
             .entry(kind.clone())
             .or_insert_with(|| History::partial(server, kind, message.received_at))
-            .add_message(message)
+            .insert_message(message, read_marker)
     }
 
     fn untrack(
         &mut self,
         server: &server::Server,
         kind: &history::Kind,
+        retention: config::History,
     ) -> Option<impl Future<Output = Result<(), history::Error>>> {
-        self.map
-            .get_mut(server)
-            .and_then(|map| map.get_mut(kind).and_then(History::make_partial))
+        self.map.get_mut(server).and_then(|map| {
+            map.get_mut(kind)
+                .and_then(|history| history.make_partial(retention))
+        })
     }
 
-    fn flush_all(&mut self, now: Instant) -> Vec<BoxFuture<'static, Message>> {
+    fn flush_all(
+        &mut self,
+        now: Instant,
+        retention: config::History,
+    ) -> Vec<BoxFuture<'static, Message>> {
         self.map
             .iter_mut()
             .flat_map(|(server, map)| {
@@ -598,7 +1187,7 @@ impl Data {
                     let server = server.clone();
                     let kind = kind.clone();
 
-                    state.flush(now).map(move |task| {
+                    state.flush(now, retention).map(move |task| {
                         task.map(move |result| Message::Flushed(server, kind, result))
                             .boxed()
                     })
@@ -642,6 +1231,9 @@ pub enum Broadcast {
     ConnectionFailed {
         error: String,
     },
+    Sts {
+        outcome: sts::Outcome,
+    },
     Disconnected {
         error: Option<String>,
     },
@@ -669,4 +1261,14 @@ pub enum Broadcast {
         ourself: bool,
         user_channels: Vec<String>,
     },
+    ChangeRealname {
+        old_user: User,
+        new_realname: String,
+        ourself: bool,
+        user_channels: Vec<String>,
+    },
+    Presence {
+        nicks: Vec<Nick>,
+        presence: Presence,
+    },
 }
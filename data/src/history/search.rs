@@ -0,0 +1,542 @@
+//! Full-text search across persisted history
+//!
+//! Each time history is written to disk, its messages are added to an
+//! inverted index mapping terms & nicknames to the buffer and day they appear
+//! in. A search only has to read the history of candidate days, rather than
+//! decompressing every history file.
+//!
+//! New entries are appended to a journal, which is folded into the compressed
+//! index once it grows large, so a flush doesn't rewrite the whole index.
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use futures::lock::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use super::{Error, Kind};
+use crate::user::NickRef;
+use crate::{compression, environment, message, server, Buffer, Message};
+
+/// Max # results returned for a single search
+const MAX_RESULTS: usize = 500;
+/// Min # characters for a word to be indexed
+const MIN_TERM_LENGTH: usize = 2;
+/// # journal entries after which the index is rewritten in full
+const COMPACT_AFTER: usize = 1000;
+
+const INDEX_FILE: &str = "search-index.json.gz";
+const JOURNAL_FILE: &str = "search-index.journal";
+
+static INDEX: Lazy<Mutex<Option<Index>>> = Lazy::new(|| Mutex::new(None));
+
+/// Add `messages` from `kind` on `server` to the search index
+pub async fn index(
+    server: &server::Server,
+    kind: &Kind,
+    messages: &[Message],
+) -> Result<(), Error> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let mut index = INDEX.lock().await;
+
+    if index.is_none() {
+        *index = Some(load().await);
+    }
+
+    let Some(index) = index.as_mut() else {
+        return Ok(());
+    };
+
+    let entries = index.update(server, kind, messages);
+
+    if !entries.is_empty() {
+        save(index, &entries).await?;
+    }
+
+    Ok(())
+}
+
+/// Search persisted history, returning the most recent matches first
+pub async fn search(query: Query) -> Result<Vec<Match>, Error> {
+    let terms = terms(&query.text);
+
+    if terms.is_empty() && query.nick.is_none() {
+        return Ok(vec![]);
+    }
+
+    let candidates = {
+        let mut index = INDEX.lock().await;
+
+        if index.is_none() {
+            *index = Some(load().await);
+        }
+
+        index
+            .as_ref()
+            .map(|index| index.candidates(&query, &terms))
+            .unwrap_or_default()
+    };
+
+    let mut matches = vec![];
+
+    for ((server, kind), days) in candidates {
+        let (Some(since), Some(until)) = (days.first(), days.last()) else {
+            continue;
+        };
+
+        let messages = match super::load_between(&server, &kind, *since, *until).await {
+            Ok(messages) => messages,
+            Err(error) => {
+                log::warn!("failed to search {server} {kind}: {error}");
+                continue;
+            }
+        };
+
+        matches.extend(
+            messages
+                .into_iter()
+                .filter(|message| {
+                    days.contains(&message.server_time.date_naive())
+                        && query.matches(message, &terms)
+                })
+                .map(|message| Match {
+                    server: server.clone(),
+                    kind: kind.clone(),
+                    message,
+                }),
+        );
+    }
+
+    matches.sort_by_key(|result| Reverse(result.message.server_time));
+    matches.truncate(MAX_RESULTS);
+
+    Ok(matches)
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub server: server::Server,
+    pub kind: Kind,
+    pub message: Message,
+}
+
+impl Match {
+    pub fn buffer(&self) -> Buffer {
+        match &self.kind {
+            Kind::Server => Buffer::Server(self.server.clone()),
+            Kind::Channel(channel) => Buffer::Channel(self.server.clone(), channel.clone()),
+            // DCC chats don't outlive the session, so open the query instead
+            Kind::Query(nick) | Kind::DccChat(nick) => {
+                Buffer::Query(self.server.clone(), nick.clone())
+            }
+        }
+    }
+}
+
+/// A search query, parsed from text with optional filters:
+///
+/// - `from:<nick>` only messages sent by nick
+/// - `in:<channel or nick>` only messages in a channel or query
+/// - `server:<server>` only messages on a server
+/// - `since:<YYYY-MM-DD>` only messages on or after date
+/// - `until:<YYYY-MM-DD>` only messages on or before date
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub text: String,
+    pub nick: Option<String>,
+    pub buffer: Option<String>,
+    pub server: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        let mut text = vec![];
+
+        for word in input.split_whitespace() {
+            let filter = word.split_once(':').filter(|(_, value)| !value.is_empty());
+
+            match filter {
+                Some(("from", nick)) => query.nick = Some(nick.to_string()),
+                Some(("in", buffer)) => query.buffer = Some(buffer.to_string()),
+                Some(("server", server)) => query.server = Some(server.to_string()),
+                Some(("since", date)) if parse_date(date).is_some() => {
+                    query.since = parse_date(date);
+                }
+                Some(("until", date)) if parse_date(date).is_some() => {
+                    query.until = parse_date(date);
+                }
+                _ => text.push(word),
+            }
+        }
+
+        query.text = text.join(" ");
+
+        query
+    }
+
+    fn includes_buffer(&self, server: &server::Server, kind: &Kind) -> bool {
+        let server_matches = self
+            .server
+            .iter()
+            .all(|filter| server.as_ref().eq_ignore_ascii_case(filter));
+
+        let buffer_matches = self.buffer.iter().all(|filter| match kind {
+            Kind::Server => false,
+            Kind::Channel(channel) => channel.eq_ignore_ascii_case(filter),
+            Kind::Query(nick) | Kind::DccChat(nick) => nick.as_ref().eq_ignore_ascii_case(filter),
+        });
+
+        server_matches && buffer_matches
+    }
+
+    fn includes_day(&self, day: NaiveDate) -> bool {
+        self.since.iter().all(|since| day >= *since) && self.until.iter().all(|until| day <= *until)
+    }
+
+    fn matches(&self, message: &Message, terms: &BTreeSet<String>) -> bool {
+        let nick_matches = self.nick.iter().all(|filter| {
+            nickname(message).is_some_and(|nick| nick.as_ref().eq_ignore_ascii_case(filter))
+        });
+
+        nick_matches && terms.is_subset(&self::terms(&message.content.text()))
+    }
+}
+
+/// Buffer & day that a term appears in
+type Bucket = (usize, NaiveDate);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    /// Indexed buffers, referenced by position from buckets
+    buffers: Vec<(server::Server, Kind)>,
+    terms: BTreeMap<String, BTreeSet<Bucket>>,
+    nicks: BTreeMap<String, BTreeSet<Bucket>>,
+    /// # entries in the journal, not yet in the compressed index
+    #[serde(skip)]
+    journaled: usize,
+}
+
+/// Terms & nicknames added to a buffer on a day, as written to the journal
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    server: server::Server,
+    kind: Kind,
+    day: NaiveDate,
+    terms: BTreeSet<String>,
+    nicks: BTreeSet<String>,
+}
+
+impl Entry {
+    fn get<'a>(
+        entries: &'a mut BTreeMap<NaiveDate, Entry>,
+        server: &server::Server,
+        kind: &Kind,
+        day: NaiveDate,
+    ) -> &'a mut Entry {
+        entries.entry(day).or_insert_with(|| Entry {
+            server: server.clone(),
+            kind: kind.clone(),
+            day,
+            terms: BTreeSet::new(),
+            nicks: BTreeSet::new(),
+        })
+    }
+}
+
+impl Index {
+    /// Returns what was added to the index
+    fn update(&mut self, server: &server::Server, kind: &Kind, messages: &[Message]) -> Vec<Entry> {
+        let buffer = self.buffer(server, kind);
+
+        let mut entries = BTreeMap::<NaiveDate, Entry>::new();
+
+        for message in messages {
+            let day = message.server_time.date_naive();
+            let bucket = (buffer, day);
+
+            for term in terms(&message.content.text()) {
+                if self.terms.entry(term.clone()).or_default().insert(bucket) {
+                    Entry::get(&mut entries, server, kind, day)
+                        .terms
+                        .insert(term);
+                }
+            }
+
+            if let Some(nick) = nickname(message) {
+                let nick = nick.as_ref().to_lowercase();
+
+                if self.nicks.entry(nick.clone()).or_default().insert(bucket) {
+                    Entry::get(&mut entries, server, kind, day)
+                        .nicks
+                        .insert(nick);
+                }
+            }
+        }
+
+        entries.into_values().collect()
+    }
+
+    /// Adds an entry read back from the journal
+    fn apply(&mut self, entry: Entry) {
+        let bucket = (self.buffer(&entry.server, &entry.kind), entry.day);
+
+        for term in entry.terms {
+            self.terms.entry(term).or_default().insert(bucket);
+        }
+
+        for nick in entry.nicks {
+            self.nicks.entry(nick).or_default().insert(bucket);
+        }
+    }
+
+    fn buffer(&mut self, server: &server::Server, kind: &Kind) -> usize {
+        self.buffers
+            .iter()
+            .position(|(s, k)| s == server && k == kind)
+            .unwrap_or_else(|| {
+                self.buffers.push((server.clone(), kind.clone()));
+                self.buffers.len() - 1
+            })
+    }
+
+    /// Buffers & the days in them which could contain matches for `query`
+    fn candidates(
+        &self,
+        query: &Query,
+        terms: &BTreeSet<String>,
+    ) -> Vec<((server::Server, Kind), BTreeSet<NaiveDate>)> {
+        let nick = query.nick.as_ref().map(|nick| nick.to_lowercase());
+
+        let buckets = terms
+            .iter()
+            .map(|term| self.terms.get(term))
+            .chain(nick.map(|nick| self.nicks.get(&nick)))
+            .try_fold(None::<BTreeSet<Bucket>>, |acc, buckets| {
+                let buckets = buckets?;
+
+                Some(Some(match acc {
+                    Some(acc) => acc.intersection(buckets).copied().collect(),
+                    None => buckets.clone(),
+                }))
+            })
+            .flatten()
+            .unwrap_or_default();
+
+        let mut candidates = BTreeMap::<usize, BTreeSet<NaiveDate>>::new();
+
+        for (buffer, day) in buckets {
+            let Some((server, kind)) = self.buffers.get(buffer) else {
+                continue;
+            };
+
+            if query.includes_buffer(server, kind) && query.includes_day(day) {
+                candidates.entry(buffer).or_default().insert(day);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|(buffer, days)| Some((self.buffers.get(buffer)?.clone(), days)))
+            .collect()
+    }
+}
+
+fn terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_LENGTH)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn nickname(message: &Message) -> Option<NickRef<'_>> {
+    match message.target.source() {
+        message::Source::User(user) => Some(user.nickname()),
+        _ => None,
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+async fn load() -> Index {
+    let (Ok(path), Ok(journal_path)) = (path(INDEX_FILE).await, path(JOURNAL_FILE).await) else {
+        return Index::default();
+    };
+
+    let mut index = match fs::read(&path).await {
+        Ok(bytes) => compression::decompress(&bytes).unwrap_or_else(|error| {
+            log::warn!("failed to decode search index: {error}");
+            Index::default()
+        }),
+        Err(_) => Index::default(),
+    };
+
+    if let Ok(journal) = fs::read_to_string(&journal_path).await {
+        for line in journal.lines() {
+            // A crash while appending may leave the last line incomplete
+            match serde_json::from_str(line) {
+                Ok(entry) => {
+                    index.apply(entry);
+                    index.journaled += 1;
+                }
+                Err(error) => log::warn!("failed to decode search index entry: {error}"),
+            }
+        }
+    }
+
+    index
+}
+
+/// Appends `entries` to the journal, or rewrites the index once the journal
+/// is large enough
+async fn save(index: &mut Index, entries: &[Entry]) -> Result<(), Error> {
+    let journal_path = path(JOURNAL_FILE).await?;
+
+    if index.journaled + entries.len() > COMPACT_AFTER {
+        let path = path(INDEX_FILE).await?;
+        let temp = path.with_extension("tmp");
+
+        let compressed = compression::compress(&*index)?;
+
+        fs::write(&temp, &compressed).await?;
+        fs::rename(&temp, &path).await?;
+
+        // Entries are already in the index, so replaying them if this
+        // fails is harmless
+        if let Err(error) = fs::remove_file(&journal_path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                return Err(error.into());
+            }
+        }
+
+        index.journaled = 0;
+    } else {
+        let mut lines = vec![];
+
+        for entry in entries {
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+        }
+
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)
+            .await?;
+        journal.write_all(&lines).await?;
+        journal.flush().await?;
+
+        index.journaled += entries.len();
+    }
+
+    Ok(())
+}
+
+async fn path(file_name: &str) -> Result<PathBuf, Error> {
+    let parent = environment::data_dir().join("history");
+
+    if !parent.exists() {
+        fs::create_dir_all(&parent).await?;
+    }
+
+    Ok(parent.join(file_name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_query() {
+        let tests = [
+            (
+                "hello world",
+                Query {
+                    text: "hello world".into(),
+                    ..Query::default()
+                },
+            ),
+            (
+                "from:casper in:#halloy release since:2024-01-01 until:2024-02-01",
+                Query {
+                    text: "release".into(),
+                    nick: Some("casper".into()),
+                    buffer: Some("#halloy".into()),
+                    since: NaiveDate::from_ymd_opt(2024, 1, 1),
+                    until: NaiveDate::from_ymd_opt(2024, 2, 1),
+                    ..Query::default()
+                },
+            ),
+            (
+                "server:libera since:yesterday",
+                Query {
+                    text: "since:yesterday".into(),
+                    server: Some("libera".into()),
+                    ..Query::default()
+                },
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(Query::parse(input), expected);
+        }
+    }
+
+    #[test]
+    fn journal_replay() {
+        let server = server::Server::from("libera");
+        let kind = Kind::Channel("#halloy".to_string());
+        let message = Message {
+            received_at: crate::time::Posix::now(),
+            server_time: chrono::DateTime::from_timestamp(0, 0).unwrap(),
+            direction: message::Direction::Received,
+            target: kind.target(message::Source::User(
+                crate::User::try_from("casper").unwrap(),
+            )),
+            content: message::plain("hello world".into()),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        };
+
+        let mut index = Index::default();
+        let entries = index.update(&server, &kind, std::slice::from_ref(&message));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].nicks, BTreeSet::from(["casper".to_string()]));
+
+        // Already indexed
+        assert!(index.update(&server, &kind, &[message]).is_empty());
+
+        let mut replayed = Index::default();
+        for entry in entries {
+            let line = serde_json::to_string(&entry).unwrap();
+            replayed.apply(serde_json::from_str(&line).unwrap());
+        }
+
+        assert_eq!(replayed.buffers, index.buffers);
+        assert_eq!(replayed.terms, index.terms);
+        assert_eq!(replayed.nicks, index.nicks);
+    }
+
+    #[test]
+    fn tokenize() {
+        let expected = ["client", "halloy", "irc", "is", "rust"]
+            .into_iter()
+            .map(String::from)
+            .collect::<BTreeSet<_>>();
+
+        assert_eq!(terms("Halloy is a Rust IRC (irc!) client?"), expected);
+    }
+}
@@ -0,0 +1,714 @@
+//! Segmented, append-only storage for the history of a single buffer
+//!
+//! Messages are written to numbered segments holding up to [`SEGMENT_SIZE`]
+//! messages. Only the newest segment is written to, one JSON message per line,
+//! so a flush appends to the end of a small file. Once a segment is full it's
+//! sealed & compressed. A manifest records the size & time range of each
+//! segment, allowing pages of older messages to be read without reading the
+//! whole log.
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use super::{insert_ordered, redact_message, Error, Page};
+use crate::{compression, config, Message};
+
+/// Max # messages written to a segment before it's sealed
+const SEGMENT_SIZE: usize = 1_000;
+const MANIFEST: &str = "manifest.json";
+/// Newest segments searched for a redacted message. Redactions are for
+/// recent messages, so older segments aren't read
+const REDACT_SEGMENTS: usize = 3;
+
+#[derive(Debug)]
+pub struct Log {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Log {
+    pub async fn open(dir: PathBuf) -> Result<Self, Error> {
+        if !dir.exists() {
+            fs::create_dir_all(&dir).await?;
+        }
+
+        let manifest = match fs::read(dir.join(MANIFEST)).await {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(manifest) => manifest,
+                Err(error) => {
+                    log::warn!("rebuilding corrupt history manifest {dir:?}: {error}");
+                    Manifest::rebuild(&dir).await?
+                }
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => Manifest::rebuild(&dir).await?,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Self { dir, manifest })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.segments.is_empty()
+    }
+
+    /// At least the `count` most recent messages, read in whole segments
+    pub async fn latest(&self, count: usize) -> Result<Page, Error> {
+        self.page(self.manifest.segments.len(), count, |_| true)
+            .await
+    }
+
+    /// At least `count` messages sent at or before `before`, read in whole
+    /// segments
+    pub async fn before(&self, before: DateTime<Utc>, count: usize) -> Result<Page, Error> {
+        let end = self
+            .manifest
+            .segments
+            .iter()
+            .rposition(|segment| segment.first <= before)
+            .map_or(0, |index| index + 1);
+
+        self.page(end, count, |message| message.server_time <= before)
+            .await
+    }
+
+    /// All messages sent between `since` & `until`, inclusive
+    pub async fn between(&self, since: NaiveDate, until: NaiveDate) -> Result<Vec<Message>, Error> {
+        let mut messages = vec![];
+
+        for segment in self.manifest.segments.iter().filter(|segment| {
+            segment.first.date_naive() <= until && segment.last.date_naive() >= since
+        }) {
+            messages.extend(
+                segment
+                    .read(&self.dir)
+                    .await?
+                    .into_iter()
+                    .filter(|message| {
+                        let day = message.server_time.date_naive();
+
+                        day >= since && day <= until
+                    }),
+            );
+        }
+
+        Ok(messages)
+    }
+
+    /// Appends `messages` to the newest segment, skipping any already stored.
+    /// Messages older than the newest segment, such as backfilled or imported
+    /// ones, are merged into the sealed segment covering their time. Returns
+    /// the messages which were added.
+    pub async fn append(&mut self, messages: Vec<Message>) -> Result<Vec<Message>, Error> {
+        let sealed = self
+            .manifest
+            .segments
+            .iter()
+            .take_while(|segment| segment.sealed)
+            .count();
+
+        // Start of the open segment, or end of the sealed ones
+        let boundary = match self.manifest.segments.get(sealed) {
+            Some(open) => Some(open.first),
+            None => self.manifest.segments.last().map(|segment| segment.last),
+        }
+        .filter(|_| sealed > 0);
+
+        let (older, newer) = messages.into_iter().partition::<Vec<_>, _>(|message| {
+            boundary.is_some_and(|boundary| message.server_time < boundary)
+        });
+
+        let mut appended = self.merge_sealed(sealed, older).await?;
+        appended.extend(self.append_open(newer).await?);
+
+        Ok(appended)
+    }
+
+    /// Merges `messages` into the first `sealed` segments, each into the
+    /// newest segment starting at or before it
+    async fn merge_sealed(
+        &mut self,
+        sealed: usize,
+        messages: Vec<Message>,
+    ) -> Result<Vec<Message>, Error> {
+        let mut by_segment = BTreeMap::<usize, Vec<Message>>::new();
+
+        for message in messages {
+            let index = self.manifest.segments[..sealed]
+                .iter()
+                .rposition(|segment| segment.first <= message.server_time)
+                .unwrap_or(0);
+
+            by_segment.entry(index).or_default().push(message);
+        }
+
+        let mut appended = vec![];
+
+        for (index, messages) in by_segment {
+            let segment = &mut self.manifest.segments[index];
+            let mut stored = segment.read(&self.dir).await?;
+            let merged = appended.len();
+
+            for message in messages {
+                if insert_ordered(&mut stored, message.clone()) {
+                    appended.push(message);
+                }
+            }
+
+            if appended.len() == merged {
+                continue;
+            }
+
+            // Sealed segments may grow past `SEGMENT_SIZE`, keeping segments
+            // ordered by time
+            write_atomic(&segment.path(&self.dir), &encode(&stored, true)?).await?;
+
+            segment.count = stored.len();
+            segment.first = stored[0].server_time;
+            segment.last = stored[stored.len() - 1].server_time;
+        }
+
+        if !appended.is_empty() {
+            self.save_manifest().await?;
+        }
+
+        Ok(appended)
+    }
+
+    /// Appends `messages` to the open segment, or new segments if there
+    /// isn't one
+    async fn append_open(&mut self, messages: Vec<Message>) -> Result<Vec<Message>, Error> {
+        if messages.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let open = self
+            .manifest
+            .segments
+            .last()
+            .filter(|segment| !segment.sealed)
+            .cloned();
+
+        let mut tail = match &open {
+            Some(segment) => segment.read(&self.dir).await?,
+            None => vec![],
+        };
+        let stored = tail.len();
+
+        let mut appended = vec![];
+        let mut in_order = true;
+
+        for message in messages {
+            let at_end = tail
+                .last()
+                .iter()
+                .all(|last| last.server_time <= message.server_time);
+
+            if insert_ordered(&mut tail, message.clone()) {
+                in_order &= at_end;
+                appended.push(message);
+            }
+        }
+
+        if appended.is_empty() {
+            return Ok(appended);
+        }
+
+        match open {
+            // Common case, new messages can be appended to the end of the file
+            Some(segment) if in_order && tail.len() < SEGMENT_SIZE => {
+                let mut lines = vec![];
+
+                for message in &tail[stored..] {
+                    serde_json::to_writer(&mut lines, message)?;
+                    lines.push(b'\n');
+                }
+
+                let mut file = fs::OpenOptions::new()
+                    .append(true)
+                    .open(segment.path(&self.dir))
+                    .await?;
+                file.write_all(&lines).await?;
+                file.flush().await?;
+
+                if let Some(segment) = self.manifest.segments.last_mut() {
+                    segment.count = tail.len();
+                    segment.last = tail[tail.len() - 1].server_time;
+                }
+            }
+            // Messages arrived out of order or the segment is full, rewrite it
+            Some(segment) => {
+                self.manifest.segments.pop();
+
+                let written = self.write_segments(&tail).await?;
+
+                if !written.contains(&segment.path(&self.dir)) {
+                    remove(&segment.path(&self.dir)).await?;
+                }
+            }
+            None => {
+                self.write_segments(&tail).await?;
+            }
+        }
+
+        self.save_manifest().await?;
+
+        Ok(appended)
+    }
+
+    /// Replaces all stored messages with `messages`
+    pub async fn overwrite(&mut self, messages: &[Message]) -> Result<(), Error> {
+        let old = std::mem::take(&mut self.manifest.segments);
+
+        let written = self.write_segments(messages).await?;
+
+        for segment in old {
+            let path = segment.path(&self.dir);
+
+            if !written.contains(&path) {
+                remove(&path).await?;
+            }
+        }
+
+        self.save_manifest().await
+    }
+
+    /// Deletes the message `msgid`, rewriting the segment it's stored in.
+    /// Only the newest [`REDACT_SEGMENTS`] are searched.
+    pub async fn redact(&mut self, msgid: &str, reason: Option<&str>) -> Result<(), Error> {
+        for index in (0..self.manifest.segments.len())
+            .rev()
+            .take(REDACT_SEGMENTS)
+        {
+            let segment = self.manifest.segments[index].clone();
+            let mut messages = segment.read(&self.dir).await?;
+
+            if !redact_message(&mut messages, msgid, reason) {
+                continue;
+            }
+
+            let bytes = encode(&messages, segment.sealed)?;
+            write_atomic(&segment.path(&self.dir), &bytes).await?;
+
+            if messages.len() != segment.count {
+                self.manifest.segments[index].count = messages.len();
+                self.save_manifest().await?;
+            }
+
+            break;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the oldest segments which fall outside of `retention`. The
+    /// newest segment is always kept.
+    pub async fn prune(&mut self, retention: config::History) -> Result<(), Error> {
+        let mut total = self
+            .manifest
+            .segments
+            .iter()
+            .map(|segment| segment.count)
+            .sum::<usize>();
+        let cutoff = retention
+            .max_age
+            .map(|days| Utc::now() - chrono::Duration::days(i64::from(days)));
+
+        let mut removed = 0;
+
+        for segment in &self.manifest.segments[..self.manifest.segments.len().saturating_sub(1)] {
+            let exceeds_count = retention
+                .max_messages
+                .is_some_and(|max| total - segment.count >= max);
+            let exceeds_age = cutoff.is_some_and(|cutoff| segment.last < cutoff);
+
+            if !exceeds_count && !exceeds_age {
+                break;
+            }
+
+            remove(&segment.path(&self.dir)).await?;
+
+            total -= segment.count;
+            removed += 1;
+        }
+
+        if removed > 0 {
+            self.manifest.segments.drain(..removed);
+            self.save_manifest().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads whole segments ending before segment `end` until at least
+    /// `count` messages matching `filter` are read
+    async fn page(
+        &self,
+        end: usize,
+        count: usize,
+        filter: impl Fn(&Message) -> bool,
+    ) -> Result<Page, Error> {
+        let mut messages = vec![];
+        let mut start = end;
+
+        while start > 0 && messages.len() < count {
+            start -= 1;
+
+            let mut older = self.manifest.segments[start].read(&self.dir).await?;
+            older.retain(&filter);
+            older.append(&mut messages);
+
+            messages = older;
+        }
+
+        Ok(Page {
+            messages,
+            has_more: start > 0,
+        })
+    }
+
+    /// Writes `messages` to new segments after the existing ones, returning
+    /// the paths written
+    async fn write_segments(&mut self, messages: &[Message]) -> Result<Vec<PathBuf>, Error> {
+        let mut written = vec![];
+
+        for chunk in messages.chunks(SEGMENT_SIZE) {
+            let segment = Segment {
+                id: self
+                    .manifest
+                    .segments
+                    .last()
+                    .map_or(0, |segment| segment.id + 1),
+                count: chunk.len(),
+                sealed: chunk.len() == SEGMENT_SIZE,
+                first: chunk[0].server_time,
+                last: chunk[chunk.len() - 1].server_time,
+            };
+
+            let bytes = encode(chunk, segment.sealed)?;
+
+            let path = segment.path(&self.dir);
+            write_atomic(&path, &bytes).await?;
+
+            written.push(path);
+            self.manifest.segments.push(segment);
+        }
+
+        Ok(written)
+    }
+
+    async fn save_manifest(&self) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(&self.manifest)?;
+
+        write_atomic(&self.dir.join(MANIFEST), &bytes).await
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    segments: Vec<Segment>,
+}
+
+impl Manifest {
+    /// Recreates the manifest from the segments found in `dir`
+    async fn rebuild(dir: &Path) -> Result<Self, Error> {
+        let mut segments = vec![];
+        let mut entries = fs::read_dir(dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+
+            let (id, sealed) = if let Some(id) = name.strip_suffix(".json.gz") {
+                (id, true)
+            } else if let Some(id) = name.strip_suffix(".jsonl") {
+                (id, false)
+            } else {
+                continue;
+            };
+
+            let Ok(id) = id.parse() else {
+                continue;
+            };
+
+            let mut segment = Segment {
+                id,
+                count: 0,
+                sealed,
+                first: DateTime::default(),
+                last: DateTime::default(),
+            };
+
+            let messages = segment.read(dir).await?;
+
+            if let (Some(first), Some(last)) = (messages.first(), messages.last()) {
+                segment.count = messages.len();
+                segment.first = first.server_time;
+                segment.last = last.server_time;
+
+                segments.push(segment);
+            }
+        }
+
+        segments.sort_by_key(|segment| segment.id);
+
+        Ok(Self { segments })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Segment {
+    id: u64,
+    count: usize,
+    sealed: bool,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+}
+
+impl Segment {
+    fn path(&self, dir: &Path) -> PathBuf {
+        if self.sealed {
+            dir.join(format!("{:08}.json.gz", self.id))
+        } else {
+            dir.join(format!("{:08}.jsonl", self.id))
+        }
+    }
+
+    async fn read(&self, dir: &Path) -> Result<Vec<Message>, Error> {
+        let bytes = match fs::read(self.path(dir)).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+
+        if self.sealed {
+            return Ok(compression::decompress(&bytes)?);
+        }
+
+        // A partially written line is skipped rather than failing the
+        // entire segment
+        Ok(bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| match serde_json::from_slice(line) {
+                Ok(message) => Some(message),
+                Err(error) => {
+                    log::warn!(
+                        "skipping unreadable message in {:?}: {error}",
+                        self.path(dir)
+                    );
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Sealed segments are compressed, the open segment is one JSON message per
+/// line
+fn encode(messages: &[Message], sealed: bool) -> Result<Vec<u8>, Error> {
+    if sealed {
+        return Ok(compression::compress(&messages)?);
+    }
+
+    let mut lines = vec![];
+
+    for message in messages {
+        serde_json::to_writer(&mut lines, message)?;
+        lines.push(b'\n');
+    }
+
+    Ok(lines)
+}
+
+/// Writes to a temporary file first, so a crash never leaves `path` partially
+/// written
+pub(super) async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let temp = path.with_extension("tmp");
+
+    fs::write(&temp, bytes).await?;
+    fs::rename(&temp, path).await?;
+
+    Ok(())
+}
+
+async fn remove(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path).await {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{source, Content, Source, Target};
+    use crate::time::Posix;
+
+    fn message(seconds: i64) -> Message {
+        Message {
+            received_at: Posix::now(),
+            server_time: DateTime::from_timestamp(seconds, 0).unwrap(),
+            direction: crate::message::Direction::Received,
+            target: Target::Server {
+                source: Source::Internal(source::Internal::Status(source::Status::Success)),
+            },
+            content: Content::Plain(format!("message {seconds}")),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("halloy-log-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn append_and_page() {
+        let dir = temp_dir("page");
+        let mut log = Log::open(dir.clone()).await.unwrap();
+
+        let count = SEGMENT_SIZE as i64 * 2 + 10;
+        for chunk in (0..count).collect::<Vec<_>>().chunks(100) {
+            log.append(chunk.iter().copied().map(message).collect())
+                .await
+                .unwrap();
+        }
+
+        // Duplicates & out of order messages are merged into the open segment
+        let appended = log
+            .append(vec![message(count - 1), message(count - 5)])
+            .await
+            .unwrap();
+        assert!(appended.is_empty());
+
+        let log = Log::open(dir.clone()).await.unwrap();
+        assert_eq!(log.manifest.segments.len(), 3);
+
+        let latest = log.latest(5).await.unwrap();
+        assert_eq!(latest.messages.len(), 10);
+        assert!(latest.has_more);
+
+        let older = log.before(latest.messages[0].server_time, 5).await.unwrap();
+        // Includes the message at `before`, which is deduplicated when merged
+        assert_eq!(older.messages.len(), SEGMENT_SIZE + 1);
+        assert_eq!(
+            older.messages[0].server_time,
+            message(SEGMENT_SIZE as i64).server_time
+        );
+        assert!(older.has_more);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn append_older() {
+        let dir = temp_dir("older");
+        let mut log = Log::open(dir.clone()).await.unwrap();
+
+        // Leave gaps to backfill into
+        let count = SEGMENT_SIZE as i64 * 2 + 10;
+        log.append((0..count).map(|n| message(n * 2)).collect())
+            .await
+            .unwrap();
+
+        let appended = log
+            .append(vec![
+                message(1),
+                message(2),
+                message(3),
+                message(count * 2 + 1),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(appended.len(), 3);
+
+        let log = Log::open(dir.clone()).await.unwrap();
+        assert_eq!(log.manifest.segments.len(), 3);
+        assert_eq!(log.manifest.segments[0].count, SEGMENT_SIZE + 2);
+        assert_eq!(log.manifest.segments[2].count, 11);
+
+        let messages = log.between(NaiveDate::MIN, NaiveDate::MAX).await.unwrap();
+        assert_eq!(messages.len(), count as usize + 3);
+        assert!(messages
+            .windows(2)
+            .all(|pair| pair[0].server_time <= pair[1].server_time));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn redact() {
+        let dir = temp_dir("redact");
+        let mut log = Log::open(dir.clone()).await.unwrap();
+
+        let with_id = |seconds| Message {
+            id: Some(format!("msg{seconds}")),
+            ..message(seconds)
+        };
+        let reaction = Message {
+            reply_to: Some("msg1".into()),
+            reaction: Some("👍".into()),
+            ..with_id(SEGMENT_SIZE as i64 + 1)
+        };
+
+        log.overwrite(
+            &(0..SEGMENT_SIZE as i64)
+                .map(with_id)
+                .chain([reaction])
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        log.redact("msg1", Some("spam")).await.unwrap();
+        log.redact(&format!("msg{}", SEGMENT_SIZE + 1), None)
+            .await
+            .unwrap();
+
+        let log = Log::open(dir.clone()).await.unwrap();
+        let messages = log.between(NaiveDate::MIN, NaiveDate::MAX).await.unwrap();
+
+        assert_eq!(messages.len(), SEGMENT_SIZE);
+        assert_eq!(messages[1].content.text(), "message deleted (spam)");
+        assert_eq!(messages[2].content.text(), "message 2");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn prune_by_count() {
+        let dir = temp_dir("prune");
+        let mut log = Log::open(dir.clone()).await.unwrap();
+
+        log.overwrite(
+            &(0..SEGMENT_SIZE as i64 * 3)
+                .map(message)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        log.prune(config::History {
+            max_messages: Some(SEGMENT_SIZE + 1),
+            max_age: None,
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(log.manifest.segments.len(), 2);
+        assert_eq!(Manifest::rebuild(&dir).await.unwrap().segments.len(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+//! Ignoring users by hostmask or account
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::User;
+
+/// An ignored user & what is ignored from them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub pattern: Pattern,
+    /// Ignored content, everything when empty
+    pub scopes: Vec<Scope>,
+}
+
+impl Entry {
+    pub fn new(pattern: Pattern, scopes: Vec<Scope>) -> Self {
+        Self { pattern, scopes }
+    }
+
+    pub fn matches(&self, user: &User, scope: Scope) -> bool {
+        (self.scopes.is_empty() || self.scopes.contains(&scope)) && self.pattern.matches(user)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Data {
+            Pattern(String),
+            Scoped {
+                pattern: String,
+                #[serde(default)]
+                scopes: Vec<Scope>,
+            },
+        }
+
+        let (pattern, scopes) = match Data::deserialize(deserializer)? {
+            Data::Pattern(pattern) => (pattern, vec![]),
+            Data::Scoped { pattern, scopes } => (pattern, scopes),
+        };
+
+        Ok(Entry::new(Pattern::from(pattern.as_str()), scopes))
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)?;
+
+        if !self.scopes.is_empty() {
+            let scopes = self
+                .scopes
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+
+            write!(f, " ({})", scopes.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// `nick!user@host` glob, where `*` & `?` are wildcards. A pattern
+    /// without `!` or `@` only matches the nick.
+    Mask(String),
+    /// Account name, written as `$a:account`
+    Account(String),
+}
+
+impl Pattern {
+    pub fn matches(&self, user: &User) -> bool {
+        match self {
+            Pattern::Mask(mask) => {
+                let nick = user.nickname();
+
+                if mask.contains(['!', '@']) {
+                    let hostmask = format!(
+                        "{}!{}@{}",
+                        nick,
+                        user.username().unwrap_or_default(),
+                        user.hostname().unwrap_or_default()
+                    );
+
+                    glob(mask, &hostmask)
+                } else {
+                    glob(mask, nick.as_ref())
+                }
+            }
+            Pattern::Account(account) => user
+                .accountname()
+                .is_some_and(|accountname| accountname.eq_ignore_ascii_case(account)),
+        }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Self {
+        match pattern.strip_prefix("$a:") {
+            Some(account) => Pattern::Account(account.to_string()),
+            None => Pattern::Mask(pattern.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Mask(mask) => write!(f, "{mask}"),
+            Pattern::Account(account) => write!(f, "$a:{account}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    Messages,
+    Notices,
+    Ctcp,
+    JoinsParts,
+    Highlights,
+}
+
+impl FromStr for Scope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "messages" => Ok(Scope::Messages),
+            "notices" => Ok(Scope::Notices),
+            "ctcp" => Ok(Scope::Ctcp),
+            "joins-parts" => Ok(Scope::JoinsParts),
+            "highlights" => Ok(Scope::Highlights),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Messages => write!(f, "messages"),
+            Scope::Notices => write!(f, "notices"),
+            Scope::Ctcp => write!(f, "ctcp"),
+            Scope::JoinsParts => write!(f, "joins-parts"),
+            Scope::Highlights => write!(f, "highlights"),
+        }
+    }
+}
+
+/// Case insensitive glob match, where `*` matches any run of characters &
+/// `?` matches a single character
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` & the text it was matched against
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches() {
+        let user = User::try_from("Troll!~troll@spam.example.com")
+            .unwrap()
+            .with_accountname("trolling");
+
+        let tests = [
+            ("troll", true),
+            ("tr*", true),
+            ("tro?l", true),
+            ("casper", false),
+            ("*!*@*.example.com", true),
+            ("*!*@example.com", false),
+            ("*!~troll@*", true),
+            ("$a:trolling", true),
+            ("$a:troll", false),
+        ];
+
+        for (pattern, expected) in tests {
+            assert_eq!(Pattern::from(pattern).matches(&user), expected, "{pattern}");
+        }
+
+        let entry = Entry::new(Pattern::from("troll"), vec![Scope::JoinsParts]);
+
+        assert!(entry.matches(&user, Scope::JoinsParts));
+        assert!(!entry.matches(&user, Scope::Messages));
+    }
+}
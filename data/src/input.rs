@@ -5,6 +5,7 @@ use irc::proto;
 use irc::proto::format;
 
 use crate::buffer::AutoFormat;
+use crate::config::{aliases, Aliases};
 use crate::message::formatting;
 use crate::time::Posix;
 use crate::{command, message, Buffer, Command, Message, Server, User};
@@ -38,14 +39,48 @@ pub fn parse(buffer: Buffer, auto_format: AutoFormat, input: &str) -> Result<Inp
         buffer,
         content,
         raw: Some(input.to_string()),
+        reply_to: None,
     })
 }
 
+/// Parses input, expanding it into the inputs an alias runs. Only the first
+/// input is recalled in input history, as the text entered.
+pub fn parse_aliased(
+    buffer: Buffer,
+    auto_format: AutoFormat,
+    aliases: &Aliases,
+    nick: Option<&str>,
+    input: &str,
+) -> Result<Vec<Input>, Error> {
+    let context = aliases::Context {
+        nick,
+        channel: buffer.channel(),
+        server: buffer.server().as_ref(),
+    };
+
+    let Some(lines) = aliases.expand(input, context) else {
+        return parse(buffer, auto_format, input).map(|input| vec![input]);
+    };
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            parse(buffer.clone(), auto_format, line).map(|parsed| Input {
+                raw: (index == 0).then(|| input.to_string()),
+                ..parsed
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct Input {
     buffer: Buffer,
     content: Content,
     raw: Option<String>,
+    /// `msgid` of the message this replies to
+    reply_to: Option<String>,
 }
 
 impl Input {
@@ -54,6 +89,16 @@ impl Input {
             buffer,
             content: Content::Command(command),
             raw: None,
+            reply_to: None,
+        }
+    }
+
+    /// Sends this input as a reply to the message `msgid`, if it's text
+    /// rather than a command
+    pub fn with_reply(self, msgid: String) -> Self {
+        Self {
+            reply_to: matches!(self.content, Content::Text(_)).then_some(msgid),
+            ..self
         }
     }
 
@@ -91,6 +136,10 @@ impl Input {
                 direction: message::Direction::Sent,
                 target: to_target(target, message::Source::User(user))?,
                 content: message::parse_fragments(text),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: self.reply_to.clone(),
+                reaction: None,
             }),
             Command::Me(target, action) => Some(Message {
                 received_at: Posix::now(),
@@ -98,13 +147,35 @@ impl Input {
                 direction: message::Direction::Sent,
                 target: to_target(target, message::Source::Action)?,
                 content: message::action_text(user.nickname(), Some(&action)),
+                is_highlight: Some(false),
+                id: None,
+                reply_to: self.reply_to.clone(),
+                reaction: None,
             }),
             _ => None,
         }
     }
 
+    /// The command if it's handled by the client, rather than sent to
+    /// the server
+    pub fn local_command(&self) -> Option<&Command> {
+        match &self.content {
+            Content::Command(command) if command.is_local() => Some(command),
+            _ => None,
+        }
+    }
+
     pub fn encoded(&self) -> Option<message::Encoded> {
-        self.content.proto(&self.buffer).map(message::Encoded::from)
+        let mut message = self.content.proto(&self.buffer)?;
+
+        if let (Some(msgid), proto::Command::PRIVMSG(..)) = (&self.reply_to, &message.command) {
+            message.tags.push(proto::Tag {
+                key: message::REPLY_TAG.to_string(),
+                value: Some(msgid.clone()),
+            });
+        }
+
+        Some(message::Encoded::from(message))
     }
 
     pub fn raw(&self) -> Option<&str> {
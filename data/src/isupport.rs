@@ -7,14 +7,18 @@ use std::str::FromStr;
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Kind {
     AWAYLEN,
+    CASEMAPPING,
     CHANLIMIT,
     CHANNELLEN,
+    CHATHISTORY,
+    CLIENTTAGDENY,
     CNOTICE,
     CPRIVMSG,
     ELIST,
     KEYLEN,
     KICKLEN,
     KNOCK,
+    MONITOR,
     NICKLEN,
     SAFELIST,
     STATUSMSG,
@@ -481,14 +485,18 @@ impl Operation {
             Operation::Add(parameter) => parameter.kind(),
             Operation::Remove(parameter) => match parameter.as_ref() {
                 "AWAYLEN" => Some(Kind::AWAYLEN),
+                "CASEMAPPING" => Some(Kind::CASEMAPPING),
                 "CHANLIMIT" => Some(Kind::CHANLIMIT),
                 "CHANNELLEN" => Some(Kind::CHANNELLEN),
+                "CHATHISTORY" => Some(Kind::CHATHISTORY),
+                "CLIENTTAGDENY" => Some(Kind::CLIENTTAGDENY),
                 "CNOTICE" => Some(Kind::CNOTICE),
                 "CPRIVMSG" => Some(Kind::CPRIVMSG),
                 "ELIST" => Some(Kind::ELIST),
                 "KEYLEN" => Some(Kind::KEYLEN),
                 "KICKLEN" => Some(Kind::KICKLEN),
                 "KNOCK" => Some(Kind::KNOCK),
+                "MONITOR" => Some(Kind::MONITOR),
                 "NICKLEN" => Some(Kind::NICKLEN),
                 "SAFELIST" => Some(Kind::SAFELIST),
                 "STATUSMSG" => Some(Kind::STATUSMSG),
@@ -575,14 +583,18 @@ impl Parameter {
     pub fn kind(&self) -> Option<Kind> {
         match self {
             Parameter::AWAYLEN(_) => Some(Kind::AWAYLEN),
+            Parameter::CASEMAPPING(_) => Some(Kind::CASEMAPPING),
             Parameter::CHANLIMIT(_) => Some(Kind::CHANLIMIT),
             Parameter::CHANNELLEN(_) => Some(Kind::CHANNELLEN),
+            Parameter::CHATHISTORY(_) => Some(Kind::CHATHISTORY),
+            Parameter::CLIENTTAGDENY(_) => Some(Kind::CLIENTTAGDENY),
             Parameter::CNOTICE => Some(Kind::CNOTICE),
             Parameter::CPRIVMSG => Some(Kind::CPRIVMSG),
             Parameter::ELIST(_) => Some(Kind::ELIST),
             Parameter::KEYLEN(_) => Some(Kind::KEYLEN),
             Parameter::KICKLEN(_) => Some(Kind::KICKLEN),
             Parameter::KNOCK => Some(Kind::KNOCK),
+            Parameter::MONITOR(_) => Some(Kind::MONITOR),
             Parameter::NICKLEN(_) => Some(Kind::NICKLEN),
             Parameter::SAFELIST => Some(Kind::SAFELIST),
             Parameter::STATUSMSG(_) => Some(Kind::STATUSMSG),
@@ -596,14 +608,36 @@ impl Parameter {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum CaseMap {
     ASCII,
+    /// Assumed when the server doesn't advertise one
+    #[default]
     RFC1459,
     RFC1459_STRICT,
     RFC7613,
 }
 
+impl CaseMap {
+    /// Folds `name` so names equal under the casemapping compare equal
+    pub fn normalize(self, name: &str) -> String {
+        match self {
+            CaseMap::ASCII => name.to_ascii_lowercase(),
+            CaseMap::RFC1459 | CaseMap::RFC1459_STRICT => name
+                .chars()
+                .map(|c| match c {
+                    '[' => '{',
+                    ']' => '}',
+                    '\\' => '|',
+                    '~' if matches!(self, CaseMap::RFC1459) => '^',
+                    c => c.to_ascii_lowercase(),
+                })
+                .collect(),
+            CaseMap::RFC7613 => name.to_lowercase(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ChannelLimit {
     pub prefix: char,
@@ -16,6 +16,7 @@ pub use self::user::User;
 pub use self::version::Version;
 
 pub mod audio;
+pub mod bouncer;
 pub mod buffer;
 pub mod channel;
 pub mod client;
@@ -25,21 +26,28 @@ pub mod config;
 pub mod ctcp;
 pub mod dashboard;
 pub mod dcc;
+pub mod dcc_chat;
 pub mod environment;
 pub mod file_transfer;
 pub mod history;
+pub mod ignore;
 pub mod input;
 pub mod isupport;
 pub mod log;
 pub mod message;
 pub mod mode;
 pub mod pane;
+pub mod sasl;
 pub mod server;
 pub mod shortcut;
 pub mod stream;
+pub mod sts;
 pub mod theme;
 pub mod time;
+pub mod typing;
 pub mod url;
 pub mod user;
+pub mod vault;
 pub mod version;
+pub mod whois;
 pub mod window;
@@ -29,6 +29,13 @@ static URL_REGEX: Lazy<Regex> = Lazy::new(|| {
 
 pub type Channel = String;
 
+/// Client tag holding the `msgid` of the message being replied to
+pub const REPLY_TAG: &str = "+draft/reply";
+/// Client tag holding an emoji reaction to the `+draft/reply` message
+pub const REACT_TAG: &str = "+draft/react";
+/// Tag holding the account of the user who sent a message, with `account-tag`
+pub const ACCOUNT_TAG: &str = "account";
+
 pub(crate) mod broadcast;
 pub mod formatting;
 pub mod source;
@@ -40,11 +47,32 @@ impl Encoded {
     pub fn user(&self) -> Option<User> {
         let source = self.source.as_ref()?;
 
-        match source {
-            proto::Source::User(user) => Some(User::from(user.clone())),
-            _ => None,
+        let proto::Source::User(user) = source else {
+            return None;
+        };
+
+        let user = User::from(user.clone());
+
+        match self.tag(ACCOUNT_TAG) {
+            Some(accountname) => Some(user.with_accountname(accountname)),
+            None => Some(user),
         }
     }
+
+    /// Value of the tag `key`, if present
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.key == key)
+            .and_then(|tag| tag.value.as_deref())
+    }
+
+    /// Whether this is a `TAGMSG` reacting to another message
+    pub fn is_reaction(&self) -> bool {
+        matches!(self.command, Command::TAGMSG(_))
+            && self.tag(REACT_TAG).is_some()
+            && self.tag(REPLY_TAG).is_some()
+    }
 }
 
 impl std::ops::Deref for Encoded {
@@ -103,33 +131,102 @@ pub struct Message {
     pub direction: Direction,
     pub target: Target,
     pub content: Content,
+    /// Whether the message matched our nickname or a `[highlights]` rule,
+    /// unknown for history stored before highlights were recorded
+    pub is_highlight: Option<bool>,
+    /// The server assigned `msgid`
+    pub id: Option<String>,
+    /// `msgid` of the message this replies or reacts to
+    pub reply_to: Option<String>,
+    /// Emoji reacting to `reply_to`, shown under that message rather
+    /// than on its own
+    pub reaction: Option<String>,
 }
 
 impl Message {
     pub fn triggers_unread(&self) -> bool {
         matches!(self.direction, Direction::Received)
             && matches!(self.target.source(), Source::User(_) | Source::Action)
+            && self.reaction.is_none()
     }
 
+    /// Whether to show the message as a highlight, falling back to mentions
+    /// of our nickname for older history
+    pub fn is_highlighted(&self, our_nick: Option<NickRef>) -> bool {
+        self.is_highlight
+            .unwrap_or_else(|| match (self.target.source(), our_nick) {
+                (Source::User(user), Some(our_nick)) => {
+                    reference_user(user.nickname(), our_nick, self)
+                }
+                _ => false,
+            })
+    }
+
+    /// `allows_highlight` checks what the client's own highlight
+    /// notifications do, such as users ignored for highlights
     pub fn received(
         encoded: Encoded,
         our_nick: Nick,
         config: &Config,
         resolve_attributes: impl Fn(&User, &str) -> Option<User>,
+        allows_highlight: impl Fn(&User) -> bool,
     ) -> Option<Message> {
         let server_time = server_time(&encoded);
+        let id = encoded.tag("msgid").map(String::from);
+        let reply_to = encoded.tag(REPLY_TAG).map(String::from);
+        let reaction = encoded
+            .is_reaction()
+            .then(|| encoded.tag(REACT_TAG).map(String::from))
+            .flatten();
         let content = content(&encoded, &our_nick, config, &resolve_attributes)?;
         let target = target(encoded, &our_nick, &resolve_attributes)?;
 
+        let is_highlight = match (&target, target.source()) {
+            _ if reaction.is_some() => false,
+            (_, Source::User(user)) if !allows_highlight(user) => false,
+            (Target::Channel { channel, .. }, Source::User(user)) => config.highlights.matches(
+                user.nickname(),
+                our_nick.as_ref().into(),
+                Some(channel),
+                &content.text(),
+            ),
+            (Target::Query { .. }, Source::User(user)) => config.highlights.matches(
+                user.nickname(),
+                our_nick.as_ref().into(),
+                None,
+                &content.text(),
+            ),
+            _ => false,
+        };
+
         Some(Message {
             received_at: Posix::now(),
             server_time,
             direction: Direction::Received,
             target,
             content,
+            is_highlight: Some(is_highlight),
+            id,
+            reply_to,
+            reaction,
         })
     }
 
+    /// A reaction we sent to the message `reply_to`
+    pub fn reaction_sent(target: Target, reply_to: String, reaction: String) -> Message {
+        Message {
+            received_at: Posix::now(),
+            server_time: Utc::now(),
+            direction: Direction::Sent,
+            target,
+            content: plain(reaction.clone()),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: Some(reply_to),
+            reaction: Some(reaction),
+        }
+    }
+
     pub fn file_transfer_request_received(from: &Nick, filename: &str) -> Message {
         Message {
             received_at: Posix::now(),
@@ -140,6 +237,10 @@ impl Message {
                 source: Source::Action,
             },
             content: plain(format!(" ∙ {from} wants to send you \"{filename}\"")),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
         }
     }
 
@@ -153,13 +254,90 @@ impl Message {
                 source: Source::Action,
             },
             content: plain(format!(" ∙ offering to send {to} \"{filename}\"")),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
         }
     }
 
+    pub fn dcc_chat_request_received(from: &Nick) -> Message {
+        Message {
+            received_at: Posix::now(),
+            server_time: Utc::now(),
+            direction: Direction::Received,
+            target: Target::Query {
+                nick: from.clone(),
+                source: Source::Action,
+            },
+            content: plain(format!(" ∙ {from} wants to chat with you directly")),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        }
+    }
+
+    pub fn dcc_chat_request_sent(to: &Nick) -> Message {
+        Message {
+            received_at: Posix::now(),
+            server_time: Utc::now(),
+            direction: Direction::Sent,
+            target: Target::Query {
+                nick: to.clone(),
+                source: Source::Action,
+            },
+            content: plain(format!(" ∙ offering {to} a direct chat")),
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
+        }
+    }
+
+    /// Whether `other` is the same message, such as one replayed from
+    /// the server's history that we have already stored.
+    pub fn is_duplicate(&self, other: &Message) -> bool {
+        if let (Some(id), Some(other_id)) = (&self.id, &other.id) {
+            return id == other_id;
+        }
+
+        self.server_time == other.server_time
+            && self.target == other.target
+            && self.content == other.content
+    }
+
     pub fn with_target(self, target: Target) -> Self {
         Self { target, ..self }
     }
 
+    /// Replaces the content of a deleted message
+    pub fn redact(&mut self, reason: Option<&str>) {
+        self.content = plain(match reason {
+            Some(reason) => format!("message deleted ({reason})"),
+            None => "message deleted".to_string(),
+        });
+        self.is_highlight = Some(false);
+    }
+
+    /// A single line summary, for quoting this message in a reply
+    pub fn preview(&self) -> String {
+        const MAX_CHARS: usize = 80;
+
+        let text = self.content.text();
+        let text = match self.target.source() {
+            Source::User(user) => format!("{}: {text}", user.nickname()),
+            _ => text.trim_start().to_string(),
+        };
+        let line = text.lines().next().unwrap_or_default();
+
+        if line.chars().count() > MAX_CHARS || line.len() < text.len() {
+            format!("{}…", line.chars().take(MAX_CHARS).collect::<String>())
+        } else {
+            line.to_string()
+        }
+    }
+
     pub fn plain(&self) -> Option<&str> {
         match &self.content {
             Content::Plain(s) => Some(s),
@@ -180,6 +358,14 @@ impl Serialize for Message {
             direction: &'a Direction,
             target: &'a Target,
             content: &'a Content,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            is_highlight: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            id: &'a Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            reply_to: &'a Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            reaction: &'a Option<String>,
             // Old field before we had fragments,
             // added for downgrade compatability
             text: Cow<'a, str>,
@@ -191,6 +377,10 @@ impl Serialize for Message {
             direction: &self.direction,
             target: &self.target,
             content: &self.content,
+            is_highlight: self.is_highlight,
+            id: &self.id,
+            reply_to: &self.reply_to,
+            reaction: &self.reaction,
             text: self.content.text(),
         }
         .serialize(serializer)
@@ -211,6 +401,14 @@ impl<'de> Deserialize<'de> for Message {
             // New field, optional for upgrade compatability
             #[serde(default, deserialize_with = "fail_as_none")]
             content: Option<Content>,
+            #[serde(default)]
+            is_highlight: Option<bool>,
+            #[serde(default)]
+            id: Option<String>,
+            #[serde(default)]
+            reply_to: Option<String>,
+            #[serde(default)]
+            reaction: Option<String>,
             // Old field before we had fragments
             text: Option<String>,
         }
@@ -221,6 +419,10 @@ impl<'de> Deserialize<'de> for Message {
             direction,
             target,
             content,
+            is_highlight,
+            id,
+            reply_to,
+            reaction,
             text,
         } = Data::deserialize(deserializer)?;
 
@@ -240,6 +442,10 @@ impl<'de> Deserialize<'de> for Message {
             direction,
             target,
             content,
+            is_highlight,
+            id,
+            reply_to,
+            reaction,
         })
     }
 }
@@ -302,7 +508,7 @@ pub enum Content {
 }
 
 impl Content {
-    fn text(&self) -> Cow<str> {
+    pub fn text(&self) -> Cow<str> {
         match self {
             Content::Plain(s) => s.into(),
             Content::Fragments(fragments) => fragments.iter().map(Fragment::as_str).join("").into(),
@@ -433,6 +639,28 @@ fn target(
                 _ => None,
             }
         }
+        Command::TAGMSG(target) => match (proto::is_channel(&target), user) {
+            (true, Some(user)) => {
+                let source = Source::User(resolve_attributes(&user, &target).unwrap_or(user));
+                Some(Target::Channel {
+                    channel: target,
+                    source,
+                })
+            }
+            (false, Some(user)) => {
+                let nick = if user.nickname() == *our_nick {
+                    User::try_from(target.as_str()).ok()?.nickname().to_owned()
+                } else {
+                    user.nickname().to_owned()
+                };
+
+                Some(Target::Query {
+                    nick,
+                    source: Source::User(user),
+                })
+            }
+            _ => None,
+        },
         Command::NOTICE(target, text) => {
             let is_action = is_action(&text);
             let source = |user| {
@@ -500,11 +728,14 @@ fn target(
         | Command::AUTHENTICATE(_)
         | Command::ACCOUNT(_)
         | Command::BATCH(_, _)
+        | Command::BOUNCER(_, _)
         | Command::CHGHOST(_, _)
         | Command::CNOTICE(_, _, _)
         | Command::CPRIVMSG(_, _, _)
         | Command::KNOCK(_, _)
-        | Command::TAGMSG(_)
+        | Command::REDACT(_, _, _)
+        | Command::MARKREAD(_, _)
+        | Command::SETNAME(_)
         | Command::USERIP(_)
         | Command::HELP(_)
         | Command::MODE(_, _, _)
@@ -621,6 +852,7 @@ fn content(
             Some(parse_fragments(text.clone()))
         }
         Command::NOTICE(_, text) => Some(parse_fragments(text.clone())),
+        Command::TAGMSG(_) if message.is_reaction() => Some(plain(message.tag(REACT_TAG)?.into())),
         Command::Numeric(RPL_TOPIC, params) => {
             let topic = params.get(2)?;
 
@@ -783,7 +1015,7 @@ pub fn action_text(nick: NickRef, action: Option<&str>) -> Content {
     }
 }
 
-pub fn reference_user(sender: NickRef, own_nick: NickRef, message: &Message) -> bool {
+fn reference_user(sender: NickRef, own_nick: NickRef, message: &Message) -> bool {
     let has_nick = match &message.content {
         Content::Plain(text) => text.contains(own_nick.as_ref()),
         Content::Fragments(fragments) => fragments
@@ -794,10 +1026,6 @@ pub fn reference_user(sender: NickRef, own_nick: NickRef, message: &Message) ->
     sender != own_nick && has_nick
 }
 
-pub fn reference_user_text(sender: NickRef, own_nick: NickRef, text: &str) -> bool {
-    sender != own_nick && text.contains(own_nick.as_ref())
-}
-
 fn fail_as_none<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     T: Deserialize<'de>,
@@ -816,6 +1044,31 @@ where
 mod test {
     use super::*;
 
+    #[test]
+    fn preview() {
+        let message = |text: &str| Message {
+            received_at: Posix::now(),
+            server_time: Utc::now(),
+            direction: Direction::Received,
+            target: Target::Channel {
+                channel: "#halloy".into(),
+                source: Source::User(User::from(Nick::from("casper"))),
+            },
+            content: plain(text.to_string()),
+            is_highlight: Some(false),
+            id: Some("abc".into()),
+            reply_to: None,
+            reaction: None,
+        };
+
+        assert_eq!(message("hello").preview(), "casper: hello");
+        assert_eq!(message("first\nsecond").preview(), "casper: first…");
+        assert_eq!(
+            message(&"a".repeat(100)).preview(),
+            format!("casper: {}…", "a".repeat(72))
+        );
+    }
+
     #[test]
     fn fragment_parsing() {
         let tests = [
@@ -2,10 +2,11 @@
 use chrono::{DateTime, Utc};
 
 use super::{parse_fragments, plain, source, Content, Direction, Message, Source, Target};
+use crate::client::Presence;
 use crate::config::buffer::UsernameFormat;
 use crate::time::Posix;
 use crate::user::Nick;
-use crate::{Config, User};
+use crate::{sts, Config, User};
 
 enum Cause {
     Server(Option<source::Server>),
@@ -27,6 +28,10 @@ fn expand(
             direction: Direction::Received,
             target,
             content,
+            is_highlight: Some(false),
+            id: None,
+            reply_to: None,
+            reaction: None,
         }
     };
 
@@ -102,6 +107,18 @@ pub fn connection_failed(error: String, sent_time: DateTime<Utc>) -> Vec<Message
     )
 }
 
+pub fn sts(outcome: sts::Outcome, sent_time: DateTime<Utc>) -> Vec<Message> {
+    let content = plain(format!(" ∙ {outcome}"));
+    expand(
+        [],
+        [],
+        true,
+        Cause::Status(source::Status::Success),
+        content,
+        sent_time,
+    )
+}
+
 pub fn disconnected(
     channels: impl IntoIterator<Item = String>,
     queries: impl IntoIterator<Item = Nick>,
@@ -231,3 +248,41 @@ pub fn change_host(
         sent_time,
     )
 }
+
+pub fn change_realname(
+    channels: impl IntoIterator<Item = String>,
+    queries: impl IntoIterator<Item = Nick>,
+    old_user: &User,
+    new_realname: &str,
+    ourself: bool,
+    sent_time: DateTime<Utc>,
+) -> Vec<Message> {
+    let content = if ourself {
+        plain(format!(" ∙ You've changed your realname to {new_realname}"))
+    } else {
+        plain(format!(
+            " ∙ {} changed their realname to {new_realname}",
+            old_user.nickname()
+        ))
+    };
+
+    expand(
+        channels,
+        queries,
+        false,
+        Cause::Server(None),
+        content,
+        sent_time,
+    )
+}
+
+pub fn presence(
+    queries: impl IntoIterator<Item = Nick>,
+    nick: &Nick,
+    presence: Presence,
+    sent_time: DateTime<Utc>,
+) -> Vec<Message> {
+    let content = plain(format!(" ∙ {nick} is {presence}"));
+
+    expand([], queries, true, Cause::Server(None), content, sent_time)
+}
@@ -16,6 +16,8 @@ pub enum Pane {
     },
     Empty,
     FileTransfers,
+    Search,
+    Highlights,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
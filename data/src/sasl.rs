@@ -0,0 +1,572 @@
+//! SASL authentication exchanges driven by `AUTHENTICATE`
+//!
+//! Reference: https://ircv3.net/specs/extensions/sasl-3.1
+use std::fmt;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::engine::Engine;
+use hmac::{Hmac, Mac};
+use p256::ecdsa::signature::hazmat::PrehashSigner;
+use p256::ecdsa::{DerSignature, SigningKey};
+use p256::pkcs8::DecodePrivateKey;
+use rand::distributions::{Alphanumeric, DistString};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::config::server::Sasl;
+
+/// Max length of an `AUTHENTICATE` param, longer payloads are split
+const CHUNK_SIZE: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    Plain,
+    External,
+    ScramSha256,
+    ScramSha1,
+    EcdsaNist256pChallenge,
+}
+
+impl Mechanism {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mechanism::Plain => "PLAIN",
+            Mechanism::External => "EXTERNAL",
+            Mechanism::ScramSha256 => "SCRAM-SHA-256",
+            Mechanism::ScramSha1 => "SCRAM-SHA-1",
+            Mechanism::EcdsaNist256pChallenge => "ECDSA-NIST256P-CHALLENGE",
+        }
+    }
+
+    /// Chooses the first of `preferred` the server supports. `supported` is
+    /// the value of the `sasl` capability, which servers may leave out.
+    pub fn choose(preferred: &[Mechanism], supported: Option<&str>) -> Option<Mechanism> {
+        match supported.filter(|supported| !supported.is_empty()) {
+            Some(supported) => preferred.iter().copied().find(|mechanism| {
+                supported
+                    .split(',')
+                    .any(|name| name.eq_ignore_ascii_case(mechanism.name()))
+            }),
+            None => preferred.first().copied(),
+        }
+    }
+}
+
+impl fmt::Display for Mechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid base64 from server")]
+    Base64(#[from] base64::DecodeError),
+    #[error("unexpected message from server")]
+    Unexpected,
+    #[error("server error: {0}")]
+    Server(String),
+    #[error("server signature doesn't match, the server may not know the password")]
+    ServerSignature,
+    #[error("password is missing")]
+    MissingPassword,
+    #[error("failed to read key {}: {1}", .0.display())]
+    Key(PathBuf, String),
+    #[error("failed to sign challenge: {0}")]
+    Signature(String),
+}
+
+/// An authentication exchange with the server using a single mechanism
+#[derive(Debug)]
+pub struct Session {
+    mechanism: Mechanism,
+    step: Step,
+    /// Base64 received so far, when the server splits its payload
+    partial: String,
+}
+
+#[derive(Debug)]
+enum Step {
+    Plain {
+        username: String,
+        password: Option<String>,
+    },
+    External,
+    Scram(Scram),
+    Ecdsa {
+        username: String,
+        key: PathBuf,
+        sent_username: bool,
+    },
+    Done,
+}
+
+impl Session {
+    pub fn new(mechanism: Mechanism, sasl: &Sasl) -> Self {
+        let username = sasl.username().unwrap_or_default().to_string();
+
+        let step = match mechanism {
+            Mechanism::Plain => Step::Plain {
+                username,
+                password: sasl.password().map(String::from),
+            },
+            Mechanism::External => Step::External,
+            Mechanism::ScramSha256 | Mechanism::ScramSha1 => {
+                let hash = if mechanism == Mechanism::ScramSha256 {
+                    Hash::Sha256
+                } else {
+                    Hash::Sha1
+                };
+
+                Step::Scram(Scram::new(
+                    hash,
+                    username,
+                    sasl.password().map(String::from),
+                    Alphanumeric.sample_string(&mut rand::thread_rng(), 24),
+                ))
+            }
+            Mechanism::EcdsaNist256pChallenge => Step::Ecdsa {
+                username,
+                key: sasl.key().cloned().unwrap_or_default(),
+                sent_username: false,
+            },
+        };
+
+        Self {
+            mechanism,
+            step,
+            partial: String::new(),
+        }
+    }
+
+    pub fn mechanism(&self) -> Mechanism {
+        self.mechanism
+    }
+
+    /// Whether the server may report success. SCRAM also authenticates the
+    /// server, so it must have sent a valid signature first.
+    pub fn is_complete(&self) -> bool {
+        match &self.step {
+            Step::Scram(scram) => matches!(scram.state, ScramState::Done),
+            Step::Plain { .. } | Step::External | Step::Ecdsa { .. } | Step::Done => true,
+        }
+    }
+
+    /// Handles the param of an `AUTHENTICATE` from the server, returning the
+    /// params to respond with. Nothing is returned while waiting for the
+    /// rest of a split payload.
+    pub fn receive(&mut self, param: &str) -> Result<Vec<String>, Error> {
+        if param != "+" {
+            self.partial.push_str(param);
+
+            if param.len() == CHUNK_SIZE {
+                return Ok(vec![]);
+            }
+        }
+
+        let challenge = BASE64.decode(std::mem::take(&mut self.partial))?;
+
+        let response = self.step(&challenge)?;
+
+        Ok(encode(&response))
+    }
+
+    fn step(&mut self, challenge: &[u8]) -> Result<Vec<u8>, Error> {
+        match &mut self.step {
+            Step::Plain { username, password } => {
+                let password = password.as_ref().ok_or(Error::MissingPassword)?;
+                let response = format!("{username}\x00{username}\x00{password}");
+
+                self.step = Step::Done;
+
+                Ok(response.into_bytes())
+            }
+            Step::External => {
+                self.step = Step::Done;
+
+                Ok(vec![])
+            }
+            Step::Scram(scram) => scram.step(challenge),
+            Step::Ecdsa {
+                username,
+                key,
+                sent_username,
+            } => {
+                if !*sent_username {
+                    *sent_username = true;
+
+                    return Ok(username.clone().into_bytes());
+                }
+
+                let signature = sign(key, challenge)?;
+
+                self.step = Step::Done;
+
+                Ok(signature)
+            }
+            Step::Done => Err(Error::Unexpected),
+        }
+    }
+}
+
+/// Encodes `response` as `AUTHENTICATE` params, split into chunks. An empty
+/// response, or one ending on a full chunk, is followed by `+`.
+fn encode(response: &[u8]) -> Vec<String> {
+    let encoded = BASE64.encode(response);
+
+    let mut params = encoded
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>();
+
+    if encoded.is_empty() || params.last().is_some_and(|last| last.len() == CHUNK_SIZE) {
+        params.push("+".to_string());
+    }
+
+    params
+}
+
+/// Signs the challenge for ECDSA-NIST256P-CHALLENGE with the PEM encoded
+/// key at `path`, which may be a SEC1 or PKCS#8 private key
+fn sign(path: &PathBuf, challenge: &[u8]) -> Result<Vec<u8>, Error> {
+    let pem = std::fs::read_to_string(path)
+        .map_err(|error| Error::Key(path.clone(), error.to_string()))?;
+
+    let key = match p256::SecretKey::from_sec1_pem(&pem) {
+        Ok(key) => SigningKey::from(key),
+        Err(_) => SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|error| Error::Key(path.clone(), error.to_string()))?,
+    };
+
+    let signature: DerSignature = key
+        .sign_prehash(challenge)
+        .map_err(|error| Error::Signature(error.to_string()))?;
+
+    Ok(signature.as_bytes().to_vec())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Hash {
+    Sha1,
+    Sha256,
+}
+
+impl Hash {
+    fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Sha1 => Sha1::digest(data).to_vec(),
+            Hash::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("any key length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Hash::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("any key length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// PBKDF2 with a single block, the length of the hash
+    fn pbkdf2(self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut u = self.hmac(password, &[salt, &1u32.to_be_bytes()].concat());
+        let mut result = u.clone();
+
+        for _ in 1..iterations {
+            u = self.hmac(password, &u);
+            result.iter_mut().zip(&u).for_each(|(r, u)| *r ^= u);
+        }
+
+        result
+    }
+}
+
+/// SCRAM client, as described in RFC 5802 & RFC 7677. Channel binding isn't
+/// supported.
+#[derive(Debug)]
+struct Scram {
+    hash: Hash,
+    username: String,
+    password: Option<String>,
+    nonce: String,
+    state: ScramState,
+}
+
+/// Upper bound on the `i=` iteration count a server may ask for, so a
+/// hostile server can't stall the client hashing the password
+const MAX_ITERATIONS: u32 = 100_000;
+
+#[derive(Debug)]
+enum ScramState {
+    Initial,
+    ClientFirst,
+    ClientFinal { server_signature: Vec<u8> },
+    Done,
+}
+
+impl Scram {
+    fn new(hash: Hash, username: String, password: Option<String>, nonce: String) -> Self {
+        Self {
+            hash,
+            username,
+            password,
+            nonce,
+            state: ScramState::Initial,
+        }
+    }
+
+    fn client_first_bare(&self) -> String {
+        let username = self.username.replace('=', "=3D").replace(',', "=2C");
+
+        format!("n={username},r={}", self.nonce)
+    }
+
+    fn step(&mut self, challenge: &[u8]) -> Result<Vec<u8>, Error> {
+        match &self.state {
+            ScramState::Initial => {
+                self.state = ScramState::ClientFirst;
+
+                Ok(format!("n,,{}", self.client_first_bare()).into_bytes())
+            }
+            ScramState::ClientFirst => {
+                let server_first = String::from_utf8_lossy(challenge).into_owned();
+                let attributes = attributes(&server_first)?;
+
+                let nonce = attribute(&attributes, 'r')?;
+                let salt = BASE64.decode(attribute(&attributes, 's')?)?;
+                let iterations = attribute(&attributes, 'i')?
+                    .parse::<u32>()
+                    .map_err(|_| Error::Unexpected)?;
+
+                // Server nonce must extend ours
+                if !nonce.starts_with(&self.nonce) || iterations == 0 || iterations > MAX_ITERATIONS
+                {
+                    return Err(Error::Unexpected);
+                }
+
+                let password = self.password.as_ref().ok_or(Error::MissingPassword)?;
+
+                // GS2 header `n,,` without channel binding
+                let client_final_without_proof = format!("c=biws,r={nonce}");
+                let auth_message = format!(
+                    "{},{server_first},{client_final_without_proof}",
+                    self.client_first_bare()
+                );
+
+                let salted_password = self.hash.pbkdf2(password.as_bytes(), &salt, iterations);
+                let client_key = self.hash.hmac(&salted_password, b"Client Key");
+                let stored_key = self.hash.digest(&client_key);
+                let client_signature = self.hash.hmac(&stored_key, auth_message.as_bytes());
+                let proof = client_key
+                    .iter()
+                    .zip(&client_signature)
+                    .map(|(key, signature)| key ^ signature)
+                    .collect::<Vec<_>>();
+
+                let server_key = self.hash.hmac(&salted_password, b"Server Key");
+                let server_signature = self.hash.hmac(&server_key, auth_message.as_bytes());
+
+                self.state = ScramState::ClientFinal { server_signature };
+
+                Ok(format!("{client_final_without_proof},p={}", BASE64.encode(proof)).into_bytes())
+            }
+            ScramState::ClientFinal { server_signature } => {
+                let server_final = String::from_utf8_lossy(challenge).into_owned();
+                let attributes = attributes(&server_final)?;
+
+                if let Ok(error) = attribute(&attributes, 'e') {
+                    return Err(Error::Server(error.to_string()));
+                }
+
+                let verifier = BASE64.decode(attribute(&attributes, 'v')?)?;
+
+                if verifier != *server_signature {
+                    return Err(Error::ServerSignature);
+                }
+
+                self.state = ScramState::Done;
+
+                Ok(vec![])
+            }
+            ScramState::Done => Err(Error::Unexpected),
+        }
+    }
+}
+
+fn attributes(message: &str) -> Result<Vec<(char, &str)>, Error> {
+    message
+        .split(',')
+        .map(|attribute| {
+            let (name, value) = attribute.split_once('=').ok_or(Error::Unexpected)?;
+            let mut chars = name.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(name), None) => Ok((name, value)),
+                _ => Err(Error::Unexpected),
+            }
+        })
+        .collect()
+}
+
+fn attribute<'a>(attributes: &[(char, &'a str)], name: char) -> Result<&'a str, Error> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| *attribute == name)
+        .map(|(_, value)| *value)
+        .ok_or(Error::Unexpected)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scram_exchange(
+        hash: Hash,
+        nonce: &str,
+        server_first: &str,
+        client_final: &str,
+        server_final: &str,
+    ) {
+        let mut scram = Scram::new(
+            hash,
+            "user".into(),
+            Some("pencil".into()),
+            nonce.to_string(),
+        );
+
+        assert_eq!(
+            scram.step(&[]).unwrap(),
+            format!("n,,n=user,r={nonce}").into_bytes()
+        );
+        assert_eq!(
+            String::from_utf8(scram.step(server_first.as_bytes()).unwrap()).unwrap(),
+            client_final
+        );
+        assert!(scram.step(server_final.as_bytes()).unwrap().is_empty());
+    }
+
+    // RFC 5802 section 5
+    #[test]
+    fn scram_sha_1() {
+        scram_exchange(
+            Hash::Sha1,
+            "fyko+d2lbbFgONRv9qkxdawL",
+            "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+            "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=",
+            "v=rmF9pqV8S7suAoZWja4dJRkFsKQ=",
+        );
+    }
+
+    // RFC 7677 section 3
+    #[test]
+    fn scram_sha_256() {
+        scram_exchange(
+            Hash::Sha256,
+            "rOprNGfwEbeRWgbNEkqO",
+            "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+            "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=",
+        );
+    }
+
+    #[test]
+    fn scram_rejects_server_signature() {
+        let mut scram = Scram::new(
+            Hash::Sha256,
+            "user".into(),
+            Some("pencil".into()),
+            "rOprNGfwEbeRWgbNEkqO".into(),
+        );
+
+        scram.step(&[]).unwrap();
+        scram
+            .step(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096")
+            .unwrap();
+
+        assert!(matches!(
+            scram.step(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ="),
+            Err(Error::ServerSignature)
+        ));
+    }
+
+    #[test]
+    fn scram_rejects_iterations() {
+        let mut scram = Scram::new(
+            Hash::Sha256,
+            "user".into(),
+            Some("pencil".into()),
+            "rOprNGfwEbeRWgbNEkqO".into(),
+        );
+
+        scram.step(&[]).unwrap();
+
+        assert!(matches!(
+            scram.step(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4294967295"),
+            Err(Error::Unexpected)
+        ));
+    }
+
+    #[test]
+    fn ecdsa_challenge() {
+        use p256::ecdsa::signature::hazmat::PrehashVerifier;
+        use p256::ecdsa::{Signature, VerifyingKey};
+        use p256::pkcs8::{EncodePrivateKey, LineEnding};
+
+        let secret = p256::SecretKey::random(&mut rand::thread_rng());
+        let path = std::env::temp_dir().join(format!("halloy-sasl-{}.pem", std::process::id()));
+        std::fs::write(&path, secret.to_pkcs8_pem(LineEnding::LF).unwrap()).unwrap();
+
+        let challenge = [7u8; 32];
+        let signature = sign(&path, &challenge).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let verifying_key = VerifyingKey::from(secret.public_key());
+        let signature = Signature::from_der(&signature).unwrap();
+
+        assert!(verifying_key.verify_prehash(&challenge, &signature).is_ok());
+    }
+
+    #[test]
+    fn choose() {
+        let preferred = [Mechanism::ScramSha256, Mechanism::ScramSha1];
+
+        assert_eq!(
+            Mechanism::choose(&preferred, Some("PLAIN,SCRAM-SHA-1,SCRAM-SHA-256")),
+            Some(Mechanism::ScramSha256)
+        );
+        assert_eq!(
+            Mechanism::choose(&preferred, Some("PLAIN,SCRAM-SHA-1")),
+            Some(Mechanism::ScramSha1)
+        );
+        assert_eq!(Mechanism::choose(&preferred, Some("PLAIN,EXTERNAL")), None);
+        assert_eq!(
+            Mechanism::choose(&preferred, None),
+            Some(Mechanism::ScramSha256)
+        );
+    }
+
+    #[test]
+    fn split_payloads() {
+        assert_eq!(encode(&[]), vec!["+"]);
+        assert_eq!(encode(b"abc"), vec!["YWJj"]);
+
+        // 300 bytes encode to exactly one full chunk
+        let params = encode(&[0; 300]);
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].len(), CHUNK_SIZE);
+        assert_eq!(params[1], "+");
+
+        let params = encode(&[0; 400]);
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[1].len(), 136);
+    }
+}
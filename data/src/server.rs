@@ -6,9 +6,10 @@ use futures::channel::mpsc::Sender;
 use irc::proto;
 use serde::{Deserialize, Serialize};
 
-use crate::config;
-use crate::config::server::Sasl;
-use crate::config::Error;
+use crate::config::server::SaslAuth;
+use crate::config::{read_command, Error};
+use crate::vault::Vault;
+use crate::{bouncer, config};
 
 pub type Handle = Sender<proto::Message>;
 
@@ -72,47 +73,155 @@ impl Map {
         self.0.iter().map(Entry::from)
     }
 
-    pub fn read_password_files(&mut self) -> Result<(), Error> {
-        for (_, config) in self.0.iter_mut() {
-            if let Some(pass_file) = &config.password_file {
-                if config.password.is_some() {
-                    return Err(Error::Parse(
-                        "Only one of password and password_file can be set.".to_string(),
-                    ));
-                }
-                let pass = fs::read_to_string(pass_file)?;
-                config.password = Some(pass);
-            }
-            if let Some(nick_pass_file) = &config.nick_password_file {
-                if config.nick_password.is_some() {
-                    return Err(Error::Parse(
-                        "Only one of nick_password and nick_password_file can be set.".to_string(),
-                    ));
-                }
-                let nick_pass = fs::read_to_string(nick_pass_file)?;
-                config.nick_password = Some(nick_pass);
-            }
+    /// Adds a server for a network listed by the bouncer `parent`, returning
+    /// it unless the network already has one
+    pub fn insert_bouncer_network(
+        &mut self,
+        parent: &Server,
+        network: &bouncer::Network,
+    ) -> Option<Server> {
+        if self.bouncer_network(parent, &network.netid).is_some() {
+            return None;
+        }
+
+        let config = self
+            .0
+            .get(parent)?
+            .with_bouncer_network(parent.clone(), network.netid.clone());
+
+        let server = network.server(parent);
+
+        self.insert(server.clone(), config);
+
+        Some(server)
+    }
+
+    /// Removes the server for a network the bouncer `parent` removed
+    pub fn remove_bouncer_network(&mut self, parent: &Server, netid: &str) -> Option<Server> {
+        let server = self.bouncer_network(parent, netid)?.clone();
+
+        self.remove(&server);
+
+        Some(server)
+    }
+
+    /// Servers for networks listed by a bouncer, rather than configured
+    pub fn bouncer_networks(&self) -> impl Iterator<Item = Entry> + '_ {
+        self.entries()
+            .filter(|entry| entry.config.bouncer.is_some())
+    }
+
+    fn bouncer_network(&self, parent: &Server, netid: &str) -> Option<&Server> {
+        self.0.iter().find_map(|(server, config)| {
+            config
+                .bouncer
+                .as_ref()
+                .is_some_and(|binding| &binding.parent == parent && binding.netid == netid)
+                .then_some(server)
+        })
+    }
+
+    /// Resolves credentials read from a file, a command or the vault. The
+    /// vault is used for credentials without any other source, under
+    /// `<server>/password`, `<server>/nick_password` &
+    /// `<server>/sasl_password`. A vault that couldn't be unlocked only
+    /// fails servers which need a password from it.
+    pub fn resolve_credentials(
+        &mut self,
+        vault: Result<Option<&Vault>, &Error>,
+    ) -> Result<(), Error> {
+        for (server, config) in self.0.iter_mut() {
+            let secret = |key: &str| {
+                vault.map(|vault| vault.and_then(|vault| vault.get(&format!("{server}/{key}"))))
+            };
+
+            resolve_credential(
+                "password",
+                &mut config.password,
+                config.password_file.as_deref(),
+                config.password_command.as_deref(),
+                secret("password"),
+                false,
+            )?;
+            resolve_credential(
+                "nick_password",
+                &mut config.nick_password,
+                config.nick_password_file.as_deref(),
+                config.nick_password_command.as_deref(),
+                secret("nick_password"),
+                false,
+            )?;
+
             if let Some(sasl) = &mut config.sasl {
-                match sasl {
-                    Sasl::Plain {
-                        password: Some(_),
-                        password_file: Some(_),
+                let (name, password, password_file, password_command) = match &mut sasl.auth {
+                    SaslAuth::Plain {
+                        password,
+                        password_file,
+                        password_command,
                         ..
-                    } => {
-                        return Err(Error::Parse("Exactly one of sasl.plain.password or sasl.plain.password_file must be set.".to_string()));
-                    }
-                    Sasl::Plain {
-                        password: password @ None,
-                        password_file: Some(pass_file),
+                    } => (
+                        "sasl.plain.password",
+                        password,
+                        password_file,
+                        password_command,
+                    ),
+                    SaslAuth::Scram {
+                        password,
+                        password_file,
+                        password_command,
                         ..
-                    } => {
-                        let pass = fs::read_to_string(pass_file)?;
-                        *password = Some(pass);
-                    }
-                    _ => {}
-                }
+                    } => (
+                        "sasl.scram.password",
+                        password,
+                        password_file,
+                        password_command,
+                    ),
+                    SaslAuth::External { .. } | SaslAuth::Ecdsa { .. } => continue,
+                };
+
+                resolve_credential(
+                    name,
+                    password,
+                    password_file.as_deref(),
+                    password_command.as_deref(),
+                    secret("sasl_password"),
+                    true,
+                )?;
             }
         }
         Ok(())
     }
 }
+
+/// Sets `value` from the one source configured for it. Without one, a
+/// `required` credential must be read from the vault.
+fn resolve_credential(
+    name: &str,
+    value: &mut Option<String>,
+    file: Option<&str>,
+    command: Option<&str>,
+    vault: Result<Option<&str>, &Error>,
+    required: bool,
+) -> Result<(), Error> {
+    let sources = [value.is_some(), file.is_some(), command.is_some()];
+
+    if sources.into_iter().filter(|source| *source).count() > 1 {
+        return Err(Error::Parse(format!(
+            "Only one of {name}, {name}_file and {name}_command can be set."
+        )));
+    }
+
+    if let Some(file) = file {
+        *value = Some(fs::read_to_string(file)?);
+    } else if let Some(command) = command {
+        *value = Some(read_command(command)?);
+    } else if value.is_none() {
+        *value = match vault {
+            Ok(secret) => secret.map(String::from),
+            Err(error) if required => return Err(error.clone()),
+            Err(_) => None,
+        };
+    }
+
+    Ok(())
+}
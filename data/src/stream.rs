@@ -11,7 +11,7 @@ use tokio::time::{self, Instant, Interval};
 use crate::client::Client;
 use crate::server::Server;
 use crate::time::Posix;
-use crate::{config, message, server};
+use crate::{config, message, server, sts};
 
 pub type Result<T = Update, E = Error> = std::result::Result<T, E>;
 
@@ -41,6 +41,12 @@ pub enum Update {
     },
     MessagesReceived(Server, Vec<message::Encoded>),
     Quit(Server, Option<String>),
+    /// STS changed how we connect to the server
+    Sts {
+        server: Server,
+        outcome: sts::Outcome,
+        sent_time: DateTime<Utc>,
+    },
 }
 
 enum State {
@@ -85,7 +91,7 @@ async fn _run(
     proxy: Option<config::Proxy>,
     sender: mpsc::UnboundedSender<Update>,
 ) -> Never {
-    let server::Entry { server, config } = server;
+    let server::Entry { server, mut config } = server;
 
     let reconnect_delay = Duration::from_secs(config.reconnect_delay);
 
@@ -111,6 +117,41 @@ async fn _run(
                     }
                 }
 
+                // Refuse plaintext while the server's STS policy lasts
+                if !config.use_tls {
+                    match sts::policy(&config.server).await {
+                        Ok(Some(policy)) => {
+                            config = config.with_sts_port(policy.port);
+
+                            let _ = sender.unbounded_send(Update::Sts {
+                                server: server.clone(),
+                                outcome: sts::Outcome::Enforced(policy),
+                                sent_time: Utc::now(),
+                            });
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            // A policy may be stored for the server, so don't
+                            // risk connecting without TLS
+                            let error = format!(
+                                "refusing to connect without TLS, as STS policies couldn't be read: {e}"
+                            );
+
+                            log::warn!("[{server}] {error}");
+
+                            let _ = sender.unbounded_send(Update::ConnectionFailed {
+                                server: server.clone(),
+                                error,
+                                sent_time: Utc::now(),
+                            });
+
+                            *last_retry = Some(Instant::now());
+
+                            continue;
+                        }
+                    }
+                }
+
                 match connect(server.clone(), config.clone(), proxy.clone()).await {
                     Ok((stream, client)) => {
                         log::info!("[{server}] connected");
@@ -200,6 +241,57 @@ async fn _run(
                                 last_retry: Some(Instant::now()),
                             };
                         }
+                        proto::Command::CAP(_, ref subcommand, ref a, ref b)
+                            if subcommand == "LS" =>
+                        {
+                            let advertisement = b
+                                .as_deref()
+                                .or(a.as_deref())
+                                .and_then(sts::Advertisement::find);
+
+                            match advertisement {
+                                // Reconnect with TLS right away
+                                Some(sts::Advertisement {
+                                    port: Some(port), ..
+                                }) if !config.use_tls => {
+                                    log::info!("[{server}] upgrading to TLS on port {port}");
+
+                                    config = config.with_sts_port(port);
+                                    is_initial = true;
+
+                                    let _ = sender.unbounded_send(Update::Sts {
+                                        server: server.clone(),
+                                        outcome: sts::Outcome::Upgraded { port },
+                                        sent_time: Utc::now(),
+                                    });
+
+                                    state = State::Disconnected { last_retry: None };
+
+                                    continue;
+                                }
+                                Some(sts::Advertisement {
+                                    duration: Some(duration),
+                                    ..
+                                }) if config.use_tls => {
+                                    match sts::update(&config.server, config.port, duration).await {
+                                        Ok(Some(outcome)) => {
+                                            let _ = sender.unbounded_send(Update::Sts {
+                                                server: server.clone(),
+                                                outcome,
+                                                sent_time: Utc::now(),
+                                            });
+                                        }
+                                        Ok(None) => {}
+                                        Err(e) => {
+                                            log::warn!("[{server}] failed to save STS policy: {e}");
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+
+                            batch.messages.push(message.into());
+                        }
                         _ => {
                             batch.messages.push(message.into());
                         }
@@ -343,3 +435,63 @@ fn ping_timeout_interval(secs: u64) -> Interval {
         Duration::from_secs(secs),
     )
 }
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sts_upgrade() {
+        let plaintext = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tls = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let plaintext_port = plaintext.local_addr().unwrap().port();
+        let tls_port = tls.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut tcp, _) = plaintext.accept().await.unwrap();
+
+            tcp.write_all(format!(":irc.test CAP * LS :sasl sts=port={tls_port}\r\n").as_bytes())
+                .await
+                .unwrap();
+
+            // Keep the connection open until we're dropped for TLS
+            future::pending::<()>().await;
+        });
+        let upgraded = tokio::spawn(async move { tls.accept().await.is_ok() });
+
+        let mut config = config::Server::default();
+        config.server = "127.0.0.1".to_string();
+        config.port = plaintext_port;
+        config.use_tls = false;
+
+        let entry = server::Entry {
+            server: Server::from("test"),
+            config,
+        };
+
+        let updates = run(entry, None)
+            .take_while(|update| future::ready(!matches!(update, Update::ConnectionFailed { .. })))
+            .collect::<Vec<_>>();
+
+        let updates = time::timeout(Duration::from_secs(10), updates)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            updates.as_slice(),
+            [
+                Update::Disconnected { .. },
+                Update::Connected { .. },
+                Update::Sts {
+                    outcome: sts::Outcome::Upgraded { port },
+                    ..
+                },
+            ] if *port == tls_port
+        ));
+        // The TLS handshake was attempted on the advertised port
+        assert!(upgraded.await.unwrap());
+    }
+}
@@ -0,0 +1,266 @@
+//! Strict Transport Security, which has us connect with TLS to servers
+//! advertising the `sts` capability
+//!
+//! Reference: https://ircv3.net/specs/extensions/sts
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{fmt, io};
+
+use chrono::{DateTime, Utc};
+use futures::lock::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// Policies read from disk once, & held while they're updated so updates
+/// from different connections can't interleave
+static POLICIES: Lazy<Mutex<Option<Policies>>> = Lazy::new(|| Mutex::new(None));
+
+/// The `sts` capability value, `port=6697,duration=2592000`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Advertisement {
+    /// Port to reconnect to with TLS, when advertised over plaintext
+    pub port: Option<u16>,
+    /// How long the policy lasts, when advertised over TLS
+    pub duration: Option<Duration>,
+}
+
+impl Advertisement {
+    /// Finds the advertisement among the caps listed with `CAP LS`
+    pub fn find(caps: &str) -> Option<Self> {
+        caps.split(' ')
+            .find_map(|cap| cap.strip_prefix("sts="))
+            .map(Self::parse)
+    }
+
+    fn parse(value: &str) -> Self {
+        let mut advertisement = Advertisement {
+            port: None,
+            duration: None,
+        };
+
+        for (key, value) in value.split(',').filter_map(|key| key.split_once('=')) {
+            match key {
+                "port" => advertisement.port = value.parse().ok(),
+                "duration" => {
+                    advertisement.duration = value.parse().ok().map(Duration::from_secs);
+                }
+                _ => {}
+            }
+        }
+
+        advertisement
+    }
+}
+
+/// TLS is required on `port` until the policy expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    pub port: u16,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// How STS changed how we connect to a server, shown in its buffer
+#[derive(Debug, Clone, Copy)]
+pub enum Outcome {
+    /// The server asked a plaintext connection to reconnect with TLS
+    Upgraded { port: u16 },
+    /// A stored policy had us connect with TLS instead of plaintext
+    Enforced(Policy),
+    /// A policy was stored, refusing plaintext connections until it expires
+    Saved(Policy),
+    /// The server removed its policy
+    Removed,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Upgraded { port } => write!(
+                f,
+                "server requires a secure connection, reconnecting with TLS on port {port}"
+            ),
+            Outcome::Enforced(Policy { port, expires_at }) => write!(
+                f,
+                "connecting with TLS on port {port}, as required by the server until {}",
+                expires_at.format("%Y-%m-%d")
+            ),
+            Outcome::Saved(Policy { expires_at, .. }) => write!(
+                f,
+                "plaintext connections to this server are refused until {}",
+                expires_at.format("%Y-%m-%d")
+            ),
+            Outcome::Removed => write!(f, "server no longer requires a secure connection"),
+        }
+    }
+}
+
+/// Policies of every host we've connected to with TLS
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Policies(HashMap<String, Policy>);
+
+impl Policies {
+    fn get(&self, host: &str, now: DateTime<Utc>) -> Option<Policy> {
+        self.0
+            .get(&host.to_lowercase())
+            .filter(|policy| policy.expires_at > now)
+            .copied()
+    }
+
+    /// Stores the policy advertised over TLS to `host` on `port`
+    fn update(
+        &mut self,
+        host: &str,
+        port: u16,
+        duration: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<Outcome> {
+        let host = host.to_lowercase();
+
+        if duration.is_zero() {
+            return self.0.remove(&host).map(|_| Outcome::Removed);
+        }
+
+        let policy = Policy {
+            port,
+            expires_at: now + chrono::Duration::from_std(duration).ok()?,
+        };
+
+        let is_new = self
+            .0
+            .insert(host, policy)
+            .filter(|previous| previous.expires_at > now)
+            .is_none();
+
+        is_new.then_some(Outcome::Saved(policy))
+    }
+}
+
+/// The unexpired policy stored for `host`. Fails when the stored policies
+/// can't be read, so plaintext can be refused rather than allowed.
+pub async fn policy(host: &str) -> Result<Option<Policy>, Error> {
+    let mut cached = POLICIES.lock().await;
+    let policies = cached_policies(&mut cached).await?;
+
+    Ok(policies.get(host, Utc::now()))
+}
+
+/// Stores the policy `host` advertised while connected with TLS on `port`,
+/// returning the outcome when it's new or removed
+pub async fn update(host: &str, port: u16, duration: Duration) -> Result<Option<Outcome>, Error> {
+    let mut cached = POLICIES.lock().await;
+    let policies = cached_policies(&mut cached).await?;
+
+    // Refresh the expiry, even if there's nothing new to show
+    let outcome = policies.update(host, port, duration, Utc::now());
+
+    save(policies).await?;
+
+    Ok(outcome)
+}
+
+/// Loads the policies into `cached` the first time they're needed. A failed
+/// load is retried next time, rather than overwriting the stored policies.
+async fn cached_policies(cached: &mut Option<Policies>) -> Result<&mut Policies, Error> {
+    let policies = match cached.take() {
+        Some(policies) => policies,
+        None => load().await?,
+    };
+
+    Ok(cached.insert(policies))
+}
+
+async fn load() -> Result<Policies, Error> {
+    let bytes = match tokio::fs::read(path()).await {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Policies::default()),
+        Err(error) => return Err(error.into()),
+    };
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Writes to a temporary file first, so a crash never leaves the policies
+/// partially written
+async fn save(policies: &Policies) -> Result<(), Error> {
+    let bytes = serde_json::to_vec(policies)?;
+
+    let path = path();
+    let temp = path.with_extension("tmp");
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    tokio::fs::write(&temp, &bytes).await?;
+    tokio::fs::rename(&temp, &path).await?;
+
+    Ok(())
+}
+
+fn path() -> PathBuf {
+    directory().join("sts.json")
+}
+
+#[cfg(not(test))]
+fn directory() -> PathBuf {
+    crate::environment::data_dir()
+}
+
+/// Tests connecting to servers never read or write the user's policies
+#[cfg(test)]
+fn directory() -> PathBuf {
+    std::env::temp_dir().join(format!("halloy-sts-{}", std::process::id()))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn advertisement() {
+        assert_eq!(
+            Advertisement::find("multi-prefix sts=port=6697,duration=300,preload sasl"),
+            Some(Advertisement {
+                port: Some(6697),
+                duration: Some(Duration::from_secs(300)),
+            })
+        );
+        assert_eq!(Advertisement::find("multi-prefix sasl"), None);
+    }
+
+    #[test]
+    fn policies() {
+        let now = Utc::now();
+        let duration = Duration::from_secs(300);
+        let mut policies = Policies::default();
+
+        assert!(matches!(
+            policies.update("irc.example.com", 6697, duration, now),
+            Some(Outcome::Saved(Policy { port: 6697, .. }))
+        ));
+        // Refreshed without a new outcome
+        assert!(policies
+            .update("IRC.example.com", 6697, duration, now)
+            .is_none());
+
+        assert!(policies.get("irc.example.com", now).is_some());
+        assert!(policies
+            .get("irc.example.com", now + chrono::Duration::seconds(300))
+            .is_none());
+
+        assert!(matches!(
+            policies.update("irc.example.com", 6697, Duration::ZERO, now),
+            Some(Outcome::Removed)
+        ));
+        assert!(policies.get("irc.example.com", now).is_none());
+    }
+}
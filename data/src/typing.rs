@@ -0,0 +1,257 @@
+//! Typing notifications with the `+typing` client tag
+//!
+//! Reference: https://ircv3.net/specs/client-tags/typing
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::isupport::CaseMap;
+use crate::user::{Nick, NickRef};
+
+/// Min time between sending `active` while typing
+const ACTIVE_THROTTLE: Duration = Duration::from_secs(3);
+/// We've paused once we haven't typed for this long
+const PAUSE_AFTER: Duration = Duration::from_secs(5);
+/// Received `active` is dropped if not renewed in time
+const ACTIVE_TIMEOUT: Duration = Duration::from_secs(6);
+/// Received `paused` is dropped if not renewed in time
+const PAUSED_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const TAG: &str = "+typing";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Active,
+    Paused,
+    Done,
+}
+
+impl FromStr for State {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(State::Active),
+            "paused" => Ok(State::Paused),
+            "done" => Ok(State::Done),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Active => write!(f, "active"),
+            State::Paused => write!(f, "paused"),
+            State::Done => write!(f, "done"),
+        }
+    }
+}
+
+/// Our typing state in a single buffer
+#[derive(Debug, Default)]
+pub struct Outgoing {
+    sent: Option<(State, Instant)>,
+    last_input: Option<Instant>,
+}
+
+impl Outgoing {
+    /// The state to send after the input changed, if any
+    pub fn input(&mut self, is_empty: bool, now: Instant) -> Option<State> {
+        if is_empty {
+            self.last_input = None;
+
+            return match self.sent.take() {
+                Some((State::Active | State::Paused, _)) => Some(State::Done),
+                _ => None,
+            };
+        }
+
+        self.last_input = Some(now);
+
+        match self.sent {
+            Some((State::Active, sent_at)) if now.duration_since(sent_at) < ACTIVE_THROTTLE => None,
+            _ => {
+                self.sent = Some((State::Active, now));
+                Some(State::Active)
+            }
+        }
+    }
+
+    /// `paused`, once we've stopped typing for a while
+    pub fn tick(&mut self, now: Instant) -> Option<State> {
+        let (State::Active, _) = self.sent? else {
+            return None;
+        };
+
+        if now.duration_since(self.last_input?) >= PAUSE_AFTER {
+            self.sent = Some((State::Paused, now));
+            Some(State::Paused)
+        } else {
+            None
+        }
+    }
+}
+
+/// Who is typing in each buffer, keyed by channel or query nick. Buffers &
+/// nicks are folded with the server's casemapping.
+#[derive(Debug, Default)]
+pub struct Typing {
+    buffers: HashMap<String, HashMap<String, (Nick, State, Instant)>>,
+}
+
+impl Typing {
+    pub fn update(
+        &mut self,
+        target: &str,
+        nick: Nick,
+        state: State,
+        now: Instant,
+        casemapping: CaseMap,
+    ) {
+        let buffer = self
+            .buffers
+            .entry(casemapping.normalize(target))
+            .or_default();
+        let key = casemapping.normalize(nick.as_ref());
+
+        if state == State::Done {
+            buffer.remove(&key);
+        } else {
+            buffer.insert(key, (nick, state, now));
+        }
+    }
+
+    /// Stop showing `nick` as typing, like when they send a message
+    pub fn remove(&mut self, target: &str, nick: NickRef<'_>, casemapping: CaseMap) {
+        if let Some(buffer) = self.buffers.get_mut(&casemapping.normalize(target)) {
+            buffer.remove(&casemapping.normalize(nick.as_ref()));
+        }
+    }
+
+    /// Nicks actively typing in `target`
+    pub fn active(&self, target: &str, casemapping: CaseMap) -> Vec<&Nick> {
+        let mut nicks = self
+            .buffers
+            .get(&casemapping.normalize(target))
+            .into_iter()
+            .flat_map(HashMap::values)
+            .filter(|(_, state, _)| *state == State::Active)
+            .map(|(nick, _, _)| nick)
+            .collect::<Vec<_>>();
+
+        nicks.sort();
+
+        nicks
+    }
+
+    /// Drops states which haven't been renewed in time
+    pub fn tick(&mut self, now: Instant) {
+        self.buffers.retain(|_, buffer| {
+            buffer.retain(|_, (_, state, updated_at)| {
+                let timeout = match state {
+                    State::Active => ACTIVE_TIMEOUT,
+                    State::Paused => PAUSED_TIMEOUT,
+                    State::Done => Duration::ZERO,
+                };
+
+                now.duration_since(*updated_at) < timeout
+            });
+
+            !buffer.is_empty()
+        });
+    }
+}
+
+/// "alice is typing…", for showing under the input
+pub fn summary(nicks: &[&Nick]) -> Option<String> {
+    match nicks {
+        [] => None,
+        [nick] => Some(format!("{nick} is typing…")),
+        [first, second] => Some(format!("{first} and {second} are typing…")),
+        [first, second, third] => Some(format!("{first}, {second} and {third} are typing…")),
+        _ => Some("Several people are typing…".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outgoing() {
+        let start = Instant::now();
+        let mut outgoing = Outgoing::default();
+
+        assert_eq!(outgoing.input(true, start), None);
+        assert_eq!(outgoing.input(false, start), Some(State::Active));
+        // Throttled
+        assert_eq!(outgoing.input(false, start + Duration::from_secs(1)), None);
+        assert_eq!(
+            outgoing.input(false, start + ACTIVE_THROTTLE),
+            Some(State::Active)
+        );
+
+        let idle = start + ACTIVE_THROTTLE + PAUSE_AFTER;
+        assert_eq!(outgoing.tick(idle), Some(State::Paused));
+        assert_eq!(outgoing.tick(idle), None);
+
+        assert_eq!(outgoing.input(true, idle), Some(State::Done));
+        assert_eq!(outgoing.input(true, idle), None);
+    }
+
+    #[test]
+    fn incoming() {
+        let start = Instant::now();
+        let mut typing = Typing::default();
+
+        let casemapping = CaseMap::RFC1459;
+
+        typing.update(
+            "#halloy",
+            Nick::from("cory[m]"),
+            State::Active,
+            start,
+            casemapping,
+        );
+        typing.update(
+            "#Halloy",
+            Nick::from("alice"),
+            State::Active,
+            start,
+            casemapping,
+        );
+        typing.update(
+            "#halloy",
+            Nick::from("bob"),
+            State::Paused,
+            start,
+            casemapping,
+        );
+
+        assert_eq!(
+            typing.active("#halloy", casemapping),
+            [&Nick::from("alice"), &Nick::from("cory[m]")]
+        );
+
+        assert_eq!(
+            summary(&typing.active("#halloy", casemapping)).as_deref(),
+            Some("alice and cory[m] are typing…")
+        );
+
+        typing.remove("#halloy", NickRef::from("Cory{m}"), casemapping);
+        assert_eq!(
+            typing.active("#halloy", casemapping),
+            [&Nick::from("alice")]
+        );
+
+        typing.tick(start + ACTIVE_TIMEOUT);
+        assert!(typing.active("#halloy", casemapping).is_empty());
+        assert_eq!(typing.buffers["#halloy"].len(), 1);
+
+        typing.tick(start + PAUSED_TIMEOUT);
+        assert!(typing.buffers.is_empty());
+    }
+}
@@ -20,6 +20,7 @@ pub struct User {
     username: Option<String>,
     hostname: Option<String>,
     accountname: Option<String>,
+    realname: Option<String>,
     access_levels: HashSet<AccessLevel>,
     away: bool,
 }
@@ -95,6 +96,7 @@ impl<'a> TryFrom<&'a str> for User {
             username,
             hostname,
             accountname: None,
+            realname: None,
             access_levels,
             away: false,
         })
@@ -121,6 +123,7 @@ impl From<Nick> for User {
             username: None,
             hostname: None,
             accountname: None,
+            realname: None,
             access_levels: HashSet::default(),
             away: false,
         }
@@ -164,6 +167,10 @@ impl User {
         self.accountname.as_deref()
     }
 
+    pub fn realname(&self) -> Option<&str> {
+        self.realname.as_deref()
+    }
+
     pub fn with_nickname(self, nickname: Nick) -> Self {
         Self { nickname, ..self }
     }
@@ -176,6 +183,13 @@ impl User {
         }
     }
 
+    pub fn with_realname(self, realname: String) -> Self {
+        Self {
+            realname: Some(realname),
+            ..self
+        }
+    }
+
     pub fn with_accountname(self, accountname: &str) -> Self {
         let accountname = if accountname == "*" || accountname == "0" {
             None
@@ -197,6 +211,18 @@ impl User {
             .unwrap_or(AccessLevel::Member)
     }
 
+    /// Prefixes of every access level, highest first
+    pub fn prefixes(&self) -> String {
+        let mut access_levels = self.access_levels.iter().collect::<Vec<_>>();
+        access_levels.sort();
+
+        access_levels
+            .into_iter()
+            .rev()
+            .map(ToString::to_string)
+            .collect()
+    }
+
     pub fn has_access_level(&self, access_level: AccessLevel) -> bool {
         self.access_levels.contains(&access_level)
     }
@@ -242,6 +268,7 @@ impl From<proto::User> for User {
             username: user.username,
             hostname: user.hostname,
             accountname: None,
+            realname: None,
             access_levels: HashSet::default(),
             away: false,
         }
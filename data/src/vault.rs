@@ -0,0 +1,201 @@
+//! Secrets encrypted with a master passphrase, so they don't need to be
+//! stored in the config file
+//!
+//! The file is a header, the argon2 salt & the nonce, followed by the
+//! secrets as JSON encrypted with ChaCha20-Poly1305.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+
+use crate::environment;
+
+const HEADER: &[u8] = b"HALLOYV1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+pub fn default_path() -> PathBuf {
+    environment::config_dir().join("vault")
+}
+
+pub struct Vault {
+    path: PathBuf,
+    salt: [u8; SALT_LEN],
+    key: Key,
+    secrets: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vault").field("path", &self.path).finish()
+    }
+}
+
+impl Vault {
+    /// Decrypts the vault at `path`, or creates an empty one if it doesn't
+    /// exist yet
+    pub fn unlock(path: &Path, passphrase: &str) -> Result<Self, Error> {
+        let path = path.to_path_buf();
+
+        if !path.exists() {
+            let mut salt = [0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+
+            return Ok(Self {
+                key: derive_key(passphrase, &salt)?,
+                path,
+                salt,
+                secrets: BTreeMap::new(),
+            });
+        }
+
+        let bytes = fs::read(&path)?;
+
+        let rest = bytes.strip_prefix(HEADER).ok_or(Error::Corrupt)?;
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(Error::Corrupt);
+        }
+
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let salt: [u8; SALT_LEN] = salt.try_into().map_err(|_| Error::Corrupt)?;
+        let key = derive_key(passphrase, &salt)?;
+
+        let plaintext = ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::WrongPassphrase)?;
+
+        let secrets = serde_json::from_slice(&plaintext).map_err(|_| Error::Corrupt)?;
+
+        Ok(Self {
+            path,
+            salt,
+            key,
+            secrets,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.secrets.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.secrets.keys().map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, secret: impl Into<String>) {
+        self.secrets.insert(key.into(), secret.into());
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.secrets.remove(key).is_some()
+    }
+
+    /// Encrypts the vault with a fresh nonce & writes it to disk
+    pub fn save(&self) -> Result<(), Error> {
+        let plaintext = serde_json::to_vec(&self.secrets).map_err(|_| Error::Corrupt)?;
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| Error::Encrypt)?;
+
+        let bytes = [HEADER, &self.salt, nonce.as_slice(), &ciphertext].concat();
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Written to a private temporary file first, so the secrets are never
+        // readable by others & a crash never leaves the vault partially written
+        let temp = self.path.with_extension("tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&temp)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            // A leftover temporary file keeps its old mode
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, Error> {
+    let mut key = Key::default();
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| Error::Passphrase(error.to_string()))?;
+
+    Ok(key)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("vault is corrupt or not a vault")]
+    Corrupt,
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+    #[error("invalid passphrase: {0}")]
+    Passphrase(String),
+    #[error("failed to encrypt vault")]
+    Encrypt,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_unlock() {
+        let path = std::env::temp_dir().join(format!("halloy-vault-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut vault = Vault::unlock(&path, "correct horse").unwrap();
+        vault.set("libera/password", "hunter2");
+        vault.save().unwrap();
+
+        let vault = Vault::unlock(&path, "correct horse").unwrap();
+        assert_eq!(vault.get("libera/password"), Some("hunter2"));
+        assert_eq!(vault.keys().collect::<Vec<_>>(), ["libera/password"]);
+
+        assert!(matches!(
+            Vault::unlock(&path, "battery staple"),
+            Err(Error::WrongPassphrase)
+        ));
+
+        let _ = fs::remove_file(&path);
+    }
+}
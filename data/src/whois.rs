@@ -0,0 +1,236 @@
+//! WHOIS replies for user hover cards, requested lazily & cached for a
+//! while
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use irc::proto::command::Numeric;
+
+use crate::isupport::CaseMap;
+use crate::time::Posix;
+use crate::user::NickRef;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Whois {
+    pub realname: Option<String>,
+    pub account: Option<String>,
+    pub away: Option<String>,
+    pub idle: Option<Duration>,
+    pub signon: Option<DateTime<Utc>>,
+}
+
+/// Replies older than this are requested again, as away & idle go stale
+const EXPIRE_AFTER: Duration = Duration::from_secs(10 * 60);
+/// Requests without a reply by then can be made again
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// WHOIS replies received & when, keyed by nick folded with the server's
+/// casemapping
+#[derive(Debug, Default)]
+pub struct Cache {
+    entries: HashMap<String, (Instant, Whois)>,
+    requested: HashMap<String, Instant>,
+}
+
+impl Cache {
+    pub fn get(&self, nick: NickRef<'_>, casemapping: CaseMap) -> Option<&Whois> {
+        self.entries
+            .get(&casemapping.normalize(nick.as_ref()))
+            .filter(|(received_at, _)| received_at.elapsed() < EXPIRE_AFTER)
+            .map(|(_, whois)| whois)
+    }
+
+    /// Whether WHOIS should be sent for `nick`, which is then marked as
+    /// requested
+    pub fn request(&mut self, nick: NickRef<'_>, casemapping: CaseMap) -> bool {
+        let key = casemapping.normalize(nick.as_ref());
+
+        self.entries
+            .retain(|_, (received_at, _)| received_at.elapsed() < EXPIRE_AFTER);
+        self.requested
+            .retain(|_, requested_at| requested_at.elapsed() < REQUEST_TIMEOUT);
+
+        if self.entries.contains_key(&key) || self.requested.contains_key(&key) {
+            return false;
+        }
+
+        self.requested.insert(key, Instant::now());
+
+        true
+    }
+
+    fn entry(&mut self, nick: String) -> &mut Whois {
+        let (received_at, whois) = self
+            .entries
+            .entry(nick)
+            .or_insert_with(|| (Instant::now(), Whois::default()));
+
+        *received_at = Instant::now();
+
+        whois
+    }
+
+    /// Records a WHOIS reply, returning whether it answers a request made for
+    /// a hover card, so shouldn't be shown in a buffer
+    pub fn update(&mut self, numeric: Numeric, args: &[String], casemapping: CaseMap) -> bool {
+        use Numeric::*;
+
+        let Some(nick) = args.get(1).map(|nick| casemapping.normalize(nick)) else {
+            return false;
+        };
+
+        let is_requested = self.requested.contains_key(&nick);
+
+        match numeric {
+            RPL_WHOISUSER => {
+                let realname = args.get(5).cloned();
+
+                self.entry(nick).realname = realname;
+            }
+            RPL_WHOISACCOUNT => {
+                let account = args.get(2).cloned();
+
+                self.entry(nick).account = account;
+            }
+            RPL_AWAY => {
+                let away = args.get(2).cloned();
+
+                // Also sent when messaging an away user, only update if known
+                if self.entries.contains_key(&nick) || is_requested {
+                    self.entry(nick).away = away;
+                }
+            }
+            RPL_WHOISIDLE => {
+                let whois = self.entry(nick);
+
+                whois.idle = args
+                    .get(2)
+                    .and_then(|idle| idle.parse().ok())
+                    .map(Duration::from_secs);
+                whois.signon = args
+                    .get(3)
+                    .and_then(|signon| signon.parse().ok())
+                    .and_then(|signon| Posix::from_seconds(signon).datetime());
+            }
+            RPL_ENDOFWHOIS | ERR_NOSUCHNICK => {
+                self.requested.remove(&nick);
+            }
+            _ => {
+                return is_requested && is_whois_reply(numeric);
+            }
+        }
+
+        is_requested
+    }
+
+    /// Forget `nick`, whose info changed
+    pub fn remove(&mut self, nick: NickRef<'_>, casemapping: CaseMap) {
+        self.entries.remove(&casemapping.normalize(nick.as_ref()));
+    }
+}
+
+fn is_whois_reply(numeric: Numeric) -> bool {
+    use Numeric::*;
+
+    matches!(
+        numeric,
+        RPL_WHOISCERTFP
+            | RPL_WHOISREGNICK
+            | RPL_WHOISSERVER
+            | RPL_WHOISOPERATOR
+            | RPL_WHOISCHANNELS
+            | RPL_WHOISSPECIAL
+            | RPL_WHOISACTUALLY
+            | RPL_WHOISHOST
+            | RPL_WHOISMODES
+            | RPL_WHOISSECURE
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn update() {
+        use Numeric::*;
+
+        let mut cache = Cache::default();
+
+        assert!(cache.request(NickRef::from("Alice[m]"), CaseMap::RFC1459));
+        assert!(!cache.request(NickRef::from("alice{m}"), CaseMap::RFC1459));
+
+        assert!(cache.update(
+            RPL_WHOISUSER,
+            &args(&[
+                "me",
+                "alice{m}",
+                "~alice",
+                "example.com",
+                "*",
+                "Alice Liddell"
+            ]),
+            CaseMap::RFC1459,
+        ));
+        assert!(cache.update(
+            RPL_WHOISACCOUNT,
+            &args(&["me", "Alice[m]", "alice_", "is logged in as"]),
+            CaseMap::RFC1459,
+        ));
+        assert!(cache.update(
+            RPL_WHOISIDLE,
+            &args(&[
+                "me",
+                "ALICE{M}",
+                "300",
+                "1700000000",
+                "seconds idle, signon time"
+            ]),
+            CaseMap::RFC1459,
+        ));
+        assert!(cache.update(
+            RPL_ENDOFWHOIS,
+            &args(&["me", "alice[m]", "End of /WHOIS list"]),
+            CaseMap::RFC1459,
+        ));
+
+        // A WHOIS not requested for a hover card is still shown
+        assert!(!cache.update(
+            RPL_WHOISUSER,
+            &args(&["me", "bob", "~bob", "example.com", "*", "Bob"]),
+            CaseMap::RFC1459,
+        ));
+
+        assert_eq!(
+            cache.get(NickRef::from("ALICE[M]"), CaseMap::RFC1459),
+            Some(&Whois {
+                realname: Some("Alice Liddell".to_string()),
+                account: Some("alice_".to_string()),
+                away: None,
+                idle: Some(Duration::from_secs(300)),
+                signon: DateTime::from_timestamp(1_700_000_000, 0),
+            })
+        );
+        assert!(!cache.request(NickRef::from("alice{m}"), CaseMap::RFC1459));
+
+        cache.remove(NickRef::from("alice{m}"), CaseMap::RFC1459);
+        assert!(cache.request(NickRef::from("alice{m}"), CaseMap::RFC1459));
+    }
+    #[test]
+    fn request_timeout() {
+        let mut cache = Cache::default();
+
+        assert!(cache.request(NickRef::from("alice"), CaseMap::RFC1459));
+        assert!(!cache.request(NickRef::from("alice"), CaseMap::RFC1459));
+
+        // No reply arrived in time, so it can be requested again
+        let requested_at = Instant::now().checked_sub(REQUEST_TIMEOUT).unwrap();
+        cache.requested.insert("alice".to_string(), requested_at);
+
+        assert!(cache.request(NickRef::from("alice"), CaseMap::RFC1459));
+    }
+}
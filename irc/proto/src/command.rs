@@ -99,6 +99,8 @@ pub enum Command {
     ACCOUNT(String),
     /* IRC extensions */
     BATCH(String, Vec<String>),
+    /// <subcommand> [<params>]
+    BOUNCER(String, Vec<String>),
     /// <new_username> <new_hostname>
     CHGHOST(String, String),
     /// <nickname> <channel> :<message>
@@ -107,6 +109,12 @@ pub enum Command {
     CPRIVMSG(String, String, String),
     /// <channel> [<message>]
     KNOCK(String, Option<String>),
+    /// <target> <msgid> [<reason>]
+    REDACT(String, String, Option<String>),
+    /// <target> [<timestamp>]
+    MARKREAD(String, Option<String>),
+    /// <realname>
+    SETNAME(String),
     /// <msgtarget>
     TAGMSG(String),
     /// <nickname>
@@ -199,10 +207,14 @@ impl Command {
             "WALLOPS" if len > 0 => WALLOPS(req!()),
             "ACCOUNT" if len > 0 => ACCOUNT(req!()),
             "BATCH" if len > 0 => BATCH(req!(), params.collect()),
+            "BOUNCER" if len > 0 => BOUNCER(req!(), params.collect()),
             "CHGHOST" if len > 1 => CHGHOST(req!(), req!()),
             "CNOTICE" if len > 2 => CNOTICE(req!(), req!(), req!()),
             "CPRIVMSG" if len > 2 => CPRIVMSG(req!(), req!(), req!()),
             "KNOCK" if len > 0 => KNOCK(req!(), opt!()),
+            "REDACT" if len > 1 => REDACT(req!(), req!(), opt!()),
+            "MARKREAD" if len > 0 => MARKREAD(req!(), opt!()),
+            "SETNAME" if len > 0 => SETNAME(req!()),
             "TAGMSG" if len > 0 => TAGMSG(req!()),
             "USERIP" if len > 0 => USERIP(req!()),
             _ => Self::Unknown(tag, params.collect()),
@@ -255,10 +267,14 @@ impl Command {
             Command::WALLOPS(a) => vec![a],
             Command::ACCOUNT(a) => vec![a],
             Command::BATCH(a, rest) => std::iter::once(a).chain(rest).collect(),
+            Command::BOUNCER(a, rest) => std::iter::once(a).chain(rest).collect(),
             Command::CHGHOST(a, b) => vec![a, b],
             Command::CNOTICE(a, b, c) => vec![a, b, c],
             Command::CPRIVMSG(a, b, c) => vec![a, b, c],
             Command::KNOCK(a, b) => std::iter::once(a).chain(b).collect(),
+            Command::REDACT(a, b, c) => [a, b].into_iter().chain(c).collect(),
+            Command::MARKREAD(a, b) => std::iter::once(a).chain(b).collect(),
+            Command::SETNAME(a) => vec![a],
             Command::TAGMSG(a) => vec![a],
             Command::USERIP(a) => vec![a],
             Command::Numeric(_, params) => params,
@@ -313,10 +329,14 @@ impl Command {
             WALLOPS(_) => "WALLOPS".to_string(),
             ACCOUNT(_) => "ACCOUNT".to_string(),
             BATCH(_, _) => "BATCH".to_string(),
+            BOUNCER(_, _) => "BOUNCER".to_string(),
             CHGHOST(_, _) => "CHGHOST".to_string(),
             CNOTICE(_, _, _) => "CNOTICE".to_string(),
             CPRIVMSG(_, _, _) => "CPRIVMSG".to_string(),
             KNOCK(_, _) => "KNOCK".to_string(),
+            REDACT(_, _, _) => "REDACT".to_string(),
+            MARKREAD(_, _) => "MARKREAD".to_string(),
+            SETNAME(_) => "SETNAME".to_string(),
             TAGMSG(_) => "TAGMSG".to_string(),
             USERIP(_) => "USERIP".to_string(),
             Numeric(numeric, _) => format!("{:03}", *numeric as u16),
@@ -356,6 +376,7 @@ pub enum Numeric {
     RPL_NONE = 300,
     RPL_AWAY = 301,
     RPL_USERHOST = 302,
+    RPL_ISON = 303,
     RPL_UNAWAY = 305,
     RPL_NOWAWAY = 306,
     RPL_WHOREPLY = 352,
@@ -453,6 +474,11 @@ pub enum Numeric {
     RPL_HELPTXT = 705,
     RPL_ENDOFHELP = 706,
     ERR_NOPRIVS = 723,
+    RPL_MONONLINE = 730,
+    RPL_MONOFFLINE = 731,
+    RPL_MONLIST = 732,
+    RPL_ENDOFMONLIST = 733,
+    ERR_MONLISTFULL = 734,
     RPL_LOGGEDIN = 900,
     RPL_LOGGEDOUT = 901,
     ERR_NICKLOCKED = 902,
@@ -497,6 +523,7 @@ impl TryFrom<u16> for Numeric {
             300 => RPL_NONE,
             301 => RPL_AWAY,
             302 => RPL_USERHOST,
+            303 => RPL_ISON,
             305 => RPL_UNAWAY,
             306 => RPL_NOWAWAY,
             352 => RPL_WHOREPLY,
@@ -594,6 +621,11 @@ impl TryFrom<u16> for Numeric {
             705 => RPL_HELPTXT,
             706 => RPL_ENDOFHELP,
             723 => ERR_NOPRIVS,
+            730 => RPL_MONONLINE,
+            731 => RPL_MONOFFLINE,
+            732 => RPL_MONLIST,
+            733 => RPL_ENDOFMONLIST,
+            734 => ERR_MONLISTFULL,
             900 => RPL_LOGGEDIN,
             901 => RPL_LOGGEDOUT,
             902 => ERR_NICKLOCKED,
@@ -1,6 +1,6 @@
 use std::io;
 
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, BytesMut};
 use proto::{format, parse, Message};
 use tokio_util::codec::{Decoder, Encoder};
 
@@ -35,8 +35,135 @@ impl Encoder<Message> for Codec {
     }
 }
 
+/// Newline delimited lines, decoded lossily so invalid UTF-8 can't end the
+/// stream. Lines longer than `max_length` are truncated, the rest being
+/// discarded up to the next newline, so a peer can't grow the buffer
+/// without bound.
+#[derive(Debug, Clone)]
+pub struct LossyLinesCodec {
+    max_length: usize,
+    /// Bytes already searched for a newline
+    next_index: usize,
+    /// Discarding the remainder of a truncated line
+    discarding: bool,
+}
+
+impl LossyLinesCodec {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+            discarding: false,
+        }
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    String::from_utf8_lossy(line).into_owned()
+}
+
+impl Decoder for LossyLinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let read_to = src.len().min(self.max_length.saturating_add(1));
+            let newline = src[self.next_index.min(read_to)..read_to]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|offset| self.next_index + offset);
+
+            match (self.discarding, newline) {
+                (true, Some(index)) => {
+                    src.advance(index + 1);
+                    self.discarding = false;
+                    self.next_index = 0;
+                }
+                (true, None) => {
+                    src.advance(read_to);
+                    self.next_index = 0;
+
+                    if src.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                (false, Some(index)) => {
+                    self.next_index = 0;
+
+                    return Ok(Some(decode_line(&src.split_to(index + 1))));
+                }
+                (false, None) if src.len() > self.max_length => {
+                    self.discarding = true;
+                    self.next_index = 0;
+
+                    return Ok(Some(decode_line(&src.split_to(self.max_length))));
+                }
+                (false, None) => {
+                    self.next_index = read_to;
+
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(line) = self.decode(src)? {
+            return Ok(Some(line));
+        }
+
+        self.next_index = 0;
+
+        if src.is_empty() || self.discarding {
+            src.clear();
+            Ok(None)
+        } else {
+            Ok(Some(decode_line(&src.split())))
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LossyLinesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let line = line.as_ref();
+
+        dst.reserve(line.len() + 1);
+        dst.put(line.as_bytes());
+        dst.put_u8(b'\n');
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     Io(#[from] io::Error),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lossy_lines() {
+        let mut codec = LossyLinesCodec::new(8);
+        let mut buf = BytesMut::from(&b"hello\r\nbad \xff\nthis line is too long\nok"[..]);
+
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some("hello"));
+        assert_eq!(
+            codec.decode(&mut buf).unwrap().as_deref(),
+            Some("bad \u{fffd}")
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some("this lin"));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut buf).unwrap().as_deref(), Some("ok"));
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+    }
+}
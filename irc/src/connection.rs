@@ -1,10 +1,11 @@
-use std::net::IpAddr;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
 use futures::{Sink, SinkExt, Stream, StreamExt};
 use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpListener, TcpStream};
-use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsStream;
 use tokio_util::codec;
 use tokio_util::codec::Framed;
 
@@ -61,7 +62,7 @@ impl<Codec> Connection<Codec> {
             )
             .await?;
 
-            Ok(Self::Tls(Framed::new(tls, codec)))
+            Ok(Self::Tls(Framed::new(tls.into(), codec)))
         } else {
             Ok(Self::Unsecured(Framed::new(tcp, codec)))
         }
@@ -81,12 +82,32 @@ impl<Codec> Connection<Codec> {
 
         match security {
             Security::Unsecured => Ok(Self::Unsecured(Framed::new(tcp, codec))),
-            Security::Secured { .. } => {
-                todo!();
+            // Our certificate is presented to whoever connects
+            Security::Secured {
+                client_cert_path,
+                client_key_path,
+                ..
+            } => {
+                let tls = tls::accept(
+                    tcp,
+                    client_cert_path.ok_or(tls::Error::MissingCertificate)?,
+                    client_key_path,
+                )
+                .await?;
+
+                Ok(Self::Tls(Framed::new(tls.into(), codec)))
             }
         }
     }
 
+    /// Address of the remote end of the connection
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Connection::Tls(framed) => framed.get_ref().get_ref().0.peer_addr(),
+            Connection::Unsecured(framed) => framed.get_ref().peer_addr(),
+        }
+    }
+
     pub async fn shutdown(self) -> Result<(), Error> {
         match self {
             Connection::Tls(framed) => {
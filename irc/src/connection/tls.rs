@@ -9,7 +9,7 @@ use tokio_rustls::{
         client::danger::{self, ServerCertVerifier},
         pki_types,
     },
-    TlsConnector,
+    server, TlsAcceptor, TlsConnector,
 };
 
 pub async fn connect<'a>(
@@ -42,18 +42,7 @@ pub async fn connect<'a>(
     };
 
     let client_config = if let Some(cert_path) = client_cert_path {
-        let cert_bytes = Bytes::from(fs::read(&cert_path).await?);
-
-        let key_bytes = if let Some(key_path) = client_key_path {
-            Bytes::from(fs::read(&key_path).await?)
-        } else {
-            cert_bytes.clone()
-        };
-
-        let certs =
-            rustls_pemfile::certs(&mut Cursor::new(&cert_bytes)).collect::<Result<Vec<_>, _>>()?;
-        let key = rustls_pemfile::private_key(&mut Cursor::new(&key_bytes))?
-            .ok_or(Error::BadPrivateKey)?;
+        let (certs, key) = load_cert(cert_path, client_key_path).await?;
 
         builder.with_client_auth_cert(certs, key)?
     } else {
@@ -67,6 +56,50 @@ pub async fn connect<'a>(
         .await?)
 }
 
+/// Accepts a TLS connection, presenting the certificate at `cert_path`
+pub async fn accept(
+    tcp: TcpStream,
+    cert_path: &PathBuf,
+    key_path: Option<&PathBuf>,
+) -> Result<server::TlsStream<TcpStream>, Error> {
+    let (certs, key) = load_cert(cert_path, key_path).await?;
+
+    let server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(TlsAcceptor::from(Arc::new(server_config))
+        .accept(tcp)
+        .await?)
+}
+
+/// Reads a PEM certificate chain & its private key, which can be in the same file
+async fn load_cert(
+    cert_path: &PathBuf,
+    key_path: Option<&PathBuf>,
+) -> Result<
+    (
+        Vec<pki_types::CertificateDer<'static>>,
+        pki_types::PrivateKeyDer<'static>,
+    ),
+    Error,
+> {
+    let cert_bytes = Bytes::from(fs::read(cert_path).await?);
+
+    let key_bytes = if let Some(key_path) = key_path {
+        Bytes::from(fs::read(key_path).await?)
+    } else {
+        cert_bytes.clone()
+    };
+
+    let certs =
+        rustls_pemfile::certs(&mut Cursor::new(&cert_bytes)).collect::<Result<Vec<_>, _>>()?;
+    let key =
+        rustls_pemfile::private_key(&mut Cursor::new(&key_bytes))?.ok_or(Error::BadPrivateKey)?;
+
+    Ok((certs, key))
+}
+
 #[derive(Debug)]
 pub struct AcceptInvalidCerts;
 
@@ -129,4 +162,6 @@ pub enum Error {
     Dns(#[from] pki_types::InvalidDnsNameError),
     #[error("missing or invalid private key")]
     BadPrivateKey,
+    #[error("a certificate is required to accept TLS connections")]
+    MissingCertificate,
 }
@@ -1,6 +1,6 @@
 pub use tokio_util::codec::BytesCodec;
 
-pub use self::codec::Codec;
+pub use self::codec::{Codec, LossyLinesCodec};
 pub use self::connection::Connection;
 
 pub mod codec;
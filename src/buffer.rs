@@ -1,21 +1,28 @@
 pub use data::buffer::Settings;
 use data::user::Nick;
-use data::{buffer, file_transfer, history, Config};
+use data::{buffer, file_transfer, history, time, Config};
 use iced::Task;
 
 use self::channel::Channel;
+use self::dcc_chat::DccChat;
 use self::file_transfers::FileTransfers;
+use self::highlights::Highlights;
 use self::query::Query;
+use self::search::Search;
 use self::server::Server;
 use crate::widget::Element;
 use crate::Theme;
 
 pub mod channel;
+pub mod dcc_chat;
 pub mod empty;
 pub mod file_transfers;
+pub mod highlights;
 mod input_view;
+mod message_context;
 pub mod query;
 mod scroll_view;
+pub mod search;
 pub mod server;
 pub mod user_context;
 
@@ -26,6 +33,9 @@ pub enum Buffer {
     Server(Server),
     Query(Query),
     FileTransfers(FileTransfers),
+    Search(Search),
+    Highlights(Highlights),
+    DccChat(DccChat),
 }
 
 #[derive(Debug, Clone)]
@@ -34,11 +44,19 @@ pub enum Message {
     Server(server::Message),
     Query(query::Message),
     FileTransfers(file_transfers::Message),
+    Search(search::Message),
+    Highlights(highlights::Message),
+    DccChat(dcc_chat::Message),
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     UserContext(user_context::Event),
+    OpenSearchResult(history::search::Match),
+    OpenHighlight(history::highlights::Highlight),
+    LoadOlderHistory,
+    LocalCommand(data::Command),
+    RetryFileTransfer(file_transfer::Id),
 }
 
 impl Buffer {
@@ -52,7 +70,10 @@ impl Buffer {
             Buffer::Channel(state) => Some(state.buffer()),
             Buffer::Server(state) => Some(state.buffer()),
             Buffer::Query(state) => Some(state.buffer()),
-            Buffer::FileTransfers(_) => None,
+            Buffer::FileTransfers(_)
+            | Buffer::Search(_)
+            | Buffer::Highlights(_)
+            | Buffer::DccChat(_) => None,
         }
     }
 
@@ -62,6 +83,7 @@ impl Buffer {
         clients: &mut data::client::Map,
         history: &mut history::Manager,
         file_transfers: &mut file_transfer::Manager,
+        dcc_chats: &mut data::dcc_chat::Manager,
         config: &Config,
     ) -> (Task<Message>, Option<Event>) {
         match (self, message) {
@@ -70,28 +92,68 @@ impl Buffer {
 
                 let event = event.map(|event| match event {
                     channel::Event::UserContext(event) => Event::UserContext(event),
+                    channel::Event::LoadOlderHistory => Event::LoadOlderHistory,
+                    channel::Event::LocalCommand(command) => Event::LocalCommand(command),
                 });
 
                 (command.map(Message::Channel), event)
             }
             (Buffer::Server(state), Message::Server(message)) => {
-                let command = state.update(message, clients, history, config);
+                let (command, event) = state.update(message, clients, history, config);
 
-                (command.map(Message::Server), None)
+                let event = event.map(|event| match event {
+                    server::Event::LoadOlderHistory => Event::LoadOlderHistory,
+                    server::Event::LocalCommand(command) => Event::LocalCommand(command),
+                });
+
+                (command.map(Message::Server), event)
             }
             (Buffer::Query(state), Message::Query(message)) => {
                 let (command, event) = state.update(message, clients, history, config);
 
                 let event = event.map(|event| match event {
                     query::Event::UserContext(event) => Event::UserContext(event),
+                    query::Event::LoadOlderHistory => Event::LoadOlderHistory,
+                    query::Event::LocalCommand(command) => Event::LocalCommand(command),
                 });
 
                 (command.map(Message::Query), event)
             }
             (Buffer::FileTransfers(state), Message::FileTransfers(message)) => {
-                let command = state.update(message, file_transfers, config);
+                let (command, event) = state.update(message, file_transfers, config);
+
+                let event = event.map(|event| match event {
+                    file_transfers::Event::Retry(id) => Event::RetryFileTransfer(id),
+                });
+
+                (command.map(Message::FileTransfers), event)
+            }
+            (Buffer::Search(state), Message::Search(message)) => {
+                let (command, event) = state.update(message);
+
+                let event = event.map(|event| match event {
+                    search::Event::Open(result) => Event::OpenSearchResult(result),
+                });
+
+                (command.map(Message::Search), event)
+            }
+            (Buffer::Highlights(state), Message::Highlights(message)) => {
+                let (command, event) = state.update(message, history);
+
+                let event = event.map(|event| match event {
+                    highlights::Event::Open(highlight) => Event::OpenHighlight(highlight),
+                });
+
+                (command.map(Message::Highlights), event)
+            }
+            (Buffer::DccChat(state), Message::DccChat(message)) => {
+                let (command, event) = state.update(message, dcc_chats, history, file_transfers);
 
-                (command.map(Message::FileTransfers), None)
+                let event = event.map(|event| match event {
+                    dcc_chat::Event::LoadOlderHistory => Event::LoadOlderHistory,
+                });
+
+                (command.map(Message::DccChat), event)
             }
             _ => (Task::none(), None),
         }
@@ -101,6 +163,7 @@ impl Buffer {
         &'a self,
         clients: &'a data::client::Map,
         file_transfers: &'a file_transfer::Manager,
+        dcc_chats: &'a data::dcc_chat::Manager,
         history: &'a history::Manager,
         settings: &'a buffer::Settings,
         config: &'a Config,
@@ -129,6 +192,11 @@ impl Buffer {
             Buffer::FileTransfers(state) => {
                 file_transfers::view(state, file_transfers).map(Message::FileTransfers)
             }
+            Buffer::Search(state) => search::view(state, config).map(Message::Search),
+            Buffer::Highlights(_) => highlights::view(history, config).map(Message::Highlights),
+            Buffer::DccChat(state) => {
+                dcc_chat::view(state, dcc_chats, history, config, theme).map(Message::DccChat)
+            }
         }
     }
 
@@ -154,7 +222,9 @@ impl Buffer {
 
     pub fn focus(&self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty | Buffer::FileTransfers(_) | Buffer::Highlights(_) => Task::none(),
+            Buffer::Search(search) => search.focus().map(Message::Search),
+            Buffer::DccChat(chat) => chat.focus().map(Message::DccChat),
             Buffer::Channel(channel) => channel.focus().map(Message::Channel),
             Buffer::Server(server) => server.focus().map(Message::Server),
             Buffer::Query(query) => query.focus().map(Message::Query),
@@ -163,7 +233,11 @@ impl Buffer {
 
     pub fn reset(&mut self) {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => {}
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Search(_)
+            | Buffer::Highlights(_)
+            | Buffer::DccChat(_) => {}
             Buffer::Channel(channel) => channel.reset(),
             Buffer::Server(server) => server.reset(),
             Buffer::Query(query) => query.reset(),
@@ -177,7 +251,12 @@ impl Buffer {
     ) -> Task<Message> {
         if let Some(buffer) = self.data() {
             match self {
-                Buffer::Empty | Buffer::Server(_) | Buffer::FileTransfers(_) => Task::none(),
+                Buffer::Empty
+                | Buffer::Server(_)
+                | Buffer::FileTransfers(_)
+                | Buffer::Search(_)
+                | Buffer::Highlights(_)
+                | Buffer::DccChat(_) => Task::none(),
                 Buffer::Channel(channel) => channel
                     .input_view
                     .insert_user(nick, buffer, history)
@@ -194,7 +273,11 @@ impl Buffer {
 
     pub fn scroll_to_start(&mut self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Search(_)
+            | Buffer::Highlights(_)
+            | Buffer::DccChat(_) => Task::none(),
             Buffer::Channel(channel) => channel
                 .scroll_view
                 .scroll_to_start()
@@ -210,9 +293,35 @@ impl Buffer {
         }
     }
 
+    pub fn scroll_to_message(&mut self, received_at: time::Posix) -> Task<Message> {
+        match self {
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Search(_)
+            | Buffer::Highlights(_)
+            | Buffer::DccChat(_) => Task::none(),
+            Buffer::Channel(channel) => channel
+                .scroll_view
+                .scroll_to_message(received_at)
+                .map(|message| Message::Channel(channel::Message::ScrollView(message))),
+            Buffer::Server(server) => server
+                .scroll_view
+                .scroll_to_message(received_at)
+                .map(|message| Message::Server(server::Message::ScrollView(message))),
+            Buffer::Query(query) => query
+                .scroll_view
+                .scroll_to_message(received_at)
+                .map(|message| Message::Query(query::Message::ScrollView(message))),
+        }
+    }
+
     pub fn scroll_to_end(&mut self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Search(_)
+            | Buffer::Highlights(_)
+            | Buffer::DccChat(_) => Task::none(),
             Buffer::Channel(channel) => channel
                 .scroll_view
                 .scroll_to_end()
@@ -5,7 +5,7 @@ use data::{channel, history, message, Config};
 use iced::widget::{column, container, row};
 use iced::{padding, Length, Task};
 
-use super::{input_view, scroll_view, user_context};
+use super::{input_view, message_context, scroll_view, user_context};
 use crate::widget::{message_content, selectable_text, Element};
 use crate::{theme, Theme};
 
@@ -21,6 +21,8 @@ pub enum Message {
 #[derive(Debug, Clone)]
 pub enum Event {
     UserContext(user_context::Event),
+    LoadOlderHistory,
+    LocalCommand(data::Command),
 }
 
 pub fn view<'a>(
@@ -42,6 +44,13 @@ pub fn view<'a>(
 
     let users = clients.get_channel_users(&state.server, &state.channel);
 
+    let supports_redaction = clients.supports_redaction(&state.server);
+    let is_operator =
+        our_user.is_some_and(|user| user.has_access_level(data::user::AccessLevel::Oper));
+    let can_delete = move |message| {
+        supports_redaction && message_context::can_delete(message, our_nick, is_operator)
+    };
+
     let messages = container(
         scroll_view::view(
             &state.scroll_view,
@@ -54,10 +63,31 @@ pub fn view<'a>(
                         .buffer
                         .format_timestamp(&message.server_time)
                         .map(|timestamp| {
-                            selectable_text(timestamp).style(theme::selectable_text::transparent)
+                            message_context::view(
+                                selectable_text(timestamp)
+                                    .style(theme::selectable_text::transparent),
+                                message,
+                                can_delete(message),
+                            )
+                            .map(scroll_view::Message::ContextMenu)
                         });
 
                 match message.target.source() {
+                    message::Source::User(user)
+                        if clients.is_ignored(
+                            &state.server,
+                            user,
+                            data::ignore::Scope::Messages,
+                        ) =>
+                    {
+                        let text = selectable_text(format!(
+                            "{} ignored message",
+                            config.buffer.nickname.brackets.format(user)
+                        ))
+                        .style(theme::selectable_text::transparent);
+
+                        Some(container(row![].push_maybe(timestamp).push(text)).into())
+                    }
                     message::Source::User(user) => {
                         let nick = user_context::view(
                             selectable_text(config.buffer.nickname.brackets.format(user)).style(
@@ -76,6 +106,8 @@ pub fn view<'a>(
                             users.iter().find(|current_user| *current_user == user),
                             state.buffer(),
                             our_user,
+                            clients.get_whois(&state.server, user.nickname()),
+                            config,
                         )
                         .map(scroll_view::Message::UserContext);
 
@@ -95,13 +127,12 @@ pub fn view<'a>(
                                     .push(space)
                                     .push(text),
                             )
-                            .style(move |theme| match our_nick {
-                                Some(nick)
-                                    if message::reference_user(user.nickname(), nick, message) =>
-                                {
+                            .style(move |theme| {
+                                if message.is_highlighted(our_nick) {
                                     theme::container::highlight(theme)
+                                } else {
+                                    Default::default()
                                 }
-                                _ => Default::default(),
                             })
                             .into(),
                         )
@@ -156,7 +187,8 @@ pub fn view<'a>(
     .width(Length::FillPortion(2))
     .height(Length::Fill);
 
-    let nick_list = nick_list::view(users, &buffer, our_user, config).map(Message::UserContext);
+    let nick_list =
+        nick_list::view(users, &buffer, our_user, clients, config).map(Message::UserContext);
 
     // If topic toggles from None to Some then it messes with messages' scroll state,
     // so produce a zero-height placeholder when topic is None.
@@ -196,9 +228,14 @@ pub fn view<'a>(
         (false, _) => { row![content] }.height(Length::Fill),
     };
 
+    let typing = (show_text_input && config.buffer.typing.show)
+        .then(|| input_view::typing(&clients.typing(&buffer)))
+        .flatten();
+
     let body = column![]
         .push(container(content).height(Length::Fill))
         .push_maybe(text_input)
+        .push_maybe(typing)
         .spacing(4)
         .height(Length::Fill);
 
@@ -242,12 +279,39 @@ impl Channel {
         match message {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
+                let command = command.map(Message::ScrollView);
 
-                let event = event.map(|event| match event {
-                    scroll_view::Event::UserContext(event) => Event::UserContext(event),
-                });
+                match event {
+                    Some(scroll_view::Event::UserContext(event)) => {
+                        (command, Some(Event::UserContext(event)))
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::Reply(reply))) => {
+                        let focus = self.input_view.reply(reply).map(Message::InputView);
+
+                        (Task::batch(vec![command, focus]), None)
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::React {
+                        msgid,
+                        reaction,
+                    })) => {
+                        let buffer = self.buffer();
 
-                (command.map(Message::ScrollView), event)
+                        if let Some(message) = clients.react(&buffer, msgid, reaction) {
+                            history.record_message(buffer.server(), message);
+                        }
+
+                        (command, None)
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::Delete(
+                        msgid,
+                    ))) => {
+                        clients.redact(&self.buffer(), &msgid);
+
+                        (command, None)
+                    }
+                    Some(scroll_view::Event::LoadOlder) => (command, Some(Event::LoadOlderHistory)),
+                    None => (command, None),
+                }
             }
             Message::InputView(message) => {
                 let buffer = self.buffer();
@@ -266,6 +330,9 @@ impl Channel {
 
                         (command, None)
                     }
+                    Some(input_view::Event::LocalCommand(local)) => {
+                        (command, Some(Event::LocalCommand(local)))
+                    }
                     None => (command, None),
                 }
             }
@@ -309,6 +376,7 @@ fn topic<'a>(
             users,
             &state.buffer(),
             our_user,
+            clients,
             config,
             theme,
         )
@@ -330,6 +398,7 @@ mod nick_list {
         users: &'a [User],
         buffer: &Buffer,
         our_user: Option<&'a User>,
+        clients: &'a data::client::Map,
         config: &'a Config,
     ) -> Element<'a, Message> {
         let column = column(users.iter().map(|user| {
@@ -341,7 +410,15 @@ mod nick_list {
                 )
             });
 
-            user_context::view(content, user, Some(user), buffer.clone(), our_user)
+            user_context::view(
+                content,
+                user,
+                Some(user),
+                buffer.clone(),
+                our_user,
+                clients.get_whois(buffer.server(), user.nickname()),
+                config,
+            )
         }))
         .padding(4)
         .spacing(1);
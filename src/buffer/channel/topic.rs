@@ -16,6 +16,7 @@ pub fn view<'a>(
     users: &'a [User],
     buffer: &Buffer,
     our_user: Option<&'a User>,
+    clients: &'a data::client::Map,
     config: &'a Config,
     theme: &'a Theme,
 ) -> Element<'a, user_context::Message> {
@@ -35,6 +36,8 @@ pub fn view<'a>(
                 Some(user),
                 buffer.clone(),
                 our_user,
+                clients.get_whois(buffer.server(), user.nickname()),
+                config,
             )
         } else {
             selectable_text(who)
@@ -0,0 +1,238 @@
+use data::dcc_chat::{self, Chat, Status};
+use data::user::Nick;
+use data::{file_transfer, history, message, Config, Server};
+use iced::widget::{button, center, column, container, row, text, text_input, Space};
+use iced::{alignment, Length, Task};
+
+use super::scroll_view;
+use crate::widget::{message_content, selectable_text, Element};
+use crate::{icon, theme, Theme};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ScrollView(scroll_view::Message),
+    Input(String),
+    Send,
+    Approve,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    LoadOlderHistory,
+}
+
+pub fn view<'a>(
+    state: &'a DccChat,
+    dcc_chats: &'a dcc_chat::Manager,
+    history: &'a history::Manager,
+    config: &'a Config,
+    theme: &'a Theme,
+) -> Element<'a, Message> {
+    let chat = dcc_chats.get(&state.id);
+
+    let lines = container(
+        scroll_view::view(
+            &state.scroll_view,
+            scroll_view::Kind::DccChat(&state.server, &state.remote_user),
+            history,
+            config,
+            move |message| {
+                let timestamp =
+                    config
+                        .buffer
+                        .format_timestamp(&message.server_time)
+                        .map(|timestamp| {
+                            selectable_text(timestamp).style(theme::selectable_text::transparent)
+                        });
+
+                match message.target.source() {
+                    message::Source::User(user) => {
+                        let nick = selectable_text(config.buffer.nickname.brackets.format(user))
+                            .style(|theme| {
+                                theme::selectable_text::nickname(
+                                    theme,
+                                    user.nick_color(theme.colors(), &config.buffer.nickname.color),
+                                    false,
+                                )
+                            });
+                        let message = message_content(
+                            &message.content,
+                            theme,
+                            scroll_view::Message::Link,
+                            theme::selectable_text::default,
+                        );
+
+                        Some(
+                            container(
+                                row![]
+                                    .push_maybe(timestamp)
+                                    .push(nick)
+                                    .push(selectable_text(" "))
+                                    .push(message),
+                            )
+                            .into(),
+                        )
+                    }
+                    message::Source::Action => {
+                        let message = message_content(
+                            &message.content,
+                            theme,
+                            scroll_view::Message::Link,
+                            theme::selectable_text::accent,
+                        );
+
+                        Some(container(row![].push_maybe(timestamp).push(message)).into())
+                    }
+                    message::Source::Server(_) | message::Source::Internal(_) => None,
+                }
+            },
+        )
+        .map(Message::ScrollView),
+    )
+    .height(Length::Fill);
+
+    let mut input = text_input("Send message...", &state.input)
+        .id(state.input_id.clone())
+        .padding(8)
+        .style(theme::text_input::primary);
+
+    if chat.is_some_and(Chat::is_connected) {
+        input = input.on_input(Message::Input).on_submit(Message::Send);
+    }
+
+    let status = match chat {
+        Some(chat) => status(chat),
+        None => text("Chat closed").style(theme::text::transparent).into(),
+    };
+
+    column![status, lines, input]
+        .spacing(4)
+        .padding([4, 8])
+        .height(Length::Fill)
+        .into()
+}
+
+fn status(chat: &Chat) -> Element<'_, Message> {
+    let security = if chat.secure { "secure " } else { "" };
+
+    let (content, can_approve) = match &chat.status {
+        Status::PendingApproval => (
+            text(format!(
+                "{} wants to start a {security}DCC chat. Accept to connect.",
+                chat.remote_user
+            ))
+            .style(theme::text::transparent),
+            true,
+        ),
+        Status::Ready => (
+            text(format!(
+                "Offered a {security}DCC chat to {}. Waiting for them to connect.",
+                chat.remote_user
+            ))
+            .style(theme::text::transparent),
+            false,
+        ),
+        Status::Connecting => (
+            text(format!("Connecting to {}...", chat.remote_user)).style(theme::text::transparent),
+            false,
+        ),
+        Status::Connected => (
+            text(format!("Connected {security}with {}", chat.remote_user))
+                .style(theme::text::transparent),
+            false,
+        ),
+        Status::Closed => (text("Chat closed").style(theme::text::transparent), false),
+        Status::Failed { error } => (
+            text(format!("Failed: {error}")).style(theme::text::error),
+            false,
+        ),
+    };
+
+    row![content, Space::with_width(Length::Fill)]
+        .push_maybe(can_approve.then(|| row_button(icon::checkmark(), Message::Approve)))
+        .push(row_button(icon::cancel(), Message::Close))
+        .spacing(2)
+        .align_y(alignment::Vertical::Center)
+        .into()
+}
+
+fn row_button(icon: crate::widget::Text, message: Message) -> Element<Message> {
+    button(center(icon))
+        .on_press(message)
+        .padding(5)
+        .width(22)
+        .height(22)
+        .style(|theme, status| theme::button::tertiary(theme, status, false))
+        .into()
+}
+
+#[derive(Debug, Clone)]
+pub struct DccChat {
+    pub id: dcc_chat::Id,
+    pub server: Server,
+    pub remote_user: Nick,
+    pub scroll_view: scroll_view::State,
+    input: String,
+    input_id: text_input::Id,
+}
+
+impl DccChat {
+    pub fn new(chat: &Chat) -> Self {
+        Self {
+            id: chat.id,
+            server: chat.server.clone(),
+            remote_user: chat.remote_user.clone(),
+            scroll_view: scroll_view::State::new(),
+            input: String::new(),
+            input_id: text_input::Id::unique(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        dcc_chats: &mut dcc_chat::Manager,
+        history: &mut history::Manager,
+        file_transfers: &mut file_transfer::Manager,
+    ) -> (Task<Message>, Option<Event>) {
+        match message {
+            Message::ScrollView(message) => {
+                let (command, event) = self.scroll_view.update(message);
+
+                let event = event.and_then(|event| match event {
+                    scroll_view::Event::LoadOlder => Some(Event::LoadOlderHistory),
+                    scroll_view::Event::UserContext(_) | scroll_view::Event::ContextMenu(_) => None,
+                });
+
+                return (command.map(Message::ScrollView), event);
+            }
+            Message::Input(input) => {
+                self.input = input;
+            }
+            Message::Send => {
+                if !self.input.is_empty() {
+                    if let Some(message) =
+                        dcc_chats.send(&self.id, &std::mem::take(&mut self.input))
+                    {
+                        history.record_dcc_chat_message(&self.server, &self.remote_user, message);
+                    }
+                }
+            }
+            Message::Approve => {
+                dcc_chats.approve(&self.id);
+            }
+            Message::Close => {
+                if let Some(port) = dcc_chats.remove(&self.id) {
+                    file_transfers.release_port(port);
+                }
+            }
+        }
+
+        (Task::none(), None)
+    }
+
+    pub fn focus(&self) -> Task<Message> {
+        text_input::focus(self.input_id.clone())
+    }
+}
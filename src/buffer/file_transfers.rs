@@ -11,9 +11,16 @@ use crate::{icon, theme};
 pub enum Message {
     Approve(file_transfer::Id),
     SavePathSelected(file_transfer::Id, Option<PathBuf>),
+    Retry(file_transfer::Id),
+    SetPaused(file_transfer::Id, bool),
     Clear(file_transfer::Id),
 }
 
+#[derive(Debug, Clone)]
+pub enum Event {
+    Retry(file_transfer::Id),
+}
+
 pub fn view<'a>(
     _state: &FileTransfers,
     file_transfers: &'a file_transfer::Manager,
@@ -66,21 +73,24 @@ impl FileTransfers {
         message: Message,
         file_transfers: &mut file_transfer::Manager,
         config: &Config,
-    ) -> Task<Message> {
+    ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::Approve(id) => {
                 if let Some(transfer) = file_transfers.get(&id).cloned() {
                     let save_directory = config.file_transfer.save_directory.clone();
-                    return Task::perform(
-                        async move {
-                            rfd::AsyncFileDialog::new()
-                                .set_directory(save_directory)
-                                .set_file_name(transfer.filename)
-                                .save_file()
-                                .await
-                                .map(|handle| handle.path().to_path_buf())
-                        },
-                        move |path| Message::SavePathSelected(id, path),
+                    return (
+                        Task::perform(
+                            async move {
+                                rfd::AsyncFileDialog::new()
+                                    .set_directory(save_directory)
+                                    .set_file_name(transfer.filename)
+                                    .save_file()
+                                    .await
+                                    .map(|handle| handle.path().to_path_buf())
+                            },
+                            move |path| Message::SavePathSelected(id, path),
+                        ),
+                        None,
                     );
                 }
             }
@@ -89,12 +99,18 @@ impl FileTransfers {
                     file_transfers.approve(&id, path);
                 }
             }
+            Message::Retry(id) => {
+                return (Task::none(), Some(Event::Retry(id)));
+            }
+            Message::SetPaused(id, paused) => {
+                file_transfers.set_paused(&id, paused);
+            }
             Message::Clear(id) => {
                 file_transfers.remove(&id);
             }
         }
 
-        Task::none()
+        (Task::none(), None)
     }
 }
 
@@ -105,7 +121,7 @@ mod transfer_row {
     use iced::widget::{column, container, progress_bar, row, text};
     use iced::{alignment, padding, Length};
 
-    use crate::buffer::file_transfers::row_button;
+    use crate::buffer::file_transfers::{row_button, text_button};
     use crate::widget::Element;
     use crate::{icon, theme};
 
@@ -128,6 +144,28 @@ mod transfer_row {
                     .style(theme::text::transparent),
                 ),
             },
+            file_transfer::Status::PendingResume { position } => container(
+                text(format!(
+                    "Transfer from {}. Waiting for them to accept resuming from {}.",
+                    transfer.remote_user,
+                    ByteSize::b(*position)
+                ))
+                .style(theme::text::transparent),
+            ),
+            file_transfer::Status::Waiting => {
+                let direction = match transfer.direction {
+                    file_transfer::Direction::Sent => "to",
+                    file_transfer::Direction::Received => "from",
+                };
+
+                container(
+                    text(format!(
+                        "Transfer {} {}. Waiting for other transfers to finish.",
+                        direction, transfer.remote_user,
+                    ))
+                    .style(theme::text::transparent),
+                )
+            }
             file_transfer::Status::Queued => {
                 let direction = match transfer.direction {
                     file_transfer::Direction::Sent => "to",
@@ -159,11 +197,24 @@ mod transfer_row {
             file_transfer::Status::Active {
                 transferred,
                 elapsed,
+                speed,
             } => {
-                let transfer_speed = if elapsed.as_secs() == 0 {
+                let transfer_speed = if transfer.paused {
+                    "(paused)".to_string()
+                } else if *speed > 0 {
+                    let eta = transfer
+                        .eta()
+                        .map(|eta| format!(", {} left", duration_formatter().convert(eta)))
+                        .unwrap_or_default();
+
+                    format!("({}/s{eta})", ByteSize::b(*speed))
+                } else if elapsed.as_secs() == 0 {
                     String::default()
                 } else {
-                    let bytes_per_second = *transferred / elapsed.as_secs();
+                    // Average until the first second of throughput is known.
+                    // Bytes resumed from weren't transferred in this time
+                    let bytes_per_second =
+                        transferred.saturating_sub(transfer.resumed_from) / elapsed.as_secs();
                     let transfer_speed = ByteSize::b(bytes_per_second);
 
                     format!("({transfer_speed}/s)")
@@ -186,21 +237,21 @@ mod transfer_row {
                 )
             }
             file_transfer::Status::Completed { elapsed, sha256 } => {
-                let mut formatter = timeago::Formatter::new();
-                formatter
-                    .ago("")
-                    .min_unit(timeago::TimeUnit::Seconds)
-                    .too_low("under a second");
-                let elapsed = formatter.convert(*elapsed);
+                let elapsed = duration_formatter().convert(*elapsed);
 
                 let direction = match transfer.direction {
                     file_transfer::Direction::Sent => "to",
                     file_transfer::Direction::Received => "from",
                 };
 
+                let sha256 = sha256
+                    .as_ref()
+                    .map(|sha256| format!(" sha256: {sha256}"))
+                    .unwrap_or_default();
+
                 container(
                     text(format!(
-                        "Completed {} {} in {elapsed}. sha256: {sha256}",
+                        "Completed {} {} in {elapsed}.{sha256}",
                         direction, transfer.remote_user,
                     ))
                     .style(theme::text::transparent),
@@ -209,6 +260,9 @@ mod transfer_row {
             file_transfer::Status::Failed { error } => {
                 container(text(format!("Failed: {error}")).style(theme::text::error))
             }
+            file_transfer::Status::Refused { reason } => {
+                container(text(format!("Refused: {reason}")).style(theme::text::error))
+            }
         };
 
         let file_size = ByteSize::b(transfer.size);
@@ -229,14 +283,25 @@ mod transfer_row {
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
             }
             file_transfer::Status::PendingReverseConfirmation
+            | file_transfer::Status::PendingResume { .. }
+            | file_transfer::Status::Waiting
             | file_transfer::Status::Queued
             | file_transfer::Status::Ready => {
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
             }
-            file_transfer::Status::Active { .. } | file_transfer::Status::Completed { .. } => {
+            file_transfer::Status::Active { .. } => {
+                buttons = buttons.push(if transfer.paused {
+                    text_button("Resume", Message::SetPaused(transfer.id, false))
+                } else {
+                    text_button("Pause", Message::SetPaused(transfer.id, true))
+                });
+                buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
+            }
+            file_transfer::Status::Completed { .. } | file_transfer::Status::Refused { .. } => {
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
             }
             file_transfer::Status::Failed { .. } => {
+                buttons = buttons.push(row_button(icon::refresh(), Message::Retry(transfer.id)));
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
             }
         }
@@ -252,6 +317,15 @@ mod transfer_row {
             .style(move |theme| theme::container::table_row(theme, idx))
             .into()
     }
+
+    fn duration_formatter() -> timeago::Formatter {
+        let mut formatter = timeago::Formatter::new();
+        formatter
+            .ago("")
+            .min_unit(timeago::TimeUnit::Seconds)
+            .too_low("under a second");
+        formatter
+    }
 }
 
 fn row_button(icon: Text, message: Message) -> Element<Message> {
@@ -263,3 +337,12 @@ fn row_button(icon: Text, message: Message) -> Element<Message> {
         .style(|theme, status| theme::button::tertiary(theme, status, false))
         .into()
 }
+
+fn text_button<'a>(label: &str, message: Message) -> Element<'a, Message> {
+    button(text(label.to_string()).size(theme::TEXT_SIZE - 1.0))
+        .on_press(message)
+        .padding([3, 6])
+        .height(22)
+        .style(|theme, status| theme::button::tertiary(theme, status, false))
+        .into()
+}
@@ -0,0 +1,164 @@
+use data::history::highlights::Highlight;
+use data::{history, message, Config};
+use iced::widget::{button, center, column, container, row, scrollable, text, Scrollable, Space};
+use iced::{alignment, padding, Length, Task};
+
+use crate::widget::Element;
+use crate::{icon, theme};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open(Highlight),
+    MarkAllRead,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Open(Highlight),
+}
+
+pub fn view<'a>(history: &'a history::Manager, config: &'a Config) -> Element<'a, Message> {
+    let mut highlights = history.highlights().rev().peekable();
+
+    let content: Element<_> = if highlights.peek().is_none() {
+        center(
+            column![
+                icon::megaphone()
+                    .size(theme::TEXT_SIZE + 3.0)
+                    .style(theme::text::transparent),
+                text("No highlights").style(theme::text::transparent)
+            ]
+            .spacing(8)
+            .align_x(iced::Alignment::Center),
+        )
+        .into()
+    } else {
+        let unread = history.unread_highlights();
+
+        let mark_all_read = button(text("Mark all as read").style(theme::text::transparent))
+            .on_press_maybe((unread > 0).then_some(Message::MarkAllRead))
+            .padding([2, 4])
+            .style(theme::button::side_menu);
+
+        let header = row![
+            text(format!("{unread} unread")).style(theme::text::transparent),
+            Space::with_width(Length::Fill),
+            mark_all_read,
+        ]
+        .align_y(alignment::Vertical::Center)
+        .padding([0, 8]);
+
+        let column = column(
+            highlights
+                .enumerate()
+                .map(|(idx, highlight)| highlight_row(highlight, idx, config)),
+        )
+        .spacing(1)
+        .padding([0, 2]);
+
+        let list = Scrollable::new(column)
+            .direction(scrollable::Direction::Vertical(
+                scrollable::Scrollbar::new().width(1).scroller_width(1),
+            ))
+            .style(theme::scrollable::hidden);
+
+        column![header, list].spacing(8).into()
+    };
+
+    container(content)
+        .padding(8)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn highlight_row<'a>(
+    highlight: &'a Highlight,
+    idx: usize,
+    config: &'a Config,
+) -> Element<'a, Message> {
+    let timestamp = highlight
+        .message
+        .server_time
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let location = match &highlight.kind {
+        history::Kind::Server => highlight.server.to_string(),
+        history::Kind::Channel(channel) => format!("{channel} @ {}", highlight.server),
+        history::Kind::Query(nick) => format!("{nick} @ {}", highlight.server),
+        history::Kind::DccChat(nick) => format!("DCC chat with {nick} @ {}", highlight.server),
+    };
+
+    let header = row![text(format!("{timestamp} · {location}")).style(theme::text::transparent)]
+        .spacing(4)
+        .align_y(alignment::Vertical::Center)
+        .push_maybe((!highlight.read).then(|| icon::dot().style(theme::text::info)));
+
+    let content = match highlight.message.target.source() {
+        message::Source::User(user) => format!(
+            "{} {}",
+            config.buffer.nickname.brackets.format(user),
+            highlight.message.content.text()
+        ),
+        _ => highlight.message.content.text().into_owned(),
+    };
+
+    let content = text(content)
+        .shaping(text::Shaping::Advanced)
+        .style(move |theme| {
+            if highlight.read {
+                theme::text::transparent(theme)
+            } else {
+                text::Style::default()
+            }
+        });
+
+    let body = column![header, content]
+        .padding(padding::top(1))
+        .width(Length::Fill)
+        .spacing(0);
+
+    button(body)
+        .on_press(Message::Open(highlight.clone()))
+        .padding(padding::top(6).bottom(6).right(4).left(8))
+        .width(Length::Fill)
+        .style(move |theme, status| match status {
+            button::Status::Hovered | button::Status::Pressed => {
+                theme::button::side_menu(theme, status)
+            }
+            _ => button::Style {
+                background: theme::container::table_row(theme, idx).background,
+                ..theme::button::side_menu(theme, status)
+            },
+        })
+        .into()
+}
+
+/// Highlights from every server, newest first
+#[derive(Debug, Clone, Default)]
+pub struct Highlights;
+
+impl Highlights {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        history: &mut history::Manager,
+    ) -> (Task<Message>, Option<Event>) {
+        match message {
+            Message::Open(highlight) => {
+                history.mark_highlight_read(&highlight.server, highlight.message.received_at);
+
+                (Task::none(), Some(Event::Open(highlight)))
+            }
+            Message::MarkAllRead => {
+                history.mark_all_highlights_read();
+
+                (Task::none(), None)
+            }
+        }
+    }
+}
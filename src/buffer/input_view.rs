@@ -1,17 +1,19 @@
 use data::input::{self, Cache, Draft};
 use data::user::Nick;
 use data::{client, history, Buffer, Config};
-use iced::widget::{container, row, text, text_input};
-use iced::Task;
+use iced::widget::{button, column, container, row, text, text_input};
+use iced::{Length, Task};
 
 use self::completion::Completion;
-use crate::theme;
 use crate::widget::{anchored_overlay, key_press, Element};
+use crate::{icon, theme};
 
 mod completion;
 
 pub enum Event {
     InputSent,
+    /// A command handled by the client rather than sent to the server
+    LocalCommand(data::Command),
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +23,23 @@ pub enum Message {
     Tab,
     Up,
     Down,
+    CancelReply,
+}
+
+/// The message we're replying to
+#[derive(Debug, Clone)]
+pub struct Reply {
+    msgid: String,
+    preview: String,
+}
+
+impl Reply {
+    pub fn new(msgid: String, message: &data::Message) -> Self {
+        Self {
+            msgid,
+            preview: message.preview(),
+        }
+    }
 }
 
 pub fn view<'a>(
@@ -75,7 +94,43 @@ pub fn view<'a>(
         .or_else(|| state.completion.view(cache.draft))
         .unwrap_or_else(|| row![].into());
 
-    anchored_overlay(input, overlay, anchored_overlay::Anchor::AboveTop, 4.0)
+    let input = anchored_overlay(input, overlay, anchored_overlay::Anchor::AboveTop, 4.0);
+
+    column![]
+        .push_maybe(state.reply.as_ref().map(reply))
+        .push(input)
+        .spacing(4)
+        .into()
+}
+
+fn reply(reply: &Reply) -> Element<'_, Message> {
+    row![
+        text(format!("Replying to {}", reply.preview))
+            .style(theme::text::transparent)
+            .width(Length::Fill),
+        button(icon::cancel())
+            .padding([2, 5])
+            .style(theme::button::bare)
+            .on_press(Message::CancelReply),
+    ]
+    .padding([0, 8])
+    .align_y(iced::Alignment::Center)
+    .into()
+}
+
+/// Who is typing, shown under the input
+pub fn typing<'a, Message: 'a>(nicks: &[&Nick]) -> Option<Element<'a, Message>> {
+    let summary = data::typing::summary(nicks)?;
+
+    Some(
+        container(
+            text(summary)
+                .size(theme::TEXT_SIZE - 1.0)
+                .style(theme::text::transparent),
+        )
+        .padding([0, 8])
+        .into(),
+    )
 }
 
 fn error<'a, 'b, Message: 'a>(error: &'b str) -> Element<'a, Message> {
@@ -91,6 +146,7 @@ pub struct State {
     error: Option<String>,
     completion: Completion,
     selected_history: Option<usize>,
+    reply: Option<Reply>,
 }
 
 impl Default for State {
@@ -106,6 +162,7 @@ impl State {
             error: None,
             completion: Completion::default(),
             selected_history: None,
+            reply: None,
         }
     }
 
@@ -131,7 +188,12 @@ impl State {
                 let channels = clients.get_channels(buffer.server());
                 let isupport = clients.get_isupport(buffer.server());
 
-                self.completion.process(&input, users, channels, &isupport);
+                self.completion
+                    .process(&input, users, channels, &isupport, &config.aliases);
+
+                if config.buffer.typing.share {
+                    clients.update_typing(&buffer, &input);
+                }
 
                 history.record_draft(Draft {
                     buffer,
@@ -155,36 +217,67 @@ impl State {
                 } else if !input.is_empty() {
                     self.completion.reset();
 
-                    // Parse input
-                    let input = match input::parse(
+                    let nick = clients
+                        .nickname(buffer.server())
+                        .map(|nick| nick.to_string());
+
+                    // Parse input, expanding aliases
+                    let inputs = match input::parse_aliased(
                         buffer.clone(),
                         config.buffer.text_input.auto_format,
+                        &config.aliases,
+                        nick.as_deref(),
                         input,
                     ) {
-                        Ok(input) => input,
+                        Ok(inputs) => inputs,
                         Err(error) => {
                             self.error = Some(error.to_string());
                             return (Task::none(), None);
                         }
                     };
 
-                    if let Some(encoded) = input.encoded() {
-                        clients.send(&buffer, encoded);
-                    }
+                    let mut local_command = None;
+                    let reply = self.reply.take();
+
+                    for input in inputs {
+                        let input = match &reply {
+                            Some(reply) => input.with_reply(reply.msgid.clone()),
+                            None => input,
+                        };
+
+                        if let Some(command) = input.local_command().cloned() {
+                            history.record_local_input(&input);
+                            local_command = Some(command);
+                            continue;
+                        }
+
+                        let label = input
+                            .encoded()
+                            .and_then(|encoded| clients.send(&buffer, encoded));
 
-                    if let Some(nick) = clients.nickname(buffer.server()) {
-                        let mut user = nick.to_owned().into();
+                        if let Some(nick) = clients.nickname(buffer.server()) {
+                            let mut user = nick.to_owned().into();
 
-                        // Resolve our attributes if sending this message in a channel
-                        if let Buffer::Channel(server, channel) = &buffer {
-                            if let Some(user_with_attributes) =
-                                clients.resolve_user_attributes(server, channel, &user)
-                            {
-                                user = user_with_attributes.clone();
+                            // Resolve our attributes if sending this message in a channel
+                            if let Buffer::Channel(server, channel) = &buffer {
+                                if let Some(user_with_attributes) =
+                                    clients.resolve_user_attributes(server, channel, &user)
+                                {
+                                    user = user_with_attributes.clone();
+                                }
                             }
+
+                            history.record_input(input, user, label);
                         }
+                    }
 
-                        history.record_input(input, user);
+                    // Sends `done` unless a message ended typing
+                    if config.buffer.typing.share {
+                        clients.update_typing(&buffer, "");
+                    }
+
+                    if let Some(command) = local_command {
+                        return (Task::none(), Some(Event::LocalCommand(command)));
                     }
 
                     (Task::none(), Some(Event::InputSent))
@@ -228,14 +321,24 @@ impl State {
                     let channels = clients.get_channels(buffer.server());
                     let isupport = clients.get_isupport(buffer.server());
 
-                    self.completion
-                        .process(&new_input, users, channels, &isupport);
+                    self.completion.process(
+                        &new_input,
+                        users,
+                        channels,
+                        &isupport,
+                        &config.aliases,
+                    );
 
                     return self.on_completion(buffer, history, new_input);
                 }
 
                 (Task::none(), None)
             }
+            Message::CancelReply => {
+                self.reply = None;
+
+                (Task::none(), None)
+            }
             Message::Down => {
                 let cache = history.input(&buffer);
 
@@ -256,8 +359,13 @@ impl State {
                         let channels = clients.get_channels(buffer.server());
                         let isupport = clients.get_isupport(buffer.server());
 
-                        self.completion
-                            .process(&new_input, users, channels, &isupport);
+                        self.completion.process(
+                            &new_input,
+                            users,
+                            channels,
+                            &isupport,
+                            &config.aliases,
+                        );
                         new_input
                     };
 
@@ -288,6 +396,14 @@ impl State {
         self.error = None;
         self.completion = Completion::default();
         self.selected_history = None;
+        self.reply = None;
+    }
+
+    /// Replies to a message with the next input sent
+    pub fn reply(&mut self, reply: Reply) -> Task<Message> {
+        self.reply = Some(reply);
+
+        self.focus()
     }
 
     pub fn insert_user(
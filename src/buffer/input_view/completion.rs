@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt;
 
+use data::config::Aliases;
 use data::isupport;
 use data::user::User;
 use iced::widget::{column, container, row, text, tooltip};
@@ -30,11 +31,12 @@ impl Completion {
         users: &[User],
         channels: &[String],
         isupport: &HashMap<isupport::Kind, isupport::Parameter>,
+        aliases: &Aliases,
     ) {
         let is_command = input.starts_with('/');
 
         if is_command {
-            self.commands.process(input, isupport);
+            self.commands.process(input, isupport, aliases);
 
             // Disallow user completions when selecting a command
             if matches!(self.commands, Commands::Selecting { .. }) {
@@ -102,7 +104,12 @@ impl Default for Commands {
 }
 
 impl Commands {
-    fn process(&mut self, input: &str, isupport: &HashMap<isupport::Kind, isupport::Parameter>) {
+    fn process(
+        &mut self,
+        input: &str,
+        isupport: &HashMap<isupport::Kind, isupport::Parameter>,
+        aliases: &Aliases,
+    ) {
         let Some((head, rest)) = input.split_once('/') else {
             *self = Self::Idle;
             return;
@@ -123,7 +130,7 @@ impl Commands {
         let command_list = COMMAND_LIST
             .iter()
             .map(|command| {
-                match command.title {
+                match command.title.as_str() {
                     "AWAY" => {
                         if let Some(isupport::Parameter::AWAYLEN(Some(max_len))) =
                             isupport.get(&isupport::Kind::AWAYLEN)
@@ -179,7 +186,7 @@ impl Commands {
                         }
                     }
                     "NAMES" => {
-                        if let Some(target_limit) = find_target_limit(isupport, command.title) {
+                        if let Some(target_limit) = find_target_limit(isupport, &command.title) {
                             return names_command(target_limit);
                         }
                     }
@@ -210,7 +217,7 @@ impl Commands {
                         }
                     }
                     "WHOIS" => {
-                        if let Some(target_limit) = find_target_limit(isupport, command.title) {
+                        if let Some(target_limit) = find_target_limit(isupport, &command.title) {
                             return whois_command(target_limit);
                         }
                     }
@@ -241,6 +248,12 @@ impl Commands {
                     isupport_parameter_to_command(isupport_parameter)
                 }
             }))
+            .chain(
+                aliases
+                    .list()
+                    .into_iter()
+                    .map(|(name, expansion)| alias_command(name, expansion)),
+            )
             .collect::<Vec<_>>();
 
         match self {
@@ -380,12 +393,18 @@ impl Commands {
 
 #[derive(Debug, Clone)]
 pub struct Command {
-    title: &'static str,
+    title: String,
     args: Vec<Arg>,
+    /// Commands run by a user defined alias
+    expansion: Option<String>,
 }
 
 impl Command {
-    fn description(&self) -> Option<&'static str> {
+    fn description(&self) -> Option<&str> {
+        if let Some(expansion) = &self.expansion {
+            return Some(expansion);
+        }
+
         Some(match self.title.to_lowercase().as_str() {
             "away" => "Mark yourself as away. If already away, the status is removed",
             "join" => "Join channel(s) with optional key(s)",
@@ -431,7 +450,7 @@ impl Command {
             .saturating_sub(2)
             .min(self.args.len().saturating_sub(1));
 
-        let title = Some(Element::from(text(self.title)));
+        let title = Some(Element::from(text(self.title.clone())));
 
         let args = self.args.iter().enumerate().map(|(index, arg)| {
             let content = text(format!("{arg}")).style(move |theme| {
@@ -476,10 +495,9 @@ impl Command {
 
         container(
             column![]
-                .push_maybe(
-                    self.description()
-                        .map(|description| text(description).style(theme::text::transparent)),
-                )
+                .push_maybe(self.description().map(|description| {
+                    text(description.to_string()).style(theme::text::transparent)
+                }))
                 .push(row(title.into_iter().chain(args))),
         )
         .style(theme::container::context)
@@ -589,7 +607,8 @@ impl Text {
 static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
     vec![
         Command {
-            title: "JOIN",
+            title: "JOIN".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channels",
@@ -604,7 +623,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "MOTD",
+            title: "MOTD".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "server",
                 optional: true,
@@ -612,7 +632,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "NICK",
+            title: "NICK".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "nickname",
                 optional: false,
@@ -620,7 +641,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "QUIT",
+            title: "QUIT".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "reason",
                 optional: true,
@@ -628,7 +650,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "MSG",
+            title: "MSG".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "targets",
@@ -645,7 +668,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "WHOIS",
+            title: "WHOIS".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "nicks",
                 optional: false,
@@ -653,7 +677,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "AWAY",
+            title: "AWAY".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "reason",
                 optional: true,
@@ -661,7 +686,17 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "ME",
+            title: "SETNAME".into(),
+            expansion: None,
+            args: vec![Arg {
+                text: "realname",
+                optional: false,
+                tooltip: None,
+            }],
+        },
+        Command {
+            title: "ME".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "action",
                 optional: false,
@@ -669,7 +704,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "MODE",
+            title: "MODE".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channel",
@@ -689,7 +725,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "PART",
+            title: "PART".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channels",
@@ -704,7 +741,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "TOPIC",
+            title: "TOPIC".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channel",
@@ -719,7 +757,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "WHO",
+            title: "WHO".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "target",
                 optional: false,
@@ -727,7 +766,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
         },
         Command {
-            title: "NAMES",
+            title: "NAMES".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channels",
@@ -737,7 +777,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "KICK",
+            title: "KICK".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channel",
@@ -757,7 +798,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "RAW",
+            title: "RAW".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "command",
@@ -772,7 +814,8 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
         },
         Command {
-            title: "FORMAT",
+            title: "FORMAT".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "text",
@@ -809,9 +852,22 @@ fn isupport_parameter_to_command(isupport_parameter: &isupport::Parameter) -> Op
     }
 }
 
+fn alias_command(name: &str, expansion: String) -> Command {
+    Command {
+        title: name.to_uppercase(),
+        args: vec![Arg {
+            text: "args",
+            optional: true,
+            tooltip: None,
+        }],
+        expansion: Some(expansion),
+    }
+}
+
 fn away_command(max_len: &u16) -> Command {
     Command {
-        title: "AWAY",
+        title: "AWAY".into(),
+        expansion: None,
         args: vec![Arg {
             text: "reason",
             optional: true,
@@ -821,7 +877,8 @@ fn away_command(max_len: &u16) -> Command {
 }
 
 static CNOTICE_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "CNOTICE",
+    title: "CNOTICE".into(),
+    expansion: None,
     args: vec![
         Arg {
             text: "nickname",
@@ -842,7 +899,8 @@ static CNOTICE_COMMAND: Lazy<Command> = Lazy::new(|| Command {
 });
 
 static CPRIVMSG_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "CPRIVMSG",
+    title: "CPRIVMSG".into(),
+    expansion: None,
     args: vec![
         Arg {
             text: "nickname",
@@ -898,7 +956,8 @@ fn join_command(
     }
 
     Command {
-        title: "JOIN",
+        title: "JOIN".into(),
+        expansion: None,
         args: vec![
             Arg {
                 text: "channels",
@@ -915,7 +974,8 @@ fn join_command(
 }
 
 static KNOCK_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "KNOCK",
+    title: "KNOCK".into(),
+    expansion: None,
     args: vec![
         Arg {
             text: "channel",
@@ -931,7 +991,8 @@ static KNOCK_COMMAND: Lazy<Command> = Lazy::new(|| Command {
 });
 
 static LIST_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "LIST",
+    title: "LIST".into(),
+    expansion: None,
     args: vec![Arg {
         text: "channels",
         optional: true,
@@ -972,7 +1033,8 @@ fn list_command(
         );
 
         Command {
-            title: "LIST",
+            title: "LIST".into(),
+            expansion: None,
             args: vec![
                 Arg {
                     text: "channels",
@@ -988,7 +1050,8 @@ fn list_command(
         }
     } else {
         Command {
-            title: "LIST",
+            title: "LIST".into(),
+            expansion: None,
             args: vec![Arg {
                 text: "channels",
                 optional: true,
@@ -1032,7 +1095,8 @@ fn msg_command(
     }
 
     Command {
-        title: "MSG",
+        title: "MSG".into(),
+        expansion: None,
         args: vec![
             Arg {
                 text: "targets",
@@ -1059,7 +1123,8 @@ fn names_command(target_limit: &isupport::CommandTargetLimit) -> Command {
     }
 
     Command {
-        title: "NAMES",
+        title: "NAMES".into(),
+        expansion: None,
         args: vec![Arg {
             text: "channels",
             optional: false,
@@ -1070,7 +1135,8 @@ fn names_command(target_limit: &isupport::CommandTargetLimit) -> Command {
 
 fn nick_command(max_len: &u16) -> Command {
     Command {
-        title: "NICK",
+        title: "NICK".into(),
+        expansion: None,
         args: vec![Arg {
             text: "nickname",
             optional: false,
@@ -1081,7 +1147,8 @@ fn nick_command(max_len: &u16) -> Command {
 
 fn part_command(max_len: &u16) -> Command {
     Command {
-        title: "PART",
+        title: "PART".into(),
+        expansion: None,
         args: vec![
             Arg {
                 text: "channels",
@@ -1102,7 +1169,8 @@ fn part_command(max_len: &u16) -> Command {
 
 fn topic_command(max_len: &u16) -> Command {
     Command {
-        title: "TOPIC",
+        title: "TOPIC".into(),
+        expansion: None,
         args: vec![
             Arg {
                 text: "channel",
@@ -1119,7 +1187,8 @@ fn topic_command(max_len: &u16) -> Command {
 }
 
 static USERIP_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "USERIP",
+    title: "USERIP".into(),
+    expansion: None,
     args: vec![Arg {
         text: "nickname",
         optional: false,
@@ -1128,7 +1197,8 @@ static USERIP_COMMAND: Lazy<Command> = Lazy::new(|| Command {
 });
 
 static WHOX_COMMAND: Lazy<Command> = Lazy::new(|| Command {
-    title: "WHO",
+    title: "WHO".into(),
+    expansion: None,
     args: vec![
         Arg {
             text: "target",
@@ -1173,7 +1243,8 @@ fn whois_command(target_limit: &isupport::CommandTargetLimit) -> Command {
     }
 
     Command {
-        title: "WHOIS",
+        title: "WHOIS".into(),
+        expansion: None,
         args: vec![Arg {
             text: "nicks",
             optional: false,
@@ -0,0 +1,122 @@
+use data::message::{Direction, Source};
+use data::user::NickRef;
+use iced::widget::{button, container, horizontal_rule, row, text, Space};
+use iced::Length;
+
+use super::input_view::Reply;
+use crate::theme;
+use crate::widget::{context_menu, Element};
+
+/// Reactions offered in the context menu
+const REACTIONS: [&str; 5] = ["👍", "❤️", "😂", "🎉", "👀"];
+
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Reply,
+    Delete,
+    HorizontalRule,
+    Reactions,
+}
+
+impl Entry {
+    fn list(can_delete: bool) -> Vec<Self> {
+        if can_delete {
+            vec![
+                Entry::Reply,
+                Entry::Delete,
+                Entry::HorizontalRule,
+                Entry::Reactions,
+            ]
+        } else {
+            vec![Entry::Reply, Entry::HorizontalRule, Entry::Reactions]
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Reply(Reply),
+    React(String, String),
+    Delete(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Reply(Reply),
+    React { msgid: String, reaction: String },
+    Delete(String),
+}
+
+pub fn update(message: Message) -> Event {
+    match message {
+        Message::Reply(reply) => Event::Reply(reply),
+        Message::React(msgid, reaction) => Event::React { msgid, reaction },
+        Message::Delete(msgid) => Event::Delete(msgid),
+    }
+}
+
+/// Adds a menu for replying, reacting to & deleting `message` to `content`,
+/// if the server gave it a `msgid`
+pub fn view<'a>(
+    content: impl Into<Element<'a, Message>>,
+    message: &'a data::Message,
+    can_delete: bool,
+) -> Element<'a, Message> {
+    let Some(msgid) = message.id.as_ref() else {
+        return content.into();
+    };
+
+    if !matches!(message.target.source(), Source::User(_) | Source::Action) {
+        return content.into();
+    }
+
+    context_menu(
+        content,
+        Entry::list(can_delete),
+        move |entry, length| match entry {
+            Entry::Reply => menu_button(
+                "Reply",
+                Message::Reply(Reply::new(msgid.clone(), message)),
+                length,
+            ),
+            Entry::Delete => menu_button("Delete message", Message::Delete(msgid.clone()), length),
+            Entry::HorizontalRule => match length {
+                Length::Fill => container(horizontal_rule(1)).padding([0, 6]).into(),
+                _ => Space::new(length, 1).into(),
+            },
+            Entry::Reactions => row(REACTIONS.into_iter().map(|reaction| {
+                button(text(reaction).shaping(text::Shaping::Advanced))
+                    .padding(5)
+                    .style(theme::button::context)
+                    .on_press(Message::React(msgid.clone(), reaction.to_string()))
+                    .into()
+            }))
+            .width(length)
+            .into(),
+        },
+    )
+}
+
+/// Whether we can delete `message`, as who sent it or an operator
+pub fn can_delete(
+    message: &data::Message,
+    our_nick: Option<NickRef<'_>>,
+    is_operator: bool,
+) -> bool {
+    let is_ours = match message.target.source() {
+        _ if matches!(message.direction, Direction::Sent) => true,
+        Source::User(user) => our_nick.is_some_and(|nick| user.nickname() == nick),
+        _ => false,
+    };
+
+    is_ours || is_operator
+}
+
+fn menu_button(content: &str, message: Message, length: Length) -> Element<'_, Message> {
+    button(text(content).style(theme::text::primary))
+        .padding(5)
+        .width(length)
+        .style(theme::button::context)
+        .on_press(message)
+        .into()
+}
@@ -3,7 +3,7 @@ use data::{history, message, Config, Server};
 use iced::widget::{column, container, row, vertical_space};
 use iced::{Length, Task};
 
-use super::{input_view, scroll_view, user_context};
+use super::{input_view, message_context, scroll_view, user_context};
 use crate::widget::{message_content, selectable_text, Element};
 use crate::{theme, Theme};
 
@@ -16,6 +16,8 @@ pub enum Message {
 #[derive(Debug, Clone)]
 pub enum Event {
     UserContext(user_context::Event),
+    LoadOlderHistory,
+    LocalCommand(data::Command),
 }
 
 pub fn view<'a>(
@@ -30,6 +32,11 @@ pub fn view<'a>(
     let buffer = state.buffer();
     let input = history.input(&buffer);
 
+    let our_nick = clients.nickname(&state.server);
+    let supports_redaction = clients.supports_redaction(&state.server);
+    let can_delete =
+        move |message| supports_redaction && message_context::can_delete(message, our_nick, false);
+
     let messages = container(
         scroll_view::view(
             &state.scroll_view,
@@ -42,10 +49,31 @@ pub fn view<'a>(
                         .buffer
                         .format_timestamp(&message.server_time)
                         .map(|timestamp| {
-                            selectable_text(timestamp).style(theme::selectable_text::transparent)
+                            message_context::view(
+                                selectable_text(timestamp)
+                                    .style(theme::selectable_text::transparent),
+                                message,
+                                can_delete(message),
+                            )
+                            .map(scroll_view::Message::ContextMenu)
                         });
 
                 match message.target.source() {
+                    message::Source::User(user)
+                        if clients.is_ignored(
+                            &state.server,
+                            user,
+                            data::ignore::Scope::Messages,
+                        ) =>
+                    {
+                        let text = selectable_text(format!(
+                            "{} ignored message",
+                            config.buffer.nickname.brackets.format(user)
+                        ))
+                        .style(theme::selectable_text::transparent);
+
+                        Some(container(row![].push_maybe(timestamp).push(text)).into())
+                    }
                     message::Source::User(user) => {
                         let nick = user_context::view(
                             selectable_text(config.buffer.nickname.brackets.format(user)).style(
@@ -64,10 +92,13 @@ pub fn view<'a>(
                             None,
                             state.buffer(),
                             None,
+                            clients.get_whois(&state.server, user.nickname()),
+                            config,
                         )
                         .map(scroll_view::Message::UserContext);
 
                         let space = selectable_text(" ");
+                        let is_highlight = message.is_highlighted(our_nick);
                         let message = message_content(
                             &message.content,
                             theme,
@@ -83,6 +114,13 @@ pub fn view<'a>(
                                     .push(space)
                                     .push(message),
                             )
+                            .style(move |theme| {
+                                if is_highlight {
+                                    theme::container::highlight(theme)
+                                } else {
+                                    Default::default()
+                                }
+                            })
                             .into(),
                         )
                     }
@@ -140,12 +178,21 @@ pub fn view<'a>(
         data::buffer::TextInputVisibility::Always => true,
     };
 
+    let typing = config
+        .buffer
+        .typing
+        .show
+        .then(|| input_view::typing(&clients.typing(&buffer)))
+        .flatten();
+
     let text_input = show_text_input.then(|| {
         column![
             vertical_space().height(4),
             input_view::view(&state.input_view, input, is_focused, !status.connected())
                 .map(Message::InputView)
         ]
+        .push_maybe(typing)
+        .spacing(4)
         .width(Length::Fill)
     });
 
@@ -192,12 +239,39 @@ impl Query {
         match message {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
+                let command = command.map(Message::ScrollView);
 
-                let event = event.map(|event| match event {
-                    scroll_view::Event::UserContext(event) => Event::UserContext(event),
-                });
+                match event {
+                    Some(scroll_view::Event::UserContext(event)) => {
+                        (command, Some(Event::UserContext(event)))
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::Reply(reply))) => {
+                        let focus = self.input_view.reply(reply).map(Message::InputView);
+
+                        (Task::batch(vec![command, focus]), None)
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::React {
+                        msgid,
+                        reaction,
+                    })) => {
+                        let buffer = self.buffer();
 
-                (command.map(Message::ScrollView), event)
+                        if let Some(message) = clients.react(&buffer, msgid, reaction) {
+                            history.record_message(buffer.server(), message);
+                        }
+
+                        (command, None)
+                    }
+                    Some(scroll_view::Event::ContextMenu(message_context::Event::Delete(
+                        msgid,
+                    ))) => {
+                        clients.redact(&self.buffer(), &msgid);
+
+                        (command, None)
+                    }
+                    Some(scroll_view::Event::LoadOlder) => (command, Some(Event::LoadOlderHistory)),
+                    None => (command, None),
+                }
             }
             Message::InputView(message) => {
                 let buffer = self.buffer();
@@ -216,6 +290,9 @@ impl Query {
 
                         (command, None)
                     }
+                    Some(input_view::Event::LocalCommand(local)) => {
+                        (command, Some(Event::LocalCommand(local)))
+                    }
                     None => (command, None),
                 }
             }
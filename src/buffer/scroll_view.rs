@@ -1,11 +1,15 @@
-use data::message::Limit;
+use std::collections::HashMap;
+
+use data::message::{Limit, Source};
 use data::server::Server;
 use data::user::Nick;
 use data::{history, time, Config};
-use iced::widget::{column, container, horizontal_rule, row, scrollable, text, Scrollable};
+use iced::widget::{
+    column, container, horizontal_rule, row, scrollable, text, tooltip, Scrollable,
+};
 use iced::{padding, Length, Task};
 
-use super::user_context;
+use super::{message_context, user_context};
 use crate::theme;
 use crate::widget::Element;
 
@@ -14,17 +18,21 @@ pub enum Message {
     Scrolled {
         count: usize,
         remaining: bool,
+        has_more: bool,
         oldest: time::Posix,
         status: Status,
         viewport: scrollable::Viewport,
     },
     UserContext(user_context::Message),
+    ContextMenu(message_context::Message),
     Link(String),
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     UserContext(user_context::Event),
+    ContextMenu(message_context::Event),
+    LoadOlder,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -32,6 +40,7 @@ pub enum Kind<'a> {
     Server(&'a Server),
     Channel(&'a Server, &'a str),
     Query(&'a Server, &'a Nick),
+    DccChat(&'a Server, &'a Nick),
 }
 
 pub fn view<'a>(
@@ -43,6 +52,7 @@ pub fn view<'a>(
 ) -> Element<'a, Message> {
     let Some(history::View {
         total,
+        has_more,
         old_messages,
         new_messages,
     }) = (match kind {
@@ -55,12 +65,20 @@ pub fn view<'a>(
         Kind::Query(server, user) => {
             history.get_query_messages(server, user, Some(state.limit), &config.buffer)
         }
+        Kind::DccChat(server, user) => {
+            history.get_dcc_chat_messages(server, user, Some(state.limit), &config.buffer)
+        }
     })
     else {
         return column![].into();
     };
 
-    let count = old_messages.len() + new_messages.len();
+    // Reactions don't count towards the limit
+    let count = old_messages
+        .iter()
+        .chain(&new_messages)
+        .filter(|message| message.reaction.is_none())
+        .count();
     let remaining = count < total;
     let oldest = old_messages
         .iter()
@@ -69,6 +87,68 @@ pub fn view<'a>(
         .map(|message| message.received_at)
         .unwrap_or_else(time::Posix::now);
     let status = state.status;
+    let font_size = config.font.size.map(f32::from).unwrap_or(theme::TEXT_SIZE) - 1.0;
+
+    // Replies quote their parent & reactions are shown under the message they
+    // react to, when it's loaded
+    let by_id = old_messages
+        .iter()
+        .chain(&new_messages)
+        .filter(|message| message.reaction.is_none())
+        .filter_map(|message| Some((message.id.as_deref()?, *message)))
+        .collect::<HashMap<_, _>>();
+    let reactions = reactions(old_messages.iter().chain(&new_messages).copied());
+
+    let format = move |message: &'a data::Message| -> Option<Element<'a, Message>> {
+        if message.reaction.is_some() {
+            return None;
+        }
+
+        let content = format(message)?;
+
+        let quote = message
+            .reply_to
+            .as_deref()
+            .and_then(|msgid| by_id.get(msgid))
+            .map(|parent| {
+                text(format!("↪ {}", parent.preview()))
+                    .size(font_size)
+                    .style(theme::text::transparent)
+            });
+
+        let reactions = message
+            .id
+            .as_deref()
+            .and_then(|msgid| reactions.get(msgid))
+            .map(|reactions| {
+                row(reactions.iter().map(|(reaction, nicks)| {
+                    tooltip(
+                        container(
+                            text(format!("{reaction} {}", nicks.len()))
+                                .size(font_size)
+                                .shaping(text::Shaping::Advanced),
+                        )
+                        .padding([0, 4])
+                        .style(theme::container::context),
+                        container(text(nicks.join(", ")).style(theme::text::transparent))
+                            .style(theme::container::context)
+                            .padding(8),
+                        tooltip::Position::Top,
+                    )
+                    .into()
+                }))
+                .spacing(4)
+                .padding(padding::top(2).bottom(2))
+            });
+
+        Some(
+            column![]
+                .push_maybe(quote)
+                .push(content)
+                .push_maybe(reactions)
+                .into(),
+        )
+    };
 
     let old = old_messages
         .into_iter()
@@ -82,8 +162,6 @@ pub fn view<'a>(
     let show_divider = !new.is_empty() || matches!(status, Status::Idle(Anchor::Bottom));
 
     let content = if show_divider {
-        let font_size = config.font.size.map(f32::from).unwrap_or(theme::TEXT_SIZE) - 1.0;
-
         let divider = row![
             container(horizontal_rule(1))
                 .width(Length::Fill)
@@ -113,6 +191,7 @@ pub fn view<'a>(
         .on_scroll(move |viewport| Message::Scrolled {
             count,
             remaining,
+            has_more,
             oldest,
             status,
             viewport,
@@ -121,6 +200,38 @@ pub fn view<'a>(
         .into()
 }
 
+/// Reactions to each `msgid`, in the order they were first used, with the
+/// nicks who reacted
+fn reactions<'a>(
+    messages: impl Iterator<Item = &'a data::Message>,
+) -> HashMap<&'a str, Vec<(&'a str, Vec<String>)>> {
+    let mut reactions = HashMap::<_, Vec<(&str, Vec<String>)>>::new();
+
+    for message in messages {
+        let (Some(msgid), Some(reaction), Source::User(user)) = (
+            message.reply_to.as_deref(),
+            message.reaction.as_deref(),
+            message.target.source(),
+        ) else {
+            continue;
+        };
+
+        let nick = user.nickname().to_string();
+        let reactions = reactions.entry(msgid).or_default();
+
+        match reactions
+            .iter_mut()
+            .find(|(existing, _)| *existing == reaction)
+        {
+            Some((_, nicks)) if !nicks.contains(&nick) => nicks.push(nick),
+            Some(_) => {}
+            None => reactions.push((reaction, vec![nick])),
+        }
+    }
+
+    reactions
+}
+
 #[derive(Debug, Clone)]
 pub struct State {
     pub scrollable: scrollable::Id,
@@ -148,10 +259,13 @@ impl State {
             Message::Scrolled {
                 count,
                 remaining,
+                has_more,
                 oldest,
                 status: old_status,
                 viewport,
             } => {
+                let mut event = None;
+
                 let relative_offset = viewport.relative_offset().y;
 
                 match old_status {
@@ -185,6 +299,14 @@ impl State {
                             }
                         }
                     }
+                    // Scrolled past all loaded messages, load older ones from disk
+                    Status::Idle(Anchor::Bottom) | Status::Unlocked(Anchor::Bottom)
+                        if old_status.is_end(relative_offset) && has_more =>
+                    {
+                        self.status = Status::Loading(Anchor::Bottom);
+                        self.limit = Limit::Bottom(count + Limit::DEFAULT_STEP);
+                        event = Some(Event::LoadOlder);
+                    }
                     _ if old_status.is_bottom(relative_offset) => {
                         self.status = Status::Idle(Anchor::Bottom);
                         self.limit = Limit::bottom();
@@ -206,9 +328,11 @@ impl State {
                 if let Some(new_offset) = self.status.new_offset(old_status, viewport) {
                     return (
                         scrollable::scroll_to(self.scrollable.clone(), new_offset),
-                        None,
+                        event,
                     );
                 }
+
+                return (Task::none(), event);
             }
             Message::UserContext(message) => {
                 return (
@@ -216,6 +340,12 @@ impl State {
                     user_context::update(message).map(Event::UserContext),
                 );
             }
+            Message::ContextMenu(message) => {
+                return (
+                    Task::none(),
+                    Some(Event::ContextMenu(message_context::update(message))),
+                );
+            }
             Message::Link(link) => {
                 let _ = open::that_detached(link);
             }
@@ -241,6 +371,16 @@ impl State {
             scrollable::AbsoluteOffset { x: 0.0, y: 0.0 },
         )
     }
+
+    /// Scroll so the message received at `received_at` is at the top of the view
+    pub fn scroll_to_message(&mut self, received_at: time::Posix) -> Task<Message> {
+        self.status = Status::Unlocked(Anchor::Bottom);
+        self.limit = Limit::Since(received_at);
+        scrollable::scroll_to(
+            self.scrollable.clone(),
+            scrollable::AbsoluteOffset { x: 0.0, y: 0.0 },
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
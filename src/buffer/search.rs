@@ -0,0 +1,202 @@
+use data::history::search::{self, Match, Query};
+use data::{message, Config};
+use iced::widget::{button, center, column, container, scrollable, text, text_input, Scrollable};
+use iced::{padding, Length, Task};
+
+use crate::widget::Element;
+use crate::{icon, theme};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Input(String),
+    Submit,
+    Results(Query, Result<Vec<Match>, String>),
+    Open(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Open(Match),
+}
+
+pub fn view<'a>(state: &'a Search, config: &'a Config) -> Element<'a, Message> {
+    let input = text_input(
+        "Search history (from: in: server: since: until:)",
+        &state.input,
+    )
+    .on_input(Message::Input)
+    .on_submit(Message::Submit)
+    .id(state.input_id.clone())
+    .padding(8)
+    .style(theme::text_input::primary);
+
+    let content: Element<_> = match &state.status {
+        Status::Idle => placeholder("Search messages across all buffers"),
+        Status::Searching => placeholder("Searching..."),
+        Status::Failed(error) => center(text(error).style(theme::text::error)).into(),
+        Status::Done(results) if results.is_empty() => placeholder("No messages found"),
+        Status::Done(results) => {
+            let column = column(
+                results
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, result)| result_row(result, idx, config)),
+            )
+            .spacing(1)
+            .padding([0, 2]);
+
+            Scrollable::new(column)
+                .direction(scrollable::Direction::Vertical(
+                    scrollable::Scrollbar::new().width(1).scroller_width(1),
+                ))
+                .style(theme::scrollable::hidden)
+                .into()
+        }
+    };
+
+    column![input, container(content).height(Length::Fill)]
+        .spacing(8)
+        .padding(8)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn placeholder<'a>(label: &'a str) -> Element<'a, Message> {
+    center(
+        column![
+            icon::search()
+                .size(theme::TEXT_SIZE + 3.0)
+                .style(theme::text::transparent),
+            text(label).style(theme::text::transparent)
+        ]
+        .spacing(8)
+        .align_x(iced::Alignment::Center),
+    )
+    .into()
+}
+
+fn result_row<'a>(result: &'a Match, idx: usize, config: &'a Config) -> Element<'a, Message> {
+    let timestamp = result
+        .message
+        .server_time
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let location = match &result.kind {
+        data::history::Kind::Server => result.server.to_string(),
+        data::history::Kind::Channel(channel) => format!("{channel} @ {}", result.server),
+        data::history::Kind::Query(nick) => format!("{nick} @ {}", result.server),
+        data::history::Kind::DccChat(nick) => {
+            format!("DCC chat with {nick} @ {}", result.server)
+        }
+    };
+
+    let header = text(format!("{timestamp} · {location}")).style(theme::text::transparent);
+
+    let content = match result.message.target.source() {
+        message::Source::User(user) => format!(
+            "{} {}",
+            config.buffer.nickname.brackets.format(user),
+            result.message.content.text()
+        ),
+        _ => result.message.content.text().into_owned(),
+    };
+
+    let body = column![header, text(content).shaping(text::Shaping::Advanced)]
+        .padding(padding::top(1))
+        .width(Length::Fill)
+        .spacing(0);
+
+    button(body)
+        .on_press(Message::Open(idx))
+        .padding(padding::top(6).bottom(6).right(4).left(8))
+        .width(Length::Fill)
+        .style(move |theme, status| match status {
+            button::Status::Hovered | button::Status::Pressed => {
+                theme::button::side_menu(theme, status)
+            }
+            _ => button::Style {
+                background: theme::container::table_row(theme, idx).background,
+                ..theme::button::side_menu(theme, status)
+            },
+        })
+        .into()
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    input: String,
+    input_id: text_input::Id,
+    status: Status,
+}
+
+#[derive(Debug, Clone)]
+enum Status {
+    Idle,
+    Searching,
+    Done(Vec<Match>),
+    Failed(String),
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            input: String::new(),
+            input_id: text_input::Id::unique(),
+            status: Status::Idle,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> (Task<Message>, Option<Event>) {
+        match message {
+            Message::Input(input) => {
+                self.input = input;
+            }
+            Message::Submit => {
+                let query = Query::parse(&self.input);
+
+                if query == Query::default() {
+                    self.status = Status::Idle;
+                    return (Task::none(), None);
+                }
+
+                self.status = Status::Searching;
+
+                return (
+                    Task::perform(search::search(query.clone()), move |result| {
+                        Message::Results(query.clone(), result.map_err(|error| error.to_string()))
+                    }),
+                    None,
+                );
+            }
+            Message::Results(query, result) => {
+                // Ignore results from an outdated query
+                if query != Query::parse(&self.input) {
+                    return (Task::none(), None);
+                }
+
+                self.status = match result {
+                    Ok(results) => Status::Done(results),
+                    Err(error) => Status::Failed(error),
+                };
+            }
+            Message::Open(idx) => {
+                if let Status::Done(results) = &self.status {
+                    return (Task::none(), results.get(idx).cloned().map(Event::Open));
+                }
+            }
+        }
+
+        (Task::none(), None)
+    }
+
+    pub fn focus(&self) -> Task<Message> {
+        text_input::focus(self.input_id.clone())
+    }
+}
@@ -12,6 +12,12 @@ pub enum Message {
     InputView(input_view::Message),
 }
 
+#[derive(Debug, Clone)]
+pub enum Event {
+    LoadOlderHistory,
+    LocalCommand(data::Command),
+}
+
 pub fn view<'a>(
     state: &'a Server,
     clients: &'a data::client::Map,
@@ -131,11 +137,17 @@ impl Server {
         clients: &mut data::client::Map,
         history: &mut history::Manager,
         config: &Config,
-    ) -> Task<Message> {
+    ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::ScrollView(message) => {
-                let (command, _) = self.scroll_view.update(message);
-                command.map(Message::ScrollView)
+                let (command, event) = self.scroll_view.update(message);
+
+                let event = event.and_then(|event| match event {
+                    scroll_view::Event::UserContext(_) | scroll_view::Event::ContextMenu(_) => None,
+                    scroll_view::Event::LoadOlder => Some(Event::LoadOlderHistory),
+                });
+
+                (command.map(Message::ScrollView), event)
             }
             Message::InputView(message) => {
                 let buffer = self.buffer();
@@ -146,11 +158,17 @@ impl Server {
                 let command = command.map(Message::InputView);
 
                 match event {
-                    Some(input_view::Event::InputSent) => Task::batch(vec![
-                        command,
-                        self.scroll_view.scroll_to_end().map(Message::ScrollView),
-                    ]),
-                    None => command,
+                    Some(input_view::Event::InputSent) => (
+                        Task::batch(vec![
+                            command,
+                            self.scroll_view.scroll_to_end().map(Message::ScrollView),
+                        ]),
+                        None,
+                    ),
+                    Some(input_view::Event::LocalCommand(local)) => {
+                        (command, Some(Event::LocalCommand(local)))
+                    }
+                    None => (command, None),
                 }
             }
         }
@@ -1,9 +1,10 @@
 use data::user::Nick;
-use data::{Buffer, User};
-use iced::widget::{button, container, horizontal_rule, row, text, Space};
+use data::whois::Whois;
+use data::{Buffer, Config, User};
+use iced::widget::{button, column, container, horizontal_rule, row, text, tooltip, Space};
 use iced::{padding, Length, Padding};
 
-use crate::widget::{context_menu, double_pass, Element};
+use crate::widget::{context_menu, double_pass, hover, Element};
 use crate::{icon, theme};
 
 #[derive(Debug, Clone, Copy)]
@@ -13,6 +14,7 @@ enum Entry {
     ToggleAccessLevelOp,
     ToggleAccessLevelVoice,
     SendFile,
+    DccChat,
     UserInfo,
     HorizontalRule,
 }
@@ -30,6 +32,7 @@ impl Entry {
                         Entry::ToggleAccessLevelOp,
                         Entry::ToggleAccessLevelVoice,
                         Entry::SendFile,
+                        Entry::DccChat,
                     ]
                 } else {
                     vec![
@@ -38,10 +41,13 @@ impl Entry {
                         Entry::Whois,
                         Entry::Query,
                         Entry::SendFile,
+                        Entry::DccChat,
                     ]
                 }
             }
-            Buffer::Server(_) | Buffer::Query(_, _) => vec![Entry::Whois, Entry::SendFile],
+            Buffer::Server(_) | Buffer::Query(_, _) => {
+                vec![Entry::Whois, Entry::SendFile, Entry::DccChat]
+            }
         }
     }
 }
@@ -53,7 +59,10 @@ pub enum Message {
     SingleClick(Nick),
     ToggleAccessLevel(Nick, String),
     SendFile(Nick),
+    DccChat(Nick),
     Link(String),
+    Hover(Nick),
+    Unhover,
 }
 
 #[derive(Debug, Clone)]
@@ -63,6 +72,10 @@ pub enum Event {
     SingleClick(Nick),
     ToggleAccessLevel(Nick, String),
     SendFile(Nick),
+    StartDccChat(Nick),
+    /// The cursor rests on `nick`, whose WHOIS is requested after a delay
+    Hovered(Nick),
+    Unhovered,
 }
 
 pub fn update(message: Message) -> Option<Event> {
@@ -72,6 +85,9 @@ pub fn update(message: Message) -> Option<Event> {
         Message::SingleClick(nick) => Some(Event::SingleClick(nick)),
         Message::ToggleAccessLevel(nick, mode) => Some(Event::ToggleAccessLevel(nick, mode)),
         Message::SendFile(nick) => Some(Event::SendFile(nick)),
+        Message::DccChat(nick) => Some(Event::StartDccChat(nick)),
+        Message::Hover(nick) => Some(Event::Hovered(nick)),
+        Message::Unhover => Some(Event::Unhovered),
         Message::Link(link) => {
             let _ = open::that_detached(link);
             None
@@ -85,6 +101,8 @@ pub fn view<'a>(
     current_user: Option<&'a User>,
     buffer: Buffer,
     our_user: Option<&'a User>,
+    whois: Option<&'a Whois>,
+    config: &'a Config,
 ) -> Element<'a, Message> {
     let entries = Entry::list(&buffer, our_user);
 
@@ -93,6 +111,21 @@ pub fn view<'a>(
         .style(theme::button::bare)
         .on_press(Message::SingleClick(user.nickname().to_owned()));
 
+    // Fetches WHOIS for the hover card once hovered for a moment
+    let content: Element<'a, Message> = if config.tooltips {
+        hover::on_hover(
+            tooltip(
+                content,
+                hover_card(current_user.unwrap_or(user), whois),
+                tooltip::Position::Bottom,
+            ),
+            Message::Hover(user.nickname().to_owned()),
+            Message::Unhover,
+        )
+    } else {
+        content.into()
+    };
+
     context_menu(content, entries, move |entry, length| {
         let nickname = user.nickname().to_owned();
 
@@ -130,6 +163,7 @@ pub fn view<'a>(
                 }
             }
             Entry::SendFile => menu_button("Send File", Message::SendFile(nickname), length),
+            Entry::DccChat => menu_button("Start DCC Chat", Message::DccChat(nickname), length),
             Entry::UserInfo => user_info(current_user, length),
             Entry::HorizontalRule => match length {
                 Length::Fill => container(horizontal_rule(1)).padding([0, 6]).into(),
@@ -148,6 +182,58 @@ fn menu_button(content: &str, message: Message, length: Length) -> Element<'_, M
         .into()
 }
 
+/// Account, realname, hostmask, away message, prefixes & idle time of `user`
+fn hover_card<'a>(user: &'a User, whois: Option<&'a Whois>) -> Element<'a, Message> {
+    let line = |label: &'static str, value: String| {
+        row![
+            text(label).style(theme::text::transparent).width(80),
+            text(value)
+        ]
+    };
+
+    let hostmask = match (user.username(), user.hostname()) {
+        (Some(username), Some(hostname)) => Some(format!("{username}@{hostname}")),
+        _ => None,
+    };
+    let account = user
+        .accountname()
+        .or_else(|| whois.and_then(|whois| whois.account.as_deref()));
+    let realname = user
+        .realname()
+        .or_else(|| whois.and_then(|whois| whois.realname.as_deref()));
+    let away = whois
+        .and_then(|whois| whois.away.clone())
+        .or_else(|| user.is_away().then(|| "Away".to_string()));
+
+    let mut formatter = timeago::Formatter::new();
+    formatter.ago("");
+
+    let idle = whois
+        .and_then(|whois| whois.idle)
+        .map(|idle| formatter.convert(idle));
+    let signon = whois.and_then(|whois| whois.signon).map(|signon| {
+        signon
+            .with_timezone(&chrono::Local)
+            .format("%c")
+            .to_string()
+    });
+
+    container(
+        column![text(format!("{}{}", user.prefixes(), user.nickname())).style(theme::text::primary)]
+            .push_maybe(realname.map(|realname| line("Realname", realname.to_string())))
+            .push_maybe(account.map(|account| line("Account", account.to_string())))
+            .push_maybe(hostmask.map(|hostmask| line("Host", hostmask)))
+            .push_maybe(away.map(|away| line("Away", away)))
+            .push_maybe(idle.map(|idle| line("Idle", idle)))
+            .push_maybe(signon.map(|signon| line("Signed on", signon)))
+            .spacing(2),
+    )
+    .style(theme::container::context)
+    .padding(8)
+    .max_width(400)
+    .into()
+}
+
 fn right_justified_padding() -> Padding {
     padding::all(5).right(5.0 + double_pass::horizontal_expansion())
 }
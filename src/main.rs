@@ -13,6 +13,7 @@ mod screen;
 mod stream;
 mod theme;
 mod url;
+mod vault;
 mod widget;
 mod window;
 
@@ -37,17 +38,23 @@ pub fn main() -> iced::Result {
     let mut args = env::args();
     args.next();
 
-    let version = args
-        .next()
-        .map(|s| s == "--version" || s == "-V")
-        .unwrap_or_default();
+    let first = args.next();
 
-    if version {
+    if matches!(first.as_deref(), Some("--version" | "-V")) {
         println!("halloy {}", environment::formatted_version());
 
         return Ok(());
     }
 
+    if first.as_deref() == Some("--vault") {
+        if let Err(error) = vault::run(args) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     #[cfg(debug_assertions)]
     let is_debug = true;
     #[cfg(not(debug_assertions))]
@@ -183,12 +190,15 @@ impl Halloy {
             },
         };
 
+        let mut clients = data::client::Map::default();
+        clients.set_highlights(&config.highlights);
+
         (
             Halloy {
                 version: Version::new(),
                 screen,
                 theme: config.themes.default.clone().into(),
-                clients: Default::default(),
+                clients,
                 servers: config.servers.clone(),
                 config,
                 modal: None,
@@ -220,6 +230,7 @@ pub enum Message {
     RouteReceived(String),
     Window(data::window::Event),
     WindowSettingsSaved(Result<(), data::window::Error>),
+    ConfigReloaded(Result<Config, config::Error>),
 }
 
 impl Halloy {
@@ -260,30 +271,23 @@ impl Halloy {
 
                 if let Some(event) = event {
                     match event {
-                        dashboard::Event::ReloadConfiguration => match Config::load() {
-                            Ok(updated) => {
-                                let removed_servers = self
-                                    .servers
-                                    .keys()
-                                    .filter(|server| !updated.servers.contains(server))
-                                    .cloned()
-                                    .collect::<Vec<_>>();
-
-                                self.servers = updated.servers.clone();
-                                self.theme = updated.themes.default.clone().into();
-                                self.config = updated;
-
-                                for server in removed_servers {
-                                    self.clients.quit(&server, None);
-                                }
-                            }
-                            Err(error) => {
-                                self.modal = Some(Modal::ReloadConfigurationError(error));
-                            }
-                        },
+                        dashboard::Event::ReloadConfiguration => {
+                            return Task::batch(vec![
+                                command.map(Message::Dashboard),
+                                track.map(Message::Dashboard),
+                                Task::perform(Config::reload(), Message::ConfigReloaded),
+                            ]);
+                        }
                         dashboard::Event::QuitServer(server) => {
                             self.clients.quit(&server, None);
                         }
+                        dashboard::Event::AddBouncerNetwork(server) => {
+                            self.modal = Some(Modal::AddBouncerNetwork {
+                                server,
+                                host: String::new(),
+                                name: String::new(),
+                            });
+                        }
                     }
                 }
 
@@ -413,6 +417,24 @@ impl Halloy {
 
                     Task::none()
                 }
+                stream::Update::Sts {
+                    server,
+                    outcome,
+                    sent_time,
+                } => {
+                    // The plaintext connection is dropped for one with TLS
+                    if let data::sts::Outcome::Upgraded { .. } = outcome {
+                        self.clients.disconnected(server.clone());
+                    }
+
+                    let Screen::Dashboard(dashboard) = &mut self.screen else {
+                        return Task::none();
+                    };
+
+                    dashboard.broadcast_sts(&server, outcome, &self.config, sent_time);
+
+                    Task::none()
+                }
                 stream::Update::MessagesReceived(server, messages) => {
                     let Screen::Dashboard(dashboard) = &mut self.screen else {
                         return Task::none();
@@ -430,6 +452,8 @@ impl Halloy {
                                         .resolve_user_attributes(&server, channel, user)
                                         .cloned()
                                 };
+                                let allows_highlight =
+                                    |user: &User| self.clients.allows_highlight(&server, user);
 
                                 match event {
                                     data::client::Event::Single(encoded, our_nick) => {
@@ -438,6 +462,7 @@ impl Halloy {
                                             our_nick,
                                             &self.config,
                                             resolve_user_attributes,
+                                            allows_highlight,
                                         ) {
                                             dashboard.record_message(&server, message);
                                         }
@@ -448,6 +473,7 @@ impl Halloy {
                                             our_nick,
                                             &self.config,
                                             resolve_user_attributes,
+                                            allows_highlight,
                                         ) {
                                             dashboard.record_message(
                                                 &server,
@@ -526,6 +552,43 @@ impl Halloy {
                                                 sent_time,
                                             );
                                         }
+                                        data::client::Broadcast::ChangeRealname {
+                                            old_user,
+                                            new_realname,
+                                            ourself,
+                                            channels,
+                                            sent_time,
+                                        } => {
+                                            dashboard.broadcast_change_realname(
+                                                &server,
+                                                old_user,
+                                                new_realname,
+                                                ourself,
+                                                channels,
+                                                &self.config,
+                                                sent_time,
+                                            );
+                                        }
+                                        data::client::Broadcast::Presence {
+                                            nicks,
+                                            presence,
+                                            sent_time,
+                                        } => {
+                                            notification::presence(
+                                                &self.config.notifications,
+                                                &nicks,
+                                                presence,
+                                                &server,
+                                            );
+
+                                            dashboard.broadcast_presence(
+                                                &server,
+                                                nicks,
+                                                presence,
+                                                &self.config,
+                                                sent_time,
+                                            );
+                                        }
                                     },
                                     data::client::Event::Notification(
                                         encoded,
@@ -537,6 +600,7 @@ impl Halloy {
                                             our_nick,
                                             &self.config,
                                             resolve_user_attributes,
+                                            allows_highlight,
                                         ) {
                                             dashboard.record_message(&server, message);
                                         }
@@ -563,6 +627,83 @@ impl Halloy {
                                             commands.push(command.map(Message::Dashboard));
                                         }
                                     }
+                                    data::client::Event::FileTransferResume(request) => {
+                                        dashboard.resume_file_transfer(request);
+                                    }
+                                    data::client::Event::DccChatRequest(request) => {
+                                        commands.push(
+                                            dashboard
+                                                .receive_dcc_chat(request, &self.config)
+                                                .map(Message::Dashboard),
+                                        );
+                                    }
+                                    data::client::Event::ChatHistorySingle(encoded, our_nick) => {
+                                        if let Some(message) = data::Message::received(
+                                            encoded,
+                                            our_nick,
+                                            &self.config,
+                                            resolve_user_attributes,
+                                            allows_highlight,
+                                        ) {
+                                            dashboard.record_chathistory_message(&server, message);
+                                        }
+                                    }
+                                    data::client::Event::ChatHistoryRequest(kind) => {
+                                        commands.push(
+                                            dashboard
+                                                .request_chathistory(&server, kind)
+                                                .map(Message::Dashboard),
+                                        );
+                                    }
+                                    data::client::Event::SaslFailed(reason) => {
+                                        dashboard.record_status(
+                                            &data::Buffer::Server(server.clone()),
+                                            data::message::source::Status::Error,
+                                            reason,
+                                        );
+                                    }
+                                    data::client::Event::Echo(encoded, our_nick, label) => {
+                                        if let Some(message) = data::Message::received(
+                                            encoded,
+                                            our_nick,
+                                            &self.config,
+                                            resolve_user_attributes,
+                                            allows_highlight,
+                                        ) {
+                                            dashboard.record_echo(&server, &label, message);
+                                        }
+                                    }
+                                    data::client::Event::ReadMarker(buffer, read_at) => {
+                                        dashboard.mark_read(&buffer, read_at);
+                                    }
+                                    data::client::Event::Redact {
+                                        target,
+                                        msgid,
+                                        reason,
+                                    } => {
+                                        commands.push(
+                                            dashboard
+                                                .redact(&server, target, msgid, reason)
+                                                .map(Message::Dashboard),
+                                        );
+                                    }
+                                    data::client::Event::BouncerNetwork(network) => {
+                                        if let Some(network_server) =
+                                            self.servers.insert_bouncer_network(&server, &network)
+                                        {
+                                            log::info!(
+                                                "[{server}] connecting to bouncer network {}",
+                                                network_server
+                                            );
+                                        }
+                                    }
+                                    data::client::Event::BouncerNetworkRemoved(netid) => {
+                                        if let Some(network_server) =
+                                            self.servers.remove_bouncer_network(&server, &netid)
+                                        {
+                                            self.clients.quit(&network_server, None);
+                                        }
+                                    }
                                 }
                             }
 
@@ -622,7 +763,9 @@ impl Halloy {
                 self.clients.tick(now);
 
                 if let Screen::Dashboard(dashboard) = &mut self.screen {
-                    dashboard.tick(now).map(Message::Dashboard)
+                    dashboard
+                        .tick(now, &mut self.clients)
+                        .map(Message::Dashboard)
                 } else {
                     Task::none()
                 }
@@ -637,6 +780,11 @@ impl Halloy {
                         modal::Event::CloseModal => {
                             self.modal = None;
                         }
+                        modal::Event::AddBouncerNetwork { server, host, name } => {
+                            self.modal = None;
+                            self.clients
+                                .add_bouncer_network(&server, &host, name.as_deref());
+                        }
                         modal::Event::AcceptNewServer => {
                             if let Some(Modal::RouteReceived(data::Url::ServerConnect {
                                 server,
@@ -680,6 +828,51 @@ impl Halloy {
                     log::error!("window settings failed to save: {:?}", err)
                 }
 
+                Task::none()
+            }
+            Message::ConfigReloaded(config) => {
+                match config {
+                    Ok(updated) => {
+                        // Keep networks listed by bouncers still configured
+                        let bouncer_networks = self
+                            .servers
+                            .bouncer_networks()
+                            .filter(|entry| {
+                                entry.config.bouncer.as_ref().is_some_and(|binding| {
+                                    updated.servers.contains(&binding.parent)
+                                })
+                            })
+                            .collect::<Vec<_>>();
+
+                        let removed_servers = self
+                            .servers
+                            .keys()
+                            .filter(|server| {
+                                !updated.servers.contains(server)
+                                    && !bouncer_networks
+                                        .iter()
+                                        .any(|entry| &entry.server == *server)
+                            })
+                            .cloned()
+                            .collect::<Vec<_>>();
+
+                        self.servers = updated.servers.clone();
+                        for entry in bouncer_networks {
+                            self.servers.insert(entry.server, entry.config);
+                        }
+                        self.theme = updated.themes.default.clone().into();
+                        self.config = updated;
+                        self.clients.set_highlights(&self.config.highlights);
+
+                        for server in removed_servers {
+                            self.clients.quit(&server, None);
+                        }
+                    }
+                    Err(error) => {
+                        self.modal = Some(Modal::ReloadConfigurationError(error));
+                    }
+                }
+
                 Task::none()
             }
         }
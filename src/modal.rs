@@ -1,6 +1,7 @@
 use crate::widget::Element;
-use data::{config, Url};
+use data::{config, Server, Url};
 
+pub mod add_bouncer_network;
 pub mod connect_to_server;
 pub mod reload_configuration_error;
 
@@ -8,18 +9,31 @@ pub mod reload_configuration_error;
 pub enum Modal {
     ReloadConfigurationError(config::Error),
     RouteReceived(Url),
+    AddBouncerNetwork {
+        server: Server,
+        host: String,
+        name: String,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Message {
     Cancel,
     AcceptNewServer,
     DangerouslyAcceptInvalidCerts(bool),
+    BouncerNetworkHost(String),
+    BouncerNetworkName(String),
+    AddBouncerNetwork,
 }
 
 pub enum Event {
     CloseModal,
     AcceptNewServer,
+    AddBouncerNetwork {
+        server: Server,
+        host: String,
+        name: Option<String>,
+    },
 }
 
 impl Modal {
@@ -34,6 +48,32 @@ impl Modal {
 
                 None
             }
+            Message::BouncerNetworkHost(value) => {
+                if let Modal::AddBouncerNetwork { host, .. } = self {
+                    *host = value;
+                }
+
+                None
+            }
+            Message::BouncerNetworkName(value) => {
+                if let Modal::AddBouncerNetwork { name, .. } = self {
+                    *name = value;
+                }
+
+                None
+            }
+            Message::AddBouncerNetwork => match self {
+                Modal::AddBouncerNetwork { server, host, name } if !host.trim().is_empty() => {
+                    Some(Event::AddBouncerNetwork {
+                        server: server.clone(),
+                        host: host.trim().to_string(),
+                        name: Some(name.trim())
+                            .filter(|name| !name.is_empty())
+                            .map(String::from),
+                    })
+                }
+                _ => None,
+            },
         }
     }
 
@@ -45,6 +85,9 @@ impl Modal {
                     url: raw, config, ..
                 } => connect_to_server::view(raw, config),
             },
+            Modal::AddBouncerNetwork { server, host, name } => {
+                add_bouncer_network::view(server, host, name)
+            }
         }
     }
 }
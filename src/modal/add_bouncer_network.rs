@@ -0,0 +1,60 @@
+use data::Server;
+use iced::{
+    alignment,
+    widget::{button, column, container, text, text_input},
+    Length,
+};
+
+use super::Message;
+use crate::{theme, widget::Element};
+
+pub fn view<'a>(server: &'a Server, host: &'a str, name: &'a str) -> Element<'a, Message> {
+    let add = button(
+        container(text("Add"))
+            .align_x(alignment::Horizontal::Center)
+            .width(Length::Fill),
+    )
+    .padding(5)
+    .width(Length::Fixed(250.0))
+    .style(theme::button::primary)
+    .on_press_maybe((!host.trim().is_empty()).then_some(Message::AddBouncerNetwork));
+
+    container(
+        column![
+            text(format!("Add network to {server}")),
+            column![
+                text_input("Host, e.g. irc.libera.chat", host)
+                    .on_input(Message::BouncerNetworkHost)
+                    .on_submit(Message::AddBouncerNetwork)
+                    .padding(5)
+                    .style(theme::text_input::primary),
+                text_input("Name (optional)", name)
+                    .on_input(Message::BouncerNetworkName)
+                    .on_submit(Message::AddBouncerNetwork)
+                    .padding(5)
+                    .style(theme::text_input::primary),
+            ]
+            .spacing(4)
+            .width(Length::Fixed(250.0)),
+            column![
+                add,
+                button(
+                    container(text("Close"))
+                        .align_x(alignment::Horizontal::Center)
+                        .width(Length::Fill),
+                )
+                .padding(5)
+                .width(Length::Fixed(250.0))
+                .style(theme::button::primary)
+                .on_press(Message::Cancel),
+            ]
+            .spacing(4),
+        ]
+        .spacing(20)
+        .align_x(iced::Alignment::Center),
+    )
+    .width(Length::Shrink)
+    .style(theme::container::default_banner)
+    .padding(25)
+    .into()
+}
@@ -1,8 +1,10 @@
 use data::{
     audio::Sound,
+    client::Presence,
     config::{self, notification},
     user::{Nick, NickRef},
 };
+use itertools::Itertools;
 
 use crate::audio;
 
@@ -42,6 +44,20 @@ pub fn file_transfer_request(
     );
 }
 
+pub fn presence(
+    config: &config::Notifications<Sound>,
+    nicks: &[Nick],
+    presence: Presence,
+    server: impl ToString,
+) {
+    let title = match nicks {
+        [nick] => format!("{nick} is {presence}"),
+        nicks => format!("{} are {presence}", nicks.iter().join(", ")),
+    };
+
+    show_notification(&config.monitor, &title, server);
+}
+
 fn show_notification(notification: &notification::Loaded, title: &str, body: impl ToString) {
     if notification.show_toast {
         toast::show(title, body);
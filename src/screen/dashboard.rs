@@ -2,15 +2,16 @@ mod command_bar;
 pub mod pane;
 pub mod sidebar;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use data::environment::RELEASE_WEBSITE;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use data::file_transfer;
 use data::history::manager::Broadcast;
+use data::message::source::Status;
 use data::user::Nick;
 use data::{client, environment, history, Config, Server, User, Version};
+use data::{dcc_chat, file_transfer};
 use iced::widget::pane_grid::{self, PaneGrid};
 use iced::widget::{column, container, row, Space};
 use iced::{clipboard, padding, window, Length, Task};
@@ -18,12 +19,18 @@ use iced::{clipboard, padding, window, Length, Task};
 use self::command_bar::CommandBar;
 use self::pane::Pane;
 use self::sidebar::Sidebar;
+use crate::buffer::dcc_chat::DccChat;
 use crate::buffer::file_transfers::FileTransfers;
+use crate::buffer::highlights::Highlights;
+use crate::buffer::search::Search;
 use crate::buffer::{self, Buffer};
 use crate::widget::{anchored_overlay, context_menu, selectable_text, shortcut, Element};
 use crate::{event, notification, theme, Theme};
 
 const SAVE_AFTER: Duration = Duration::from_secs(3);
+/// How long the cursor rests on a user before WHOIS is requested for their
+/// hover card
+const HOVER_DELAY: Duration = Duration::from_millis(500);
 
 pub struct Dashboard {
     panes: pane_grid::State<Pane>,
@@ -33,6 +40,9 @@ pub struct Dashboard {
     last_changed: Option<Instant>,
     command_bar: Option<CommandBar>,
     file_transfers: file_transfer::Manager,
+    dcc_chats: dcc_chat::Manager,
+    /// User whose hover card is shown, until WHOIS is requested for it
+    hovered_user: Option<(Server, Nick)>,
 }
 
 #[derive(Debug)]
@@ -48,13 +58,26 @@ pub enum Message {
     Shortcut(shortcut::Command),
     FileTransfer(file_transfer::task::Update),
     SendFileSelected(Server, Nick, Option<PathBuf>),
+    DccChat(dcc_chat::task::Update),
     CloseContextMenu(bool),
+    RequestChatHistory(Server, String, Option<DateTime<Utc>>),
+    ExportHistory(
+        data::Buffer,
+        history::export::Format,
+        Option<NaiveDate>,
+        Option<NaiveDate>,
+        Option<PathBuf>,
+    ),
+    ImportHistory(data::Buffer, history::import::Format, Option<Vec<PathBuf>>),
+    HoverElapsed(Server, Nick),
 }
 
 #[derive(Debug)]
 pub enum Event {
     ReloadConfiguration,
     QuitServer(Server),
+    /// Add a network to the bouncer on the server
+    AddBouncerNetwork(Server),
 }
 
 impl Dashboard {
@@ -65,13 +88,18 @@ impl Dashboard {
             panes,
             focus: None,
             side_menu: Sidebar::new(),
-            history: history::Manager::default(),
+            history: history::Manager::new(config.history, config.logging.clone()),
             last_changed: None,
             command_bar: None,
             file_transfers: file_transfer::Manager::new(config.file_transfer.clone()),
+            dcc_chats: dcc_chat::Manager::new(config.file_transfer.clone()),
+            hovered_user: None,
         };
 
-        let command = dashboard.track();
+        let command = Task::batch(vec![
+            dashboard.track(),
+            Task::perform(dashboard.history.load_highlights(), Message::History),
+        ]);
 
         (dashboard, command)
     }
@@ -79,10 +107,16 @@ impl Dashboard {
     pub fn restore(dashboard: data::Dashboard, config: &Config) -> (Self, Task<Message>) {
         let mut dashboard = Dashboard::from_data(dashboard, config);
 
+        let load_highlights = Task::perform(dashboard.history.load_highlights(), Message::History);
+
         let command = if let Some((pane, _)) = dashboard.panes.panes.iter().next() {
-            Task::batch(vec![dashboard.focus_pane(*pane), dashboard.track()])
+            Task::batch(vec![
+                dashboard.focus_pane(*pane),
+                dashboard.track(),
+                load_highlights,
+            ])
         } else {
-            dashboard.track()
+            Task::batch(vec![dashboard.track(), load_highlights])
         };
 
         (dashboard, command)
@@ -125,11 +159,12 @@ impl Dashboard {
                             clients,
                             &mut self.history,
                             &mut self.file_transfers,
+                            &mut self.dcc_chats,
                             config,
                         );
 
-                        if let Some(buffer::Event::UserContext(event)) = event {
-                            match event {
+                        match event {
+                            Some(buffer::Event::UserContext(event)) => match event {
                                 buffer::user_context::Event::ToggleAccessLevel(nick, mode) => {
                                     let Some(buffer) = pane.buffer.data() else {
                                         return (Task::none(), None);
@@ -180,6 +215,29 @@ impl Dashboard {
                                         }
                                     }
                                 }
+                                buffer::user_context::Event::Hovered(nick) => {
+                                    if let Some(buffer) = pane.buffer.data() {
+                                        let server = buffer.server().clone();
+
+                                        self.hovered_user = Some((server.clone(), nick.clone()));
+
+                                        return (
+                                            Task::perform(
+                                                async move {
+                                                    tokio::time::sleep(HOVER_DELAY).await;
+                                                    (server, nick)
+                                                },
+                                                |(server, nick)| {
+                                                    Message::HoverElapsed(server, nick)
+                                                },
+                                            ),
+                                            None,
+                                        );
+                                    }
+                                }
+                                buffer::user_context::Event::Unhovered => {
+                                    self.hovered_user = None;
+                                }
                                 buffer::user_context::Event::OpenQuery(nick) => {
                                     if let Some(data) = pane.buffer.data() {
                                         let buffer =
@@ -230,7 +288,92 @@ impl Dashboard {
                                         );
                                     }
                                 }
+                                buffer::user_context::Event::StartDccChat(nick) => {
+                                    if let Some(buffer) = pane.buffer.data() {
+                                        let server = buffer.server().clone();
+
+                                        return (
+                                            self.start_dcc_chat(server, nick, clients, config),
+                                            None,
+                                        );
+                                    }
+                                }
+                            },
+                            Some(buffer::Event::OpenSearchResult(result)) => {
+                                let buffer = result.buffer();
+
+                                return (
+                                    Task::batch([
+                                        self.open_buffer(buffer.clone(), config),
+                                        self.scroll_to_message(&buffer, result.message.received_at),
+                                    ]),
+                                    None,
+                                );
+                            }
+                            Some(buffer::Event::OpenHighlight(highlight)) => {
+                                let buffer = highlight.buffer();
+
+                                return (
+                                    Task::batch([
+                                        self.open_buffer(buffer.clone(), config),
+                                        self.scroll_to_message(
+                                            &buffer,
+                                            highlight.message.received_at,
+                                        ),
+                                    ]),
+                                    None,
+                                );
+                            }
+                            Some(buffer::Event::LocalCommand(local)) => {
+                                if let Some(buffer) = pane.buffer.data() {
+                                    return (
+                                        Task::batch([
+                                            command.map(move |message| {
+                                                Message::Pane(pane::Message::Buffer(id, message))
+                                            }),
+                                            self.local_command(buffer, local, clients),
+                                        ]),
+                                        None,
+                                    );
+                                }
+                            }
+                            Some(buffer::Event::RetryFileTransfer(transfer)) => {
+                                let server = self
+                                    .file_transfers
+                                    .get(&transfer)
+                                    .map(|transfer| transfer.server.clone());
+
+                                if let Some(server) = server {
+                                    if let Some(event) =
+                                        self.file_transfers.retry(&transfer, config.proxy.clone())
+                                    {
+                                        return (
+                                            self.handle_file_transfer_event(&server, event),
+                                            None,
+                                        );
+                                    }
+                                }
                             }
+                            Some(buffer::Event::LoadOlderHistory) => {
+                                if let Some(resource) = pane.resource() {
+                                    if let Some(task) =
+                                        self.history.load_older(&resource.server, resource.kind)
+                                    {
+                                        return (
+                                            Task::batch([
+                                                command.map(move |message| {
+                                                    Message::Pane(pane::Message::Buffer(
+                                                        id, message,
+                                                    ))
+                                                }),
+                                                Task::perform(task, Message::History),
+                                            ]),
+                                            None,
+                                        );
+                                    }
+                                }
+                            }
+                            None => {}
                         }
 
                         return (
@@ -291,6 +434,15 @@ impl Dashboard {
                     sidebar::Event::Leave(buffer) => {
                         return self.leave_buffer(clients, buffer);
                     }
+                    sidebar::Event::AddBouncerNetwork(server) => {
+                        return (Task::none(), Some(Event::AddBouncerNetwork(server)));
+                    }
+                    sidebar::Event::RemoveBouncerNetwork(server) => {
+                        clients.remove_bouncer_network(&server);
+                    }
+                    sidebar::Event::OpenDccChat(id) => {
+                        return (self.open_dcc_chat(id, config), None);
+                    }
                     sidebar::Event::ToggleFileTransfers => {
                         return (self.toggle_file_transfers(config), None);
                     }
@@ -401,6 +553,35 @@ impl Dashboard {
                                 command_bar::Buffer::ToggleFileTransfers => {
                                     (self.toggle_file_transfers(config), None)
                                 }
+                                command_bar::Buffer::ToggleSearch => {
+                                    (self.toggle_search(config), None)
+                                }
+                                command_bar::Buffer::ToggleHighlights => {
+                                    (self.toggle_highlights(config), None)
+                                }
+                                command_bar::Buffer::ExportHistory => {
+                                    let buffer = self
+                                        .focus
+                                        .and_then(|pane| self.panes.get(pane))
+                                        .and_then(|pane| pane.buffer.data());
+
+                                    if let Some(buffer) = buffer {
+                                        (
+                                            self.local_command(
+                                                buffer,
+                                                data::Command::Export(
+                                                    history::export::Format::default(),
+                                                    None,
+                                                    None,
+                                                ),
+                                                clients,
+                                            ),
+                                            None,
+                                        )
+                                    } else {
+                                        (Task::none(), None)
+                                    }
+                                }
                             },
                             command_bar::Command::Configuration(command) => match command {
                                 command_bar::Configuration::OpenDirectory => {
@@ -556,6 +737,11 @@ impl Dashboard {
             Message::FileTransfer(update) => {
                 self.file_transfers.update(update);
             }
+            Message::DccChat(update) => {
+                if let Some(event) = self.dcc_chats.update(update) {
+                    return (self.handle_dcc_chat_event(event, config), None);
+                }
+            }
             Message::SendFileSelected(server, to, path) => {
                 if let Some(server_handle) = clients.get_server_handle(&server) {
                     if let Some(path) = path {
@@ -573,6 +759,46 @@ impl Dashboard {
                     }
                 }
             }
+            Message::ExportHistory(buffer, format, since, until, Some(path)) => {
+                return (
+                    Task::perform(
+                        self.history.export(
+                            buffer.server(),
+                            history::Kind::from(buffer.clone()),
+                            since,
+                            until,
+                            format,
+                            &config.buffer,
+                            path,
+                        ),
+                        Message::History,
+                    ),
+                    None,
+                );
+            }
+            Message::ImportHistory(buffer, format, Some(paths)) => {
+                return (
+                    Task::perform(
+                        self.history.import(
+                            buffer.server(),
+                            history::Kind::from(buffer.clone()),
+                            format,
+                            paths,
+                        ),
+                        Message::History,
+                    ),
+                    None,
+                );
+            }
+            Message::ExportHistory(_, _, _, _, None) | Message::ImportHistory(_, _, None) => {}
+            Message::HoverElapsed(server, nick) => {
+                // Only request WHOIS if the cursor stayed on the user
+                if self.hovered_user.as_ref() == Some(&(server.clone(), nick.clone())) {
+                    self.hovered_user = None;
+
+                    clients.request_whois(&server, data::user::NickRef::from(nick.as_ref()));
+                }
+            }
             Message::CloseContextMenu(any_closed) => {
                 if !any_closed {
                     if self.is_pane_maximized() {
@@ -582,6 +808,9 @@ impl Dashboard {
                     }
                 }
             }
+            Message::RequestChatHistory(server, target, latest) => {
+                clients.send_chathistory_request(&server, &target, latest);
+            }
         }
 
         (Task::none(), None)
@@ -607,6 +836,7 @@ impl Dashboard {
                 maximized,
                 clients,
                 &self.file_transfers,
+                &self.dcc_chats,
                 &self.history,
                 config,
                 theme,
@@ -634,6 +864,7 @@ impl Dashboard {
                 config.sidebar,
                 config.tooltips,
                 &self.file_transfers,
+                &self.dcc_chats,
                 version,
             )
             .map(|e| e.map(Message::Sidebar));
@@ -754,13 +985,116 @@ impl Dashboard {
         }
     }
 
-    // TODO: Perhaps rewrite this, i just did this quickly.
     fn toggle_file_transfers(&mut self, config: &Config) -> Task<Message> {
+        self.toggle_internal_buffer(Buffer::FileTransfers(FileTransfers::new()), config)
+    }
+
+    /// Handles a command which isn't sent to the server
+    fn local_command(
+        &mut self,
+        buffer: data::Buffer,
+        command: data::Command,
+        clients: &mut client::Map,
+    ) -> Task<Message> {
+        match command {
+            data::Command::Export(format, since, until) => {
+                let name = buffer
+                    .target()
+                    .unwrap_or_else(|| buffer.server().to_string());
+                let file_name = format!("{name}.{}", format.extension());
+
+                Task::perform(
+                    async move {
+                        rfd::AsyncFileDialog::new()
+                            .set_file_name(file_name)
+                            .save_file()
+                            .await
+                            .map(|handle| handle.path().to_path_buf())
+                    },
+                    move |path| Message::ExportHistory(buffer.clone(), format, since, until, path),
+                )
+            }
+            data::Command::Import(format) => Task::perform(
+                async move {
+                    rfd::AsyncFileDialog::new()
+                        .pick_files()
+                        .await
+                        .map(|handles| {
+                            handles
+                                .iter()
+                                .map(|handle| handle.path().to_path_buf())
+                                .collect()
+                        })
+                },
+                move |paths| Message::ImportHistory(buffer.clone(), format, paths),
+            ),
+            data::Command::Ignore(Some(entry)) => {
+                let text = format!("ignoring {entry}");
+                clients.ignore(buffer.server(), entry);
+
+                self.record_status(&buffer, Status::Success, text);
+
+                Task::none()
+            }
+            data::Command::Ignore(None) => {
+                let entries = clients.ignores(buffer.server());
+
+                let text = if entries.is_empty() {
+                    "no ignored users".to_string()
+                } else {
+                    format!(
+                        "ignoring {}",
+                        entries
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                };
+
+                self.record_status(&buffer, Status::Success, text);
+
+                Task::none()
+            }
+            data::Command::Unignore(pattern) => {
+                let (status, text) = if clients.unignore(buffer.server(), &pattern) {
+                    (Status::Success, format!("no longer ignoring {pattern}"))
+                } else {
+                    (Status::Error, format!("{pattern} is not ignored"))
+                };
+
+                self.record_status(&buffer, status, text);
+
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    pub fn record_status(&mut self, buffer: &data::Buffer, status: Status, text: String) {
+        self.history.record_status(
+            buffer.server(),
+            history::Kind::from(buffer.clone()),
+            status,
+            text,
+        );
+    }
+
+    fn toggle_search(&mut self, config: &Config) -> Task<Message> {
+        self.toggle_internal_buffer(Buffer::Search(Search::new()), config)
+    }
+
+    fn toggle_highlights(&mut self, config: &Config) -> Task<Message> {
+        self.toggle_internal_buffer(Buffer::Highlights(Highlights::new()), config)
+    }
+
+    // TODO: Perhaps rewrite this, i just did this quickly.
+    fn toggle_internal_buffer(&mut self, buffer: Buffer, config: &Config) -> Task<Message> {
         let panes = self.panes.clone();
 
-        // If file transfers already is open, we close it.
+        // If buffer already is open, we close it.
         for (id, pane) in panes.iter() {
-            if let Buffer::FileTransfers(_) = pane.buffer {
+            if std::mem::discriminant(&pane.buffer) == std::mem::discriminant(&buffer) {
                 return self.close_pane(*id);
             }
         }
@@ -769,9 +1103,10 @@ impl Dashboard {
         if self.panes.len() == 1 {
             for (id, pane) in panes.iter() {
                 if let Buffer::Empty = &pane.buffer {
-                    self.panes.panes.entry(*id).and_modify(|p| {
-                        *p = Pane::new(Buffer::FileTransfers(FileTransfers::new()), config)
-                    });
+                    self.panes
+                        .panes
+                        .entry(*id)
+                        .and_modify(|p| *p = Pane::new(buffer, config));
                     self.last_changed = Some(Instant::now());
 
                     return self.focus_pane(*id);
@@ -784,7 +1119,7 @@ impl Dashboard {
 
         if let Some(pane) = self.focus.take() {
             if let Some(state) = self.panes.get_mut(pane) {
-                state.buffer = Buffer::FileTransfers(FileTransfers::new());
+                state.buffer = buffer;
                 self.last_changed = Some(Instant::now());
 
                 commands.extend(vec![self.reset_pane(pane), self.focus_pane(pane)]);
@@ -794,6 +1129,25 @@ impl Dashboard {
         Task::batch(commands)
     }
 
+    fn scroll_to_message(
+        &mut self,
+        buffer: &data::Buffer,
+        received_at: data::time::Posix,
+    ) -> Task<Message> {
+        let Some((id, pane)) = self
+            .panes
+            .iter_mut()
+            .find(|(_, pane)| pane.buffer.data().as_ref() == Some(buffer))
+        else {
+            return Task::none();
+        };
+        let id = *id;
+
+        pane.buffer
+            .scroll_to_message(received_at)
+            .map(move |message| Message::Pane(pane::Message::Buffer(id, message)))
+    }
+
     fn open_buffer(&mut self, kind: data::Buffer, config: &Config) -> Task<Message> {
         let panes = self.panes.clone();
 
@@ -846,6 +1200,155 @@ impl Dashboard {
         Task::none()
     }
 
+    fn open_dcc_chat(&mut self, id: dcc_chat::Id, config: &Config) -> Task<Message> {
+        let Some(chat) = self.dcc_chats.get(&id) else {
+            return Task::none();
+        };
+        let buffer = Buffer::DccChat(DccChat::new(chat));
+
+        let panes = self.panes.clone();
+
+        // If chat already is open, we focus it.
+        for (pane, state) in panes.iter() {
+            if matches!(&state.buffer, Buffer::DccChat(chat) if chat.id == id) {
+                self.focus = Some(*pane);
+
+                return self.focus_pane(*pane);
+            }
+        }
+
+        // If we only have one pane, and its empty, we replace it.
+        if self.panes.len() == 1 {
+            for (pane, state) in panes.iter() {
+                if let Buffer::Empty = &state.buffer {
+                    self.panes
+                        .panes
+                        .entry(*pane)
+                        .and_modify(|p| *p = Pane::new(buffer, config));
+                    self.last_changed = Some(Instant::now());
+
+                    return self.focus_pane(*pane);
+                }
+            }
+        }
+
+        let pane_to_split = {
+            if let Some(pane) = self.focus {
+                pane
+            } else if let Some(pane) = self.panes.panes.keys().last() {
+                *pane
+            } else {
+                log::error!("Didn't find any panes");
+                return Task::none();
+            }
+        };
+
+        let result = self.panes.split(
+            pane_grid::Axis::Horizontal,
+            pane_to_split,
+            Pane::new(buffer, config),
+        );
+        self.last_changed = Some(Instant::now());
+
+        if let Some((pane, _)) = result {
+            return self.focus_pane(pane);
+        }
+
+        Task::none()
+    }
+
+    /// Offers `nick` a chat, listening on a port shared with file transfers
+    fn start_dcc_chat(
+        &mut self,
+        server: Server,
+        nick: Nick,
+        clients: &client::Map,
+        config: &Config,
+    ) -> Task<Message> {
+        let Some(server_handle) = clients.get_server_handle(&server) else {
+            return Task::none();
+        };
+
+        let Some(our_nick) = clients.nickname(&server) else {
+            return Task::none();
+        };
+
+        let Some(port) = self.file_transfers.reserve_port() else {
+            log::error!("No port available to offer a DCC chat to {nick}");
+            return Task::none();
+        };
+
+        let event = self.dcc_chats.offer(
+            dcc_chat::OfferRequest {
+                to: nick,
+                nick: our_nick.to_owned(),
+                server: server.clone(),
+                server_handle: server_handle.clone(),
+            },
+            port,
+        );
+
+        self.handle_dcc_chat_event(event, config)
+    }
+
+    pub fn receive_dcc_chat(
+        &mut self,
+        request: dcc_chat::ReceiveRequest,
+        config: &Config,
+    ) -> Task<Message> {
+        let event = self.dcc_chats.receive(request, config.proxy.as_ref());
+
+        self.handle_dcc_chat_event(event, config)
+    }
+
+    fn handle_dcc_chat_event(
+        &mut self,
+        event: dcc_chat::manager::Event,
+        config: &Config,
+    ) -> Task<Message> {
+        match event {
+            dcc_chat::manager::Event::NewChat(chat, task) => {
+                let server = &chat.server;
+
+                let open = match chat.direction {
+                    file_transfer::Direction::Received => {
+                        self.record_message(
+                            server,
+                            data::Message::dcc_chat_request_received(&chat.remote_user),
+                        );
+
+                        Task::none()
+                    }
+                    file_transfer::Direction::Sent => {
+                        self.record_message(
+                            server,
+                            data::Message::dcc_chat_request_sent(&chat.remote_user),
+                        );
+
+                        self.open_dcc_chat(chat.id, config)
+                    }
+                };
+
+                Task::batch([open, Task::run(task, Message::DccChat)])
+            }
+            dcc_chat::manager::Event::Received {
+                server,
+                remote_user,
+                message,
+            } => {
+                self.history
+                    .record_dcc_chat_message(&server, &remote_user, message);
+
+                Task::none()
+            }
+            dcc_chat::manager::Event::PortReleased(port) => {
+                self.file_transfers.release_port(port);
+
+                Task::none()
+            }
+        }
+    }
+
     pub fn leave_buffer(
         &mut self,
         clients: &mut data::client::Map,
@@ -905,6 +1408,47 @@ impl Dashboard {
         self.history.record_message(server, message);
     }
 
+    pub fn record_chathistory_message(&mut self, server: &Server, message: data::Message) {
+        self.history.record_chathistory_message(server, message);
+    }
+
+    pub fn record_echo(&mut self, server: &Server, label: &str, echo: data::Message) {
+        self.history.record_echo(server, label, echo);
+    }
+
+    /// Another client read `buffer` up to `read_at`
+    pub fn mark_read(&mut self, buffer: &data::Buffer, read_at: DateTime<Utc>) {
+        self.history.mark_read(buffer, read_at);
+    }
+
+    /// Deletes a message which was redacted
+    pub fn redact(
+        &mut self,
+        server: &Server,
+        target: data::message::Target,
+        msgid: String,
+        reason: Option<String>,
+    ) -> Task<Message> {
+        Task::perform(
+            self.history.redact(server, target, msgid, reason),
+            Message::History,
+        )
+    }
+
+    pub fn request_chathistory(&self, server: &Server, kind: history::Kind) -> Task<Message> {
+        let target = match &kind {
+            history::Kind::Channel(channel) => channel.clone(),
+            history::Kind::Query(nick) => nick.to_string(),
+            history::Kind::Server | history::Kind::DccChat(_) => return Task::none(),
+        };
+        let server = server.clone();
+
+        Task::perform(
+            self.history.latest_server_time(&server, kind),
+            move |latest| Message::RequestChatHistory(server.clone(), target.clone(), latest),
+        )
+    }
+
     pub fn broadcast_quit(
         &mut self,
         server: &Server,
@@ -995,6 +1539,45 @@ impl Dashboard {
         );
     }
 
+    pub fn broadcast_change_realname(
+        &mut self,
+        server: &Server,
+        old_user: User,
+        new_realname: String,
+        ourself: bool,
+        user_channels: Vec<String>,
+        config: &Config,
+        sent_time: DateTime<Utc>,
+    ) {
+        self.history.broadcast(
+            server,
+            Broadcast::ChangeRealname {
+                old_user,
+                new_realname,
+                ourself,
+                user_channels,
+            },
+            config,
+            sent_time,
+        );
+    }
+
+    pub fn broadcast_presence(
+        &mut self,
+        server: &Server,
+        nicks: Vec<Nick>,
+        presence: client::Presence,
+        config: &Config,
+        sent_time: DateTime<Utc>,
+    ) {
+        self.history.broadcast(
+            server,
+            Broadcast::Presence { nicks, presence },
+            config,
+            sent_time,
+        );
+    }
+
     pub fn broadcast_connecting(
         &mut self,
         server: &Server,
@@ -1051,6 +1634,17 @@ impl Dashboard {
         );
     }
 
+    pub fn broadcast_sts(
+        &mut self,
+        server: &Server,
+        outcome: data::sts::Outcome,
+        config: &Config,
+        sent_time: DateTime<Utc>,
+    ) {
+        self.history
+            .broadcast(server, Broadcast::Sts { outcome }, config, sent_time);
+    }
+
     fn get_focused_mut(&mut self) -> Option<(pane_grid::Pane, &mut Pane)> {
         let pane = self.focus?;
         self.panes.get_mut(pane).map(|state| (pane, state))
@@ -1175,7 +1769,11 @@ impl Dashboard {
         )
     }
 
-    pub fn tick(&mut self, now: Instant) -> Task<Message> {
+    pub fn tick(&mut self, now: Instant, clients: &mut data::client::Map) -> Task<Message> {
+        for (buffer, read_at) in self.history.update_read_markers() {
+            clients.mark_read(&buffer, read_at);
+        }
+
         let history = Task::batch(
             self.history
                 .tick(now.into())
@@ -1260,6 +1858,10 @@ impl Dashboard {
         None
     }
 
+    pub fn resume_file_transfer(&mut self, request: file_transfer::ResumeRequest) {
+        self.file_transfers.resume(request);
+    }
+
     pub fn handle_file_transfer_event(
         &mut self,
         server: &Server,
@@ -1290,6 +1892,7 @@ impl Dashboard {
 
                 Task::run(task, Message::FileTransfer)
             }
+            file_transfer::manager::Event::Retried(task) => Task::run(task, Message::FileTransfer),
         }
     }
 
@@ -1318,6 +1921,14 @@ impl Dashboard {
                     Buffer::FileTransfers(FileTransfers::new()),
                     buffer::Settings::default(),
                 )),
+                data::Pane::Search => Configuration::Pane(Pane::with_settings(
+                    Buffer::Search(Search::new()),
+                    buffer::Settings::default(),
+                )),
+                data::Pane::Highlights => Configuration::Pane(Pane::with_settings(
+                    Buffer::Highlights(Highlights::new()),
+                    buffer::Settings::default(),
+                )),
             }
         }
 
@@ -1325,10 +1936,12 @@ impl Dashboard {
             panes: pane_grid::State::with_configuration(configuration(dashboard.pane)),
             focus: None,
             side_menu: Sidebar::new(),
-            history: history::Manager::default(),
+            history: history::Manager::new(config.history, config.logging.clone()),
             last_changed: None,
             command_bar: None,
             file_transfers: file_transfer::Manager::new(config.file_transfer.clone()),
+            dcc_chats: dcc_chat::Manager::new(config.file_transfer.clone()),
+            hovered_user: None,
         }
     }
 }
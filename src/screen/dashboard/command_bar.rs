@@ -125,6 +125,9 @@ pub enum Buffer {
     Close,
     Replace(data::Buffer),
     ToggleFileTransfers,
+    ToggleSearch,
+    ToggleHighlights,
+    ExportHistory,
 }
 
 #[derive(Debug, Clone)]
@@ -193,10 +196,16 @@ impl Buffer {
         is_focused_buffer: bool,
         resize_buffer: data::buffer::Resize,
     ) -> Vec<Self> {
-        let mut list = vec![Buffer::New, Buffer::ToggleFileTransfers];
+        let mut list = vec![
+            Buffer::New,
+            Buffer::ToggleFileTransfers,
+            Buffer::ToggleSearch,
+            Buffer::ToggleHighlights,
+        ];
 
         if is_focused_buffer {
             list.push(Buffer::Close);
+            list.push(Buffer::ExportHistory);
 
             match resize_buffer {
                 data::buffer::Resize::Maximize => list.push(Buffer::Maximize(true)),
@@ -286,6 +295,9 @@ impl std::fmt::Display for Buffer {
                 data::Buffer::Query(_, nick) => write!(f, "Change to {}", nick),
             },
             Buffer::ToggleFileTransfers => write!(f, "Toggle File Transfers"),
+            Buffer::ToggleSearch => write!(f, "Toggle Search"),
+            Buffer::ToggleHighlights => write!(f, "Toggle Highlights"),
+            Buffer::ExportHistory => write!(f, "Export History"),
         }
     }
 }
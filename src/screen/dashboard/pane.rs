@@ -1,4 +1,5 @@
-use data::{file_transfer, history, Config};
+use data::user::NickRef;
+use data::{dcc_chat, file_transfer, history, Config};
 use iced::widget::{button, center, container, pane_grid, row, text};
 use uuid::Uuid;
 
@@ -52,6 +53,7 @@ impl Pane {
         maximized: bool,
         clients: &'a data::client::Map,
         file_transfers: &'a file_transfer::Manager,
+        dcc_chats: &'a dcc_chat::Manager,
         history: &'a history::Manager,
         config: &'a Config,
         theme: &'a Theme,
@@ -60,21 +62,34 @@ impl Pane {
             Buffer::Empty => "".to_string(),
             Buffer::Channel(state) => {
                 let channel = &state.channel;
-                let server = &state.server;
+                let server = clients.server_name(&state.server);
                 let users = clients
                     .get_channel_users(&state.server, &state.channel)
                     .len();
 
                 format!("{channel} @ {server} - {users} users")
             }
-            Buffer::Server(state) => state.server.to_string(),
+            Buffer::Server(state) => clients.server_name(&state.server),
             Buffer::Query(state) => {
                 let nick = &state.nick;
                 let server = &state.server;
+                let presence = clients
+                    .get_user_presence(server, NickRef::from(nick.as_ref()))
+                    .map(|presence| format!(" - {presence}"))
+                    .unwrap_or_default();
+                let server = clients.server_name(server);
 
-                format!("{nick} @ {server}")
+                format!("{nick} @ {server}{presence}")
             }
             Buffer::FileTransfers(_) => "File Transfers".to_string(),
+            Buffer::Search(_) => "Search".to_string(),
+            Buffer::Highlights(_) => match history.unread_highlights() {
+                0 => "Highlights".to_string(),
+                unread => format!("Highlights - {unread} unread"),
+            },
+            Buffer::DccChat(state) => {
+                format!("DCC chat with {} @ {}", state.remote_user, state.server)
+            }
         };
 
         let title_bar = self.title_bar.view(
@@ -94,6 +109,7 @@ impl Pane {
             .view(
                 clients,
                 file_transfers,
+                dcc_chats,
                 history,
                 &self.settings,
                 config,
@@ -126,7 +142,11 @@ impl Pane {
                 server: query.server.clone(),
                 kind: history::Kind::Query(query.nick.clone()),
             }),
-            Buffer::FileTransfers(_) => None,
+            Buffer::DccChat(chat) => Some(history::Resource {
+                server: chat.server.clone(),
+                kind: history::Kind::DccChat(chat.remote_user.clone()),
+            }),
+            Buffer::FileTransfers(_) | Buffer::Search(_) | Buffer::Highlights(_) => None,
         }
     }
 
@@ -259,6 +279,10 @@ impl From<Pane> for data::Pane {
             Buffer::Server(state) => data::Buffer::Server(state.server),
             Buffer::Query(state) => data::Buffer::Query(state.server, state.nick),
             Buffer::FileTransfers(_) => return data::Pane::FileTransfers,
+            Buffer::Search(_) => return data::Pane::Search,
+            Buffer::Highlights(_) => return data::Pane::Highlights,
+            // Chats end with the session
+            Buffer::DccChat(_) => return data::Pane::Empty,
         };
 
         data::Pane::Buffer {
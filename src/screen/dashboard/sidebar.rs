@@ -1,7 +1,9 @@
 use std::time::{Duration, Instant};
 
+use data::client::Presence;
 use data::dashboard::DefaultAction;
-use data::{file_transfer, history, Buffer, Version};
+use data::user::NickRef;
+use data::{dcc_chat, file_transfer, history, Buffer, Server, Version};
 use iced::widget::{
     button, center, column, container, horizontal_space, pane_grid, row, scrollable, text,
     vertical_space, Scrollable,
@@ -9,6 +11,7 @@ use iced::widget::{
 use iced::{padding, Length};
 
 use super::pane::Pane;
+use crate::buffer;
 use crate::widget::{context_menu, tooltip, Element};
 use crate::{icon, theme};
 
@@ -19,6 +22,9 @@ pub enum Message {
     Close(pane_grid::Pane),
     Swap(pane_grid::Pane, pane_grid::Pane),
     Leave(Buffer),
+    AddBouncerNetwork(Server),
+    RemoveBouncerNetwork(Server),
+    OpenDccChat(dcc_chat::Id),
     ToggleFileTransfers,
     ToggleCommandBar,
     ReloadConfigFile,
@@ -32,6 +38,9 @@ pub enum Event {
     Close(pane_grid::Pane),
     Swap(pane_grid::Pane, pane_grid::Pane),
     Leave(Buffer),
+    AddBouncerNetwork(Server),
+    RemoveBouncerNetwork(Server),
+    OpenDccChat(dcc_chat::Id),
     ToggleFileTransfers,
     ToggleCommandBar,
     ReloadConfigFile,
@@ -63,6 +72,9 @@ impl Sidebar {
             Message::Close(pane) => Event::Close(pane),
             Message::Swap(from, to) => Event::Swap(from, to),
             Message::Leave(buffer) => Event::Leave(buffer),
+            Message::AddBouncerNetwork(server) => Event::AddBouncerNetwork(server),
+            Message::RemoveBouncerNetwork(server) => Event::RemoveBouncerNetwork(server),
+            Message::OpenDccChat(id) => Event::OpenDccChat(id),
             Message::ToggleFileTransfers => Event::ToggleFileTransfers,
             Message::ToggleCommandBar => Event::ToggleCommandBar,
             Message::ReloadConfigFile => {
@@ -83,6 +95,7 @@ impl Sidebar {
         config: data::config::Sidebar,
         show_tooltips: bool,
         file_transfers: &'a file_transfer::Manager,
+        dcc_chats: &'a dcc_chat::Manager,
         version: &'a Version,
     ) -> Option<Element<'a, Message>> {
         if self.hidden {
@@ -100,17 +113,31 @@ impl Sidebar {
                         Buffer::Server(server.clone()),
                         false,
                         false,
+                        None,
+                        None,
                         config.default_action,
+                        Some(clients.server_name(server)),
                     ));
                 }
                 data::client::State::Ready(connection) => {
+                    let bouncer_entry = if clients.supports_bouncer_networks(server) {
+                        Some(Entry::AddNetwork)
+                    } else if clients.bouncer_binding(server).is_some() {
+                        Some(Entry::RemoveNetwork)
+                    } else {
+                        None
+                    };
+
                     column = column.push(buffer_button(
                         panes,
                         focus,
                         Buffer::Server(server.clone()),
                         true,
                         false,
+                        None,
+                        bouncer_entry,
                         config.default_action,
+                        Some(clients.server_name(server)),
                     ));
 
                     for channel in connection.channels() {
@@ -128,7 +155,10 @@ impl Sidebar {
                                     )
                                 })
                                 .unwrap_or(false),
+                            None,
+                            None,
                             config.default_action,
+                            None,
                         ));
                     }
 
@@ -145,10 +175,17 @@ impl Sidebar {
                                     history.has_unread(server, &history::Kind::Query(user.clone()))
                                 })
                                 .unwrap_or(false),
+                            connection.presence(NickRef::from(user.as_ref())),
+                            None,
                             config.default_action,
+                            None,
                         ));
                     }
 
+                    for chat in dcc_chats.list().filter(|chat| &chat.server == server) {
+                        column = column.push(dcc_chat_button(panes, chat));
+                    }
+
                     column = column.push(vertical_space().height(12));
                 }
             }
@@ -274,6 +311,8 @@ enum Entry {
     Close(pane_grid::Pane),
     Swap(pane_grid::Pane, pane_grid::Pane),
     Leave,
+    AddNetwork,
+    RemoveNetwork,
 }
 
 impl Entry {
@@ -300,13 +339,50 @@ impl Entry {
     }
 }
 
+fn dcc_chat_button<'a>(
+    panes: &pane_grid::State<Pane>,
+    chat: &dcc_chat::Chat,
+) -> Element<'a, Message> {
+    let is_open = panes.iter().any(
+        |(_, state)| matches!(&state.buffer, buffer::Buffer::DccChat(open) if open.id == chat.id),
+    );
+
+    let row = row![]
+        .push(horizontal_space().width(3))
+        .push_maybe(
+            chat.is_connected()
+                .then_some(icon::dot().size(6).style(theme::text::success)),
+        )
+        .push(horizontal_space().width(if chat.is_connected() { 10 } else { 16 }))
+        .push(
+            text(format!("DCC: {}", chat.remote_user))
+                .style(theme::text::primary)
+                .shaping(text::Shaping::Advanced),
+        )
+        .align_y(iced::Alignment::Center);
+
+    button(row)
+        .padding(5)
+        .width(Length::Fill)
+        .style(if is_open {
+            theme::button::side_menu_selected
+        } else {
+            theme::button::side_menu
+        })
+        .on_press(Message::OpenDccChat(chat.id))
+        .into()
+}
+
 fn buffer_button<'a>(
     panes: &pane_grid::State<Pane>,
     focus: Option<pane_grid::Pane>,
     buffer: Buffer,
     connected: bool,
     has_unread: bool,
+    presence: Option<Presence>,
+    bouncer_entry: Option<Entry>,
     default_action: DefaultAction,
+    server_name: Option<String>,
 ) -> Element<'a, Message> {
     let open = panes
         .iter()
@@ -319,7 +395,7 @@ fn buffer_button<'a>(
             } else {
                 theme::text::error
             }),
-            text(server.to_string())
+            text(server_name.unwrap_or_else(|| server.to_string()))
                 .style(theme::text::primary)
                 .shaping(text::Shaping::Advanced)
         ]
@@ -341,7 +417,11 @@ fn buffer_button<'a>(
             .push(horizontal_space().width(if has_unread { 10 } else { 16 }))
             .push(
                 text(nick.to_string())
-                    .style(theme::text::primary)
+                    .style(if presence == Some(Presence::Offline) {
+                        theme::text::transparent
+                    } else {
+                        theme::text::primary
+                    })
                     .shaping(text::Shaping::Advanced),
             )
             .align_y(iced::Alignment::Center),
@@ -363,7 +443,8 @@ fn buffer_button<'a>(
             },
         });
 
-    let entries = Entry::list(panes.len(), open, focus);
+    let mut entries = Entry::list(panes.len(), open, focus);
+    entries.extend(bouncer_entry);
 
     if entries.is_empty() || !connected {
         base.into()
@@ -385,6 +466,14 @@ fn buffer_button<'a>(
                     },
                     Message::Leave(buffer.clone()),
                 ),
+                Entry::AddNetwork => (
+                    "Add network",
+                    Message::AddBouncerNetwork(buffer.server().clone()),
+                ),
+                Entry::RemoveNetwork => (
+                    "Remove network",
+                    Message::RemoveBouncerNetwork(buffer.server().clone()),
+                ),
             };
 
             button(text(content).style(theme::text::primary))
@@ -0,0 +1,62 @@
+//! `halloy --vault` for managing secrets in the vault from a terminal
+use data::config;
+use data::vault::Vault;
+
+const USAGE: &str = "usage: halloy --vault (list | set <key> | remove <key>)";
+
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let config = config::Vault::load().map_err(|error| error.to_string())?;
+    let path = config.path();
+
+    let command = args.next().ok_or(USAGE)?;
+    let key = args.next();
+
+    if !matches!(
+        (command.as_str(), &key),
+        ("list", None) | ("set" | "remove", Some(_))
+    ) {
+        return Err(USAGE.to_string());
+    }
+
+    let passphrase = match config.passphrase().map_err(|error| error.to_string())? {
+        Some(passphrase) => passphrase,
+        None => {
+            let passphrase = prompt("Vault passphrase: ")?;
+
+            if !path.exists() && prompt("Confirm passphrase: ")? != passphrase {
+                return Err("passphrases don't match".to_string());
+            }
+
+            passphrase
+        }
+    };
+
+    let mut vault = Vault::unlock(&path, &passphrase).map_err(|error| error.to_string())?;
+
+    match (command.as_str(), key) {
+        ("set", Some(key)) => {
+            let secret = prompt(&format!("Secret for {key}: "))?;
+
+            vault.set(key, secret);
+            vault.save().map_err(|error| error.to_string())?;
+        }
+        ("remove", Some(key)) => {
+            if !vault.remove(&key) {
+                return Err(format!("{key} isn't in the vault"));
+            }
+
+            vault.save().map_err(|error| error.to_string())?;
+        }
+        _ => {
+            for key in vault.keys() {
+                println!("{key}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn prompt(prompt: &str) -> Result<String, String> {
+    rpassword::prompt_password(prompt).map_err(|error| error.to_string())
+}
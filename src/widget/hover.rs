@@ -6,6 +6,20 @@ pub fn hover<'a, Message: 'a>(
     component::Hover::new(f).into()
 }
 
+/// Publishes `on_enter` whenever the cursor enters `content` & `on_leave`
+/// when it leaves
+pub fn on_hover<'a, Message: Clone + 'a>(
+    content: impl Into<Element<'a, Message>>,
+    on_enter: Message,
+    on_leave: Message,
+) -> Element<'a, Message> {
+    widget::Hover::new(content, move |cursor| match cursor {
+        widget::Cursor::Entered => Some(on_enter.clone()),
+        widget::Cursor::Left => Some(on_leave.clone()),
+    })
+    .into()
+}
+
 mod component {
     use iced::widget::{component, Component};
 
@@ -49,8 +63,10 @@ mod component {
         }
 
         fn view(&self, hovered: &Self::State) -> Element<'_, Self::Event> {
-            super::widget::Hover::new((self.view)(*hovered).map(Event::Message), Event::Change)
-                .into()
+            super::widget::Hover::new((self.view)(*hovered).map(Event::Message), |cursor| {
+                Some(Event::Change(cursor))
+            })
+            .into()
         }
     }
 
@@ -79,13 +95,13 @@ mod widget {
 
     pub struct Hover<'a, Message> {
         content: Element<'a, Message>,
-        on_change: Box<dyn Fn(Cursor) -> Message + 'a>,
+        on_change: Box<dyn Fn(Cursor) -> Option<Message> + 'a>,
     }
 
     impl<'a, Message> Hover<'a, Message> {
         pub fn new(
             content: impl Into<Element<'a, Message>>,
-            on_change: impl Fn(Cursor) -> Message + 'a,
+            on_change: impl Fn(Cursor) -> Option<Message> + 'a,
         ) -> Self {
             Self {
                 content: content.into(),
@@ -167,14 +183,14 @@ mod widget {
             let prev_hovered = *hovered;
             *hovered = cursor.position_over(layout.bounds()).is_some();
 
-            match (prev_hovered, *hovered) {
-                (true, false) => {
-                    shell.publish((self.on_change)(Cursor::Left));
-                }
-                (false, true) => {
-                    shell.publish((self.on_change)(Cursor::Entered));
-                }
-                _ => {}
+            let change = match (prev_hovered, *hovered) {
+                (true, false) => (self.on_change)(Cursor::Left),
+                (false, true) => (self.on_change)(Cursor::Entered),
+                _ => None,
+            };
+
+            if let Some(message) = change {
+                shell.publish(message);
             }
 
             self.content.as_widget_mut().on_event(